@@ -1,22 +1,42 @@
 use crate::command::add::LoadTestAdd;
+use crate::command::book::{
+    LoadTestBookDepthCommand, LoadTestCancelBookOrderCommand, LoadTestPlaceBookOrderCommand,
+};
 use crate::command::command_user_id_file_name::LoadTestDumpLogUserIdFileName;
 use crate::command::dump_log::{DumpLog, LoadTestDumpLogFileName};
 use crate::command::load_test_file::LoadTestFileCommand;
+use crate::command::order::{LoadTestCancelOrderCommand, LoadTestPlaceOrderCommand};
+use crate::command::price_history::LoadTestPriceHistoryCommand;
+use crate::command::subscribe_prices::LoadTestSubscribePricesCommand;
+use crate::command::trigger::{LoadTestPlaceTriggerCommand, LoadTestUpdateTriggerCommand};
 use crate::command::user_id::LoadTestUserIdCommand;
+use crate::command::user_id_order_id::LoadTestUserIdOrderIdCommand;
 use crate::command::user_id_stock_symbol::LoadTestUserIdStockSymbolCommand;
 use crate::command::user_id_stock_symbol_amount_created::LoadTestUserIdStockSymbolAmountCommand;
+use crate::command::validate_log::LoadTestValidateLogCommand;
+use crate::protos::{DumpLogChunk, FileRequest, ListDumpsRequest, LogViolation};
 use crate::services::DayTraderServicesStack;
 use crate::ParseLoadTestCommandError;
-use tonic::Status;
+use tokio::fs::File;
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tonic::{Status, Streaming};
 use tracing::debug;
 
 pub mod add;
+pub mod book;
 pub mod command_user_id_file_name;
 pub mod dump_log;
 pub mod load_test_file;
+pub mod log_filter;
+pub mod order;
+pub mod price_history;
+pub mod subscribe_prices;
+pub mod trigger;
 pub mod user_id;
+pub mod user_id_order_id;
 pub mod user_id_stock_symbol;
 pub mod user_id_stock_symbol_amount_created;
+pub mod validate_log;
 
 #[derive(PartialEq, Debug, clap::Subcommand, Clone)]
 pub enum LoadTestCommand {
@@ -26,16 +46,16 @@ pub enum LoadTestCommand {
     Quote(LoadTestUserIdStockSymbolCommand),
     /// Buy the dollar amount of the stock for the specified user at the current price.
     Buy(LoadTestUserIdStockSymbolAmountCommand),
-    /// Commits the most recently executed BUY command
-    CommitBuy(LoadTestUserIdCommand),
-    /// Cancels the most recently executed BUY Command
-    CancelBuy(LoadTestUserIdCommand),
+    /// Commits the given BUY order, or the most recently executed BUY command
+    CommitBuy(LoadTestUserIdOrderIdCommand),
+    /// Cancels the given BUY order, or the most recently executed BUY Command
+    CancelBuy(LoadTestUserIdOrderIdCommand),
     /// Sell the specified dollar mount of the stock currently held by the specified user at the current price.
     Sell(LoadTestUserIdStockSymbolAmountCommand),
-    /// Commits the most recently executed SELL command
-    CommitSell(LoadTestUserIdCommand),
-    /// Cancels the most recently executed SELL Command
-    CancelSell(LoadTestUserIdCommand),
+    /// Commits the given SELL order, or the most recently executed SELL command
+    CommitSell(LoadTestUserIdOrderIdCommand),
+    /// Cancels the given SELL order, or the most recently executed SELL Command
+    CancelSell(LoadTestUserIdOrderIdCommand),
     /// Sets a defined amount of the given stock to buy when the current stock price is less than or equal to the BUY_TRIGGER
     SetBuyAmount(LoadTestUserIdStockSymbolAmountCommand),
     /// Cancels a SET_BUY command issued for the given stock
@@ -48,16 +68,46 @@ pub enum LoadTestCommand {
     SetSellTrigger(LoadTestUserIdStockSymbolAmountCommand),
     /// Cancels the SET_SELL associated with the given stock and user
     CancelSetSell(LoadTestUserIdStockSymbolCommand),
+    /// Reserves the amount and arms a buy trigger at the price in one step
+    PlaceBuyTrigger(LoadTestPlaceTriggerCommand),
+    /// Reserves the shares and arms a sell trigger at the price in one step
+    PlaceSellTrigger(LoadTestPlaceTriggerCommand),
+    /// Amends the amount and/or price of a buy or sell trigger
+    UpdateTrigger(LoadTestUpdateTriggerCommand),
     /// Provides a summary to the client of the given user's transaction history and the current status of their accounts as well as any set buy or sell triggers and their parameters
     DisplaySummary(LoadTestUserIdCommand),
-    /// Print out to the specified file the complete set of transactions that have occurred in the system.
+    /// Stream the complete set of transactions that have occurred in the system into the specified local file.
     DumpLogFileName(LoadTestDumpLogFileName),
-    /// Print out the history of the users transactions to the user specified file
+    /// Stream the history of the users transactions into the specified local file
     DumpLogUser(LoadTestDumpLogUserIdFileName),
     /// Get the information about the user's current state
     GetUserInfo(LoadTestUserIdCommand),
-    /// Get a log file from the server
+    /// Show each holding's quantity, cost basis, market value and profit or loss
+    GetPortfolio(LoadTestUserIdCommand),
+    /// List the user's buys and sells waiting to be committed or cancelled
+    ListPendingOrders(LoadTestUserIdCommand),
+    /// Place a limit buy, limit sell, stop loss or trailing stop order, filled when the price reaches it
+    PlaceOrder(LoadTestPlaceOrderCommand),
+    /// Cancel an open order placed with PLACE_ORDER
+    CancelOrder(LoadTestCancelOrderCommand),
+    /// List the user's open orders placed with PLACE_ORDER
+    ListOrders(LoadTestUserIdCommand),
+    /// Place a limit order in the order book, matched against other users' orders
+    PlaceBookOrder(LoadTestPlaceBookOrderCommand),
+    /// Cancel an order resting in the order book
+    CancelBookOrder(LoadTestCancelBookOrderCommand),
+    /// Show the best prices on either side of a stock's order book
+    BookDepth(LoadTestBookDepthCommand),
+    /// Print every new stock price until interrupted
+    SubscribePrices(LoadTestSubscribePricesCommand),
+    /// Show the prices fetched for a stock, as ticks or OHLC candles
+    PriceHistory(LoadTestPriceHistoryCommand),
+    /// Print a user's fills, trigger executions, expiries and balance changes until interrupted
+    SubscribeAccountEvents(LoadTestUserIdCommand),
+    /// Get a log dump from the server, or list the available dumps
     File(LoadTestFileCommand),
+    /// Check the XML dump of the log against the course XSD and print every offending element
+    ValidateLog(LoadTestValidateLogCommand),
 }
 
 impl LoadTestCommand {
@@ -70,19 +120,19 @@ impl LoadTestCommand {
             LoadTestCommand::Buy(LoadTestUserIdStockSymbolAmountCommand { user_id, .. }) => {
                 Some(user_id.clone())
             }
-            LoadTestCommand::CommitBuy(LoadTestUserIdCommand { user_id, .. }) => {
+            LoadTestCommand::CommitBuy(LoadTestUserIdOrderIdCommand { user_id, .. }) => {
                 Some(user_id.clone())
             }
-            LoadTestCommand::CancelBuy(LoadTestUserIdCommand { user_id, .. }) => {
+            LoadTestCommand::CancelBuy(LoadTestUserIdOrderIdCommand { user_id, .. }) => {
                 Some(user_id.clone())
             }
             LoadTestCommand::Sell(LoadTestUserIdStockSymbolAmountCommand { user_id, .. }) => {
                 Some(user_id.clone())
             }
-            LoadTestCommand::CommitSell(LoadTestUserIdCommand { user_id, .. }) => {
+            LoadTestCommand::CommitSell(LoadTestUserIdOrderIdCommand { user_id, .. }) => {
                 Some(user_id.clone())
             }
-            LoadTestCommand::CancelSell(LoadTestUserIdCommand { user_id, .. }) => {
+            LoadTestCommand::CancelSell(LoadTestUserIdOrderIdCommand { user_id, .. }) => {
                 Some(user_id.clone())
             }
             LoadTestCommand::SetBuyAmount(LoadTestUserIdStockSymbolAmountCommand {
@@ -107,6 +157,15 @@ impl LoadTestCommand {
             LoadTestCommand::CancelSetSell(LoadTestUserIdStockSymbolCommand {
                 user_id, ..
             }) => Some(user_id.clone()),
+            LoadTestCommand::PlaceBuyTrigger(LoadTestPlaceTriggerCommand { user_id, .. }) => {
+                Some(user_id.clone())
+            }
+            LoadTestCommand::PlaceSellTrigger(LoadTestPlaceTriggerCommand { user_id, .. }) => {
+                Some(user_id.clone())
+            }
+            LoadTestCommand::UpdateTrigger(LoadTestUpdateTriggerCommand { user_id, .. }) => {
+                Some(user_id.clone())
+            }
             LoadTestCommand::DisplaySummary(LoadTestUserIdCommand { user_id, .. }) => {
                 Some(user_id.clone())
             }
@@ -117,7 +176,35 @@ impl LoadTestCommand {
             LoadTestCommand::GetUserInfo(LoadTestUserIdCommand { user_id, .. }) => {
                 Some(user_id.clone())
             }
+            LoadTestCommand::GetPortfolio(LoadTestUserIdCommand { user_id, .. }) => {
+                Some(user_id.clone())
+            }
+            LoadTestCommand::ListPendingOrders(LoadTestUserIdCommand { user_id, .. }) => {
+                Some(user_id.clone())
+            }
+            LoadTestCommand::PlaceOrder(LoadTestPlaceOrderCommand { user_id, .. }) => {
+                Some(user_id.clone())
+            }
+            LoadTestCommand::CancelOrder(LoadTestCancelOrderCommand { user_id, .. }) => {
+                Some(user_id.clone())
+            }
+            LoadTestCommand::ListOrders(LoadTestUserIdCommand { user_id, .. }) => {
+                Some(user_id.clone())
+            }
+            LoadTestCommand::PlaceBookOrder(LoadTestPlaceBookOrderCommand { user_id, .. }) => {
+                Some(user_id.clone())
+            }
+            LoadTestCommand::CancelBookOrder(LoadTestCancelBookOrderCommand {
+                user_id, ..
+            }) => Some(user_id.clone()),
+            LoadTestCommand::BookDepth(_) => None,
+            LoadTestCommand::SubscribePrices(_) => None,
+            LoadTestCommand::PriceHistory(_) => None,
+            LoadTestCommand::SubscribeAccountEvents(LoadTestUserIdCommand { user_id, .. }) => {
+                Some(user_id.clone())
+            }
             LoadTestCommand::File(_) => None,
+            LoadTestCommand::ValidateLog(_) => None,
         }
     }
 
@@ -193,19 +280,37 @@ impl LoadTestCommand {
                 .set_sell_amount(set_sell_amount)
                 .await
                 .map(|resp| debug!("{resp:?}")),
-            LoadTestCommand::DumpLogFileName(dump_log) => client
+            LoadTestCommand::DumpLogFileName(dump_log) => {
+                let file_name = dump_log.file_name.clone();
+                let dump = client.day_trader.stream_dump_log(dump_log).await?;
+                write_dump(dump.into_inner(), &file_name).await
+            }
+            LoadTestCommand::SetBuyTrigger(set_buy_trigger) => client
                 .day_trader
-                .dump_log(dump_log)
+                .set_buy_trigger(set_buy_trigger)
                 .await
                 .map(|resp| debug!("{resp:?}")),
-            LoadTestCommand::SetBuyTrigger(set_buy_trigger) => client
+            LoadTestCommand::DumpLogUser(dump_log_user) => {
+                let file_name = dump_log_user.file_name.clone();
+                let dump = client
+                    .day_trader
+                    .stream_dump_log_user(dump_log_user)
+                    .await?;
+                write_dump(dump.into_inner(), &file_name).await
+            }
+            LoadTestCommand::PlaceBuyTrigger(place_buy_trigger) => client
                 .day_trader
-                .set_buy_trigger(set_buy_trigger)
+                .place_buy_trigger(place_buy_trigger)
                 .await
                 .map(|resp| debug!("{resp:?}")),
-            LoadTestCommand::DumpLogUser(dump_log_user) => client
+            LoadTestCommand::PlaceSellTrigger(place_sell_trigger) => client
                 .day_trader
-                .dump_log_user(dump_log_user)
+                .place_sell_trigger(place_sell_trigger)
+                .await
+                .map(|resp| debug!("{resp:?}")),
+            LoadTestCommand::UpdateTrigger(update_trigger) => client
+                .day_trader
+                .update_trigger(update_trigger)
                 .await
                 .map(|resp| debug!("{resp:?}")),
             LoadTestCommand::GetUserInfo(get_user_info) => client
@@ -213,15 +318,230 @@ impl LoadTestCommand {
                 .get_user_info(get_user_info)
                 .await
                 .map(|resp| debug!("{resp:?}")),
-            LoadTestCommand::File(file) => client
+            LoadTestCommand::GetPortfolio(get_portfolio) => client
+                .day_trader
+                .get_portfolio(get_portfolio)
+                .await
+                .map(|resp| {
+                    let portfolio = resp.into_inner();
+                    for holding in portfolio.holdings {
+                        println!(
+                            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                            holding.stock_symbol,
+                            holding.quantity,
+                            holding.average_cost,
+                            holding.cost_basis,
+                            holding.price,
+                            holding.market_value,
+                            holding.unrealized_pnl,
+                            holding.realized_pnl
+                        );
+                    }
+                    println!(
+                        "total\t\t\t{}\t\t{}\t{}\t{}",
+                        portfolio.cost_basis,
+                        portfolio.market_value,
+                        portfolio.unrealized_pnl,
+                        portfolio.realized_pnl
+                    );
+                }),
+            LoadTestCommand::ListPendingOrders(list_pending_orders) => client
+                .day_trader
+                .list_pending_orders(list_pending_orders)
+                .await
+                .map(|resp| {
+                    for order in resp.into_inner().orders {
+                        println!(
+                            "{}\t{}\t{}\t{}\t{}",
+                            order.order_id,
+                            order.side().as_str_name(),
+                            order.stock_symbol,
+                            order.amount_dollars,
+                            order.quoted_price
+                        );
+                    }
+                }),
+            LoadTestCommand::PlaceOrder(place_order) => client
+                .day_trader
+                .place_order(place_order)
+                .await
+                .map(|resp| {
+                    let resp = resp.into_inner();
+                    println!("{}\t{}", resp.order_id, resp.status().as_str_name());
+                }),
+            LoadTestCommand::CancelOrder(cancel_order) => client
+                .day_trader
+                .cancel_order(cancel_order)
+                .await
+                .map(|resp| debug!("{resp:?}")),
+            LoadTestCommand::ListOrders(list_orders) => client
+                .day_trader
+                .list_orders(list_orders)
+                .await
+                .map(|resp| {
+                    for order in resp.into_inner().orders {
+                        println!(
+                            "{}\t{}\t{}\t{}\t{}\t{}",
+                            order.order_id,
+                            order.order_type().as_str_name(),
+                            order.time_in_force().as_str_name(),
+                            order.stock_symbol,
+                            order.amount,
+                            order.price
+                        );
+                    }
+                }),
+            LoadTestCommand::PlaceBookOrder(place_book_order) => client
+                .day_trader
+                .place_book_order(place_book_order)
+                .await
+                .map(|resp| {
+                    let resp = resp.into_inner();
+                    for fill in resp.fills {
+                        println!("{}\t{}\t{}", fill.order_id, fill.price, fill.quantity);
+                    }
+                    println!("{}\t{}", resp.order_id, resp.resting);
+                }),
+            LoadTestCommand::CancelBookOrder(cancel_book_order) => client
+                .day_trader
+                .cancel_book_order(cancel_book_order)
+                .await
+                .map(|resp| debug!("{resp:?}")),
+            LoadTestCommand::BookDepth(book_depth) => client
+                .day_trader
+                .get_book_depth(book_depth)
+                .await
+                .map(|resp| {
+                    let resp = resp.into_inner();
+                    for (side, levels) in [("BID", resp.bids), ("ASK", resp.asks)] {
+                        for level in levels {
+                            println!(
+                                "{side}\t{}\t{}\t{}",
+                                level.price, level.quantity, level.orders
+                            );
+                        }
+                    }
+                }),
+            LoadTestCommand::SubscribePrices(subscribe_prices) => {
+                let mut prices = client
+                    .day_trader
+                    .subscribe_prices(subscribe_prices)
+                    .await?
+                    .into_inner();
+                while let Some(price) = prices.message().await? {
+                    println!("{}\t{}", price.stock_symbol, price.price);
+                }
+                Ok(())
+            }
+            LoadTestCommand::PriceHistory(price_history) => client
                 .day_trader
-                .file(file)
+                .get_price_history(price_history)
+                .await
+                .map(|resp| {
+                    let history = resp.into_inner();
+                    for tick in history.ticks {
+                        println!("{}\t{}", tick.time_unix_seconds, tick.price);
+                    }
+                    for candle in history.candles {
+                        println!(
+                            "{}\t{}\t{}\t{}\t{}\t{}",
+                            candle.start_unix_seconds,
+                            candle.open,
+                            candle.high,
+                            candle.low,
+                            candle.close,
+                            candle.ticks
+                        );
+                    }
+                }),
+            LoadTestCommand::SubscribeAccountEvents(subscribe_account_events) => {
+                let mut events = client
+                    .day_trader
+                    .subscribe_account_events(subscribe_account_events)
+                    .await?
+                    .into_inner();
+                while let Some(event) = events.message().await? {
+                    println!(
+                        "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                        event.event_type().as_str_name(),
+                        event.stock_symbol,
+                        event.side().as_str_name(),
+                        event.order_id,
+                        event.shares,
+                        event.price,
+                        event.dollars,
+                        event.balance
+                    );
+                }
+                Ok(())
+            }
+            LoadTestCommand::File(LoadTestFileCommand { file: Some(file) }) => client
+                .day_trader
+                .file(FileRequest { filename: file })
                 .await
                 .map(|resp| debug!("{resp:?}")),
+            LoadTestCommand::File(LoadTestFileCommand { file: None }) => client
+                .day_trader
+                .list_dumps(ListDumpsRequest {})
+                .await
+                .map(|resp| {
+                    for dump in resp.into_inner().dumps {
+                        println!("{}\t{}", dump.name, dump.size_bytes);
+                    }
+                }),
+            LoadTestCommand::ValidateLog(validate_log) => {
+                let violations = client.day_trader.validate_log(validate_log).await?;
+                print_violations(violations.into_inner()).await
+            }
         }
     }
 }
 
+/// Writes a streamed dump to a local file as it arrives.
+async fn write_dump(mut dump: Streaming<DumpLogChunk>, file_name: &str) -> Result<(), Status> {
+    let write_error =
+        |e: std::io::Error| Status::internal(format!("failed to write {file_name}: {e}"));
+
+    let mut file = BufWriter::new(File::create(file_name).await.map_err(write_error)?);
+    let mut size = 0;
+    while let Some(DumpLogChunk { data }) = dump.message().await? {
+        size += data.len();
+        file.write_all(&data).await.map_err(write_error)?;
+    }
+    file.flush().await.map_err(write_error)?;
+
+    debug!("wrote {size} bytes to {file_name}");
+
+    Ok(())
+}
+
+/// Prints every violation as it arrives, failing if there were any.
+async fn print_violations(mut violations: Streaming<LogViolation>) -> Result<(), Status> {
+    let mut count = 0;
+    while let Some(violation) = violations.message().await? {
+        count += 1;
+        println!(
+            "{}\t{}\t{}\t{}\t{}/{}: {}\t{}",
+            violation.timestamp_unix_seconds,
+            violation.server,
+            violation.transaction_num,
+            violation.user_id,
+            violation.element,
+            violation.field,
+            violation.message,
+            violation.log
+        );
+    }
+
+    if count == 0 {
+        Ok(())
+    } else {
+        Err(Status::failed_precondition(format!(
+            "{count} elements do not conform to the XSD"
+        )))
+    }
+}
+
 impl TryFrom<&str> for LoadTestCommand {
     type Error = ParseLoadTestCommandError;
 
@@ -284,25 +604,31 @@ impl TryFrom<&str> for LoadTestCommand {
                         _ => unreachable!(),
                     }
                 }
-                str @ ("COMMIT_BUY" | "COMMIT_SELL" | "CANCEL_SELL" | "DISPLAY_SUMMARY"
-                | "CANCEL_BUY") => {
-                    let cmd =
-                        LoadTestUserIdCommand::try_from((request_num, iter)).map_err(|reason| {
-                            CommandParseFailure {
-                                command: str.to_string(),
-                                value: value.to_string(),
-                                reason,
-                            }
-                        })?;
+                str @ ("COMMIT_BUY" | "COMMIT_SELL" | "CANCEL_SELL" | "CANCEL_BUY") => {
+                    let cmd = LoadTestUserIdOrderIdCommand::try_from((request_num, iter)).map_err(
+                        |reason| CommandParseFailure {
+                            command: str.to_string(),
+                            value: value.to_string(),
+                            reason,
+                        },
+                    )?;
                     match str {
                         "COMMIT_BUY" => CommitBuy(cmd),
                         "COMMIT_SELL" => CommitSell(cmd),
                         "CANCEL_SELL" => CancelSell(cmd),
-                        "DISPLAY_SUMMARY" => DisplaySummary(cmd),
                         "CANCEL_BUY" => CancelBuy(cmd),
                         _ => unreachable!(),
                     }
                 }
+                "DISPLAY_SUMMARY" => DisplaySummary(
+                    LoadTestUserIdCommand::try_from((request_num, iter)).map_err(|reason| {
+                        CommandParseFailure {
+                            command: "DISPLAY_SUMMARY".to_string(),
+                            value: value.to_string(),
+                            reason,
+                        }
+                    })?,
+                ),
                 "DUMPLOG" => {
                     let cmd = DumpLog::try_from((request_num, iter)).map_err(|reason| {
                         CommandParseFailure {
@@ -17,7 +17,7 @@ impl IntoRequest<AddRequest> for LoadTestAdd {
     fn into_request(self) -> Request<AddRequest> {
         Request::new(AddRequest {
             user_id: self.user_id,
-            amount: self.amount,
+            amount: self.amount.to_string(),
             request_num: self.request_num,
         })
     }
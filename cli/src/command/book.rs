@@ -0,0 +1,68 @@
+use crate::command::order::LoadTestOrderSide;
+use crate::protos::{
+    CancelBookOrderRequest, GetBookDepthRequest, OrderSide, PlaceBookOrderRequest,
+};
+use tonic::{IntoRequest, Request};
+
+#[derive(PartialEq, Debug, clap::Args, Clone)]
+pub struct LoadTestPlaceBookOrderCommand {
+    pub user_id: String,
+    pub stock_symbol: String,
+    #[arg(value_enum)]
+    pub side: LoadTestOrderSide,
+    /// The highest price a buy pays, or the lowest a sell accepts
+    pub price: f64,
+    /// Shares to buy or sell
+    pub quantity: f64,
+    #[arg(default_value_t = -1)]
+    pub request_num: i32,
+}
+
+#[derive(PartialEq, Debug, clap::Args, Clone)]
+pub struct LoadTestCancelBookOrderCommand {
+    pub user_id: String,
+    /// The order returned by PLACE_BOOK_ORDER
+    pub order_id: i64,
+    #[arg(default_value_t = -1)]
+    pub request_num: i32,
+}
+
+#[derive(PartialEq, Debug, clap::Args, Clone)]
+pub struct LoadTestBookDepthCommand {
+    pub stock_symbol: String,
+    /// Price levels to show per side
+    #[arg(long, default_value_t = 10)]
+    pub levels: i32,
+}
+
+impl IntoRequest<PlaceBookOrderRequest> for LoadTestPlaceBookOrderCommand {
+    fn into_request(self) -> Request<PlaceBookOrderRequest> {
+        Request::new(PlaceBookOrderRequest {
+            user_id: self.user_id,
+            stock_symbol: self.stock_symbol,
+            side: OrderSide::from(self.side).into(),
+            price: self.price.to_string(),
+            quantity: self.quantity.to_string(),
+            request_num: self.request_num,
+        })
+    }
+}
+
+impl IntoRequest<CancelBookOrderRequest> for LoadTestCancelBookOrderCommand {
+    fn into_request(self) -> Request<CancelBookOrderRequest> {
+        Request::new(CancelBookOrderRequest {
+            user_id: self.user_id,
+            order_id: self.order_id,
+            request_num: self.request_num,
+        })
+    }
+}
+
+impl IntoRequest<GetBookDepthRequest> for LoadTestBookDepthCommand {
+    fn into_request(self) -> Request<GetBookDepthRequest> {
+        Request::new(GetBookDepthRequest {
+            stock_symbol: self.stock_symbol,
+            levels: self.levels,
+        })
+    }
+}
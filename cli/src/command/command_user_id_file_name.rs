@@ -1,14 +1,24 @@
-use crate::protos::{DumpLogRequest, DumpLogUserRequest};
+use crate::command::log_filter::{LoadTestDumpFormat, LoadTestLogFilter};
+use crate::protos::{DumpFormat, DumpLogRequest, DumpLogUserRequest};
 use proptest_derive::Arbitrary;
 use tonic::{IntoRequest, Request};
 
 #[derive(Clone, Debug, clap::Args, PartialEq, Arbitrary)]
 pub struct LoadTestDumpLogUserIdFileName {
     pub user_id: String,
+    /// the local file to write the dump to
     #[arg(default_value_t = String::from("log.xml"))]
+    #[proptest(regex = "fuzz-dump-[a-z]\\.xml")]
     pub file_name: String,
     #[arg(default_value_t = -1)]
     pub request_num: i32,
+    /// users in the filter are ignored, only the user's own entries are dumped
+    #[command(flatten)]
+    #[proptest(value = "LoadTestLogFilter::default()")]
+    pub filter: LoadTestLogFilter,
+    #[arg(long, value_enum, default_value_t)]
+    #[proptest(value = "LoadTestDumpFormat::default()")]
+    pub format: LoadTestDumpFormat,
 }
 
 impl IntoRequest<DumpLogUserRequest> for LoadTestDumpLogUserIdFileName {
@@ -17,6 +27,8 @@ impl IntoRequest<DumpLogUserRequest> for LoadTestDumpLogUserIdFileName {
             user_id: self.user_id,
             filename: self.file_name,
             request_num: self.request_num,
+            filter: Some(self.filter.into()),
+            format: DumpFormat::from(self.format).into(),
         })
     }
 }
@@ -26,6 +38,8 @@ impl IntoRequest<DumpLogRequest> for LoadTestDumpLogUserIdFileName {
         Request::new(DumpLogRequest {
             filename: self.file_name,
             request_num: self.request_num,
+            filter: Some(self.filter.into()),
+            format: DumpFormat::from(self.format).into(),
         })
     }
 }
@@ -1,5 +1,6 @@
 use crate::command::command_user_id_file_name::LoadTestDumpLogUserIdFileName;
-use crate::protos::DumpLogRequest;
+use crate::command::log_filter::{LoadTestDumpFormat, LoadTestLogFilter};
+use crate::protos::{DumpFormat, DumpLogRequest};
 use crate::split_ext::CommandParseIterExt;
 use crate::CommandParseFailure;
 use proptest_derive::Arbitrary;
@@ -23,12 +24,16 @@ impl TryFrom<(i32, Split<'_, char>)> for DumpLog {
             None => Ok(DumpLog::NoUser(LoadTestDumpLogFileName {
                 file_name: arg1,
                 request_num,
+                filter: LoadTestLogFilter::default(),
+                format: LoadTestDumpFormat::default(),
             })),
             Some(filename) => {
                 let command = DumpLog::User(LoadTestDumpLogUserIdFileName {
                     user_id: arg1,
                     file_name: filename.to_string(),
                     request_num,
+                    filter: LoadTestLogFilter::default(),
+                    format: LoadTestDumpFormat::default(),
                 });
                 value.require_finished(2).map(|_| command)
             }
@@ -38,9 +43,17 @@ impl TryFrom<(i32, Split<'_, char>)> for DumpLog {
 
 #[derive(Clone, Debug, clap::Args, PartialEq, Arbitrary)]
 pub struct LoadTestDumpLogFileName {
+    /// the local file to write the dump to
+    #[proptest(regex = "fuzz-dump-[a-z]\\.xml")]
     pub file_name: String,
     #[arg(default_value_t = -1)]
     pub request_num: i32,
+    #[command(flatten)]
+    #[proptest(value = "LoadTestLogFilter::default()")]
+    pub filter: LoadTestLogFilter,
+    #[arg(long, value_enum, default_value_t)]
+    #[proptest(value = "LoadTestDumpFormat::default()")]
+    pub format: LoadTestDumpFormat,
 }
 
 impl IntoRequest<DumpLogRequest> for LoadTestDumpLogFileName {
@@ -48,6 +61,8 @@ impl IntoRequest<DumpLogRequest> for LoadTestDumpLogFileName {
         Request::new(DumpLogRequest {
             filename: self.file_name,
             request_num: self.request_num,
+            filter: Some(self.filter.into()),
+            format: DumpFormat::from(self.format).into(),
         })
     }
 }
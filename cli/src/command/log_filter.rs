@@ -0,0 +1,61 @@
+use crate::protos::{DumpFormat, LogFilter};
+
+#[derive(Clone, Debug, Default, clap::Args, PartialEq)]
+pub struct LoadTestLogFilter {
+    /// only dump entries at or after this unix timestamp (seconds)
+    #[arg(long, default_value_t = 0)]
+    pub from: i64,
+    /// only dump entries before this unix timestamp (seconds)
+    #[arg(long, default_value_t = 0)]
+    pub to: i64,
+    /// only dump these kinds of entries, e.g. ErrorMessages or AccountChanges
+    #[arg(long = "log-type")]
+    pub log_types: Vec<String>,
+    /// only dump entries for these commands, e.g. Add or CommitBuy
+    #[arg(long = "command")]
+    pub commands: Vec<String>,
+    /// only dump entries of these users
+    #[arg(long = "user")]
+    pub user_ids: Vec<String>,
+}
+
+impl From<LoadTestLogFilter> for LogFilter {
+    fn from(
+        LoadTestLogFilter {
+            from,
+            to,
+            log_types,
+            commands,
+            user_ids,
+        }: LoadTestLogFilter,
+    ) -> Self {
+        LogFilter {
+            from_unix_seconds: from,
+            to_unix_seconds: to,
+            log_types,
+            commands,
+            user_ids,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, clap::ValueEnum, PartialEq)]
+pub enum LoadTestDumpFormat {
+    /// the course XSD
+    #[default]
+    Xml,
+    /// one JSON object per line
+    JsonLines,
+    /// a header row, then one row per entry
+    Csv,
+}
+
+impl From<LoadTestDumpFormat> for DumpFormat {
+    fn from(value: LoadTestDumpFormat) -> Self {
+        match value {
+            LoadTestDumpFormat::Xml => DumpFormat::Xml,
+            LoadTestDumpFormat::JsonLines => DumpFormat::JsonLines,
+            LoadTestDumpFormat::Csv => DumpFormat::Csv,
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use crate::protos::{CancelOrderRequest, OrderSide, OrderType, PlaceOrderRequest, TimeInForce};
+use tonic::{IntoRequest, Request};
+
+#[derive(clap::ValueEnum, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LoadTestOrderSide {
+    Buy,
+    Sell,
+}
+
+#[derive(clap::ValueEnum, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LoadTestOrderType {
+    /// Buy once the price is at or below PRICE
+    LimitBuy,
+    /// Sell once the price is at or above PRICE
+    LimitSell,
+    /// Sell once the price is at or below PRICE
+    StopLoss,
+    /// Sell once the price is PRICE below its highest since the order was placed
+    TrailingStop,
+}
+
+#[derive(clap::ValueEnum, Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum LoadTestTimeInForce {
+    /// Good till cancelled
+    #[default]
+    Gtc,
+    /// Cancelled at the end of the UTC day
+    Day,
+    /// Filled at the current price or not at all
+    Ioc,
+}
+
+#[derive(PartialEq, Debug, clap::Args, Clone)]
+pub struct LoadTestPlaceOrderCommand {
+    pub user_id: String,
+    pub stock_symbol: String,
+    #[arg(value_enum)]
+    pub order_type: LoadTestOrderType,
+    /// Dollars to spend for a limit buy, shares to sell for every other type
+    pub amount: f64,
+    /// The limit price, the stop price of a stop loss, or the trail amount of a trailing stop
+    pub price: f64,
+    #[arg(long, value_enum, default_value_t)]
+    pub time_in_force: LoadTestTimeInForce,
+    #[arg(default_value_t = -1)]
+    pub request_num: i32,
+}
+
+#[derive(PartialEq, Debug, clap::Args, Clone)]
+pub struct LoadTestCancelOrderCommand {
+    pub user_id: String,
+    /// The order returned by PLACE_ORDER
+    pub order_id: i64,
+    #[arg(default_value_t = -1)]
+    pub request_num: i32,
+}
+
+impl From<LoadTestOrderSide> for OrderSide {
+    fn from(value: LoadTestOrderSide) -> Self {
+        match value {
+            LoadTestOrderSide::Buy => OrderSide::Buy,
+            LoadTestOrderSide::Sell => OrderSide::Sell,
+        }
+    }
+}
+
+impl From<LoadTestOrderType> for OrderType {
+    fn from(value: LoadTestOrderType) -> Self {
+        match value {
+            LoadTestOrderType::LimitBuy => OrderType::LimitBuy,
+            LoadTestOrderType::LimitSell => OrderType::LimitSell,
+            LoadTestOrderType::StopLoss => OrderType::StopLoss,
+            LoadTestOrderType::TrailingStop => OrderType::TrailingStop,
+        }
+    }
+}
+
+impl From<LoadTestTimeInForce> for TimeInForce {
+    fn from(value: LoadTestTimeInForce) -> Self {
+        match value {
+            LoadTestTimeInForce::Gtc => TimeInForce::Gtc,
+            LoadTestTimeInForce::Day => TimeInForce::Day,
+            LoadTestTimeInForce::Ioc => TimeInForce::Ioc,
+        }
+    }
+}
+
+impl IntoRequest<PlaceOrderRequest> for LoadTestPlaceOrderCommand {
+    fn into_request(self) -> Request<PlaceOrderRequest> {
+        Request::new(PlaceOrderRequest {
+            user_id: self.user_id,
+            stock_symbol: self.stock_symbol,
+            order_type: OrderType::from(self.order_type).into(),
+            time_in_force: TimeInForce::from(self.time_in_force).into(),
+            amount: self.amount.to_string(),
+            price: self.price.to_string(),
+            request_num: self.request_num,
+        })
+    }
+}
+
+impl IntoRequest<CancelOrderRequest> for LoadTestCancelOrderCommand {
+    fn into_request(self) -> Request<CancelOrderRequest> {
+        Request::new(CancelOrderRequest {
+            user_id: self.user_id,
+            order_id: self.order_id,
+            request_num: self.request_num,
+        })
+    }
+}
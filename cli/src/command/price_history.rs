@@ -0,0 +1,60 @@
+use crate::protos::{CandleInterval, GetPriceHistoryRequest};
+use tonic::{IntoRequest, Request};
+
+#[derive(clap::ValueEnum, Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum LoadTestCandleInterval {
+    /// Every price as fetched
+    #[default]
+    Tick,
+    #[value(name = "1m")]
+    OneMinute,
+    #[value(name = "5m")]
+    FiveMinutes,
+    #[value(name = "15m")]
+    FifteenMinutes,
+    #[value(name = "1h")]
+    OneHour,
+    #[value(name = "1d")]
+    OneDay,
+}
+
+#[derive(PartialEq, Debug, clap::Args, Clone)]
+pub struct LoadTestPriceHistoryCommand {
+    pub stock_symbol: String,
+    #[arg(long, value_enum, default_value_t)]
+    pub interval: LoadTestCandleInterval,
+    /// only prices at or after this unix timestamp (seconds)
+    #[arg(long, default_value_t = 0)]
+    pub from: i64,
+    /// only prices before this unix timestamp (seconds)
+    #[arg(long, default_value_t = 0)]
+    pub to: i64,
+    /// How many of the most recent ticks or candles to show, 0 for the server's default
+    #[arg(long, default_value_t = 0)]
+    pub limit: i32,
+}
+
+impl From<LoadTestCandleInterval> for CandleInterval {
+    fn from(value: LoadTestCandleInterval) -> Self {
+        match value {
+            LoadTestCandleInterval::Tick => CandleInterval::Tick,
+            LoadTestCandleInterval::OneMinute => CandleInterval::OneMinute,
+            LoadTestCandleInterval::FiveMinutes => CandleInterval::FiveMinutes,
+            LoadTestCandleInterval::FifteenMinutes => CandleInterval::FifteenMinutes,
+            LoadTestCandleInterval::OneHour => CandleInterval::OneHour,
+            LoadTestCandleInterval::OneDay => CandleInterval::OneDay,
+        }
+    }
+}
+
+impl IntoRequest<GetPriceHistoryRequest> for LoadTestPriceHistoryCommand {
+    fn into_request(self) -> Request<GetPriceHistoryRequest> {
+        Request::new(GetPriceHistoryRequest {
+            stock_symbol: self.stock_symbol,
+            from_unix_seconds: self.from,
+            to_unix_seconds: self.to,
+            interval: CandleInterval::from(self.interval).into(),
+            limit: self.limit,
+        })
+    }
+}
@@ -0,0 +1,16 @@
+use crate::protos::SubscribePricesRequest;
+use tonic::{IntoRequest, Request};
+
+#[derive(PartialEq, Debug, clap::Args, Clone)]
+pub struct LoadTestSubscribePricesCommand {
+    /// Only print these symbols, every symbol if none are given
+    pub stock_symbols: Vec<String>,
+}
+
+impl IntoRequest<SubscribePricesRequest> for LoadTestSubscribePricesCommand {
+    fn into_request(self) -> Request<SubscribePricesRequest> {
+        Request::new(SubscribePricesRequest {
+            stock_symbols: self.stock_symbols,
+        })
+    }
+}
@@ -0,0 +1,72 @@
+use crate::command::order::LoadTestOrderSide;
+use crate::protos::{
+    OrderSide, PlaceBuyTriggerRequest, PlaceSellTriggerRequest, UpdateTriggerRequest,
+};
+use tonic::{IntoRequest, Request};
+
+#[derive(PartialEq, Debug, clap::Args, Clone)]
+pub struct LoadTestPlaceTriggerCommand {
+    pub user_id: String,
+    pub stock_symbol: String,
+    /// Dollars to spend for a buy trigger, shares to sell for a sell trigger
+    pub amount: f64,
+    pub trigger_price: f64,
+    #[arg(default_value_t = -1)]
+    pub request_num: i32,
+}
+
+#[derive(PartialEq, Debug, clap::Args, Clone)]
+pub struct LoadTestUpdateTriggerCommand {
+    pub user_id: String,
+    pub stock_symbol: String,
+    #[arg(value_enum)]
+    pub side: LoadTestOrderSide,
+    /// The new amount, left unchanged if not given
+    #[arg(long)]
+    pub amount: Option<f64>,
+    /// The new trigger price, left unchanged if not given
+    #[arg(long)]
+    pub trigger_price: Option<f64>,
+    #[arg(default_value_t = -1)]
+    pub request_num: i32,
+}
+
+impl IntoRequest<PlaceBuyTriggerRequest> for LoadTestPlaceTriggerCommand {
+    fn into_request(self) -> Request<PlaceBuyTriggerRequest> {
+        Request::new(PlaceBuyTriggerRequest {
+            user_id: self.user_id,
+            stock_symbol: self.stock_symbol,
+            amount: self.amount.to_string(),
+            trigger_price: self.trigger_price.to_string(),
+            request_num: self.request_num,
+        })
+    }
+}
+
+impl IntoRequest<PlaceSellTriggerRequest> for LoadTestPlaceTriggerCommand {
+    fn into_request(self) -> Request<PlaceSellTriggerRequest> {
+        Request::new(PlaceSellTriggerRequest {
+            user_id: self.user_id,
+            stock_symbol: self.stock_symbol,
+            amount: self.amount.to_string(),
+            trigger_price: self.trigger_price.to_string(),
+            request_num: self.request_num,
+        })
+    }
+}
+
+impl IntoRequest<UpdateTriggerRequest> for LoadTestUpdateTriggerCommand {
+    fn into_request(self) -> Request<UpdateTriggerRequest> {
+        Request::new(UpdateTriggerRequest {
+            user_id: self.user_id,
+            stock_symbol: self.stock_symbol,
+            side: OrderSide::from(self.side).into(),
+            amount: self.amount.map(|it| it.to_string()).unwrap_or_default(),
+            trigger_price: self
+                .trigger_price
+                .map(|it| it.to_string())
+                .unwrap_or_default(),
+            request_num: self.request_num,
+        })
+    }
+}
@@ -1,6 +1,6 @@
 use crate::protos::{
-    CancelBuyRequest, CancelSellRequest, CommitBuyRequest, CommitSellRequest,
-    DisplaySummaryRequest, GetUserInfoRequest,
+    DisplaySummaryRequest, GetPortfolioRequest, GetUserInfoRequest, ListOrdersRequest,
+    ListPendingOrdersRequest, SubscribeAccountEventsRequest,
 };
 use crate::split_ext::CommandParseIterExt;
 use crate::CommandParseFailure;
@@ -27,54 +27,50 @@ impl TryFrom<(i32, Split<'_, char>)> for LoadTestUserIdCommand {
     }
 }
 
-impl IntoRequest<CommitBuyRequest> for LoadTestUserIdCommand {
-    fn into_request(self) -> Request<CommitBuyRequest> {
-        Request::new(CommitBuyRequest {
+impl IntoRequest<DisplaySummaryRequest> for LoadTestUserIdCommand {
+    fn into_request(self) -> Request<DisplaySummaryRequest> {
+        Request::new(DisplaySummaryRequest {
             user_id: self.user_id,
             request_num: self.request_num,
         })
     }
 }
 
-impl IntoRequest<CommitSellRequest> for LoadTestUserIdCommand {
-    fn into_request(self) -> Request<CommitSellRequest> {
-        Request::new(CommitSellRequest {
+impl IntoRequest<GetUserInfoRequest> for LoadTestUserIdCommand {
+    fn into_request(self) -> Request<GetUserInfoRequest> {
+        Request::new(GetUserInfoRequest {
             user_id: self.user_id,
-            request_num: self.request_num,
         })
     }
 }
 
-impl IntoRequest<CancelSellRequest> for LoadTestUserIdCommand {
-    fn into_request(self) -> Request<CancelSellRequest> {
-        Request::new(CancelSellRequest {
+impl IntoRequest<GetPortfolioRequest> for LoadTestUserIdCommand {
+    fn into_request(self) -> Request<GetPortfolioRequest> {
+        Request::new(GetPortfolioRequest {
             user_id: self.user_id,
-            request_num: self.request_num,
         })
     }
 }
 
-impl IntoRequest<DisplaySummaryRequest> for LoadTestUserIdCommand {
-    fn into_request(self) -> Request<DisplaySummaryRequest> {
-        Request::new(DisplaySummaryRequest {
+impl IntoRequest<ListPendingOrdersRequest> for LoadTestUserIdCommand {
+    fn into_request(self) -> Request<ListPendingOrdersRequest> {
+        Request::new(ListPendingOrdersRequest {
             user_id: self.user_id,
-            request_num: self.request_num,
         })
     }
 }
 
-impl IntoRequest<CancelBuyRequest> for LoadTestUserIdCommand {
-    fn into_request(self) -> Request<CancelBuyRequest> {
-        Request::new(CancelBuyRequest {
+impl IntoRequest<ListOrdersRequest> for LoadTestUserIdCommand {
+    fn into_request(self) -> Request<ListOrdersRequest> {
+        Request::new(ListOrdersRequest {
             user_id: self.user_id,
-            request_num: self.request_num,
         })
     }
 }
 
-impl IntoRequest<GetUserInfoRequest> for LoadTestUserIdCommand {
-    fn into_request(self) -> Request<GetUserInfoRequest> {
-        Request::new(GetUserInfoRequest {
+impl IntoRequest<SubscribeAccountEventsRequest> for LoadTestUserIdCommand {
+    fn into_request(self) -> Request<SubscribeAccountEventsRequest> {
+        Request::new(SubscribeAccountEventsRequest {
             user_id: self.user_id,
         })
     }
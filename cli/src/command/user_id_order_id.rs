@@ -0,0 +1,70 @@
+use crate::protos::{CancelBuyRequest, CancelSellRequest, CommitBuyRequest, CommitSellRequest};
+use crate::split_ext::CommandParseIterExt;
+use crate::CommandParseFailure;
+use proptest_derive::Arbitrary;
+use std::str::Split;
+use tonic::{IntoRequest, Request};
+
+#[derive(Debug, PartialEq, clap::Args, Clone, Arbitrary)]
+pub struct LoadTestUserIdOrderIdCommand {
+    pub user_id: String,
+    #[arg(default_value_t = -1)]
+    pub request_num: i32,
+    /// the order returned by BUY or SELL, the most recent one if omitted
+    #[arg(long, default_value_t = 0)]
+    #[proptest(value = "0")]
+    pub order_id: i64,
+}
+
+impl TryFrom<(i32, Split<'_, char>)> for LoadTestUserIdOrderIdCommand {
+    type Error = CommandParseFailure;
+
+    fn try_from((request_num, mut value): (i32, Split<'_, char>)) -> Result<Self, Self::Error> {
+        let command = LoadTestUserIdOrderIdCommand {
+            request_num,
+            user_id: value.user_id(0)?,
+            order_id: 0,
+        };
+        value.require_finished(1).map(|_| command)
+    }
+}
+
+impl IntoRequest<CommitBuyRequest> for LoadTestUserIdOrderIdCommand {
+    fn into_request(self) -> Request<CommitBuyRequest> {
+        Request::new(CommitBuyRequest {
+            user_id: self.user_id,
+            request_num: self.request_num,
+            order_id: self.order_id,
+        })
+    }
+}
+
+impl IntoRequest<CommitSellRequest> for LoadTestUserIdOrderIdCommand {
+    fn into_request(self) -> Request<CommitSellRequest> {
+        Request::new(CommitSellRequest {
+            user_id: self.user_id,
+            request_num: self.request_num,
+            order_id: self.order_id,
+        })
+    }
+}
+
+impl IntoRequest<CancelSellRequest> for LoadTestUserIdOrderIdCommand {
+    fn into_request(self) -> Request<CancelSellRequest> {
+        Request::new(CancelSellRequest {
+            user_id: self.user_id,
+            request_num: self.request_num,
+            order_id: self.order_id,
+        })
+    }
+}
+
+impl IntoRequest<CancelBuyRequest> for LoadTestUserIdOrderIdCommand {
+    fn into_request(self) -> Request<CancelBuyRequest> {
+        Request::new(CancelBuyRequest {
+            user_id: self.user_id,
+            request_num: self.request_num,
+            order_id: self.order_id,
+        })
+    }
+}
@@ -22,7 +22,7 @@ impl IntoRequest<BuyRequest> for LoadTestUserIdStockSymbolAmountCommand {
         Request::new(BuyRequest {
             user_id: self.user_id,
             stock_symbol: self.stock_symbol,
-            amount: self.amount,
+            amount: self.amount.to_string(),
             request_num: self.request_num,
         })
     }
@@ -33,7 +33,7 @@ impl IntoRequest<SetBuyAmountRequest> for LoadTestUserIdStockSymbolAmountCommand
         Request::new(SetBuyAmountRequest {
             user_id: self.user_id,
             stock_symbol: self.stock_symbol,
-            amount: self.amount,
+            amount: self.amount.to_string(),
             request_num: self.request_num,
         })
     }
@@ -44,7 +44,7 @@ impl IntoRequest<SellRequest> for LoadTestUserIdStockSymbolAmountCommand {
         Request::new(SellRequest {
             user_id: self.user_id,
             stock_symbol: self.stock_symbol,
-            amount: self.amount,
+            amount: self.amount.to_string(),
             request_num: self.request_num,
         })
     }
@@ -55,7 +55,7 @@ impl IntoRequest<SetSellAmountRequest> for LoadTestUserIdStockSymbolAmountComman
         Request::new(SetSellAmountRequest {
             user_id: self.user_id,
             stock_symbol: self.stock_symbol,
-            amount: self.amount,
+            amount: self.amount.to_string(),
             request_num: self.request_num,
         })
     }
@@ -66,7 +66,7 @@ impl IntoRequest<SetSellTriggerRequest> for LoadTestUserIdStockSymbolAmountComma
         Request::new(SetSellTriggerRequest {
             user_id: self.user_id,
             stock_symbol: self.stock_symbol,
-            amount: self.amount,
+            amount: self.amount.to_string(),
             request_num: self.request_num,
         })
     }
@@ -77,7 +77,7 @@ impl IntoRequest<SetBuyTriggerRequest> for LoadTestUserIdStockSymbolAmountComman
         Request::new(SetBuyTriggerRequest {
             user_id: self.user_id,
             stock_symbol: self.stock_symbol,
-            amount: self.amount,
+            amount: self.amount.to_string(),
             request_num: self.request_num,
         })
     }
@@ -0,0 +1,17 @@
+use crate::command::log_filter::LoadTestLogFilter;
+use crate::protos::ValidateLogRequest;
+use tonic::{IntoRequest, Request};
+
+#[derive(Clone, Debug, Default, clap::Args, PartialEq)]
+pub struct LoadTestValidateLogCommand {
+    #[command(flatten)]
+    pub filter: LoadTestLogFilter,
+}
+
+impl IntoRequest<ValidateLogRequest> for LoadTestValidateLogCommand {
+    fn into_request(self) -> Request<ValidateLogRequest> {
+        Request::new(ValidateLogRequest {
+            filter: Some(self.filter.into()),
+        })
+    }
+}
@@ -2,6 +2,7 @@ use crate::command::add::LoadTestAdd;
 use crate::command::command_user_id_file_name::LoadTestDumpLogUserIdFileName;
 use crate::command::dump_log::LoadTestDumpLogFileName;
 use crate::command::user_id::LoadTestUserIdCommand;
+use crate::command::user_id_order_id::LoadTestUserIdOrderIdCommand;
 use crate::command::user_id_stock_symbol::LoadTestUserIdStockSymbolCommand;
 use crate::command::user_id_stock_symbol_amount_created::LoadTestUserIdStockSymbolAmountCommand;
 use crate::command::LoadTestCommand;
@@ -24,19 +25,19 @@ impl Arbitrary for LoadTestCommand {
                 LoadTestCommandType::Buy => any::<LoadTestUserIdStockSymbolAmountCommand>()
                     .prop_map(LoadTestCommand::Buy)
                     .sboxed(),
-                LoadTestCommandType::CommitBuy => any::<LoadTestUserIdCommand>()
+                LoadTestCommandType::CommitBuy => any::<LoadTestUserIdOrderIdCommand>()
                     .prop_map(LoadTestCommand::CommitBuy)
                     .sboxed(),
-                LoadTestCommandType::CancelBuy => any::<LoadTestUserIdCommand>()
+                LoadTestCommandType::CancelBuy => any::<LoadTestUserIdOrderIdCommand>()
                     .prop_map(LoadTestCommand::CancelBuy)
                     .sboxed(),
                 LoadTestCommandType::Sell => any::<LoadTestUserIdStockSymbolAmountCommand>()
                     .prop_map(LoadTestCommand::Sell)
                     .sboxed(),
-                LoadTestCommandType::CommitSell => any::<LoadTestUserIdCommand>()
+                LoadTestCommandType::CommitSell => any::<LoadTestUserIdOrderIdCommand>()
                     .prop_map(LoadTestCommand::CommitSell)
                     .sboxed(),
-                LoadTestCommandType::CancelSell => any::<LoadTestUserIdCommand>()
+                LoadTestCommandType::CancelSell => any::<LoadTestUserIdOrderIdCommand>()
                     .prop_map(LoadTestCommand::CancelSell)
                     .sboxed(),
                 LoadTestCommandType::SetBuyAmount => {
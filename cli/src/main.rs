@@ -15,7 +15,7 @@ use tracing_subscriber::EnvFilter;
 
 use cli::command::LoadTestCommand;
 use cli::fuzz::LoadTestCommandType;
-use cli::services::DayTraderServicesStack;
+use cli::services::{DayTraderServicesStack, SessionToken};
 
 const DEFAULT_RUST_LOG: &str = "none,cli=debug";
 
@@ -41,9 +41,13 @@ async fn main() -> Result<(), anyhow::Error> {
         .connect()
         .await?;
 
-    let stack = DayTraderServicesStack::new(&channel);
+    let stack = DayTraderServicesStack::new(&channel, SessionToken::new(args.token.as_deref())?);
 
     match commands {
+        CommandList::Login { user_id, password } => {
+            let token = stack.clone().login(user_id, password).await?;
+            println!("{token}");
+        }
         CommandList::Fuzz(cases, strat) => {
             let (send, mut recv) = tokio::sync::mpsc::channel::<(
                 LoadTestCommand,
@@ -119,6 +123,9 @@ struct CliArgs {
     /// The uri of the gRPC services.
     #[arg(default_value_t = String::from("http://localhost:80"))]
     services_uri: String,
+    /// The session token to authenticate requests with, as printed by `login`.
+    #[arg(long, env = "DAY_TRADER_TOKEN")]
+    token: Option<String>,
     #[command(subcommand)]
     command: CliCommand,
 }
@@ -135,6 +142,8 @@ enum CliCommand {
     },
     /// Fuzz the API
     Fuzz(Fuzz),
+    /// Log in and print a session token.
+    Login { user_id: String, password: String },
 }
 
 #[derive(clap::ValueEnum, Clone, Debug, PartialEq, Copy)]
@@ -252,6 +261,7 @@ fn spawn_commands(
 }
 
 enum CommandList {
+    Login { user_id: String, password: String },
     Fuzz(u32, SBoxedStrategy<LoadTestCommand>),
     List(Vec<LoadTestCommand>, Mode),
 }
@@ -269,6 +279,10 @@ fn command_list_from_cli_command(command: &CliCommand) -> Result<CommandList, an
             };
             CommandList::Fuzz(number.to_owned(), any_with::<LoadTestCommand>(types))
         }
+        CliCommand::Login { user_id, password } => CommandList::Login {
+            user_id: user_id.clone(),
+            password: password.clone(),
+        },
     })
 }
 
@@ -298,8 +312,17 @@ fn parse_commands_from_file(file: &Path) -> Result<Vec<LoadTestCommand>, anyhow:
 mod tests {
     use super::*;
     use cli::command::add::LoadTestAdd;
+    use cli::command::book::LoadTestPlaceBookOrderCommand;
     use cli::command::command_user_id_file_name::LoadTestDumpLogUserIdFileName;
     use cli::command::dump_log::LoadTestDumpLogFileName;
+    use cli::command::log_filter::{LoadTestDumpFormat, LoadTestLogFilter};
+    use cli::command::order::{
+        LoadTestOrderSide, LoadTestOrderType, LoadTestPlaceOrderCommand, LoadTestTimeInForce,
+    };
+    use cli::command::price_history::{LoadTestCandleInterval, LoadTestPriceHistoryCommand};
+    use cli::command::trigger::LoadTestUpdateTriggerCommand;
+    use cli::command::user_id_order_id::LoadTestUserIdOrderIdCommand;
+    use cli::command::validate_log::LoadTestValidateLogCommand;
     use cli::CommandParseFailure::{FloatParseError, MissingArgs};
     use cli::ParseLoadTestCommandError;
     use cli::ParseLoadTestCommandError::{MissingSpace, UnknownCommand};
@@ -387,6 +410,8 @@ mod tests {
             Ok(LoadTestCommand::DumpLogFileName(LoadTestDumpLogFileName {
                 file_name: "abc.xml".to_string(),
                 request_num: 1,
+                filter: LoadTestLogFilter::default(),
+                format: LoadTestDumpFormat::default(),
             })),
             LoadTestCommand::try_from("[1] DUMPLOG,abc.xml")
         );
@@ -400,9 +425,219 @@ mod tests {
                     user_id: "hello".to_string(),
                     file_name: "abc.xml".to_string(),
                     request_num: 1,
+                    filter: LoadTestLogFilter::default(),
+                    format: LoadTestDumpFormat::default(),
                 }
             )),
             LoadTestCommand::try_from("[1] DUMPLOG,hello,abc.xml")
         );
     }
+
+    #[test]
+    fn parse_login_with_token() {
+        let args = CliArgs::try_parse_from([
+            "cli",
+            "--token",
+            "abc",
+            "http://localhost:8000",
+            "login",
+            "marcus",
+            "hunter2",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            args,
+            CliArgs {
+                services_uri: "http://localhost:8000".to_string(),
+                token: Some("abc".to_string()),
+                command: CliCommand::Login {
+                    user_id: "marcus".to_string(),
+                    password: "hunter2".to_string(),
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn parse_dump_log_filters() {
+        let args = CliArgs::try_parse_from([
+            "cli",
+            "dump-log-file-name",
+            "errors.xml",
+            "--from",
+            "1609459200",
+            "--log-type",
+            "ErrorMessages",
+            "--command",
+            "Buy",
+            "--command",
+            "Sell",
+            "--format",
+            "json-lines",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            args.command,
+            CliCommand::Single(LoadTestCommand::DumpLogFileName(LoadTestDumpLogFileName {
+                file_name: "errors.xml".to_string(),
+                request_num: -1,
+                filter: LoadTestLogFilter {
+                    from: 1609459200,
+                    to: 0,
+                    log_types: vec!["ErrorMessages".to_string()],
+                    commands: vec!["Buy".to_string(), "Sell".to_string()],
+                    user_ids: vec![],
+                },
+                format: LoadTestDumpFormat::JsonLines,
+            }))
+        );
+    }
+
+    #[test]
+    fn parse_commit_buy_order_id() {
+        let expected = |order_id| {
+            LoadTestCommand::CommitBuy(LoadTestUserIdOrderIdCommand {
+                user_id: "marcus".to_string(),
+                request_num: 3,
+                order_id,
+            })
+        };
+
+        assert_eq!(
+            LoadTestCommand::try_from("[3] COMMIT_BUY,marcus"),
+            Ok(expected(0))
+        );
+
+        let args = CliArgs::try_parse_from(["cli", "commit-buy", "marcus", "3", "--order-id", "7"])
+            .unwrap();
+        assert_eq!(args.command, CliCommand::Single(expected(7)));
+    }
+
+    #[test]
+    fn parse_validate_log() {
+        let args = CliArgs::try_parse_from([
+            "cli",
+            "validate-log",
+            "--user",
+            "marcus",
+            "--to",
+            "1619827200",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            args.command,
+            CliCommand::Single(LoadTestCommand::ValidateLog(LoadTestValidateLogCommand {
+                filter: LoadTestLogFilter {
+                    to: 1619827200,
+                    user_ids: vec!["marcus".to_string()],
+                    ..LoadTestLogFilter::default()
+                },
+            }))
+        );
+    }
+
+    #[test]
+    fn parse_place_order() {
+        let args = CliArgs::try_parse_from([
+            "cli",
+            "place-order",
+            "marcus",
+            "ABC",
+            "trailing-stop",
+            "5",
+            "2.5",
+            "--time-in-force",
+            "day",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            args.command,
+            CliCommand::Single(LoadTestCommand::PlaceOrder(LoadTestPlaceOrderCommand {
+                user_id: "marcus".to_string(),
+                stock_symbol: "ABC".to_string(),
+                order_type: LoadTestOrderType::TrailingStop,
+                amount: 5.0,
+                price: 2.5,
+                time_in_force: LoadTestTimeInForce::Day,
+                request_num: -1,
+            }))
+        );
+    }
+
+    #[test]
+    fn parse_update_trigger() {
+        let args = CliArgs::try_parse_from([
+            "cli",
+            "update-trigger",
+            "marcus",
+            "ABC",
+            "sell",
+            "--trigger-price",
+            "12.5",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            args.command,
+            CliCommand::Single(LoadTestCommand::UpdateTrigger(
+                LoadTestUpdateTriggerCommand {
+                    user_id: "marcus".to_string(),
+                    stock_symbol: "ABC".to_string(),
+                    side: LoadTestOrderSide::Sell,
+                    amount: None,
+                    trigger_price: Some(12.5),
+                    request_num: -1,
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_place_book_order() {
+        let args = CliArgs::try_parse_from([
+            "cli",
+            "place-book-order",
+            "marcus",
+            "ABC",
+            "buy",
+            "9.5",
+            "3",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            args.command,
+            CliCommand::Single(LoadTestCommand::PlaceBookOrder(
+                LoadTestPlaceBookOrderCommand {
+                    user_id: "marcus".to_string(),
+                    stock_symbol: "ABC".to_string(),
+                    side: LoadTestOrderSide::Buy,
+                    price: 9.5,
+                    quantity: 3.0,
+                    request_num: -1,
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn parse_price_history() {
+        let args =
+            CliArgs::try_parse_from(["cli", "price-history", "ABC", "--interval", "5m"]).unwrap();
+
+        assert_eq!(
+            args.command,
+            CliCommand::Single(LoadTestCommand::PriceHistory(LoadTestPriceHistoryCommand {
+                stock_symbol: "ABC".to_string(),
+                interval: LoadTestCandleInterval::FiveMinutes,
+                from: 0,
+                to: 0,
+                limit: 0,
+            }))
+        );
+    }
 }
@@ -1,20 +1,66 @@
 use crate::protos::day_trader_client::DayTraderClient;
 use crate::protos::quote_client::QuoteClient;
+use crate::protos::LoginRequest;
+use anyhow::{anyhow, bail};
+use tonic::metadata::{Ascii, MetadataValue};
+use tonic::service::interceptor::InterceptedService;
+use tonic::service::Interceptor;
 use tonic::transport::Channel;
+use tonic::{Request, Status};
+
+/// Attaches a session token, if any, to every request as `authorization: Bearer <token>`.
+#[derive(Clone)]
+pub struct SessionToken(Option<MetadataValue<Ascii>>);
+
+impl SessionToken {
+    pub fn new(token: Option<&str>) -> anyhow::Result<Self> {
+        let value = token
+            .map(|token| format!("Bearer {token}").parse())
+            .transpose()
+            .map_err(|e| anyhow!("invalid session token: {e}"))?;
+        Ok(Self(value))
+    }
+}
+
+impl Interceptor for SessionToken {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        if let Some(value) = &self.0 {
+            request
+                .metadata_mut()
+                .insert("authorization", value.clone());
+        }
+        Ok(request)
+    }
+}
 
 #[derive(Clone)]
 pub struct DayTraderServicesStack {
     pub quote: QuoteClient<Channel>,
-    pub day_trader: DayTraderClient<Channel>,
+    pub day_trader: DayTraderClient<InterceptedService<Channel, SessionToken>>,
 }
 
 impl DayTraderServicesStack {
-    pub fn new(channel: &Channel) -> Self {
+    pub fn new(channel: &Channel, token: SessionToken) -> Self {
         Self {
             quote: QuoteClient::new(channel.clone()),
-            day_trader: DayTraderClient::new(channel.clone()),
+            day_trader: DayTraderClient::with_interceptor(channel.clone(), token),
         }
     }
+
+    /// Logs in and returns a session token for `user_id`.
+    pub async fn login(&mut self, user_id: String, password: String) -> anyhow::Result<String> {
+        let response = self
+            .day_trader
+            .login(LoginRequest { user_id, password })
+            .await?
+            .into_inner();
+
+        if !response.success {
+            bail!("invalid credentials for {}", response.user_id);
+        }
+
+        Ok(response.token)
+    }
 }
 
 pub trait DayTraderCall {
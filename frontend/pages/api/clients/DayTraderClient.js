@@ -7,10 +7,50 @@ const def = loadSync(protoPath)
 const definitions = grpc.loadPackageDefinition(def)
 const DayTraderClient = new definitions.day_trader.DayTrader(process.env.transactionURI || 'localhost:8000', grpc.credentials.createInsecure());
 
+const BEARER_PREFIX = 'Bearer ';
 
-export function Add(userId, amount, requestNum) {
+/**
+ * The session token of the request, from the cookie the login and signup routes set or from an
+ * `Authorization: Bearer <token>` header.
+ */
+export function sessionToken(req) {
+    if (req.cookies && req.cookies.token) {
+        return req.cookies.token;
+    }
+    const authorization = req.headers.authorization || '';
+    return authorization.startsWith(BEARER_PREFIX) ? authorization.slice(BEARER_PREFIX.length) : '';
+}
+
+/**
+ * The user id the session token was issued to. Lean checks the token, this only reads it.
+ */
+export function sessionUser(token) {
+    try {
+        return JSON.parse(Buffer.from(token.split('.')[1], 'base64url').toString()).sub;
+    } catch (error) {
+        return undefined;
+    }
+}
+
+/**
+ * Sets the session token as a cookie the browser sends back with every api request.
+ */
+export function setSessionCookie(res, token) {
+    res.setHeader('Set-Cookie', `token=${token}; Path=/; HttpOnly; SameSite=Strict`);
+}
+
+function metadata(token) {
+    const metadata = new grpc.Metadata();
+    if (token) {
+        metadata.set('authorization', BEARER_PREFIX + token);
+    }
+    return metadata;
+}
+
+
+export function Add(userId, amount, requestNum, token) {
     return new Promise((accept, reject) => {
-        DayTraderClient.Add({userId, amount, requestNum}, (err, value) => {
+        DayTraderClient.Add({userId, amount, requestNum}, metadata(token), (err, value) => {
             if (err == null) {
                 accept(value)
             } else {
@@ -21,9 +61,9 @@ export function Add(userId, amount, requestNum) {
 }
 
 
-export function Buy(userId, stockSymbol, amount, requestNum) {
+export function Buy(userId, stockSymbol, amount, requestNum, token) {
     return new Promise((accept, reject) => {
-        DayTraderClient.Buy({userId, stockSymbol, amount, requestNum}, (err, value) => {
+        DayTraderClient.Buy({userId, stockSymbol, amount, requestNum}, metadata(token), (err, value) => {
             if (err == null) {
                 accept(value)
             } else {
@@ -33,9 +73,9 @@ export function Buy(userId, stockSymbol, amount, requestNum) {
     })
 }
 
-export function CommitBuy(userId, requestNum) {
+export function CommitBuy(userId, requestNum, token) {
     return new Promise((accept, reject) => {
-        DayTraderClient.CommitBuy({userId, requestNum}, (err, value) => {
+        DayTraderClient.CommitBuy({userId, requestNum}, metadata(token), (err, value) => {
             if (err == null) {
                 accept(value)
             } else {
@@ -45,9 +85,9 @@ export function CommitBuy(userId, requestNum) {
     })
 }
 
-export function CancelBuy(userId, requestNum) {
+export function CancelBuy(userId, requestNum, token) {
     return new Promise((accept, reject) => {
-        DayTraderClient.CancelBuy({userId, requestNum}, (err, value) => {
+        DayTraderClient.CancelBuy({userId, requestNum}, metadata(token), (err, value) => {
             if (err == null) {
                 accept(value)
             } else {
@@ -57,9 +97,9 @@ export function CancelBuy(userId, requestNum) {
     })
 }
 
-export function Sell(userId, stockSymbol, amount, requestNum) {
+export function Sell(userId, stockSymbol, amount, requestNum, token) {
     return new Promise((accept, reject) => {
-        DayTraderClient.Sell({userId, amount, stockSymbol, requestNum}, (err, value) => {
+        DayTraderClient.Sell({userId, amount, stockSymbol, requestNum}, metadata(token), (err, value) => {
             if (err == null) {
                 accept(value)
             } else {
@@ -69,9 +109,9 @@ export function Sell(userId, stockSymbol, amount, requestNum) {
     })
 }
 
-export function CommitSell(userId, requestNum) {
+export function CommitSell(userId, requestNum, token) {
     return new Promise((accept, reject) => {
-        DayTraderClient.CommitSell({userId, requestNum}, (err, value) => {
+        DayTraderClient.CommitSell({userId, requestNum}, metadata(token), (err, value) => {
             if (err == null) {
                 accept(value)
             } else {
@@ -81,9 +121,9 @@ export function CommitSell(userId, requestNum) {
     })
 }
 
-export function CancelSell(userId, requestNum) {
+export function CancelSell(userId, requestNum, token) {
     return new Promise((accept, reject) => {
-        DayTraderClient.CancelSell({userId, requestNum}, (err, value) => {
+        DayTraderClient.CancelSell({userId, requestNum}, metadata(token), (err, value) => {
             if (err == null) {
                 accept(value)
             } else {
@@ -93,9 +133,9 @@ export function CancelSell(userId, requestNum) {
     })
 }
 
-export function DumpLogUser(userId, filename, requestNum) {
+export function DumpLogUser(userId, filename, requestNum, token) {
     return new Promise((accept, reject) => {
-        DayTraderClient.DumpLogUser({ userId, filename, requestNum }, (err, value) => {
+        DayTraderClient.DumpLogUser({ userId, filename, requestNum }, metadata(token), (err, value) => {
         if (err == null) {
             accept(value);
         } else {
@@ -105,9 +145,9 @@ export function DumpLogUser(userId, filename, requestNum) {
     });
 }
 
-export function DumpLog(filename, requestNum) {
+export function DumpLog(filename, requestNum, token) {
     return new Promise((accept, reject) => {
-        DayTraderClient.DumpLog({ filename, requestNum }, (err, value) => {
+        DayTraderClient.DumpLog({ filename, requestNum }, metadata(token), (err, value) => {
         if (err == null) {
             accept(value);
         } else {
@@ -117,9 +157,9 @@ export function DumpLog(filename, requestNum) {
     });
 }
 
-export function DisplaySummary(userId, requestNum) {
+export function DisplaySummary(userId, requestNum, token) {
     return new Promise((accept, reject) => {
-        DayTraderClient.DisplaySummary({ userId, requestNum }, (err, value) => {
+        DayTraderClient.DisplaySummary({ userId, requestNum }, metadata(token), (err, value) => {
         if (err == null) {
             accept(value);
         } else {
@@ -129,9 +169,9 @@ export function DisplaySummary(userId, requestNum) {
     });
 }
 
-export function SetBuyAmount(userId, stockSymbol, amount, requestNum) {
+export function SetBuyAmount(userId, stockSymbol, amount, requestNum, token) {
     return new Promise((accept, reject) => {
-        DayTraderClient.SetBuyAmount({ userId, stockSymbol, amount, requestNum }, (err, value) => {
+        DayTraderClient.SetBuyAmount({ userId, stockSymbol, amount, requestNum }, metadata(token), (err, value) => {
         if (err == null) {
             accept(value);
         } else {
@@ -141,9 +181,9 @@ export function SetBuyAmount(userId, stockSymbol, amount, requestNum) {
     });
 }
 
-export function CancelSetBuy(userId, stockSymbol, requestNum) {
+export function CancelSetBuy(userId, stockSymbol, requestNum, token) {
     return new Promise((accept, reject) => {
-        DayTraderClient.CancelSetBuy({ userId, stockSymbol, requestNum }, (err, value) => {
+        DayTraderClient.CancelSetBuy({ userId, stockSymbol, requestNum }, metadata(token), (err, value) => {
         if (err == null) {
             accept(value);
         } else {
@@ -153,9 +193,9 @@ export function CancelSetBuy(userId, stockSymbol, requestNum) {
     });
 }
 
-export function SetBuyTrigger(userId, stockSymbol, amount, requestNum) {
+export function SetBuyTrigger(userId, stockSymbol, amount, requestNum, token) {
     return new Promise((accept, reject) => {
-        DayTraderClient.SetBuyTrigger({ userId, stockSymbol, amount, requestNum }, (err, value) => {
+        DayTraderClient.SetBuyTrigger({ userId, stockSymbol, amount, requestNum }, metadata(token), (err, value) => {
         if (err == null) {
             accept(value);
         } else {
@@ -165,9 +205,9 @@ export function SetBuyTrigger(userId, stockSymbol, amount, requestNum) {
     });
 }
 
-export function SetSellAmount(userId, stockSymbol, amount, requestNum) {
+export function SetSellAmount(userId, stockSymbol, amount, requestNum, token) {
     return new Promise((accept, reject) => {
-        DayTraderClient.SetSellAmount({ userId, stockSymbol, amount, requestNum }, (err, value) => {
+        DayTraderClient.SetSellAmount({ userId, stockSymbol, amount, requestNum }, metadata(token), (err, value) => {
         if (err == null) {
             accept(value);
         } else {
@@ -177,9 +217,9 @@ export function SetSellAmount(userId, stockSymbol, amount, requestNum) {
     });
 }
 
-export function SetSellTrigger(userId, stockSymbol, amount, requestNum) {
+export function SetSellTrigger(userId, stockSymbol, amount, requestNum, token) {
     return new Promise((accept, reject) => {
-        DayTraderClient.SetSellTrigger({ userId, stockSymbol, amount, requestNum }, (err, value) => {
+        DayTraderClient.SetSellTrigger({ userId, stockSymbol, amount, requestNum }, metadata(token), (err, value) => {
         if (err == null) {
             accept(value);
         } else {
@@ -189,9 +229,9 @@ export function SetSellTrigger(userId, stockSymbol, amount, requestNum) {
     });
     }
 
-export function CancelSetSell(userId, stockSymbol, requestNum) {
+export function CancelSetSell(userId, stockSymbol, requestNum, token) {
     return new Promise((accept, reject) => {
-        DayTraderClient.CancelSetSell({ userId, stockSymbol, requestNum }, (err, value) => {
+        DayTraderClient.CancelSetSell({ userId, stockSymbol, requestNum }, metadata(token), (err, value) => {
         if (err == null) {
             accept(value);
         } else {
@@ -201,9 +241,9 @@ export function CancelSetSell(userId, stockSymbol, requestNum) {
     });
 }
 
-export function GetAllStocks() {
+export function GetAllStocks(token) {
     return new Promise((accept, reject) => {
-        DayTraderClient.GetAllStocks({}, (err, value) => {
+        DayTraderClient.GetAllStocks({}, metadata(token), (err, value) => {
         if (err == null) {
             accept(value);
         } else {
@@ -213,9 +253,9 @@ export function GetAllStocks() {
     });
 }
 
-export function GetUserInfo(userId) {
+export function GetUserInfo(userId, token) {
     return new Promise((accept, reject) => {
-      DayTraderClient.GetUserInfo({ userId }, (err, value) => {
+      DayTraderClient.GetUserInfo({ userId }, metadata(token), (err, value) => {
         if (err == null) {
           accept(value);
         } else {
@@ -225,9 +265,9 @@ export function GetUserInfo(userId) {
     });
   }
   
-export function Login(userId) {
+export function Login(userId, password) {
     return new Promise((accept, reject) => {
-        DayTraderClient.Login({ userId }, (err, value) => {
+        DayTraderClient.Login({ userId, password }, (err, value) => {
         if (err == null) {
             accept(value);
         } else {
@@ -237,9 +277,9 @@ export function Login(userId) {
     });
 }
 
-export function Quote(userId, stockSymbol, requestNum) {
+export function Quote(userId, stockSymbol, requestNum, token) {
     return new Promise((accept, reject) => {
-        DayTraderClient.Quote({ userId, stockSymbol, requestNum }, (err, value) => {
+        DayTraderClient.Quote({ userId, stockSymbol, requestNum }, metadata(token), (err, value) => {
         if (err == null) {
             accept(value);
         } else {
@@ -249,9 +289,9 @@ export function Quote(userId, stockSymbol, requestNum) {
     });
 }
 
-export function GetFile(filename) {
+export function GetFile(filename, token) {
     return new Promise((accept, reject) => {
-        DayTraderClient.File({ filename }, (err, value) => {
+        DayTraderClient.File({ filename }, metadata(token), (err, value) => {
             if (err == null) {
                 accept(value);
             } else {
@@ -261,4 +301,14 @@ export function GetFile(filename) {
     });
 }
 
-  
\ No newline at end of file
+export function Register(userId, password) {
+    return new Promise((accept, reject) => {
+        DayTraderClient.Register({ userId, password }, (err, value) => {
+            if (err == null) {
+                accept(value);
+            } else {
+                reject(err);
+            }
+        });
+    });
+}
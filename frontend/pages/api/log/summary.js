@@ -1,6 +1,7 @@
-import { DisplaySummary } from "../clients/DayTraderClient";
+import { DisplaySummary, sessionToken } from "../clients/DayTraderClient";
 
 export default async function userSummary(req, res){
+    const token = sessionToken(req);
     const user = req.body.username;
     if(process.env.DUMMY_DATA == "true"){
         const response = {
@@ -9,7 +10,7 @@ export default async function userSummary(req, res){
         }
         return res.status(200).json(response)
     }else{
-        const grpcCall = await DisplaySummary(user, -1);
+        const grpcCall = await DisplaySummary(user, -1, token);
         const response = {
             user_commands: grpcCall.userCommands,
             account_transactions: grpcCall.accountTransactions,
@@ -1,7 +1,8 @@
-import { DumpLog, GetFile } from "../clients/DayTraderClient";
+import { DumpLog, GetFile, sessionToken } from "../clients/DayTraderClient";
 
 
 export default async function dumplog(req, res){
+    const token = sessionToken(req);
     if(process.env.DUMMY_DATA == "true"){
         const response = {
             xml: "",
@@ -10,8 +11,8 @@ export default async function dumplog(req, res){
         return res.status(200).json(response)
     }else{
         const filename = "dumplog.xml";
-        const grpcCall = await DumpLog(filename, -1);
-        const grpcFileCall = await GetFile(grpcCall.xml)
+        const grpcCall = await DumpLog(filename, -1, token);
+        const grpcFileCall = await GetFile(grpcCall.xml, token)
         const response = {
             file: grpcFileCall.contents,
             success: true,
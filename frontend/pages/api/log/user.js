@@ -1,6 +1,7 @@
-import { DumpLogUser, GetFile } from "../clients/DayTraderClient";
+import { DumpLogUser, GetFile, sessionToken } from "../clients/DayTraderClient";
 
 export default async function userLog(req, res){
+    const token = sessionToken(req);
     const user = req.body.username;
     if(process.env.DUMMY_DATA == "true"){
         const response = {
@@ -10,8 +11,8 @@ export default async function userLog(req, res){
         return res.status(200).json(response)
     }else{
         const filename = "dumploguser.xml";
-        const grpcCall = await DumpLogUser(user, filename, -1);
-        const grpcFileCall = await GetFile(grpcCall.xml)
+        const grpcCall = await DumpLogUser(user, filename, -1, token);
+        const grpcFileCall = await GetFile(grpcCall.xml, token)
         const response = {
             file: grpcFileCall.contents,
             success: true,
@@ -1,4 +1,4 @@
-import { Quote } from "../clients/DayTraderClient";
+import { Quote, sessionToken, sessionUser } from "../clients/DayTraderClient";
 
 const stocks = {
     "ABC": {
@@ -104,14 +104,15 @@ function getRandomNumber(min, max) {
 }
 
 export default async function getStocks(req, res){
-    const username = req.body.username;
+    const token = sessionToken(req);
+    const username = sessionUser(token);
     if(process.env.DUMMY_DATA == "true"){
         const stock = req.query.id.toUpperCase()
         const response = stocks[stock] ? stocks[stock] : {name: "NOTFOUND"}
         return res.status(200).json(response)
     }else{
         const stock = req.query.id.toLowerCase()
-        const grpcCall = await Quote(username, stock, -1);
+        const grpcCall = await Quote(username, stock, -1, token);
         const percentChange = getRandomNumber(-20, 20);
         const stockChange = grpcCall.price * (percentChange/100)
         const response = {
@@ -1,6 +1,7 @@
-import { CancelBuy } from "../../clients/DayTraderClient";
+import { CancelBuy, sessionToken } from "../../clients/DayTraderClient";
 
 export default async function cancelBuy(req, res){
+    const token = sessionToken(req);
     const username = req.body.username
     if(process.env.DUMMY_DATA == "true"){
         const response = {
@@ -8,7 +9,7 @@ export default async function cancelBuy(req, res){
         }
         return res.status(200).json(response)
     }else{
-        const grpcCall = await CancelBuy(username, -1);
+        const grpcCall = await CancelBuy(username, -1, token);
         const response = {
             success: grpcCall.success,
         }
@@ -1,7 +1,8 @@
-import { CommitBuy } from "../../clients/DayTraderClient";
+import { CommitBuy, sessionToken } from "../../clients/DayTraderClient";
 
 
 export default async function commitBuy(req, res){
+    const token = sessionToken(req);
     const username = req.body.username
     if(process.env.DUMMY_DATA == "true"){
         const response = {
@@ -9,7 +10,7 @@ export default async function commitBuy(req, res){
         }
         return res.status(200).json(response)
     }else{
-        const grpcCall = await CommitBuy(username, -1);
+        const grpcCall = await CommitBuy(username, -1, token);
         const response = {
             success: grpcCall.success,
         }
@@ -1,6 +1,7 @@
-import { Buy } from "../../clients/DayTraderClient";
+import { Buy, sessionToken } from "../../clients/DayTraderClient";
 
 export default async function buy(req, res){
+    const token = sessionToken(req);
     const username = req.body.username
     const stock = req.body.stock
     const amount = req.body.amount
@@ -10,7 +11,7 @@ export default async function buy(req, res){
         }
         return res.status(200).json(response)
     }else{
-        const grpcCall = await Buy(username, stock, amount, -1);
+        const grpcCall = await Buy(username, stock, amount, -1, token);
         const response = {
             success: grpcCall.success,
         }
@@ -1,6 +1,7 @@
-import { SetBuyTrigger } from "../../clients/DayTraderClient";
+import { SetBuyTrigger, sessionToken } from "../../clients/DayTraderClient";
 
 export default async function setBuyTrigger(req, res){
+    const token = sessionToken(req);
     const username = req.body.username;
     const stock_symbol = req.body.stock;
     const amount = req.body.amount;
@@ -10,7 +11,7 @@ export default async function setBuyTrigger(req, res){
         }
         return res.status(200).json(response)
     }else{
-        const grpcCall = await SetBuyTrigger(username, stock_symbol, amount, -1);
+        const grpcCall = await SetBuyTrigger(username, stock_symbol, amount, -1, token);
         const response = {
             success: grpcCall.success,
         }
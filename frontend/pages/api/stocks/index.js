@@ -1,4 +1,4 @@
-import { GetAllStocks, Quote } from "../clients/DayTraderClient";
+import { GetAllStocks, Quote, sessionToken, sessionUser } from "../clients/DayTraderClient";
 
 const dummy_stocks = [
     {
@@ -120,16 +120,17 @@ function getRandomNumber(min, max) {
 }
 
 export default async function getStocks(req, res){
+    const token = sessionToken(req);
     if(process.env.DUMMY_DATA == "true"){
         return res.status(200).json(dummy_stocks)
     }else{
-        var grpcCall = (await GetAllStocks()).stocks;
+        var grpcCall = (await GetAllStocks(token)).stocks;
         if(grpcCall === undefined){
-            // get quotes on default stocks with startup user ID
+            // get quotes on default stocks as the signed in user
             for(const stock of defaultSearchStocks){
-                const grpcCall = await Quote("admin_startup_user", stock, -1);
+                const grpcCall = await Quote(sessionUser(token), stock, -1, token);
             }
-            grpcCall = (await GetAllStocks()).stocks;
+            grpcCall = (await GetAllStocks(token)).stocks;
         }
         const response = grpcCall.map((stock) => {
             const percentChange = getRandomNumber(-20, 20);
@@ -1,7 +1,8 @@
-import { CancelSetSell } from "../../clients/DayTraderClient";
+import { CancelSetSell, sessionToken } from "../../clients/DayTraderClient";
 
 
 export default async function cancelSellTrigger(req, res){
+    const token = sessionToken(req);
     const username = req.body.username;
     const stock_symbol = req.body.stock;
     if(process.env.DUMMY_DATA == "true"){
@@ -10,7 +11,7 @@ export default async function cancelSellTrigger(req, res){
         }
         return res.status(200).json(response)
     }else{
-        const grpcCall = await CancelSetSell(username, stock_symbol, -1);
+        const grpcCall = await CancelSetSell(username, stock_symbol, -1, token);
         const response = {
             success: true,
         }
@@ -1,7 +1,8 @@
-import { SetSellAmount } from "../../clients/DayTraderClient";
+import { SetSellAmount, sessionToken } from "../../clients/DayTraderClient";
 
 
 export default async function setSellAmount(req, res){
+    const token = sessionToken(req);
     const username = req.body.username;
     const stock_symbol = req.body.stock;
     const amount = req.body.amount;
@@ -11,7 +12,7 @@ export default async function setSellAmount(req, res){
         }
         return res.status(200).json(response)
     }else{
-        const grpcCall = await SetSellAmount(username, stock_symbol, amount, -1);
+        const grpcCall = await SetSellAmount(username, stock_symbol, amount, -1, token);
         const response = {
             success: grpcCall.success,
         }
@@ -1,4 +1,4 @@
-import { GetUserInfo } from "../clients/DayTraderClient";
+import { GetUserInfo, sessionToken } from "../clients/DayTraderClient";
 
 const dummy_data = {
     balance: 523.45,
@@ -53,11 +53,12 @@ const dummy_data = {
 }
 
 export default async function getuser(req, res){
+    const token = sessionToken(req);
     const userId = req.query.userId;
     if(process.env.DUMMY_DATA == "true"){
         return res.status(200).json(dummy_data)
     }else{
-        const grpcCall = await GetUserInfo(userId);
+        const grpcCall = await GetUserInfo(userId, token);
         const response = {
             balance: grpcCall.balance,
             stock: grpcCall.stock ? grpcCall.stock.map((stock) => {
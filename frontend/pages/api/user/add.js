@@ -1,6 +1,7 @@
-import { Add } from "../clients/DayTraderClient";
+import { Add, sessionToken } from "../clients/DayTraderClient";
 
 export default async function addBalance(req, res){
+    const token = sessionToken(req);
     const userId = req.body.username;
     const amount = req.body.amount;
     if(process.env.DUMMY_DATA == "true"){
@@ -9,7 +10,7 @@ export default async function addBalance(req, res){
         }
         return res.status(200).json(response)
     }else{
-        const grpcCall = await Add(userId, amount, -1);
+        const grpcCall = await Add(userId, amount, -1, token);
         const response = {
             success: grpcCall.success,
         }
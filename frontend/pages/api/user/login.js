@@ -1,4 +1,4 @@
-import { Login } from "../clients/DayTraderClient";
+import { Login, setSessionCookie } from "../clients/DayTraderClient";
 
 
 export default async function login(req, res){
@@ -9,11 +9,14 @@ export default async function login(req, res){
         }
         return res.status(200).json(response)
     }else{
-        const grpcCall = await Login(req.body.username);
+        const grpcCall = await Login(req.body.username, req.body.password);
+        if (grpcCall.success) {
+            setSessionCookie(res, grpcCall.token);
+        }
         const response = {
             success: grpcCall.success,
             user: grpcCall.userId,
         }
         return res.status(200).json(response)
     }
-}
\ No newline at end of file
+}
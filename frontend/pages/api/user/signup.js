@@ -1,8 +1,9 @@
-import { Add } from "../clients/DayTraderClient";
+import { Add, Login, Register, setSessionCookie } from "../clients/DayTraderClient";
 
 
 export default async function signup(req, res){
     const username = req.body.username;
+    const password = req.body.password;
     if(process.env.DUMMY_DATA == "true"){
         const response = {
             success: true,
@@ -10,11 +11,18 @@ export default async function signup(req, res){
         }
         return res.status(200).json(response)
     }else{
-        const grpcCall = await Add(username, 0, -1);;
+        try {
+            await Register(username, password);
+        } catch (error) {
+            return res.status(200).json({ success: false, error: error.details })
+        }
+        const login = await Login(username, password);
+        setSessionCookie(res, login.token);
+        const grpcCall = await Add(username, 0, -1, login.token);
         const response = {
             success: grpcCall.success,
             user: username
         }
         return res.status(200).json(response)
     }
-}
\ No newline at end of file
+}
@@ -62,11 +62,14 @@ function stock({ stock }) {
     )
 }
 
-export async function getServerSideProps({ query }) {
+export async function getServerSideProps({ query, req }) {
     const { id } = query
     const url = process.env.FRONTEND_URL ? process.env.FRONTEND_URL : "http://localhost:3000/"
     try{
-        const res = await fetch(url.concat("api/stocks/",id));
+        // forward the session cookie so the api route can call lean as the signed in user
+        const res = await fetch(url.concat("api/stocks/",id), {
+            headers: { cookie: req.headers.cookie || "" }
+        });
         const data = await res.json()
         return { props: { stock: data } }
     }catch(error){
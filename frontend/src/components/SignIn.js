@@ -16,7 +16,8 @@ export default function SignIn() {
     event.preventDefault();
     const data = new FormData(event.currentTarget);
     const body = {
-      username: data.get('email')
+      username: data.get('email'),
+      password: data.get('password')
     }
     try{
       const response = await fetch('/api/user/login', {
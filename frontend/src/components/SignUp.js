@@ -7,13 +7,21 @@ import Box from '@mui/material/Box';
 import LockOutlinedIcon from '@mui/icons-material/LockOutlined';
 import Typography from '@mui/material/Typography';
 import Container from '@mui/material/Container';
+import { useState } from 'react';
 
 export default function SignUp() {
+  const [error, setError] = useState("");
+
   const handleSubmit = async (event) => {
     event.preventDefault();
     const data = new FormData(event.currentTarget);
+    if (data.get('password') !== data.get('confirm')) {
+      setError("Passwords do not match");
+      return;
+    }
     const body = {
-      username: data.get('email')
+      username: data.get('email'),
+      password: data.get('password')
     }
     try{
       const response = await fetch('/api/user/signup', {
@@ -26,11 +34,14 @@ export default function SignUp() {
       const responseParsed = await response.json();
       if (responseParsed.success) {
         localStorage.setItem('jwt', responseParsed.user);
+        setError("");
+        window.location.href = "/";
+      }else{
+        setError("Could not sign up, please try another username");
       }
-      window.location.href = "/"
     }catch(error){
       console.log("error:",error);
-      setError("Invalid username or password, please try again");
+      setError("Could not sign up, please try again");
       return;
     }
   };
@@ -76,12 +87,18 @@ export default function SignUp() {
             margin="normal"
             required
             fullWidth
-            name="password"
+            name="confirm"
             label="Re-enter password"
             type="password"
-            id="password"
+            id="confirm"
             autoComplete="current-password"
           />
+          <Typography
+            variant='subtitle2'
+            color="error"
+          >
+            {error}
+          </Typography>
           <Button
             type="submit"
             fullWidth
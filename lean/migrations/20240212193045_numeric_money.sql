@@ -0,0 +1,22 @@
+-- Add migration script here
+alter table trader
+    alter column balance type numeric(20, 2) using round(balance::numeric, 2);
+
+alter table queued_buy
+    alter column quoted_price type numeric(20, 2) using round(quoted_price::numeric, 2),
+    alter column amount_dollars type numeric(20, 2) using round(amount_dollars::numeric, 2);
+
+alter table queued_sell
+    alter column quoted_price type numeric(20, 2) using round(quoted_price::numeric, 2),
+    alter column amount_dollars type numeric(20, 2) using round(amount_dollars::numeric, 2);
+
+alter table stock
+    alter column amount type numeric(26, 6) using trunc(amount::numeric, 6);
+
+alter table buy_trigger
+    alter column amount_dollars type numeric(20, 2) using round(amount_dollars::numeric, 2),
+    alter column trigger_price type numeric(20, 2) using round(trigger_price::numeric, 2);
+
+alter table sell_trigger
+    alter column amount_stock type numeric(26, 6) using trunc(amount_stock::numeric, 6),
+    alter column trigger_price type numeric(20, 2) using round(trigger_price::numeric, 2);
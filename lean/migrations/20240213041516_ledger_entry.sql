@@ -0,0 +1,44 @@
+-- Add migration script here
+-- every change to a trader's cash, reserved cash or holdings is recorded as a transfer from the
+-- credit account to the debit account. holdings are counted in shares of stock_symbol, every other
+-- account in dollars.
+create table ledger_entry
+(
+    id           bigserial primary key,
+    time_created timestamp      not null default now(),
+    user_id      text           not null,
+    debit        text           not null check (debit in ('cash', 'reserved_cash', 'holdings', 'house')),
+    credit       text           not null check (credit in ('cash', 'reserved_cash', 'holdings', 'house')),
+    stock_symbol text,
+    amount       numeric(26, 6) not null check (amount > 0),
+    check (debit <> credit),
+    check ((stock_symbol is not null) = ('holdings' in (debit, credit)))
+);
+
+create index ledger_entry_user_id_idx on ledger_entry (user_id);
+
+-- opening entries so existing balances can be rebuilt from the ledger
+insert into ledger_entry (user_id, debit, credit, amount)
+select user_id, 'cash', 'house', balance
+from trader
+where balance > 0;
+
+insert into ledger_entry (user_id, debit, credit, amount)
+select user_id, 'house', 'cash', -balance
+from trader
+where balance < 0;
+
+insert into ledger_entry (user_id, debit, credit, amount)
+select user_id, 'reserved_cash', 'house', amount_dollars
+from queued_buy
+where amount_dollars > 0;
+
+insert into ledger_entry (user_id, debit, credit, amount)
+select owner_id, 'reserved_cash', 'house', amount_dollars
+from buy_trigger
+where amount_dollars > 0;
+
+insert into ledger_entry (user_id, debit, credit, stock_symbol, amount)
+select owner_id, 'holdings', 'house', stock_symbol, amount
+from stock
+where amount > 0;
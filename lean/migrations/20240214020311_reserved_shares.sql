@@ -0,0 +1,27 @@
+-- Add migration script here
+
+-- shares set aside for a queued sell or a sell trigger stay in the owner's holdings but are
+-- reserved until the sale executes or is cancelled
+alter table stock
+    add column reserved numeric(26, 6) not null default 0;
+
+-- sell triggers and queued sells used to remove their shares from stock.amount, put them back as
+-- reserved shares
+insert into stock (owner_id, stock_symbol, amount, reserved)
+select owner_id, stock_symbol, sum(shares), sum(shares)
+from (select owner_id, stock_symbol, amount_stock as shares
+      from sell_trigger
+      union all
+      select user_id, stock_symbol, ceil(amount_dollars / quoted_price * 1000000) / 1000000
+      from queued_sell) reservations
+group by owner_id, stock_symbol
+on conflict (owner_id, stock_symbol) do update set amount   = stock.amount + excluded.amount,
+                                                  reserved = stock.reserved + excluded.reserved;
+
+insert into ledger_entry (user_id, debit, credit, stock_symbol, amount)
+select owner_id, 'holdings', 'house', stock_symbol, reserved
+from stock
+where reserved > 0;
+
+alter table stock
+    add check (reserved >= 0 and reserved <= amount);
@@ -0,0 +1,7 @@
+-- Add migration script here
+create table credential
+(
+    user_id       text primary key,
+    password_hash text    not null,
+    is_admin      boolean not null default false
+);
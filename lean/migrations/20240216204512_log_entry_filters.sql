@@ -0,0 +1,30 @@
+-- Add migration script here
+
+-- the variant of `Log` an entry holds, e.g. 'ErrorMessages'
+create function log_entry_type(log jsonb) returns text
+    language sql
+    immutable
+    parallel safe
+as
+$$
+select jsonb_object_keys(log)
+limit 1
+$$;
+
+-- the `CommandType` of an entry, null for variants without one
+create function log_entry_command(log jsonb) returns text
+    language sql
+    immutable
+    parallel safe
+as
+$$
+select log -> log_entry_type(log) ->> 'command'
+$$;
+
+create index log_entry_timestamp_idx on log_entry (timestamp);
+
+create index log_entry_username_timestamp_idx on log_entry (username, timestamp);
+
+create index log_entry_type_timestamp_idx on log_entry (log_entry_type(log), timestamp);
+
+create index log_entry_command_timestamp_idx on log_entry (log_entry_command(log), timestamp);
@@ -0,0 +1,17 @@
+-- Add migration script here
+
+-- a user may stage several buys and sells at once, each is addressed by an order id that is unique
+-- across both tables
+create sequence order_id_seq;
+
+alter table queued_buy
+    drop constraint queued_buy_pkey,
+    add column order_id bigint primary key default nextval('order_id_seq');
+
+alter table queued_sell
+    drop constraint queued_sell_pkey,
+    add column order_id bigint primary key default nextval('order_id_seq');
+
+create index on queued_buy (user_id, order_id);
+
+create index on queued_sell (user_id, order_id);
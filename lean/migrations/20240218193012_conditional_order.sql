@@ -0,0 +1,32 @@
+-- Add migration script here
+
+-- limit, stop loss and trailing stop orders wait for the price to reach them. any number may be
+-- placed per symbol. limit buys reserve amount_dollars of cash, every other type reserves
+-- amount_stock shares.
+create table conditional_order
+(
+    order_id       bigint primary key default nextval('order_id_seq'),
+    owner_id       text           not null,
+    stock_symbol   text           not null,
+    order_type     text           not null check (order_type in ('limit_buy', 'limit_sell', 'stop_loss', 'trailing_stop')),
+    time_in_force  text           not null check (time_in_force in ('gtc', 'day', 'ioc')),
+    amount_dollars numeric(20, 2) check (amount_dollars > 0),
+    amount_stock   numeric(26, 6) check (amount_stock > 0),
+    -- the limit price, or the stop price of a stop loss
+    limit_price    numeric(20, 2) check (limit_price > 0),
+    -- how far below the highest price seen since it was placed a trailing stop sells
+    trail_amount   numeric(20, 2) check (trail_amount > 0),
+    high_water     numeric(20, 2),
+    time_created   timestamp      not null default now(),
+    -- the end of the (UTC) day a day order was placed, null for gtc and ioc orders
+    expires_at     timestamp,
+    check ((order_type = 'limit_buy') = (amount_dollars is not null)),
+    check ((order_type = 'limit_buy') = (amount_stock is null)),
+    check ((order_type = 'trailing_stop') = (limit_price is null)),
+    check ((order_type = 'trailing_stop') = (trail_amount is not null)),
+    check ((order_type = 'trailing_stop') = (high_water is not null))
+);
+
+create index on conditional_order (stock_symbol);
+
+create index on conditional_order (owner_id, order_id);
@@ -0,0 +1,21 @@
+-- Add migration script here
+
+-- limit orders resting in the order book, matched between traders rather than against the quote
+-- price. quantity is the shares still unfilled. buys reserve reserved_dollars of cash, enough for
+-- the quantity at the limit price, sells reserve the shares.
+create table book_order
+(
+    order_id         bigint primary key default nextval('order_id_seq'),
+    owner_id         text           not null,
+    stock_symbol     text           not null,
+    side             text           not null check (side in ('buy', 'sell')),
+    limit_price      numeric(20, 2) not null check (limit_price > 0),
+    quantity         numeric(26, 6) not null check (quantity > 0),
+    reserved_dollars numeric(20, 2) check (reserved_dollars >= 0),
+    time_created     timestamp      not null default now(),
+    check ((side = 'buy') = (reserved_dollars is not null))
+);
+
+create index on book_order (stock_symbol, order_id);
+
+create index on book_order (owner_id, order_id);
@@ -0,0 +1,18 @@
+-- Add migration script here
+create function notify_balance_changed() returns trigger as $$
+begin
+    if tg_op = 'UPDATE' and new.balance = old.balance then
+        return new;
+    end if;
+    perform pg_notify('account_event', json_build_object(
+        'user_id', new.user_id,
+        'type', 'balance_changed',
+        'balance', new.balance::text
+    )::text);
+    return new;
+end;
+$$ language plpgsql;
+
+create trigger trader_balance_changed
+    after insert or update of balance on trader
+    for each row execute function notify_balance_changed();
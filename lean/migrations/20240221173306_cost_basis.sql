@@ -0,0 +1,10 @@
+-- Add migration script here
+
+-- what the shares still held cost in total, and the profit or loss of every share sold so far,
+-- both by average cost. holdings bought before this was tracked start from a cost of 0
+alter table stock
+    add column cost_basis   numeric(20, 2) not null default 0,
+    add column realized_pnl numeric(20, 2) not null default 0;
+
+alter table stock
+    add check (cost_basis >= 0);
@@ -0,0 +1,12 @@
+-- Add migration script here
+
+-- every price fetched from the quote server, for charting
+create table price_history
+(
+    price_id     bigserial primary key,
+    stock_symbol text           not null,
+    price        numeric(20, 2) not null,
+    time_created timestamp      not null default now()
+);
+
+create index on price_history (stock_symbol, time_created);
@@ -39,7 +39,7 @@ pub async fn display_summary(
                         stock_symbol: command
                             .stock_symbol
                             .unwrap_or_else(|| "no stock symbol".to_string()),
-                        funds: command.funds.unwrap_or(0.0),
+                        funds: command.funds.unwrap_or_default().to_string(),
                     });
                 }
                 Log::AccountChanges(transaction) => {
@@ -49,7 +49,7 @@ pub async fn display_summary(
                         server: entry.server,
                         action: transaction.action,
                         username: entry.username,
-                        funds: transaction.funds,
+                        funds: transaction.funds.to_string(),
                     });
                 }
                 _ => {}
@@ -1,21 +1,42 @@
+use crate::ledger::Account;
 use crate::log::AccountTransaction;
+use crate::{begin_transaction, commit_transaction, ledger};
 use anyhow::bail;
+use rust_decimal::Decimal;
 use sqlx::{query, PgPool};
+use std::ops::DerefMut;
 
 #[tracing::instrument(skip(pool))]
-pub async fn add(pool: &PgPool, user_id: &str, amount: f64) -> anyhow::Result<AccountTransaction> {
+pub async fn add(
+    pool: &PgPool,
+    user_id: &str,
+    amount: Decimal,
+) -> anyhow::Result<AccountTransaction> {
     if !amount.is_sign_positive() {
         bail!("amount must be positive")
     }
 
+    let mut transaction = begin_transaction(pool).await?;
+
     query!(
         "INSERT INTO trader (user_id, balance) VALUES ($1, $2) ON CONFLICT (user_id) DO UPDATE SET balance = trader.balance + $2",
         user_id,
         amount
     )
-    .execute(pool)
+    .execute(transaction.deref_mut())
+    .await?;
+
+    ledger::transfer(
+        &mut transaction,
+        user_id,
+        Account::House,
+        Account::Cash,
+        amount,
+    )
     .await?;
 
+    commit_transaction(transaction).await?;
+
     Ok(AccountTransaction(amount))
 }
 
@@ -23,14 +44,15 @@ pub async fn add(pool: &PgPool, user_id: &str, amount: f64) -> anyhow::Result<Ac
 mod tests {
     use super::*;
     use pretty_assertions::assert_eq;
+    use rust_decimal_macros::dec;
 
     #[sqlx::test]
     async fn test_add_new_user(pool: PgPool) -> anyhow::Result<()> {
-        let _log = add(&pool, "marcus", 100_f64).await?;
+        let _log = add(&pool, "marcus", dec!(100)).await?;
 
         struct Trader {
             user_id: String,
-            balance: f64,
+            balance: Decimal,
         }
 
         let Trader { user_id, balance } =
@@ -39,20 +61,20 @@ mod tests {
                 .await?;
 
         assert_eq!(user_id, "marcus");
-        assert_eq!(balance, 100_f64);
+        assert_eq!(balance, dec!(100));
 
         Ok(())
     }
 
     #[sqlx::test]
     async fn test_add_old_user(pool: PgPool) -> anyhow::Result<()> {
-        let add1 = add(&pool, "marcus", 100_f64);
-        let add2 = add(&pool, "marcus", 100_f64);
+        let add1 = add(&pool, "marcus", dec!(100));
+        let add2 = add(&pool, "marcus", dec!(100));
         let (_log1, _log2) = tokio::try_join!(add1, add2)?;
 
         struct Trader {
             user_id: String,
-            balance: f64,
+            balance: Decimal,
         }
 
         let Trader { user_id, balance } =
@@ -61,7 +83,7 @@ mod tests {
                 .await?;
 
         assert_eq!(user_id, "marcus");
-        assert_eq!(balance, 200_f64);
+        assert_eq!(balance, dec!(200));
 
         Ok(())
     }
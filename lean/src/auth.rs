@@ -0,0 +1,365 @@
+use anyhow::{anyhow, bail};
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::SaltString;
+use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tonic::service::Interceptor;
+use tonic::{Request, Status};
+
+const BEARER_PREFIX: &str = "Bearer ";
+
+/**
+ * The claims of a session token. Attached to a request's extensions by [Authenticator::intercept]
+ * once its token has been validated.
+ */
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    /// the user id the token was issued to
+    pub sub: String,
+    pub admin: bool,
+    /// expiry, seconds since the unix epoch
+    pub exp: u64,
+}
+
+/**
+ * Issues and validates signed session tokens.
+ */
+#[derive(Clone)]
+pub struct Authenticator {
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    ttl: Duration,
+}
+
+impl Authenticator {
+    pub fn new(secret: &[u8], ttl: Duration) -> Self {
+        Self {
+            encoding_key: EncodingKey::from_secret(secret),
+            decoding_key: DecodingKey::from_secret(secret),
+            ttl,
+        }
+    }
+
+    pub fn from_env() -> anyhow::Result<Self> {
+        let secret = std::env::var("SESSION_TOKEN_SECRET")
+            .map_err(|e| anyhow!("failed to get SESSION_TOKEN_SECRET from env: {e}"))?;
+
+        let ttl = std::env::var("SESSION_TOKEN_TTL_SECONDS")
+            .unwrap_or_else(|_| (60 * 60).to_string())
+            .parse::<u64>()
+            .map_err(|e| anyhow!("failed to parse SESSION_TOKEN_TTL_SECONDS: {e}"))?;
+
+        Ok(Self::new(secret.as_bytes(), Duration::from_secs(ttl)))
+    }
+
+    pub fn issue(&self, user_id: &str, admin: bool) -> anyhow::Result<String> {
+        let exp = (SystemTime::now() + self.ttl)
+            .duration_since(UNIX_EPOCH)?
+            .as_secs();
+
+        let session = Session {
+            sub: user_id.to_string(),
+            admin,
+            exp,
+        };
+
+        Ok(jsonwebtoken::encode(
+            &Header::new(Algorithm::HS256),
+            &session,
+            &self.encoding_key,
+        )?)
+    }
+
+    pub fn validate(&self, token: &str) -> anyhow::Result<Session> {
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.leeway = 0;
+
+        Ok(jsonwebtoken::decode::<Session>(token, &self.decoding_key, &validation)?.claims)
+    }
+
+    /**
+     * Validates the bearer token in the `authorization` metadata, if any, and attaches its
+     * [Session] to the request. Requests without a token pass through unauthenticated so that
+     * `Login` and `Register` can be reached; every other RPC checks for a session with
+     * [authorize] or [authorize_admin].
+     */
+    #[allow(clippy::result_large_err)]
+    pub fn intercept(&self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let Some(authorization) = request.metadata().get("authorization") else {
+            return Ok(request);
+        };
+
+        let token = authorization
+            .to_str()
+            .ok()
+            .and_then(|value| value.strip_prefix(BEARER_PREFIX))
+            .ok_or_else(|| Status::unauthenticated("malformed authorization metadata"))?;
+
+        let session = self
+            .validate(token)
+            .map_err(|e| Status::unauthenticated(format!("invalid session token: {e}")))?;
+
+        request.extensions_mut().insert(session);
+
+        Ok(request)
+    }
+}
+
+impl Interceptor for Authenticator {
+    fn call(&mut self, request: Request<()>) -> Result<Request<()>, Status> {
+        self.intercept(request)
+    }
+}
+
+/**
+ * Checks the request was made by `user_id` or by an admin.
+ */
+#[allow(clippy::result_large_err)]
+pub fn authorize<T>(request: &Request<T>, user_id: &str) -> Result<(), Status> {
+    let session = session(request)?;
+
+    if session.admin || session.sub == user_id {
+        Ok(())
+    } else {
+        Err(Status::permission_denied(format!(
+            "{} may not act for {user_id}",
+            session.sub
+        )))
+    }
+}
+
+/**
+ * Checks the request was made by an admin.
+ */
+#[allow(clippy::result_large_err)]
+pub fn authorize_admin<T>(request: &Request<T>) -> Result<(), Status> {
+    let session = session(request)?;
+
+    if session.admin {
+        Ok(())
+    } else {
+        Err(Status::permission_denied(format!(
+            "{} is not an admin",
+            session.sub
+        )))
+    }
+}
+
+#[allow(clippy::result_large_err)]
+fn session<T>(request: &Request<T>) -> Result<&Session, Status> {
+    request
+        .extensions()
+        .get::<Session>()
+        .ok_or_else(|| Status::unauthenticated("missing session token"))
+}
+
+/**
+ * Stores credentials for a new user. Fails if the user already has credentials.
+ */
+#[tracing::instrument(skip(pool, password))]
+pub async fn register(pool: &PgPool, user_id: &str, password: &str) -> anyhow::Result<()> {
+    if password.is_empty() {
+        bail!("password must not be empty");
+    }
+
+    let password_hash = hash_password(password.to_string()).await?;
+
+    let result = sqlx::query!(
+        "INSERT INTO credential (user_id, password_hash) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+        user_id,
+        password_hash
+    )
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        bail!("user {user_id} already exists");
+    }
+
+    Ok(())
+}
+
+/**
+ * Creates or updates an admin's credentials.
+ */
+#[tracing::instrument(skip(pool, password))]
+pub async fn upsert_admin(pool: &PgPool, user_id: &str, password: &str) -> anyhow::Result<()> {
+    let password_hash = hash_password(password.to_string()).await?;
+
+    sqlx::query!(
+        "
+        INSERT INTO credential (user_id, password_hash, is_admin) VALUES ($1, $2, true)
+        ON CONFLICT (user_id) DO UPDATE SET password_hash = $2, is_admin = true
+        ",
+        user_id,
+        password_hash
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/**
+ * Checks a user's password. Returns whether the user is an admin, or `None` if the user does not
+ * exist or the password is wrong.
+ */
+#[tracing::instrument(skip(pool, password))]
+pub async fn check_credentials(
+    pool: &PgPool,
+    user_id: &str,
+    password: &str,
+) -> anyhow::Result<Option<bool>> {
+    let Some(credential) = sqlx::query!(
+        "SELECT password_hash, is_admin FROM credential WHERE user_id = $1",
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    let password = password.to_string();
+    let password_hash = credential.password_hash;
+    let matches = tokio::task::spawn_blocking(move || {
+        PasswordHash::new(&password_hash).map(|hash| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &hash)
+                .is_ok()
+        })
+    })
+    .await?
+    .map_err(|e| anyhow!("stored password hash for {user_id} is invalid: {e}"))?;
+
+    Ok(matches.then_some(credential.is_admin))
+}
+
+async fn hash_password(password: String) -> anyhow::Result<String> {
+    tokio::task::spawn_blocking(move || {
+        let salt = SaltString::generate(&mut OsRng);
+        Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|e| anyhow!("failed to hash password: {e}"))
+    })
+    .await?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use tonic::metadata::MetadataValue;
+
+    fn authenticator() -> Authenticator {
+        Authenticator::new(b"secret", Duration::from_secs(60))
+    }
+
+    fn request_with_token(token: &str) -> Request<()> {
+        let mut request = Request::new(());
+        request.metadata_mut().insert(
+            "authorization",
+            MetadataValue::try_from(format!("{BEARER_PREFIX}{token}")).unwrap(),
+        );
+        request
+    }
+
+    #[test]
+    fn test_issue_and_validate() -> anyhow::Result<()> {
+        let authenticator = authenticator();
+        let token = authenticator.issue("marcus", false)?;
+
+        let session = authenticator.validate(&token)?;
+
+        assert_eq!(session.sub, "marcus");
+        assert!(!session.admin);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejects_token_signed_with_other_secret() -> anyhow::Result<()> {
+        let token = Authenticator::new(b"other", Duration::from_secs(60)).issue("marcus", true)?;
+
+        assert!(authenticator().validate(&token).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejects_expired_token() -> anyhow::Result<()> {
+        let token = Authenticator::new(b"secret", Duration::ZERO).issue("marcus", false)?;
+        std::thread::sleep(Duration::from_secs(1));
+
+        assert!(authenticator().validate(&token).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_authorize() -> anyhow::Result<()> {
+        let authenticator = authenticator();
+
+        let user =
+            authenticator.intercept(request_with_token(&authenticator.issue("marcus", false)?))?;
+        assert!(authorize(&user, "marcus").is_ok());
+        assert_eq!(
+            authorize(&user, "daws").unwrap_err().code(),
+            tonic::Code::PermissionDenied
+        );
+        assert_eq!(
+            authorize_admin(&user).unwrap_err().code(),
+            tonic::Code::PermissionDenied
+        );
+
+        let admin =
+            authenticator.intercept(request_with_token(&authenticator.issue("root", true)?))?;
+        assert!(authorize(&admin, "daws").is_ok());
+        assert!(authorize_admin(&admin).is_ok());
+
+        let anonymous = authenticator.intercept(Request::new(()))?;
+        assert_eq!(
+            authorize(&anonymous, "marcus").unwrap_err().code(),
+            tonic::Code::Unauthenticated
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_intercept_rejects_invalid_token() {
+        let result = authenticator().intercept(request_with_token("garbage"));
+
+        assert_eq!(result.unwrap_err().code(), tonic::Code::Unauthenticated);
+    }
+
+    #[sqlx::test]
+    async fn test_register_and_check_credentials(pool: PgPool) -> anyhow::Result<()> {
+        register(&pool, "marcus", "hunter2").await?;
+
+        assert_eq!(
+            check_credentials(&pool, "marcus", "hunter2").await?,
+            Some(false)
+        );
+        assert_eq!(check_credentials(&pool, "marcus", "hunter3").await?, None);
+        assert_eq!(check_credentials(&pool, "daws", "hunter2").await?, None);
+        assert!(register(&pool, "marcus", "other").await.is_err());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_upsert_admin(pool: PgPool) -> anyhow::Result<()> {
+        register(&pool, "root", "hunter2").await?;
+        upsert_admin(&pool, "root", "correct horse").await?;
+
+        assert_eq!(
+            check_credentials(&pool, "root", "correct horse").await?,
+            Some(true)
+        );
+        assert_eq!(check_credentials(&pool, "root", "hunter2").await?, None);
+
+        Ok(())
+    }
+}
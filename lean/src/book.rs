@@ -0,0 +1,6 @@
+pub use engine::{MatchingEngine, NewBookOrder};
+pub use order_book::Side;
+
+mod engine;
+
+mod order_book;
@@ -0,0 +1,735 @@
+use crate::book::order_book::{Depth, Fill, OrderBook, RestingOrder, Side};
+use crate::events::{self, AccountEvent};
+use crate::ledger::Account;
+use crate::log::{
+    save_log_entry, AccountTransaction, AccountTransactionLog, CommandType, Log, LogEntry,
+    SystemEventLog,
+};
+use crate::money::{dollars_for_shares, dollars_to_cover};
+use crate::{begin_transaction, commit_transaction, holdings, ledger, TransactionType};
+use anyhow::{bail, Context};
+use rust_decimal::Decimal;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::collections::HashMap;
+use std::ops::DerefMut;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/**
+ * A limit order for the book as requested by a user.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewBookOrder {
+    pub stock_symbol: String,
+    pub side: Side,
+    /// the highest price a buy pays, or the lowest a sell accepts
+    pub price: Decimal,
+    /// shares
+    pub quantity: Decimal,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlacedBookOrder {
+    pub order_id: i64,
+    pub fills: Vec<Fill>,
+    /// shares left resting in the book
+    pub resting: Decimal,
+    pub account_transaction: AccountTransaction,
+}
+
+/**
+ * Matches limit orders between traders. Each symbol's [OrderBook] is loaded from `book_order` the
+ * first time it is used and is only changed, under its lock, after the database transaction that
+ * settles the change commits, so the two never disagree.
+ */
+#[derive(Clone)]
+pub struct MatchingEngine {
+    pool: PgPool,
+    books: Arc<Mutex<HashMap<String, Arc<Mutex<OrderBook>>>>>,
+}
+
+impl MatchingEngine {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            books: Arc::default(),
+        }
+    }
+
+    async fn book(&self, stock_symbol: &str) -> anyhow::Result<Arc<Mutex<OrderBook>>> {
+        let mut books = self.books.lock().await;
+
+        if let Some(book) = books.get(stock_symbol) {
+            return Ok(book.clone());
+        }
+
+        let book = Arc::new(Mutex::new(load_book(&self.pool, stock_symbol).await?));
+        books.insert(stock_symbol.to_string(), book.clone());
+
+        Ok(book)
+    }
+
+    /**
+     * Like [Self::book], but `None` rather than a new empty book for a symbol without resting
+     * orders, so looking up symbols nobody trades doesn't grow `books`.
+     */
+    async fn existing_book(
+        &self,
+        stock_symbol: &str,
+    ) -> anyhow::Result<Option<Arc<Mutex<OrderBook>>>> {
+        let mut books = self.books.lock().await;
+
+        if let Some(book) = books.get(stock_symbol) {
+            return Ok(Some(book.clone()));
+        }
+
+        let book = load_book(&self.pool, stock_symbol).await?;
+        if book.is_empty() {
+            return Ok(None);
+        }
+
+        let book = Arc::new(Mutex::new(book));
+        books.insert(stock_symbol.to_string(), book.clone());
+
+        Ok(Some(book))
+    }
+
+    /**
+     * Reserves what the order needs, trades it against the crossing resting orders with price-time
+     * priority, and leaves whatever is unfilled resting in the book.
+     */
+    #[tracing::instrument(skip(self))]
+    pub async fn place(
+        &self,
+        user_id: &str,
+        order: NewBookOrder,
+        request_num: i32,
+    ) -> anyhow::Result<PlacedBookOrder> {
+        if order.quantity <= Decimal::ZERO {
+            bail!("order quantity must be positive, was {}", order.quantity);
+        }
+        if order.price <= Decimal::ZERO {
+            bail!("order price must be positive, was {}", order.price);
+        }
+
+        let book = self.book(&order.stock_symbol).await?;
+        let mut book = book.lock().await;
+
+        let fills = book.matches(order.side, user_id, order.price, order.quantity)?;
+        let resting = order.quantity - fills.iter().map(|it| it.quantity).sum::<Decimal>();
+
+        let mut transaction = begin_transaction(&self.pool).await?;
+
+        let (reserved_dollars, reserved) = reserve(&mut transaction, user_id, &order).await?;
+
+        let order_id = sqlx::query_scalar!(
+            "
+            INSERT INTO book_order (owner_id, stock_symbol, side, limit_price, quantity, reserved_dollars)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING order_id
+            ",
+            user_id,
+            order.stock_symbol,
+            order.side.as_str(),
+            order.price,
+            order.quantity,
+            reserved_dollars,
+        )
+        .fetch_one(transaction.deref_mut())
+        .await?;
+
+        let mut refunded = AccountTransaction(Decimal::ZERO);
+        for fill in &fills {
+            let taker = Taker {
+                order_id,
+                owner_id: user_id,
+                side: order.side,
+            };
+            refunded = refunded
+                + settle(
+                    &mut transaction,
+                    &order.stock_symbol,
+                    &taker,
+                    fill,
+                    request_num,
+                )
+                .await?;
+        }
+
+        commit_transaction(transaction).await?;
+
+        book.apply(order.side, &fills);
+        if !resting.is_zero() {
+            book.insert(
+                order.side,
+                RestingOrder {
+                    order_id,
+                    owner_id: user_id.to_string(),
+                    price: order.price,
+                    quantity: resting,
+                },
+            );
+        }
+
+        Ok(PlacedBookOrder {
+            order_id,
+            fills,
+            resting,
+            account_transaction: reserved + refunded,
+        })
+    }
+
+    /**
+     * Takes the user's resting order out of the book and returns what it still reserved.
+     */
+    #[tracing::instrument(skip(self))]
+    pub async fn cancel(&self, user_id: &str, order_id: i64) -> anyhow::Result<AccountTransaction> {
+        let no_order = || format!("no open book order {order_id} for user_id {user_id}");
+
+        let stock_symbol = sqlx::query_scalar!(
+            "SELECT stock_symbol FROM book_order WHERE order_id = $1 AND owner_id = $2",
+            order_id,
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .with_context(no_order)?;
+
+        let book = self.book(&stock_symbol).await?;
+        let mut book = book.lock().await;
+
+        let mut transaction = begin_transaction(&self.pool).await?;
+
+        let record = sqlx::query!(
+            "
+            DELETE FROM book_order WHERE order_id = $1 AND owner_id = $2
+            RETURNING side, limit_price, quantity, reserved_dollars
+            ",
+            order_id,
+            user_id
+        )
+        .fetch_optional(transaction.deref_mut())
+        .await?
+        .with_context(no_order)?;
+
+        let side = Side::try_from(record.side.as_str())?;
+
+        let refunded = match record.reserved_dollars {
+            Some(reserved_dollars) => {
+                refund_cash(&mut transaction, user_id, reserved_dollars).await?
+            }
+            None => {
+                holdings::release(&mut transaction, user_id, &stock_symbol, record.quantity)
+                    .await?;
+                AccountTransaction(Decimal::ZERO)
+            }
+        };
+
+        commit_transaction(transaction).await?;
+
+        book.cancel(side, record.limit_price, order_id);
+
+        Ok(refunded)
+    }
+
+    /**
+     * The best `levels` price levels on either side of the symbol's book.
+     */
+    #[tracing::instrument(skip(self))]
+    pub async fn depth(&self, stock_symbol: &str, levels: usize) -> anyhow::Result<Depth> {
+        let Some(book) = self.existing_book(stock_symbol).await? else {
+            return Ok(Depth::default());
+        };
+        let book = book.lock().await;
+        Ok(book.depth(levels))
+    }
+}
+
+#[tracing::instrument(skip(pool))]
+async fn load_book(pool: &PgPool, stock_symbol: &str) -> anyhow::Result<OrderBook> {
+    let records = sqlx::query!(
+        "
+        SELECT order_id, owner_id, side, limit_price, quantity FROM book_order
+        WHERE stock_symbol = $1
+        ORDER BY order_id
+        ",
+        stock_symbol
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut book = OrderBook::default();
+    for record in records {
+        book.insert(
+            Side::try_from(record.side.as_str())?,
+            RestingOrder {
+                order_id: record.order_id,
+                owner_id: record.owner_id,
+                price: record.limit_price,
+                quantity: record.quantity,
+            },
+        );
+    }
+
+    Ok(book)
+}
+
+/// returns the cash reserved by a buy, and the change to the trader's balance
+async fn reserve(
+    transaction: &mut Transaction<'static, Postgres>,
+    user_id: &str,
+    order: &NewBookOrder,
+) -> anyhow::Result<(Option<Decimal>, AccountTransaction)> {
+    if order.side == Side::Sell {
+        holdings::reserve(transaction, user_id, &order.stock_symbol, order.quantity).await?;
+        return Ok((None, AccountTransaction(Decimal::ZERO)));
+    }
+
+    let reserved_dollars = dollars_to_cover(order.quantity, order.price)?;
+
+    let result = sqlx::query!(
+        "UPDATE trader SET balance = balance - $1 WHERE user_id = $2 AND balance >= $1",
+        reserved_dollars,
+        user_id,
+    )
+    .execute(transaction.deref_mut())
+    .await?;
+
+    if result.rows_affected() == 0 {
+        bail!("Insufficient funds");
+    }
+
+    ledger::transfer(
+        transaction,
+        user_id,
+        Account::Cash,
+        Account::ReservedCash,
+        reserved_dollars,
+    )
+    .await?;
+
+    Ok((
+        Some(reserved_dollars),
+        AccountTransaction(-reserved_dollars),
+    ))
+}
+
+struct Taker<'a> {
+    order_id: i64,
+    owner_id: &'a str,
+    side: Side,
+}
+
+/**
+ * Moves the shares of a fill from seller to buyer and the cost from the buyer's reserved cash to
+ * the seller's balance. Returns the cash refunded to the taker if the fill completed a buy for less
+ * than it reserved. Refunds to the maker are logged here, the taker's are left to the caller.
+ */
+async fn settle(
+    transaction: &mut Transaction<'static, Postgres>,
+    stock_symbol: &str,
+    taker: &Taker<'_>,
+    fill: &Fill,
+    request_num: i32,
+) -> anyhow::Result<AccountTransaction> {
+    let cost = dollars_for_shares(fill.quantity, fill.price)?;
+
+    let ((buyer, buy_order), (seller, sell_order)) = match taker.side {
+        Side::Buy => (
+            (taker.owner_id, taker.order_id),
+            (fill.maker_owner_id.as_str(), fill.maker_order_id),
+        ),
+        Side::Sell => (
+            (fill.maker_owner_id.as_str(), fill.maker_order_id),
+            (taker.owner_id, taker.order_id),
+        ),
+    };
+
+    holdings::add(transaction, buyer, stock_symbol, fill.quantity, cost).await?;
+    ledger::transfer(
+        transaction,
+        buyer,
+        Account::ReservedCash,
+        Account::House,
+        cost,
+    )
+    .await?;
+    ledger::transfer_shares(
+        transaction,
+        buyer,
+        stock_symbol,
+        Account::House,
+        Account::Holdings,
+        fill.quantity,
+    )
+    .await?;
+    log_fill(
+        transaction,
+        buyer,
+        stock_symbol,
+        request_num,
+        CommandType::CommitBuy,
+        TransactionType::DebitReserved,
+        cost,
+    )
+    .await?;
+
+    holdings::remove_reserved(transaction, seller, stock_symbol, fill.quantity, cost).await?;
+    ledger::transfer_shares(
+        transaction,
+        seller,
+        stock_symbol,
+        Account::Holdings,
+        Account::House,
+        fill.quantity,
+    )
+    .await?;
+    sqlx::query!(
+        "UPDATE trader SET balance = balance + $2 WHERE user_id = $1",
+        seller,
+        cost
+    )
+    .execute(transaction.deref_mut())
+    .await?;
+    ledger::transfer(transaction, seller, Account::House, Account::Cash, cost).await?;
+    log_fill(
+        transaction,
+        seller,
+        stock_symbol,
+        request_num,
+        CommandType::CommitSell,
+        TransactionType::Add,
+        cost,
+    )
+    .await?;
+
+    for (user_id, side, order_id) in [
+        (buyer, Side::Buy, buy_order),
+        (seller, Side::Sell, sell_order),
+    ] {
+        events::publish(
+            transaction,
+            &AccountEvent::fill(
+                user_id,
+                side,
+                stock_symbol,
+                Some(order_id),
+                fill.quantity,
+                fill.price,
+                cost,
+            ),
+        )
+        .await?;
+    }
+
+    take_fill(transaction, sell_order, fill.quantity, Decimal::ZERO).await?;
+    let refunded = match take_fill(transaction, buy_order, fill.quantity, cost).await? {
+        Some(leftover) => refund_cash(transaction, buyer, leftover).await?,
+        None => AccountTransaction(Decimal::ZERO),
+    };
+
+    if buy_order == taker.order_id {
+        return Ok(refunded);
+    }
+
+    if !refunded.0.is_zero() {
+        save_log_entry(
+            transaction.deref_mut(),
+            LogEntry::new(
+                request_num,
+                buyer.to_string(),
+                Log::AccountChanges(AccountTransactionLog {
+                    action: TransactionType::Add.to_string(),
+                    funds: refunded.0,
+                }),
+            ),
+        )
+        .await?;
+    }
+
+    Ok(AccountTransaction(Decimal::ZERO))
+}
+
+/**
+ * Takes a fill and its cost out of a book order. Once nothing is left to fill the order is removed
+ * and, for a buy, the reserved cash it did not spend is returned.
+ */
+async fn take_fill(
+    transaction: &mut Transaction<'static, Postgres>,
+    order_id: i64,
+    quantity: Decimal,
+    cost: Decimal,
+) -> anyhow::Result<Option<Decimal>> {
+    let record = sqlx::query!(
+        "SELECT quantity, reserved_dollars FROM book_order WHERE order_id = $1 FOR UPDATE",
+        order_id
+    )
+    .fetch_one(transaction.deref_mut())
+    .await?;
+
+    let reserved_dollars = record.reserved_dollars.map(|it| it - cost);
+
+    if record.quantity > quantity {
+        sqlx::query!(
+            "UPDATE book_order SET quantity = $2, reserved_dollars = $3 WHERE order_id = $1",
+            order_id,
+            record.quantity - quantity,
+            reserved_dollars,
+        )
+        .execute(transaction.deref_mut())
+        .await?;
+        return Ok(None);
+    }
+
+    sqlx::query!("DELETE FROM book_order WHERE order_id = $1", order_id)
+        .execute(transaction.deref_mut())
+        .await?;
+
+    Ok(reserved_dollars)
+}
+
+async fn refund_cash(
+    transaction: &mut Transaction<'static, Postgres>,
+    user_id: &str,
+    amount: Decimal,
+) -> anyhow::Result<AccountTransaction> {
+    sqlx::query!(
+        "UPDATE trader SET balance = balance + $1 WHERE user_id = $2",
+        amount,
+        user_id
+    )
+    .execute(transaction.deref_mut())
+    .await?;
+
+    ledger::transfer(
+        transaction,
+        user_id,
+        Account::ReservedCash,
+        Account::Cash,
+        amount,
+    )
+    .await?;
+
+    Ok(AccountTransaction(amount))
+}
+
+async fn log_fill(
+    transaction: &mut Transaction<'static, Postgres>,
+    user_id: &str,
+    stock_symbol: &str,
+    request_num: i32,
+    command: CommandType,
+    action: TransactionType,
+    funds: Decimal,
+) -> anyhow::Result<()> {
+    save_log_entry(
+        transaction.deref_mut(),
+        LogEntry::new(
+            request_num,
+            user_id.to_string(),
+            Log::AccountChanges(AccountTransactionLog {
+                action: action.to_string(),
+                funds,
+            }),
+        ),
+    )
+    .await?;
+
+    save_log_entry(
+        transaction.deref_mut(),
+        LogEntry::new(
+            request_num,
+            user_id.to_string(),
+            Log::SystemEvents(SystemEventLog {
+                command,
+                stock_symbol: Some(stock_symbol.to_string()),
+                filename: None,
+                funds: Some(funds),
+            }),
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::add::add;
+    use crate::buy::{commit_buy, init_buy};
+    use crate::orders::OrderExpiry;
+    use pretty_assertions::assert_eq;
+    use rust_decimal_macros::dec;
+
+    fn order(side: Side, price: Decimal, quantity: Decimal) -> NewBookOrder {
+        NewBookOrder {
+            stock_symbol: "ABC".to_string(),
+            side,
+            price,
+            quantity,
+        }
+    }
+
+    async fn balance(pool: &PgPool, user_id: &str) -> anyhow::Result<Decimal> {
+        Ok(
+            sqlx::query_scalar!("SELECT balance FROM trader WHERE user_id = $1", user_id)
+                .fetch_one(pool)
+                .await?,
+        )
+    }
+
+    async fn shares(pool: &PgPool, user_id: &str) -> anyhow::Result<(Decimal, Decimal)> {
+        let record = sqlx::query!(
+            "SELECT amount, reserved FROM stock WHERE owner_id = $1 AND stock_symbol = 'ABC'",
+            user_id
+        )
+        .fetch_one(pool)
+        .await?;
+        Ok((record.amount, record.reserved))
+    }
+
+    /// alice has $100 and bob has 10 shares bought at $10
+    async fn setup(pool: &PgPool) -> anyhow::Result<()> {
+        let _log = add(pool, "alice", dec!(100)).await?;
+        let _log = add(pool, "bob", dec!(100)).await?;
+        let _log = init_buy(pool, "bob", "ABC", dec!(10), dec!(100)).await?;
+        commit_buy(pool, "bob", None, OrderExpiry::default().buy).await?;
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_match_between_traders(pool: PgPool) -> anyhow::Result<()> {
+        setup(&pool).await?;
+        let engine = MatchingEngine::new(pool.clone());
+
+        let ask = engine
+            .place("bob", order(Side::Sell, dec!(9), dec!(4)), 1)
+            .await?;
+        assert_eq!(ask.fills, vec![]);
+        assert_eq!(ask.resting, dec!(4));
+        assert_eq!(shares(&pool, "bob").await?, (dec!(10), dec!(4)));
+
+        // crosses the ask, trading at the ask's price and refunding the difference
+        let bid = engine
+            .place("alice", order(Side::Buy, dec!(10), dec!(6)), 2)
+            .await?;
+        assert_eq!(
+            bid.fills,
+            vec![Fill {
+                maker_order_id: ask.order_id,
+                maker_owner_id: "bob".to_string(),
+                price: dec!(9),
+                quantity: dec!(4),
+            }]
+        );
+        assert_eq!(bid.resting, dec!(2));
+        assert_eq!(bid.account_transaction, AccountTransaction(dec!(-60)));
+
+        assert_eq!(balance(&pool, "alice").await?, dec!(40));
+        assert_eq!(balance(&pool, "bob").await?, dec!(36));
+        assert_eq!(shares(&pool, "alice").await?, (dec!(4), dec!(0)));
+        assert_eq!(shares(&pool, "bob").await?, (dec!(6), dec!(0)));
+
+        // the rest of the bid fills later against a new ask, refunding what it did not spend
+        let ask = engine
+            .place("bob", order(Side::Sell, dec!(8), dec!(5)), 3)
+            .await?;
+        assert_eq!(ask.fills.len(), 1);
+        assert_eq!(ask.fills[0].price, dec!(10));
+        assert_eq!(ask.resting, dec!(3));
+        assert_eq!(balance(&pool, "alice").await?, dec!(44));
+        assert_eq!(balance(&pool, "bob").await?, dec!(56));
+        assert_eq!(shares(&pool, "alice").await?, (dec!(6), dec!(0)));
+
+        let depth = engine.depth("ABC", 10).await?;
+        assert_eq!(depth.bids, vec![]);
+        assert_eq!(depth.asks.len(), 1);
+        assert_eq!(depth.asks[0].quantity, dec!(3));
+
+        let discrepancies = ledger::audit(&pool).await?;
+        assert_eq!(discrepancies, vec![]);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_maker_refund(pool: PgPool) -> anyhow::Result<()> {
+        setup(&pool).await?;
+        let engine = MatchingEngine::new(pool.clone());
+
+        // reserves $3.34 to cover a third of a share at $10, but spends $3.33
+        let bid = engine
+            .place("alice", order(Side::Buy, dec!(10), dec!(0.333333)), 1)
+            .await?;
+        assert_eq!(bid.account_transaction, AccountTransaction(dec!(-3.34)));
+
+        let ask = engine
+            .place("bob", order(Side::Sell, dec!(9), dec!(0.333333)), 2)
+            .await?;
+        assert_eq!(ask.resting, dec!(0));
+        assert_eq!(ask.account_transaction, AccountTransaction(dec!(0)));
+        assert_eq!(balance(&pool, "alice").await?, dec!(100) - dec!(3.33));
+        assert_eq!(balance(&pool, "bob").await?, dec!(3.33));
+
+        let remaining = sqlx::query_scalar!("SELECT count(*) FROM book_order")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(remaining, Some(0));
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_cancel_and_reload(pool: PgPool) -> anyhow::Result<()> {
+        setup(&pool).await?;
+        let engine = MatchingEngine::new(pool.clone());
+
+        let bid = engine
+            .place("alice", order(Side::Buy, dec!(5), dec!(10)), 1)
+            .await?;
+        let ask = engine
+            .place("bob", order(Side::Sell, dec!(6), dec!(2)), 2)
+            .await?;
+
+        // a fresh engine sees the same book
+        let engine = MatchingEngine::new(pool.clone());
+        let depth = engine.depth("ABC", 10).await?;
+        assert_eq!(depth.bids.len(), 1);
+        assert_eq!(depth.asks.len(), 1);
+
+        assert!(engine.cancel("bob", bid.order_id).await.is_err());
+
+        let refunded = engine.cancel("alice", bid.order_id).await?;
+        assert_eq!(refunded, AccountTransaction(dec!(50)));
+        assert_eq!(balance(&pool, "alice").await?, dec!(100));
+
+        let _refunded = engine.cancel("bob", ask.order_id).await?;
+        assert_eq!(shares(&pool, "bob").await?, (dec!(10), dec!(0)));
+
+        assert_eq!(engine.depth("ABC", 10).await?, Depth::default());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_depth_does_not_cache_empty_books(pool: PgPool) -> anyhow::Result<()> {
+        setup(&pool).await?;
+        let engine = MatchingEngine::new(pool.clone());
+        engine
+            .place("bob", order(Side::Sell, dec!(6), dec!(2)), 1)
+            .await?;
+
+        let engine = MatchingEngine::new(pool.clone());
+        assert_eq!(engine.depth("XYZ", 10).await?, Depth::default());
+        assert!(engine.books.lock().await.is_empty());
+
+        assert_eq!(engine.depth("ABC", 10).await?.asks.len(), 1);
+        assert_eq!(
+            engine.books.lock().await.keys().collect::<Vec<_>>(),
+            vec!["ABC"]
+        );
+
+        Ok(())
+    }
+}
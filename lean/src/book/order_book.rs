@@ -0,0 +1,373 @@
+use crate::proto;
+use anyhow::bail;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, VecDeque};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl Side {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Side::Buy => "buy",
+            Side::Sell => "sell",
+        }
+    }
+}
+
+impl TryFrom<&str> for Side {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "buy" => Ok(Side::Buy),
+            "sell" => Ok(Side::Sell),
+            _ => bail!("unknown side {value}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RestingOrder {
+    pub order_id: i64,
+    pub owner_id: String,
+    pub price: Decimal,
+    /// shares still unfilled
+    pub quantity: Decimal,
+}
+
+/**
+ * Part of an incoming order matched against a resting one, at the resting order's price.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fill {
+    pub maker_order_id: i64,
+    pub maker_owner_id: String,
+    pub price: Decimal,
+    pub quantity: Decimal,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Level {
+    pub price: Decimal,
+    pub quantity: Decimal,
+    pub orders: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Depth {
+    /// best (highest) first
+    pub bids: Vec<Level>,
+    /// best (lowest) first
+    pub asks: Vec<Level>,
+}
+
+/**
+ * The resting orders of one symbol, by price level and oldest first within a level.
+ */
+#[derive(Debug, Default)]
+pub struct OrderBook {
+    bids: BTreeMap<Reverse<Decimal>, VecDeque<RestingOrder>>,
+    asks: BTreeMap<Decimal, VecDeque<RestingOrder>>,
+}
+
+impl OrderBook {
+    pub fn insert(&mut self, side: Side, order: RestingOrder) {
+        match side {
+            Side::Buy => self.bids.entry(Reverse(order.price)).or_default(),
+            Side::Sell => self.asks.entry(order.price).or_default(),
+        }
+        .push_back(order);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bids.is_empty() && self.asks.is_empty()
+    }
+
+    pub fn cancel(&mut self, side: Side, price: Decimal, order_id: i64) -> Option<RestingOrder> {
+        let (level, empty) = match side {
+            Side::Buy => {
+                let level = self.bids.get_mut(&Reverse(price))?;
+                (remove_order(level, order_id), level.is_empty())
+            }
+            Side::Sell => {
+                let level = self.asks.get_mut(&price)?;
+                (remove_order(level, order_id), level.is_empty())
+            }
+        };
+        if empty {
+            match side {
+                Side::Buy => self.bids.remove(&Reverse(price)),
+                Side::Sell => self.asks.remove(&price),
+            };
+        }
+        level
+    }
+
+    /**
+     * The fills an incoming order would take, best price first and oldest first within a price,
+     * without changing the book. Errors rather than trade a user with themselves.
+     */
+    pub fn matches(
+        &self,
+        side: Side,
+        owner_id: &str,
+        limit: Decimal,
+        quantity: Decimal,
+    ) -> anyhow::Result<Vec<Fill>> {
+        let mut remaining = quantity;
+        let mut fills = Vec::new();
+
+        for maker in self.opposite(side) {
+            let crosses = match side {
+                Side::Buy => maker.price <= limit,
+                Side::Sell => maker.price >= limit,
+            };
+            if remaining.is_zero() || !crosses {
+                break;
+            }
+            if maker.owner_id == owner_id {
+                bail!("order would trade with your own order {}", maker.order_id);
+            }
+
+            let quantity = remaining.min(maker.quantity);
+            remaining -= quantity;
+            fills.push(Fill {
+                maker_order_id: maker.order_id,
+                maker_owner_id: maker.owner_id.clone(),
+                price: maker.price,
+                quantity,
+            });
+        }
+
+        Ok(fills)
+    }
+
+    /**
+     * Takes fills returned by [OrderBook::matches] out of the resting orders they matched.
+     */
+    pub fn apply(&mut self, side: Side, fills: &[Fill]) {
+        for fill in fills {
+            match side {
+                Side::Buy => {
+                    if let Some(level) = self.asks.get_mut(&fill.price) {
+                        if take_fill(level, fill) {
+                            self.asks.remove(&fill.price);
+                        }
+                    }
+                }
+                Side::Sell => {
+                    if let Some(level) = self.bids.get_mut(&Reverse(fill.price)) {
+                        if take_fill(level, fill) {
+                            self.bids.remove(&Reverse(fill.price));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /**
+     * The total resting quantity at each of the best `levels` prices on either side.
+     */
+    pub fn depth(&self, levels: usize) -> Depth {
+        Depth {
+            bids: self
+                .bids
+                .iter()
+                .take(levels)
+                .map(|(Reverse(price), orders)| level(*price, orders))
+                .collect(),
+            asks: self
+                .asks
+                .iter()
+                .take(levels)
+                .map(|(price, orders)| level(*price, orders))
+                .collect(),
+        }
+    }
+
+    /// the resting orders an incoming order on `side` could match, in priority order
+    fn opposite(&self, side: Side) -> Box<dyn Iterator<Item = &RestingOrder> + '_> {
+        match side {
+            Side::Buy => Box::new(self.asks.values().flatten()),
+            Side::Sell => Box::new(self.bids.values().flatten()),
+        }
+    }
+}
+
+fn remove_order(level: &mut VecDeque<RestingOrder>, order_id: i64) -> Option<RestingOrder> {
+    let index = level.iter().position(|it| it.order_id == order_id)?;
+    level.remove(index)
+}
+
+/// returns whether the level is now empty
+fn take_fill(level: &mut VecDeque<RestingOrder>, fill: &Fill) -> bool {
+    if let Some(maker) = level
+        .iter_mut()
+        .find(|it| it.order_id == fill.maker_order_id)
+    {
+        maker.quantity -= fill.quantity;
+    }
+    level.retain(|it| !it.quantity.is_zero());
+    level.is_empty()
+}
+
+fn level(price: Decimal, orders: &VecDeque<RestingOrder>) -> Level {
+    Level {
+        price,
+        quantity: orders.iter().map(|it| it.quantity).sum(),
+        orders: orders.len(),
+    }
+}
+
+impl From<proto::OrderSide> for Side {
+    fn from(value: proto::OrderSide) -> Self {
+        match value {
+            proto::OrderSide::Buy => Side::Buy,
+            proto::OrderSide::Sell => Side::Sell,
+        }
+    }
+}
+
+impl From<Fill> for proto::BookFill {
+    fn from(fill: Fill) -> Self {
+        proto::BookFill {
+            order_id: fill.maker_order_id,
+            price: fill.price.to_string(),
+            quantity: fill.quantity.to_string(),
+        }
+    }
+}
+
+impl From<Level> for proto::BookLevel {
+    fn from(level: Level) -> Self {
+        proto::BookLevel {
+            price: level.price.to_string(),
+            quantity: level.quantity.to_string(),
+            orders: level.orders.try_into().unwrap_or(i32::MAX),
+        }
+    }
+}
+
+impl From<Depth> for proto::GetBookDepthResponse {
+    fn from(depth: Depth) -> Self {
+        proto::GetBookDepthResponse {
+            bids: depth.bids.into_iter().map(proto::BookLevel::from).collect(),
+            asks: depth.asks.into_iter().map(proto::BookLevel::from).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rust_decimal_macros::dec;
+
+    fn resting(order_id: i64, owner_id: &str, price: Decimal, quantity: Decimal) -> RestingOrder {
+        RestingOrder {
+            order_id,
+            owner_id: owner_id.to_string(),
+            price,
+            quantity,
+        }
+    }
+
+    fn book() -> OrderBook {
+        let mut book = OrderBook::default();
+        book.insert(Side::Sell, resting(1, "alice", dec!(10.5), dec!(3)));
+        book.insert(Side::Sell, resting(2, "bob", dec!(10), dec!(2)));
+        book.insert(Side::Sell, resting(3, "carol", dec!(10), dec!(4)));
+        book.insert(Side::Buy, resting(4, "alice", dec!(9), dec!(5)));
+        book.insert(Side::Buy, resting(5, "bob", dec!(9.5), dec!(1)));
+        book
+    }
+
+    #[test]
+    fn test_price_time_priority() {
+        let mut book = book();
+
+        let fills = book
+            .matches(Side::Buy, "dave", dec!(10.5), dec!(7))
+            .unwrap();
+        let fills_summary = fills
+            .iter()
+            .map(|it| (it.maker_order_id, it.price, it.quantity))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fills_summary,
+            vec![
+                (2, dec!(10), dec!(2)),
+                (3, dec!(10), dec!(4)),
+                (1, dec!(10.5), dec!(1)),
+            ]
+        );
+
+        book.apply(Side::Buy, &fills);
+        assert_eq!(
+            book.depth(10).asks,
+            vec![Level {
+                price: dec!(10.5),
+                quantity: dec!(2),
+                orders: 1
+            }]
+        );
+    }
+
+    #[test]
+    fn test_limit_stops_matching() {
+        let book = book();
+
+        let fills = book
+            .matches(Side::Sell, "dave", dec!(9.5), dec!(5))
+            .unwrap();
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].maker_order_id, 5);
+        assert_eq!(fills[0].quantity, dec!(1));
+
+        assert!(book
+            .matches(Side::Buy, "dave", dec!(9.99), dec!(5))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_no_self_trade() {
+        let book = book();
+        assert!(book.matches(Side::Buy, "bob", dec!(10), dec!(1)).is_err());
+    }
+
+    #[test]
+    fn test_cancel_and_depth() {
+        let mut book = book();
+
+        assert_eq!(book.cancel(Side::Sell, dec!(10), 99), None);
+        assert_eq!(
+            book.cancel(Side::Buy, dec!(9.5), 5),
+            Some(resting(5, "bob", dec!(9.5), dec!(1)))
+        );
+
+        assert_eq!(
+            book.depth(1),
+            Depth {
+                bids: vec![Level {
+                    price: dec!(9),
+                    quantity: dec!(5),
+                    orders: 1
+                }],
+                asks: vec![Level {
+                    price: dec!(10),
+                    quantity: dec!(6),
+                    orders: 2
+                }],
+            }
+        );
+    }
+}
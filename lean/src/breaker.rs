@@ -0,0 +1,305 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tonic::{Code, Status};
+use tracing::{info, warn};
+
+/**
+ * When to stop asking the quote server for prices, and for how long.
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BreakerConfig {
+    /// consecutive failures that open the breaker
+    pub failure_threshold: u32,
+    /// how long the breaker stays open before a request is let through to see if the quote server
+    /// is back
+    pub open_for: Duration,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            open_for: Duration::from_secs(10),
+        }
+    }
+}
+
+impl BreakerConfig {
+    pub fn from_env() -> Self {
+        let default = Self::default();
+
+        let failure_threshold = std::env::var("QUOTE_BREAKER_FAILURE_THRESHOLD")
+            .map(|it| {
+                it.parse::<u32>()
+                    .expect("QUOTE_BREAKER_FAILURE_THRESHOLD must be a number")
+            })
+            .unwrap_or(default.failure_threshold);
+
+        let open_for = std::env::var("QUOTE_BREAKER_OPEN_SECONDS")
+            .map(|it| {
+                it.parse::<u64>()
+                    .expect("QUOTE_BREAKER_OPEN_SECONDS must be a number")
+            })
+            .map_or(default.open_for, Duration::from_secs);
+
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            open_for,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Closed {
+        failures: u32,
+    },
+    Open {
+        since: Instant,
+    },
+    /// one request is finding out whether the quote server is back
+    HalfOpen {
+        since: Instant,
+    },
+}
+
+/**
+ * Fails quotes fast while the quote server keeps failing, rather than making every request wait on
+ * it. Once open, a single request is let through every `open_for` and closes the breaker again if
+ * it succeeds.
+ */
+#[derive(Clone)]
+pub struct CircuitBreaker {
+    config: BreakerConfig,
+    state: Arc<Mutex<State>>,
+}
+
+/**
+ * Why a quote couldn't be had. Surfaced as `UNAVAILABLE` by [status].
+ */
+#[derive(Debug, thiserror::Error)]
+pub enum QuoteUnavailable {
+    #[error("the quote server is unavailable, not quoting {0} until it recovers")]
+    CircuitOpen(String),
+    #[error("failed to get a quote for {0}")]
+    Failed(String),
+}
+
+impl CircuitBreaker {
+    pub fn new(config: BreakerConfig) -> Self {
+        Self {
+            config,
+            state: Arc::new(Mutex::new(State::Closed { failures: 0 })),
+        }
+    }
+
+    /**
+     * Whether to call the quote server now.
+     */
+    pub fn allow(&self) -> bool {
+        self.allow_at(Instant::now())
+    }
+
+    pub fn is_open(&self) -> bool {
+        !matches!(*self.lock(), State::Closed { .. })
+    }
+
+    pub fn record_success(&self) {
+        let mut state = self.lock();
+        if !matches!(*state, State::Closed { .. }) {
+            info!("quote server recovered, closing the circuit breaker");
+        }
+        *state = State::Closed { failures: 0 };
+    }
+
+    pub fn record_failure(&self) {
+        self.record_failure_at(Instant::now())
+    }
+
+    /**
+     * Records an error from the quote server. One that doesn't mean it is unhealthy, say
+     * `NOT_FOUND` for an unknown symbol, still shows it is answering, so it counts as a success
+     * and ends any probe rather than leaving the breaker half open.
+     */
+    pub fn record_status(&self, status: &Status) {
+        self.record_status_at(status, Instant::now())
+    }
+
+    fn allow_at(&self, now: Instant) -> bool {
+        let mut state = self.lock();
+        match *state {
+            State::Closed { .. } => true,
+            // a probe that never reports back, say because its request was dropped, is given up
+            // on as well
+            State::Open { since } | State::HalfOpen { since }
+                if now.duration_since(since) >= self.config.open_for =>
+            {
+                *state = State::HalfOpen { since: now };
+                true
+            }
+            State::Open { .. } | State::HalfOpen { .. } => false,
+        }
+    }
+
+    fn record_failure_at(&self, now: Instant) {
+        let mut state = self.lock();
+        *state = match *state {
+            State::Closed { failures } if failures + 1 < self.config.failure_threshold => {
+                State::Closed {
+                    failures: failures + 1,
+                }
+            }
+            State::Closed { .. } | State::HalfOpen { .. } => {
+                warn!(
+                    "quote server is failing, opening the circuit breaker for {:?}",
+                    self.config.open_for
+                );
+                State::Open { since: now }
+            }
+            // a request sent before the breaker opened
+            open @ State::Open { .. } => open,
+        };
+    }
+
+    fn record_status_at(&self, status: &Status, now: Instant) {
+        if is_failure(status) {
+            self.record_failure_at(now);
+        } else {
+            self.record_success();
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().expect("circuit breaker lock poisoned")
+    }
+}
+
+/**
+ * Whether `status` from the quote server means it is unhealthy, rather than that the request was
+ * bad.
+ */
+pub fn is_failure(status: &Status) -> bool {
+    matches!(
+        status.code(),
+        Code::Unavailable
+            // what tonic makes of the quote channel's request timeout
+            | Code::Cancelled
+            | Code::DeadlineExceeded
+            | Code::Internal
+            | Code::Unknown
+            | Code::ResourceExhausted
+    )
+}
+
+/**
+ * `UNAVAILABLE` if `err` came from not getting a quote, so clients know to retry later, and
+ * `INTERNAL` otherwise.
+ */
+pub fn status(err: &anyhow::Error, message: String) -> Status {
+    if err.downcast_ref::<QuoteUnavailable>().is_some() {
+        Status::unavailable(message)
+    } else {
+        Status::internal(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::quote_client::QuoteClient;
+    use crate::proto::QuoteRequest;
+    use pretty_assertions::assert_eq;
+    use tonic::transport::Channel;
+
+    #[test]
+    fn test_opens_after_threshold_and_probes() {
+        let breaker = CircuitBreaker::new(BreakerConfig {
+            failure_threshold: 2,
+            open_for: Duration::from_secs(10),
+        });
+        let start = Instant::now();
+
+        breaker.record_failure_at(start);
+        assert!(breaker.allow_at(start));
+        breaker.record_failure_at(start);
+        assert!(!breaker.allow_at(start + Duration::from_secs(9)));
+
+        // one probe at a time once open_for has passed
+        let probe = start + Duration::from_secs(10);
+        assert!(breaker.allow_at(probe));
+        assert!(!breaker.allow_at(probe));
+
+        // a failed probe opens it again
+        breaker.record_failure_at(probe);
+        assert_eq!(*breaker.lock(), State::Open { since: probe });
+        assert!(!breaker.allow_at(probe + Duration::from_secs(1)));
+
+        // a successful one closes it
+        assert!(breaker.allow_at(probe + Duration::from_secs(10)));
+        breaker.record_success();
+        assert!(!breaker.is_open());
+        assert!(breaker.allow_at(probe + Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn test_probes_end_on_any_answer() {
+        let breaker = CircuitBreaker::new(BreakerConfig {
+            failure_threshold: 1,
+            open_for: Duration::from_secs(10),
+        });
+        let start = Instant::now();
+        breaker.record_failure_at(start);
+
+        // the quote server answering at all closes it
+        let probe = start + Duration::from_secs(10);
+        assert!(breaker.allow_at(probe));
+        breaker.record_status_at(&Status::not_found("no such symbol"), probe);
+        assert_eq!(*breaker.lock(), State::Closed { failures: 0 });
+
+        // failing statuses still open it
+        breaker.record_status_at(&Status::unavailable("down"), probe);
+        assert_eq!(*breaker.lock(), State::Open { since: probe });
+
+        let probe = probe + Duration::from_secs(10);
+        assert!(breaker.allow_at(probe));
+        breaker.record_status_at(&Status::internal("broken"), probe);
+        assert_eq!(*breaker.lock(), State::Open { since: probe });
+    }
+
+    #[test]
+    fn test_status() {
+        let unavailable = anyhow::Error::new(QuoteUnavailable::CircuitOpen("AAPL".to_string()))
+            .context("failed to buy");
+        assert_eq!(
+            status(&unavailable, "buy".to_string()).code(),
+            Code::Unavailable
+        );
+
+        let other = anyhow::anyhow!("insufficient funds").context("failed to buy");
+        assert_eq!(status(&other, "buy".to_string()).code(), Code::Internal);
+    }
+
+    #[tokio::test]
+    async fn test_timed_out_quotes_are_failures() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        // accepts connections and never answers on them
+        tokio::spawn(async move {
+            let mut connections = vec![];
+            while let Ok((stream, _)) = listener.accept().await {
+                connections.push(stream);
+            }
+        });
+
+        let channel = Channel::from_shared(format!("http://{addr}"))
+            .unwrap()
+            .timeout(Duration::from_millis(100))
+            .connect_lazy();
+        let status = QuoteClient::new(channel)
+            .quote(QuoteRequest::default())
+            .await
+            .unwrap_err();
+
+        assert!(is_failure(&status), "{status:?}");
+    }
+}
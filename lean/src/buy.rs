@@ -9,38 +9,41 @@ mod init_buy;
 #[cfg(test)]
 mod tests {
     use pretty_assertions::assert_eq;
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
     use sqlx::PgPool;
     use time::PrimitiveDateTime;
 
     use crate::buy::cancel_buy::cancel_buy;
     use crate::buy::init_buy::init_buy;
+    use crate::orders::OrderExpiry;
 
     use super::*;
 
     #[sqlx::test]
     async fn test_init_no_user(pool: PgPool) -> anyhow::Result<()> {
-        let response = init_buy(&pool, "marcus", "APPL", 50_f64, 100_f64).await;
+        let response = init_buy(&pool, "marcus", "APPL", dec!(50), dec!(100)).await;
         assert!(response.is_err(), "expected error but was {response:?}");
         Ok(())
     }
 
     #[derive(Debug, PartialEq)]
     struct Balance {
-        balance: f64,
+        balance: Decimal,
     }
 
     #[derive(Debug, PartialEq)]
     struct QueuedBuyNoTime {
         user_id: String,
         stock_symbol: String,
-        quoted_price: f64,
-        amount_dollars: f64,
+        quoted_price: Decimal,
+        amount_dollars: Decimal,
     }
 
     #[sqlx::test]
     async fn test_sufficient_funds(pool: PgPool) -> anyhow::Result<()> {
-        let _log = crate::add::add(&pool, "marcus", 100_f64).await?;
-        let buy = init_buy(&pool, "marcus", "APPL", 50_f64, 100_f64).await;
+        let _log = crate::add::add(&pool, "marcus", dec!(100)).await?;
+        let buy = init_buy(&pool, "marcus", "APPL", dec!(50), dec!(100)).await;
 
         assert!(buy.is_ok(), "expected ok but was {buy:?}");
 
@@ -51,7 +54,11 @@ mod tests {
         .fetch_one(&pool)
         .await?;
 
-        assert_eq!(balance, 0_f64, "expected balance to be 0 but was {balance}");
+        assert_eq!(
+            balance,
+            dec!(0),
+            "expected balance to be 0 but was {balance}"
+        );
 
         let queued_buy = sqlx::query_as!(
             QueuedBuyNoTime,
@@ -65,8 +72,8 @@ mod tests {
             QueuedBuyNoTime {
                 user_id: String::from("marcus"),
                 stock_symbol: String::from("APPL"),
-                quoted_price: 50_f64,
-                amount_dollars: 100_f64,
+                quoted_price: dec!(50),
+                amount_dollars: dec!(100),
             }
         );
 
@@ -75,20 +82,22 @@ mod tests {
 
     #[sqlx::test]
     async fn test_insufficient_funds(pool: PgPool) -> anyhow::Result<()> {
-        let _log = crate::add::add(&pool, "marcus", 100_f64).await?;
-        let buy = init_buy(&pool, "marcus", "AAPL", 50_f64, 200_f64).await;
+        let _log = crate::add::add(&pool, "marcus", dec!(100)).await?;
+        let buy = init_buy(&pool, "marcus", "AAPL", dec!(50), dec!(200)).await;
         assert!(buy.is_err(), "expected error but was {buy:?}");
         Ok(())
     }
 
     #[sqlx::test]
-    async fn test_override_queued_buy(pool: PgPool) -> anyhow::Result<()> {
-        let _log = crate::add::add(&pool, "marcus", 400_f64).await?;
-        let _log = init_buy(&pool, "marcus", "AAPL", 50_f64, 200_f64).await?;
+    async fn test_multiple_queued_buys(pool: PgPool) -> anyhow::Result<()> {
+        let _log = crate::add::add(&pool, "marcus", dec!(400)).await?;
+        let (first, _log) = init_buy(&pool, "marcus", "AAPL", dec!(50), dec!(200)).await?;
 
-        let buy = init_buy(&pool, "marcus", "TSLA", 50_f64, 100_f64).await;
+        let buy = init_buy(&pool, "marcus", "TSLA", dec!(50), dec!(100)).await;
 
         assert!(buy.is_ok(), "expected ok but was {buy:?}");
+        let (second, _log) = buy?;
+        assert_ne!(first, second);
 
         let Balance { balance } = sqlx::query_as!(
             Balance,
@@ -98,34 +107,67 @@ mod tests {
         .await?;
 
         assert_eq!(
-            balance, 300_f64,
-            "expected balance to be 300 but was {balance}"
+            balance,
+            dec!(100),
+            "expected balance to be 100 but was {balance}"
         );
 
-        let queued_buy = sqlx::query_as!(
+        let queued_buys = sqlx::query_as!(
             QueuedBuyNoTime,
-            "SELECT user_id, stock_symbol, quoted_price, amount_dollars FROM queued_buy WHERE user_id = 'marcus'"
+            "SELECT user_id, stock_symbol, quoted_price, amount_dollars FROM queued_buy WHERE user_id = 'marcus' ORDER BY order_id"
         )
-        .fetch_one(&pool)
+        .fetch_all(&pool)
         .await?;
 
         assert_eq!(
-            queued_buy,
-            QueuedBuyNoTime {
-                user_id: String::from("marcus"),
-                stock_symbol: String::from("TSLA"),
-                quoted_price: 50_f64,
-                amount_dollars: 100_f64,
-            }
+            queued_buys,
+            vec![
+                QueuedBuyNoTime {
+                    user_id: String::from("marcus"),
+                    stock_symbol: String::from("AAPL"),
+                    quoted_price: dec!(50),
+                    amount_dollars: dec!(200),
+                },
+                QueuedBuyNoTime {
+                    user_id: String::from("marcus"),
+                    stock_symbol: String::from("TSLA"),
+                    quoted_price: dec!(50),
+                    amount_dollars: dec!(100),
+                }
+            ]
         );
 
         Ok(())
     }
 
+    #[sqlx::test]
+    async fn commit_buy_defaults_to_most_recent(pool: PgPool) -> anyhow::Result<()> {
+        let _log = crate::add::add(&pool, "marcus", dec!(400)).await?;
+        let (first, _log) = init_buy(&pool, "marcus", "AAPL", dec!(50), dec!(200)).await?;
+        let _log = init_buy(&pool, "marcus", "TSLA", dec!(50), dec!(100)).await?;
+
+        commit_buy(&pool, "marcus", None, OrderExpiry::default().buy).await?;
+
+        let remaining =
+            sqlx::query_scalar!("SELECT order_id FROM queued_buy WHERE user_id = 'marcus'")
+                .fetch_all(&pool)
+                .await?;
+        assert_eq!(remaining, vec![first]);
+
+        let tsla = sqlx::query_scalar!(
+            "SELECT amount FROM stock WHERE owner_id = 'marcus' AND stock_symbol = 'TSLA'"
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(tsla, dec!(2));
+
+        Ok(())
+    }
+
     #[sqlx::test]
     async fn init_buy_removes_funds(pool: PgPool) -> anyhow::Result<()> {
-        let _log = crate::add::add(&pool, "marcus", 400_f64).await?;
-        let _log = init_buy(&pool, "marcus", "AAPL", 50_f64, 200_f64).await?;
+        let _log = crate::add::add(&pool, "marcus", dec!(400)).await?;
+        let _log = init_buy(&pool, "marcus", "AAPL", dec!(50), dec!(200)).await?;
 
         let Balance { balance } = sqlx::query_as!(
             Balance,
@@ -135,7 +177,8 @@ mod tests {
         .await?;
 
         assert_eq!(
-            balance, 200_f64,
+            balance,
+            dec!(200),
             "expected balance to be 200 but was {balance}"
         );
 
@@ -144,7 +187,7 @@ mod tests {
 
     #[sqlx::test]
     async fn commit_buy_no_buy(pool: PgPool) -> anyhow::Result<()> {
-        let buy = commit_buy(&pool, "marcus").await;
+        let buy = commit_buy(&pool, "marcus", None, OrderExpiry::default().buy).await;
         assert!(buy.is_err(), "expected error but was {buy:?}");
         Ok(())
     }
@@ -153,23 +196,25 @@ mod tests {
     struct Stock {
         owner_id: String,
         stock_symbol: String,
-        amount: f64,
+        amount: Decimal,
+        reserved: Decimal,
     }
 
     #[derive(Debug, PartialEq)]
     struct QueuedBuy {
         user_id: String,
         stock_symbol: String,
-        quoted_price: f64,
-        amount_dollars: f64,
+        quoted_price: Decimal,
+        amount_dollars: Decimal,
         time_created: PrimitiveDateTime,
+        order_id: i64,
     }
 
     #[sqlx::test]
     async fn commit_buy_with_buy(pool: PgPool) -> anyhow::Result<()> {
-        let _log = crate::add::add(&pool, "marcus", 400_f64).await?;
-        let _log = init_buy(&pool, "marcus", "AAPL", 50_f64, 200_f64).await?;
-        let buy = commit_buy(&pool, "marcus").await;
+        let _log = crate::add::add(&pool, "marcus", dec!(400)).await?;
+        let _log = init_buy(&pool, "marcus", "AAPL", dec!(50), dec!(200)).await?;
+        let buy = commit_buy(&pool, "marcus", None, OrderExpiry::default().buy).await;
         assert!(buy.is_ok(), "expected error but was {buy:?}");
 
         let queued_buy = sqlx::query_as!(
@@ -183,7 +228,10 @@ mod tests {
 
         let appl = sqlx::query_as!(
             Stock,
-            "SELECT * FROM stock WHERE owner_id = 'marcus' AND stock_symbol = 'AAPL'"
+            "
+            SELECT owner_id, stock_symbol, amount, reserved FROM stock
+            WHERE owner_id = 'marcus' AND stock_symbol = 'AAPL'
+            "
         )
         .fetch_optional(&pool)
         .await?;
@@ -193,7 +241,8 @@ mod tests {
             Some(Stock {
                 owner_id: String::from("marcus"),
                 stock_symbol: String::from("AAPL"),
-                amount: 4_f64,
+                amount: dec!(4),
+                reserved: dec!(0),
             })
         );
 
@@ -202,15 +251,15 @@ mod tests {
 
     #[sqlx::test]
     async fn commit_timed_out_buy(pool: PgPool) -> anyhow::Result<()> {
-        let _log = crate::add::add(&pool, "marcus", 400_f64).await?;
+        let _log = crate::add::add(&pool, "marcus", dec!(400)).await?;
 
-        let _log = init_buy(&pool, "marcus", "AAPL", 50_f64, 200_f64).await?;
+        let _log = init_buy(&pool, "marcus", "AAPL", dec!(50), dec!(200)).await?;
 
         sqlx::query!("UPDATE queued_buy SET time_created = time_created - interval '6 minutes' WHERE user_id = 'marcus'")
             .execute(&pool)
             .await?;
 
-        let buy = commit_buy(&pool, "marcus").await;
+        let buy = commit_buy(&pool, "marcus", None, OrderExpiry::default().buy).await;
 
         assert!(buy.is_err(), "expected error but was {buy:?}");
 
@@ -230,23 +279,23 @@ mod tests {
         .fetch_one(&pool)
         .await?;
 
-        assert_eq!(balance, 400_f64);
+        assert_eq!(balance, dec!(400));
 
         Ok(())
     }
 
     #[sqlx::test]
     async fn test_cancel_buy_with_no_pending_buy(pool: PgPool) -> anyhow::Result<()> {
-        let cancel = cancel_buy(&pool, "marcus").await;
+        let cancel = cancel_buy(&pool, "marcus", None, OrderExpiry::default().buy).await;
         assert!(cancel.is_err(), "expected error but was {cancel:?}");
         Ok(())
     }
 
     #[sqlx::test]
     async fn test_cancel_buy_with_pending_buy(pool: PgPool) -> anyhow::Result<()> {
-        let _log = crate::add::add(&pool, "marcus", 400_f64).await?;
-        let _log = init_buy(&pool, "marcus", "AAPL", 50_f64, 200_f64).await?;
-        let cancel = cancel_buy(&pool, "marcus").await;
+        let _log = crate::add::add(&pool, "marcus", dec!(400)).await?;
+        let _log = init_buy(&pool, "marcus", "AAPL", dec!(50), dec!(200)).await?;
+        let cancel = cancel_buy(&pool, "marcus", None, OrderExpiry::default().buy).await;
         assert!(cancel.is_ok(), "expected ok but was {cancel:?}");
 
         let queued_buy = sqlx::query_as!(
@@ -266,7 +315,8 @@ mod tests {
         .await?;
 
         assert_eq!(
-            balance, 400_f64,
+            balance,
+            dec!(400),
             "expected balance to be 400 but was {balance}"
         );
 
@@ -275,14 +325,14 @@ mod tests {
 
     #[sqlx::test]
     async fn test_cancel_buy_with_expired_queued_buy(pool: PgPool) -> anyhow::Result<()> {
-        let _log = crate::add::add(&pool, "marcus", 400_f64).await?;
-        let _log = init_buy(&pool, "marcus", "AAPL", 50_f64, 200_f64).await?;
+        let _log = crate::add::add(&pool, "marcus", dec!(400)).await?;
+        let _log = init_buy(&pool, "marcus", "AAPL", dec!(50), dec!(200)).await?;
 
         sqlx::query!("UPDATE queued_buy SET time_created = now() - interval '6 minutes' WHERE user_id = 'marcus'")
             .execute(&pool)
             .await?;
 
-        let cancel = cancel_buy(&pool, "marcus").await;
+        let cancel = cancel_buy(&pool, "marcus", None, OrderExpiry::default().buy).await;
         assert!(cancel.is_err(), "expected error but was {cancel:?}");
 
         let queued_buy = sqlx::query_as!(
@@ -302,7 +352,8 @@ mod tests {
         .await?;
 
         assert_eq!(
-            balance, 400_f64,
+            balance,
+            dec!(400),
             "expected balance to be 400 but was {balance}"
         );
 
@@ -1,30 +1,41 @@
+use crate::ledger::Account;
 use crate::log::AccountTransaction;
-use crate::{begin_transaction, commit_transaction};
-use sqlx::types::time::{OffsetDateTime, PrimitiveDateTime};
+use crate::orders::{is_expired, no_queued_order};
+use crate::{begin_transaction, commit_transaction, ledger};
+use rust_decimal::Decimal;
+use sqlx::types::time::PrimitiveDateTime;
 use sqlx::{PgPool, Postgres, Transaction};
 use std::ops::DerefMut;
 use std::time::Duration;
 
 #[derive(Debug, PartialEq)]
 pub struct AmountDollarsTimeCreated {
-    amount_dollars: f64,
+    amount_dollars: Decimal,
     time_created: PrimitiveDateTime,
 }
 
+/**
+ * Cancels the user's queued buy `order_id`, or their most recent one if `None`. A buy older than
+ * `expiry` is still refunded, but reported as missing.
+ */
 #[tracing::instrument(skip(pool))]
-pub async fn cancel_buy(pool: &PgPool, user_id: &str) -> anyhow::Result<AccountTransaction> {
+pub async fn cancel_buy(
+    pool: &PgPool,
+    user_id: &str,
+    order_id: Option<i64>,
+    expiry: Duration,
+) -> anyhow::Result<AccountTransaction> {
     let mut transaction = begin_transaction(pool).await?;
 
-    let amount_dollars_time_created = delete_queued_buy(user_id, &mut transaction).await?;
+    let amount_dollars_time_created =
+        delete_queued_buy(user_id, order_id, &mut transaction).await?;
 
     let account_transaction =
         update_trader_balance(user_id, &mut transaction, &amount_dollars_time_created).await?;
 
-    let now = OffsetDateTime::now_utc();
-    let now = PrimitiveDateTime::new(now.date(), now.time());
-    if amount_dollars_time_created.time_created + Duration::from_secs(60) < now {
+    if is_expired(amount_dollars_time_created.time_created, expiry) {
         commit_transaction(transaction).await?;
-        anyhow::bail!("no queued buy for user_id {user_id}");
+        return Err(no_queued_order("buy", user_id, order_id));
     }
 
     commit_transaction(transaction).await?;
@@ -46,6 +57,15 @@ async fn update_trader_balance(
     .execute(transaction.deref_mut())
     .await?;
 
+    ledger::transfer(
+        transaction,
+        user_id,
+        Account::ReservedCash,
+        Account::Cash,
+        amount_dollars_time_created.amount_dollars,
+    )
+    .await?;
+
     Ok(AccountTransaction(
         amount_dollars_time_created.amount_dollars,
     ))
@@ -54,17 +74,28 @@ async fn update_trader_balance(
 #[tracing::instrument(skip_all)]
 async fn delete_queued_buy(
     user_id: &str,
+    order_id: Option<i64>,
     transaction: &mut Transaction<'static, Postgres>,
 ) -> anyhow::Result<AmountDollarsTimeCreated> {
     let Some(amount_dollars_time_created) = sqlx::query_as!(
         AmountDollarsTimeCreated,
-        "DELETE FROM queued_buy WHERE user_id = $1 RETURNING amount_dollars, time_created",
-        user_id
+        "
+        DELETE FROM queued_buy
+        WHERE order_id = (
+            SELECT order_id FROM queued_buy
+            WHERE user_id = $1 AND ($2::bigint IS NULL OR order_id = $2)
+            ORDER BY order_id DESC
+            LIMIT 1
+        )
+        RETURNING amount_dollars, time_created
+        ",
+        user_id,
+        order_id
     )
     .fetch_optional(transaction.deref_mut())
     .await?
     else {
-        anyhow::bail!("no queued buy for user_id {user_id}");
+        return Err(no_queued_order("buy", user_id, order_id));
     };
 
     Ok(amount_dollars_time_created)
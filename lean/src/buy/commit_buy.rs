@@ -1,27 +1,51 @@
-use crate::{begin_transaction, commit_transaction};
-use sqlx::types::time::{OffsetDateTime, PrimitiveDateTime};
+use crate::book::Side;
+use crate::events::{self, AccountEvent};
+use crate::ledger::Account;
+use crate::money::shares_to_buy;
+use crate::orders::{is_expired, no_queued_order};
+use crate::{begin_transaction, commit_transaction, holdings, ledger};
+use rust_decimal::Decimal;
+use sqlx::types::time::PrimitiveDateTime;
 use sqlx::{PgPool, Postgres, Transaction};
 use std::ops::DerefMut;
 use std::time::Duration;
 
 #[derive(Debug, PartialEq)]
 pub struct QueuedBuyNoUserId {
+    order_id: i64,
     stock_symbol: String,
-    quoted_price: f64,
-    amount_dollars: f64,
+    quoted_price: Decimal,
+    amount_dollars: Decimal,
     time_created: PrimitiveDateTime,
 }
 
+/**
+ * Commits the user's queued buy `order_id`, or their most recent one if `None`. A buy older than
+ * `expiry` is refunded instead.
+ */
 #[tracing::instrument(skip(pool))]
-pub async fn commit_buy(pool: &PgPool, user_id: &str) -> anyhow::Result<()> {
+pub async fn commit_buy(
+    pool: &PgPool,
+    user_id: &str,
+    order_id: Option<i64>,
+    expiry: Duration,
+) -> anyhow::Result<()> {
     let mut transaction = begin_transaction(pool).await?;
 
-    let queued_buy_no_user_id = delete_queued_buy(user_id, &mut transaction).await?;
+    let queued_buy_no_user_id = delete_queued_buy(user_id, order_id, &mut transaction).await?;
 
-    let now = OffsetDateTime::now_utc();
-    let now = PrimitiveDateTime::new(now.date(), now.time());
-    if queued_buy_no_user_id.time_created + Duration::from_secs(60) < now {
+    if is_expired(queued_buy_no_user_id.time_created, expiry) {
         update_trader_balance(user_id, &mut transaction, &queued_buy_no_user_id).await?;
+        events::publish(
+            &mut transaction,
+            &AccountEvent::expired(
+                user_id,
+                Side::Buy,
+                &queued_buy_no_user_id.stock_symbol,
+                Some(queued_buy_no_user_id.order_id),
+            ),
+        )
+        .await?;
         commit_transaction(transaction).await?;
         anyhow::bail!("no queued buy for user_id {user_id}");
     }
@@ -47,6 +71,16 @@ async fn update_trader_balance(
     )
     .execute(&mut *connection)
     .await?;
+
+    ledger::transfer(
+        transaction,
+        user_id,
+        Account::ReservedCash,
+        Account::Cash,
+        queued_buy_no_user_id.amount_dollars,
+    )
+    .await?;
+
     Ok(())
 }
 
@@ -56,15 +90,50 @@ async fn update_stock(
     transaction: &mut Transaction<'static, Postgres>,
     queued_buy_no_user_id: QueuedBuyNoUserId,
 ) -> anyhow::Result<()> {
-    let connection = transaction.deref_mut();
-    sqlx::query!(
-    "INSERT INTO stock (owner_id, stock_symbol, amount) VALUES ($1, $2, $3) ON CONFLICT (owner_id, stock_symbol) DO UPDATE SET amount = stock.amount + $3",
-    user_id,
-    queued_buy_no_user_id.stock_symbol,
-    queued_buy_no_user_id.amount_dollars / queued_buy_no_user_id.quoted_price,
-)
-        .execute(&mut *connection)
-        .await?;
+    let amount = shares_to_buy(
+        queued_buy_no_user_id.amount_dollars,
+        queued_buy_no_user_id.quoted_price,
+    )?;
+    holdings::add(
+        transaction,
+        user_id,
+        &queued_buy_no_user_id.stock_symbol,
+        amount,
+        queued_buy_no_user_id.amount_dollars,
+    )
+    .await?;
+
+    events::publish(
+        transaction,
+        &AccountEvent::fill(
+            user_id,
+            Side::Buy,
+            &queued_buy_no_user_id.stock_symbol,
+            Some(queued_buy_no_user_id.order_id),
+            amount,
+            queued_buy_no_user_id.quoted_price,
+            queued_buy_no_user_id.amount_dollars,
+        ),
+    )
+    .await?;
+
+    ledger::transfer(
+        transaction,
+        user_id,
+        Account::ReservedCash,
+        Account::House,
+        queued_buy_no_user_id.amount_dollars,
+    )
+    .await?;
+    ledger::transfer_shares(
+        transaction,
+        user_id,
+        &queued_buy_no_user_id.stock_symbol,
+        Account::House,
+        Account::Holdings,
+        amount,
+    )
+    .await?;
 
     Ok(())
 }
@@ -72,17 +141,29 @@ async fn update_stock(
 #[tracing::instrument(skip_all)]
 async fn delete_queued_buy(
     user_id: &str,
+    order_id: Option<i64>,
     transaction: &mut Transaction<'static, Postgres>,
 ) -> anyhow::Result<QueuedBuyNoUserId> {
     let connection = transaction.deref_mut();
     let Some(queued_buy_no_user_id) = sqlx::query_as!(
         QueuedBuyNoUserId,
-        "DELETE FROM queued_buy WHERE user_id = $1 RETURNING stock_symbol, quoted_price, amount_dollars, time_created",
-        user_id
+        "
+        DELETE FROM queued_buy
+        WHERE order_id = (
+            SELECT order_id FROM queued_buy
+            WHERE user_id = $1 AND ($2::bigint IS NULL OR order_id = $2)
+            ORDER BY order_id DESC
+            LIMIT 1
+        )
+        RETURNING order_id, stock_symbol, quoted_price, amount_dollars, time_created
+        ",
+        user_id,
+        order_id
     )
-        .fetch_optional(&mut *connection)
-        .await? else {
-        anyhow::bail!("no queued buy for user_id {user_id}");
+    .fetch_optional(&mut *connection)
+    .await?
+    else {
+        return Err(no_queued_order("buy", user_id, order_id));
     };
 
     Ok(queued_buy_no_user_id)
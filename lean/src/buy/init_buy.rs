@@ -1,29 +1,26 @@
+use crate::ledger::Account;
 use crate::log::AccountTransaction;
-use crate::{begin_transaction, commit_transaction};
+use crate::{begin_transaction, commit_transaction, ledger};
+use rust_decimal::Decimal;
 use sqlx::{PgConnection, PgPool, Postgres, Transaction};
 use std::ops::DerefMut;
 
-#[derive(Debug, PartialEq)]
-pub struct AmountDollars {
-    amount_dollars: f64,
-}
-
+/**
+ * Queues a buy alongside any the user already has pending. Returns the order id of the new buy.
+ */
 #[tracing::instrument(skip(pool))]
 pub async fn init_buy(
     pool: &PgPool,
     user_id: &str,
     stock_symbol: &str,
-    quoted_price: f64,
-    amount_dollars: f64,
-) -> anyhow::Result<AccountTransaction> {
+    quoted_price: Decimal,
+    amount_dollars: Decimal,
+) -> anyhow::Result<(i64, AccountTransaction)> {
     let mut transaction = begin_transaction(pool).await?;
 
-    let changes = delete_and_maybe_update(user_id, &mut transaction).await?;
+    let changes = update_trader_balance(user_id, amount_dollars, &mut transaction).await?;
 
-    let changes =
-        changes + update_trader_balance(user_id, amount_dollars, &mut transaction).await?;
-
-    insert_queued_buy(
+    let order_id = insert_queued_buy(
         user_id,
         stock_symbol,
         quoted_price,
@@ -34,36 +31,36 @@ pub async fn init_buy(
 
     commit_transaction(transaction).await?;
 
-    Ok(changes)
+    Ok((order_id, changes))
 }
 
 #[tracing::instrument(skip_all)]
 async fn insert_queued_buy(
     user_id: &str,
     stock_symbol: &str,
-    quoted_price: f64,
-    amount_dollars: f64,
+    quoted_price: Decimal,
+    amount_dollars: Decimal,
     transaction: &mut Transaction<'static, Postgres>,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<i64> {
     let connection: &mut PgConnection = &mut *transaction;
-    sqlx::query!(
+    let order_id = sqlx::query_scalar!(
         "INSERT INTO queued_buy (user_id, stock_symbol, quoted_price, amount_dollars)\
-     VALUES ($1, $2, $3, $4)",
+     VALUES ($1, $2, $3, $4) RETURNING order_id",
         user_id,
         stock_symbol,
         quoted_price,
         amount_dollars,
     )
-    .execute(connection)
+    .fetch_one(connection)
     .await?;
 
-    Ok(())
+    Ok(order_id)
 }
 
 #[tracing::instrument(skip_all)]
 async fn update_trader_balance(
     user_id: &str,
-    amount_dollars: f64,
+    amount_dollars: Decimal,
     transaction: &mut Transaction<'static, Postgres>,
 ) -> anyhow::Result<AccountTransaction> {
     let connection = transaction.deref_mut();
@@ -79,34 +76,14 @@ async fn update_trader_balance(
         anyhow::bail!("no trader for user_id {user_id}");
     }
 
-    Ok(AccountTransaction(-amount_dollars))
-}
-
-#[tracing::instrument(skip_all)]
-async fn delete_and_maybe_update(
-    user_id: &str,
-    transaction: &mut Transaction<'static, Postgres>,
-) -> anyhow::Result<AccountTransaction> {
-    let connection = transaction.deref_mut();
-    let old_buy = sqlx::query_as!(
-        AmountDollars,
-        "DELETE FROM queued_buy WHERE user_id = $1 RETURNING amount_dollars",
-        user_id
+    ledger::transfer(
+        transaction,
+        user_id,
+        Account::Cash,
+        Account::ReservedCash,
+        amount_dollars,
     )
-    .fetch_optional(&mut *connection)
     .await?;
 
-    match old_buy {
-        Some(AmountDollars { amount_dollars }) => {
-            sqlx::query!(
-                "UPDATE trader SET balance = balance + $1 WHERE user_id = $2",
-                amount_dollars,
-                user_id
-            )
-            .execute(&mut *connection)
-            .await?;
-            Ok(AccountTransaction(amount_dollars))
-        }
-        None => Ok(AccountTransaction(0.0)),
-    }
+    Ok(AccountTransaction(-amount_dollars))
 }
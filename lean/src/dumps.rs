@@ -0,0 +1,145 @@
+use crate::proto::Dump;
+use anyhow::{anyhow, bail};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/**
+ * The directory log dumps are written to and the only place the `File` RPC reads from. Dumps are
+ * addressed by bare file names; clients never supply a path.
+ */
+pub struct DumpDir {
+    root: PathBuf,
+    sequence: AtomicU64,
+}
+
+impl DumpDir {
+    pub fn new(root: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let root = root.into();
+        std::fs::create_dir_all(&root)
+            .map_err(|e| anyhow!("failed to create dump dir {}: {e}", root.display()))?;
+        Ok(Self {
+            root,
+            sequence: AtomicU64::new(0),
+        })
+    }
+
+    pub fn from_env() -> anyhow::Result<Self> {
+        Self::new(std::env::var("DUMP_DIR").unwrap_or_else(|_| String::from("dumps")))
+    }
+
+    /**
+     * Generates a fresh name for a dump and the path to write it to.
+     */
+    pub fn new_dump(&self, kind: &str, extension: &str) -> (String, PathBuf) {
+        let timestamp = time::OffsetDateTime::now_utc().unix_timestamp_nanos();
+        let sequence = self.sequence.fetch_add(1, Ordering::Relaxed);
+        let name = format!("{kind}-{timestamp}-{sequence}.{extension}");
+        let path = self.root.join(&name);
+        (name, path)
+    }
+
+    /**
+     * Resolves a client supplied dump name to a path inside the dump dir. Anything but a plain
+     * file name (absolute paths, `..`, nested directories, hidden files) is rejected.
+     */
+    pub fn resolve(&self, name: &str) -> anyhow::Result<PathBuf> {
+        let mut components = Path::new(name).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(file_name)), None) if !name.starts_with('.') => {
+                Ok(self.root.join(file_name))
+            }
+            _ => bail!("invalid dump name {name:?}"),
+        }
+    }
+
+    pub async fn list(&self) -> anyhow::Result<Vec<Dump>> {
+        let mut entries = tokio::fs::read_dir(&self.root).await?;
+        let mut dumps = Vec::new();
+
+        while let Some(entry) = entries.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            if !metadata.is_file() {
+                continue;
+            }
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+            dumps.push(Dump {
+                name,
+                size_bytes: metadata.len(),
+            });
+        }
+
+        dumps.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(dumps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn dump_dir(test: &str) -> DumpDir {
+        let root = std::env::temp_dir().join(format!("lean-dumps-{test}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        DumpDir::new(root).expect("failed to create dump dir")
+    }
+
+    #[test]
+    fn test_resolve_rejects_paths() {
+        let dumps = dump_dir("resolve");
+
+        for name in [
+            "",
+            ".",
+            "..",
+            "../etc/passwd",
+            "/etc/passwd",
+            "nested/log.xml",
+            ".hidden",
+        ] {
+            assert!(
+                dumps.resolve(name).is_err(),
+                "expected {name:?} to be rejected"
+            );
+        }
+
+        assert_eq!(
+            dumps.resolve("log.xml").unwrap(),
+            dumps.root.join("log.xml")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_dumps() -> anyhow::Result<()> {
+        let dumps = dump_dir("list");
+
+        let (first, first_path) = dumps.new_dump("dumplog", "xml");
+        let (second, second_path) = dumps.new_dump("dumplog", "xml");
+        assert_ne!(first, second);
+
+        tokio::fs::write(&first_path, b"<log></log>").await?;
+        tokio::fs::write(&second_path, b"").await?;
+        tokio::fs::create_dir(dumps.root.join("directory")).await?;
+
+        let mut expected = vec![
+            Dump {
+                name: first,
+                size_bytes: 11,
+            },
+            Dump {
+                name: second,
+                size_bytes: 0,
+            },
+        ];
+        expected.sort_by(|a, b| a.name.cmp(&b.name));
+
+        assert_eq!(dumps.list().await?, expected);
+
+        tokio::fs::remove_dir_all(&dumps.root).await?;
+
+        Ok(())
+    }
+}
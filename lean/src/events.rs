@@ -0,0 +1,281 @@
+use crate::book::Side;
+use crate::prices::receiver_stream;
+use crate::proto;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgListener;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::ops::DerefMut;
+use std::time::Duration;
+use tokio::sync::broadcast::Sender;
+use tracing::{error, warn};
+
+/// the postgres channel account events are published on, also used by the `trader` balance trigger
+const ACCOUNT_EVENT_CHANNEL: &str = "account_event";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountEventType {
+    Fill,
+    TriggerExecuted,
+    Expired,
+    BalanceChanged,
+}
+
+/**
+ * Something that happened to a user's account. Which fields are set depends on the type: balance
+ * changes only carry the new balance, expiries carry no price.
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AccountEvent {
+    pub user_id: String,
+    #[serde(rename = "type")]
+    pub event_type: AccountEventType,
+    pub stock_symbol: Option<String>,
+    pub order_id: Option<i64>,
+    pub side: Option<Side>,
+    pub shares: Option<Decimal>,
+    pub price: Option<Decimal>,
+    pub dollars: Option<Decimal>,
+    pub balance: Option<Decimal>,
+}
+
+impl AccountEvent {
+    pub fn fill(
+        user_id: &str,
+        side: Side,
+        stock_symbol: &str,
+        order_id: Option<i64>,
+        shares: Decimal,
+        price: Decimal,
+        dollars: Decimal,
+    ) -> Self {
+        AccountEvent {
+            order_id,
+            shares: Some(shares),
+            price: Some(price),
+            dollars: Some(dollars),
+            ..Self::new(user_id, AccountEventType::Fill, side, stock_symbol)
+        }
+    }
+
+    pub fn trigger_executed(
+        user_id: &str,
+        side: Side,
+        stock_symbol: &str,
+        shares: Decimal,
+        price: Decimal,
+        dollars: Decimal,
+    ) -> Self {
+        AccountEvent {
+            shares: Some(shares),
+            price: Some(price),
+            dollars: Some(dollars),
+            ..Self::new(
+                user_id,
+                AccountEventType::TriggerExecuted,
+                side,
+                stock_symbol,
+            )
+        }
+    }
+
+    pub fn expired(user_id: &str, side: Side, stock_symbol: &str, order_id: Option<i64>) -> Self {
+        AccountEvent {
+            order_id,
+            ..Self::new(user_id, AccountEventType::Expired, side, stock_symbol)
+        }
+    }
+
+    fn new(user_id: &str, event_type: AccountEventType, side: Side, stock_symbol: &str) -> Self {
+        AccountEvent {
+            user_id: user_id.to_string(),
+            event_type,
+            stock_symbol: Some(stock_symbol.to_string()),
+            order_id: None,
+            side: Some(side),
+            shares: None,
+            price: None,
+            dollars: None,
+            balance: None,
+        }
+    }
+}
+
+/**
+ * Publishes `event` once `transaction` commits, so subscribers never see a fill that was rolled
+ * back.
+ */
+pub async fn publish(
+    transaction: &mut Transaction<'static, Postgres>,
+    event: &AccountEvent,
+) -> anyhow::Result<()> {
+    let payload = serde_json::to_string(event)?;
+    sqlx::query!(
+        "SELECT FROM pg_notify($1, $2)",
+        ACCOUNT_EVENT_CHANNEL,
+        payload
+    )
+    .execute(transaction.deref_mut())
+    .await?;
+    Ok(())
+}
+
+/**
+ * Forwards every account event published to postgres, by any server sharing the database, to
+ * the subscribers of [AccountEvents].
+ */
+pub struct AccountEventListener {
+    pool: PgPool,
+    sender: Sender<AccountEvent>,
+}
+
+/**
+ * A handle to subscribe to the events [AccountEventListener] receives.
+ */
+#[derive(Clone)]
+pub struct AccountEvents {
+    sender: Sender<AccountEvent>,
+}
+
+impl AccountEventListener {
+    pub fn new(pool: PgPool) -> (Self, AccountEvents) {
+        let channel_size = std::env::var("ACCOUNT_EVENT_CHANNEL_SIZE")
+            .unwrap_or_else(|_| "1000".to_string())
+            .parse::<usize>()
+            .expect("ACCOUNT_EVENT_CHANNEL_SIZE must be a number");
+
+        let (sender, _) = tokio::sync::broadcast::channel(channel_size);
+        (
+            AccountEventListener {
+                pool,
+                sender: sender.clone(),
+            },
+            AccountEvents { sender },
+        )
+    }
+
+    pub async fn run(self) {
+        loop {
+            if let Err(e) = self.listen().await {
+                error!("account event listener failed, reconnecting: {e:?}");
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        }
+    }
+
+    async fn listen(&self) -> anyhow::Result<()> {
+        let mut listener = PgListener::connect_with(&self.pool).await?;
+        listener.listen(ACCOUNT_EVENT_CHANNEL).await?;
+
+        loop {
+            let notification = listener.recv().await?;
+            match serde_json::from_str::<AccountEvent>(notification.payload()) {
+                // no subscribers is not an error
+                Ok(event) => drop(self.sender.send(event)),
+                Err(e) => warn!(
+                    "ignoring malformed account event {}: {e}",
+                    notification.payload()
+                ),
+            }
+        }
+    }
+}
+
+impl AccountEvents {
+    /**
+     * Every event for `user_id` received after subscribing.
+     */
+    pub fn subscribe(&self, user_id: String) -> BoxStream<'static, AccountEvent> {
+        receiver_stream(self.sender.subscribe(), "account event")
+            .filter(move |event| futures::future::ready(event.user_id == user_id))
+            .boxed()
+    }
+}
+
+impl From<Side> for proto::OrderSide {
+    fn from(side: Side) -> Self {
+        match side {
+            Side::Buy => proto::OrderSide::Buy,
+            Side::Sell => proto::OrderSide::Sell,
+        }
+    }
+}
+
+impl From<AccountEventType> for proto::AccountEventType {
+    fn from(event_type: AccountEventType) -> Self {
+        match event_type {
+            AccountEventType::Fill => proto::AccountEventType::Fill,
+            AccountEventType::TriggerExecuted => proto::AccountEventType::TriggerExecuted,
+            AccountEventType::Expired => proto::AccountEventType::Expired,
+            AccountEventType::BalanceChanged => proto::AccountEventType::BalanceChanged,
+        }
+    }
+}
+
+impl From<AccountEvent> for proto::AccountEvent {
+    fn from(event: AccountEvent) -> Self {
+        let text = |it: Option<Decimal>| it.map(|it| it.to_string()).unwrap_or_default();
+        proto::AccountEvent {
+            event_type: proto::AccountEventType::from(event.event_type).into(),
+            stock_symbol: event.stock_symbol.unwrap_or_default(),
+            order_id: event.order_id.unwrap_or_default(),
+            side: proto::OrderSide::from(event.side.unwrap_or(Side::Buy)).into(),
+            shares: text(event.shares),
+            price: text(event.price),
+            dollars: text(event.dollars),
+            balance: text(event.balance),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::add::add;
+    use crate::buy::{commit_buy, init_buy};
+    use crate::orders::OrderExpiry;
+    use pretty_assertions::assert_eq;
+    use rust_decimal_macros::dec;
+
+    #[sqlx::test]
+    async fn test_subscribe_sees_committed_events(pool: PgPool) -> anyhow::Result<()> {
+        let (listener, events) = AccountEventListener::new(pool.clone());
+        let marcus = events.subscribe("marcus".to_string());
+        let others = events.subscribe("nobody".to_string());
+        let listening = tokio::spawn(listener.run());
+        // give the listener time to LISTEN before anything is published
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        let _log = add(&pool, "marcus", dec!(100)).await?;
+        let _log = init_buy(&pool, "marcus", "AAPL", dec!(10), dec!(40)).await?;
+        commit_buy(&pool, "marcus", None, OrderExpiry::default().buy).await?;
+
+        let received = tokio::time::timeout(
+            Duration::from_secs(5),
+            marcus
+                .map(|it| (it.event_type, it.balance, it.shares))
+                .take(3)
+                .collect::<Vec<_>>(),
+        )
+        .await?;
+        assert_eq!(
+            received,
+            vec![
+                (AccountEventType::BalanceChanged, Some(dec!(100)), None),
+                (AccountEventType::BalanceChanged, Some(dec!(60)), None),
+                (AccountEventType::Fill, None, Some(dec!(4))),
+            ]
+        );
+
+        // closing the channel ends the other subscription, which never saw marcus' events
+        listening.abort();
+        let _ = listening.await;
+        drop(events);
+        assert_eq!(others.collect::<Vec<_>>().await, vec![]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+use crate::breaker::{BreakerConfig, CircuitBreaker};
+use crate::proto::quote_client::QuoteClient;
+use crate::proto::quote_server::{Quote, QuoteServer};
+use crate::proto::{QuoteRequest, QuoteResponse};
+use crate::trigger::UpdatedPrice;
+use crate::{prices, CachedQuote};
+use sqlx::PgPool;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc::Receiver;
+use tonic::transport::server::TcpIncoming;
+use tonic::transport::{Channel, Server};
+use tonic::{Request, Response, Status};
+
+/**
+ * A quote server that answers every request with the same price, or fails every one with
+ * `UNAVAILABLE` if there is no price.
+ */
+struct FixedQuote {
+    price: Option<f64>,
+}
+
+#[tonic::async_trait]
+impl Quote for FixedQuote {
+    async fn quote(
+        &self,
+        request: Request<QuoteRequest>,
+    ) -> Result<Response<QuoteResponse>, Status> {
+        let QuoteRequest {
+            user_id,
+            stock_symbol,
+            ..
+        } = request.into_inner();
+        let price = self
+            .price
+            .ok_or_else(|| Status::unavailable("quote server is down"))?;
+
+        Ok(Response::new(QuoteResponse {
+            quote: price,
+            sym: stock_symbol,
+            user_id,
+            timestamp: 1_700_000_000_000,
+            crypto_key: "key".to_string(),
+        }))
+    }
+}
+
+/**
+ * Serves a [FixedQuote] on a free port and returns a client of it.
+ */
+async fn quote_client(price: Option<f64>) -> QuoteClient<Channel> {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let incoming = TcpIncoming::from_listener(listener, true, None).unwrap();
+    tokio::spawn(
+        Server::builder()
+            .add_service(QuoteServer::new(FixedQuote { price }))
+            .serve_with_incoming(incoming),
+    );
+
+    let channel = Channel::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect_lazy();
+    QuoteClient::new(channel)
+}
+
+/**
+ * A [CachedQuote] of a [FixedQuote] whose breaker opens on the first failure, and the receiving
+ * end of the price updates it sends the [crate::trigger::Triggerer].
+ */
+pub async fn cached_quote(
+    pool: PgPool,
+    price: Option<f64>,
+    stale_fallback: Option<Duration>,
+) -> (CachedQuote, Receiver<UpdatedPrice>) {
+    let (quote_update_sender, updates) = tokio::sync::mpsc::channel(100);
+    let (log_sender, _) = tokio::sync::mpsc::channel(100);
+
+    let quote = CachedQuote {
+        cache: moka::future::Cache::builder().max_capacity(100).build(),
+        postgres: pool,
+        quote: quote_client(price).await,
+        quote_update_sender,
+        price_sender: prices::price_channel(),
+        log_sender,
+        breaker: CircuitBreaker::new(BreakerConfig {
+            failure_threshold: 1,
+            open_for: Duration::from_secs(60),
+        }),
+        stale_fallback,
+    };
+    (quote, updates)
+}
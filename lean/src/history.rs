@@ -0,0 +1,359 @@
+use crate::log::unix_seconds;
+use crate::proto;
+use anyhow::bail;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use std::time::Duration;
+use time::PrimitiveDateTime;
+
+/// the most ticks or candles returned when the request doesn't say
+const DEFAULT_LIMIT: i64 = 1000;
+const MAX_LIMIT: i64 = 10_000;
+
+/**
+ * Records a price fetched from the quote server.
+ */
+#[tracing::instrument(skip(pool))]
+pub async fn record(pool: &PgPool, stock_symbol: &str, price: Decimal) -> anyhow::Result<()> {
+    sqlx::query!(
+        "INSERT INTO price_history (stock_symbol, price) VALUES ($1, $2)",
+        stock_symbol,
+        price
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/**
+ * The most recent price recorded for the stock, if any was in the last `max_age`.
+ */
+#[tracing::instrument(skip(pool))]
+pub async fn latest(
+    pool: &PgPool,
+    stock_symbol: &str,
+    max_age: Duration,
+) -> anyhow::Result<Option<Tick>> {
+    Ok(sqlx::query_as!(
+        Tick,
+        r#"
+        SELECT price AS "price!", time_created AS "time!" FROM price_history
+        WHERE stock_symbol = $1
+            AND time_created >= now()::timestamp - $2::bigint * interval '1 second'
+        ORDER BY time_created DESC, price_id DESC
+        LIMIT 1
+        "#,
+        stock_symbol,
+        i64::try_from(max_age.as_secs())?
+    )
+    .fetch_optional(pool)
+    .await?)
+}
+
+/**
+ * Selects the price history of one stock. Only the most recent `limit` ticks or candles in the
+ * range are returned, oldest first.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriceHistoryQuery {
+    pub stock_symbol: String,
+    /// inclusive
+    pub from: Option<PrimitiveDateTime>,
+    /// exclusive
+    pub to: Option<PrimitiveDateTime>,
+    /// `None` for raw ticks
+    pub candle_seconds: Option<i64>,
+    pub limit: i64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PriceHistory {
+    Ticks(Vec<Tick>),
+    Candles(Vec<Candle>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tick {
+    pub price: Decimal,
+    pub time: PrimitiveDateTime,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candle {
+    pub start: PrimitiveDateTime,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    pub ticks: i64,
+}
+
+impl PriceHistoryQuery {
+    pub async fn fetch(&self, pool: &PgPool) -> anyhow::Result<PriceHistory> {
+        Ok(match self.candle_seconds {
+            None => PriceHistory::Ticks(self.ticks(pool).await?),
+            Some(seconds) => PriceHistory::Candles(self.candles(pool, seconds).await?),
+        })
+    }
+
+    #[tracing::instrument(skip(pool))]
+    async fn ticks(&self, pool: &PgPool) -> anyhow::Result<Vec<Tick>> {
+        Ok(sqlx::query_as!(
+            Tick,
+            r#"
+            SELECT price AS "price!", time_created AS "time!" FROM (
+                SELECT price_id, price, time_created FROM price_history
+                WHERE stock_symbol = $1
+                    AND ($2::timestamp IS NULL OR time_created >= $2)
+                    AND ($3::timestamp IS NULL OR time_created < $3)
+                ORDER BY time_created DESC, price_id DESC
+                LIMIT $4
+            ) recent
+            ORDER BY time_created, price_id
+            "#,
+            self.stock_symbol,
+            self.from,
+            self.to,
+            self.limit
+        )
+        .fetch_all(pool)
+        .await?)
+    }
+
+    /**
+     * The open, high, low and close of the ticks in each `seconds` long bucket, counted from the
+     * unix epoch. Buckets without ticks are left out.
+     */
+    #[tracing::instrument(skip(pool))]
+    async fn candles(&self, pool: &PgPool, seconds: i64) -> anyhow::Result<Vec<Candle>> {
+        Ok(sqlx::query_as!(
+            Candle,
+            r#"
+            SELECT
+                start AS "start!",
+                open AS "open!",
+                high AS "high!",
+                low AS "low!",
+                close AS "close!",
+                ticks AS "ticks!"
+            FROM (
+                SELECT
+                    date_bin($4::bigint * interval '1 second', time_created, 'epoch') AS start,
+                    (array_agg(price ORDER BY time_created, price_id))[1] AS open,
+                    max(price) AS high,
+                    min(price) AS low,
+                    (array_agg(price ORDER BY time_created DESC, price_id DESC))[1] AS close,
+                    count(*) AS ticks
+                FROM price_history
+                WHERE stock_symbol = $1
+                    AND ($2::timestamp IS NULL OR time_created >= $2)
+                    AND ($3::timestamp IS NULL OR time_created < $3)
+                GROUP BY 1
+                ORDER BY 1 DESC
+                LIMIT $5
+            ) recent
+            ORDER BY start
+            "#,
+            self.stock_symbol,
+            self.from,
+            self.to,
+            seconds,
+            self.limit
+        )
+        .fetch_all(pool)
+        .await?)
+    }
+}
+
+impl TryFrom<proto::GetPriceHistoryRequest> for PriceHistoryQuery {
+    type Error = anyhow::Error;
+
+    fn try_from(request: proto::GetPriceHistoryRequest) -> Result<Self, Self::Error> {
+        let candle_seconds = match request.interval() {
+            proto::CandleInterval::Tick => None,
+            proto::CandleInterval::OneMinute => Some(60),
+            proto::CandleInterval::FiveMinutes => Some(5 * 60),
+            proto::CandleInterval::FifteenMinutes => Some(15 * 60),
+            proto::CandleInterval::OneHour => Some(60 * 60),
+            proto::CandleInterval::OneDay => Some(24 * 60 * 60),
+        };
+
+        let limit = match i64::from(request.limit) {
+            0 => DEFAULT_LIMIT,
+            limit if (1..=MAX_LIMIT).contains(&limit) => limit,
+            limit => bail!("limit must be between 1 and {MAX_LIMIT}, was {limit}"),
+        };
+
+        if request.stock_symbol.is_empty() {
+            bail!("stock symbol is required");
+        }
+
+        Ok(PriceHistoryQuery {
+            stock_symbol: request.stock_symbol,
+            from: unix_seconds(request.from_unix_seconds)?,
+            to: unix_seconds(request.to_unix_seconds)?,
+            candle_seconds,
+            limit,
+        })
+    }
+}
+
+impl From<PriceHistory> for proto::GetPriceHistoryResponse {
+    fn from(history: PriceHistory) -> Self {
+        match history {
+            PriceHistory::Ticks(ticks) => proto::GetPriceHistoryResponse {
+                ticks: ticks.into_iter().map(proto::PriceTick::from).collect(),
+                candles: vec![],
+            },
+            PriceHistory::Candles(candles) => proto::GetPriceHistoryResponse {
+                ticks: vec![],
+                candles: candles.into_iter().map(proto::Candle::from).collect(),
+            },
+        }
+    }
+}
+
+impl From<Tick> for proto::PriceTick {
+    fn from(tick: Tick) -> Self {
+        proto::PriceTick {
+            price: tick.price.to_string(),
+            time_unix_seconds: tick.time.assume_utc().unix_timestamp(),
+        }
+    }
+}
+
+impl From<Candle> for proto::Candle {
+    fn from(candle: Candle) -> Self {
+        proto::Candle {
+            start_unix_seconds: candle.start.assume_utc().unix_timestamp(),
+            open: candle.open.to_string(),
+            high: candle.high.to_string(),
+            low: candle.low.to_string(),
+            close: candle.close.to_string(),
+            ticks: candle.ticks,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rust_decimal_macros::dec;
+    use time::macros::datetime;
+
+    async fn tick(pool: &PgPool, price: Decimal, time: PrimitiveDateTime) -> anyhow::Result<()> {
+        sqlx::query!(
+            "INSERT INTO price_history (stock_symbol, price, time_created) VALUES ('AAPL', $1, $2)",
+            price,
+            time
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    fn query(candle_seconds: Option<i64>, limit: i64) -> PriceHistoryQuery {
+        PriceHistoryQuery {
+            stock_symbol: "AAPL".to_string(),
+            from: Some(datetime!(2024-02-22 10:00)),
+            to: None,
+            candle_seconds,
+            limit,
+        }
+    }
+
+    #[sqlx::test]
+    async fn test_ticks_and_candles(pool: PgPool) -> anyhow::Result<()> {
+        tick(&pool, dec!(99), datetime!(2024-02-22 09:59:59)).await?;
+        tick(&pool, dec!(10), datetime!(2024-02-22 10:00:05)).await?;
+        tick(&pool, dec!(12), datetime!(2024-02-22 10:00:30)).await?;
+        tick(&pool, dec!(9), datetime!(2024-02-22 10:00:40)).await?;
+        tick(&pool, dec!(11), datetime!(2024-02-22 10:00:59)).await?;
+        tick(&pool, dec!(13), datetime!(2024-02-22 10:02:00)).await?;
+        record(&pool, "XYZ", dec!(1)).await?;
+
+        let ticks = query(None, 2).fetch(&pool).await?;
+        assert_eq!(
+            ticks,
+            PriceHistory::Ticks(vec![
+                Tick {
+                    price: dec!(11),
+                    time: datetime!(2024-02-22 10:00:59)
+                },
+                Tick {
+                    price: dec!(13),
+                    time: datetime!(2024-02-22 10:02:00)
+                },
+            ])
+        );
+
+        let candles = query(Some(60), 10).fetch(&pool).await?;
+        assert_eq!(
+            candles,
+            PriceHistory::Candles(vec![
+                Candle {
+                    start: datetime!(2024-02-22 10:00),
+                    open: dec!(10),
+                    high: dec!(12),
+                    low: dec!(9),
+                    close: dec!(11),
+                    ticks: 4,
+                },
+                Candle {
+                    start: datetime!(2024-02-22 10:02),
+                    open: dec!(13),
+                    high: dec!(13),
+                    low: dec!(13),
+                    close: dec!(13),
+                    ticks: 1,
+                },
+            ])
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_latest_within_max_age(pool: PgPool) -> anyhow::Result<()> {
+        sqlx::query!(
+            "
+            INSERT INTO price_history (stock_symbol, price, time_created)
+            VALUES ('AAPL', 10, now()::timestamp - interval '2 hours'),
+                ('AAPL', 11, now()::timestamp - interval '3 hours')
+            "
+        )
+        .execute(&pool)
+        .await?;
+        record(&pool, "XYZ", dec!(1)).await?;
+
+        let hour = Duration::from_secs(60 * 60);
+        assert_eq!(latest(&pool, "AAPL", hour).await?, None);
+        let latest = latest(&pool, "AAPL", 4 * hour).await?.unwrap();
+        assert_eq!(latest.price, dec!(10));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_from_request() {
+        let request = |interval: proto::CandleInterval, limit: i32| proto::GetPriceHistoryRequest {
+            stock_symbol: "AAPL".to_string(),
+            from_unix_seconds: 0,
+            to_unix_seconds: 0,
+            interval: interval.into(),
+            limit,
+        };
+
+        let ticks = PriceHistoryQuery::try_from(request(proto::CandleInterval::Tick, 0)).unwrap();
+        assert_eq!(ticks.candle_seconds, None);
+        assert_eq!(ticks.limit, DEFAULT_LIMIT);
+
+        let hourly =
+            PriceHistoryQuery::try_from(request(proto::CandleInterval::OneHour, 24)).unwrap();
+        assert_eq!(hourly.candle_seconds, Some(3600));
+        assert_eq!(hourly.limit, 24);
+
+        assert!(PriceHistoryQuery::try_from(request(proto::CandleInterval::Tick, -1)).is_err());
+    }
+}
@@ -0,0 +1,135 @@
+use anyhow::bail;
+use rust_decimal::Decimal;
+use sqlx::{Postgres, Transaction};
+use std::ops::DerefMut;
+
+/**
+ * Adds bought shares to the owner's holdings, along with what they `cost` to their cost basis.
+ */
+#[tracing::instrument(skip(transaction))]
+pub async fn add(
+    transaction: &mut Transaction<'static, Postgres>,
+    owner_id: &str,
+    stock_symbol: &str,
+    shares: Decimal,
+    cost: Decimal,
+) -> anyhow::Result<()> {
+    sqlx::query!(
+        "
+        INSERT INTO stock (owner_id, stock_symbol, amount, cost_basis)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (owner_id, stock_symbol)
+        DO UPDATE SET
+            amount = stock.amount + $3,
+            cost_basis = stock.cost_basis + $4
+        ",
+        owner_id,
+        stock_symbol,
+        shares,
+        cost
+    )
+    .execute(transaction.deref_mut())
+    .await?;
+
+    Ok(())
+}
+
+/**
+ * Reserves `shares` of the owner's free (unreserved) holdings for a queued sell or sell trigger.
+ * Fails if the owner does not have enough free shares.
+ */
+#[tracing::instrument(skip(transaction))]
+pub async fn reserve(
+    transaction: &mut Transaction<'static, Postgres>,
+    owner_id: &str,
+    stock_symbol: &str,
+    shares: Decimal,
+) -> anyhow::Result<()> {
+    let result = sqlx::query!(
+        "
+        UPDATE stock SET reserved = reserved + $1
+        WHERE owner_id = $2 AND stock_symbol = $3 AND amount - reserved >= $1
+        ",
+        shares,
+        owner_id,
+        stock_symbol
+    )
+    .execute(transaction.deref_mut())
+    .await?;
+
+    if result.rows_affected() == 0 {
+        bail!("not enough free shares of {stock_symbol} to reserve {shares}");
+    }
+
+    Ok(())
+}
+
+/**
+ * Returns reserved shares to the owner's free holdings when a sale is cancelled or expires.
+ */
+#[tracing::instrument(skip(transaction))]
+pub async fn release(
+    transaction: &mut Transaction<'static, Postgres>,
+    owner_id: &str,
+    stock_symbol: &str,
+    shares: Decimal,
+) -> anyhow::Result<()> {
+    let result = sqlx::query!(
+        "
+        UPDATE stock SET reserved = reserved - $1
+        WHERE owner_id = $2 AND stock_symbol = $3 AND reserved >= $1
+        ",
+        shares,
+        owner_id,
+        stock_symbol
+    )
+    .execute(transaction.deref_mut())
+    .await?;
+
+    if result.rows_affected() == 0 {
+        bail!("{shares} shares of {stock_symbol} are not reserved");
+    }
+
+    Ok(())
+}
+
+/**
+ * Removes reserved shares from the owner's holdings when a sale executes. Their average cost comes
+ * off the cost basis, and what the sale made over it is realized.
+ */
+#[tracing::instrument(skip(transaction))]
+pub async fn remove_reserved(
+    transaction: &mut Transaction<'static, Postgres>,
+    owner_id: &str,
+    stock_symbol: &str,
+    shares: Decimal,
+    proceeds: Decimal,
+) -> anyhow::Result<()> {
+    let result = sqlx::query!(
+        "
+        UPDATE stock SET
+            amount = amount - $1,
+            reserved = reserved - $1,
+            cost_basis = cost_basis - sold.cost,
+            realized_pnl = realized_pnl + $4 - sold.cost
+        FROM (
+            SELECT CASE WHEN amount = $1 THEN cost_basis ELSE round(cost_basis * $1 / amount, 2) END AS cost
+            FROM stock
+            WHERE owner_id = $2 AND stock_symbol = $3
+        ) sold
+        WHERE owner_id = $2 AND stock_symbol = $3 AND reserved >= $1
+        ",
+        shares,
+        owner_id,
+        stock_symbol,
+        proceeds
+    )
+    .execute(transaction.deref_mut())
+    .await?;
+
+    if result.rows_affected() == 0 {
+        bail!("{shares} shares of {stock_symbol} are not reserved");
+    }
+
+    Ok(())
+}
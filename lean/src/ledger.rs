@@ -0,0 +1,278 @@
+use anyhow::bail;
+use rust_decimal::Decimal;
+use sqlx::{PgConnection, PgExecutor, Postgres, Transaction};
+use std::ops::DerefMut;
+
+/**
+ * The accounts a ledger entry moves value between. Holdings are counted in shares, every other
+ * account in dollars.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Account {
+    /// the trader's spendable balance, mirrored by `trader.balance`
+    Cash,
+    /// dollars set aside for a queued buy or a buy trigger
+    ReservedCash,
+    /// shares the trader owns
+    Holdings,
+    /// the other side of every trade, deposit and withdrawal
+    House,
+}
+
+impl Account {
+    fn as_str(self) -> &'static str {
+        match self {
+            Account::Cash => "cash",
+            Account::ReservedCash => "reserved_cash",
+            Account::Holdings => "holdings",
+            Account::House => "house",
+        }
+    }
+}
+
+/**
+ * Records `amount` dollars moving from `credit` to `debit`. Must be called in the same transaction
+ * as the balance change it describes. Zero amounts are not recorded.
+ */
+#[tracing::instrument(skip(transaction))]
+pub async fn transfer(
+    transaction: &mut Transaction<'static, Postgres>,
+    user_id: &str,
+    credit: Account,
+    debit: Account,
+    amount: Decimal,
+) -> anyhow::Result<()> {
+    if credit == Account::Holdings || debit == Account::Holdings {
+        bail!("holdings are transferred in shares, use transfer_shares");
+    }
+
+    insert(
+        transaction.deref_mut(),
+        user_id,
+        credit,
+        debit,
+        None,
+        amount,
+    )
+    .await
+}
+
+/**
+ * Records `shares` of `stock_symbol` moving from `credit` to `debit`, one of which must be
+ * [Account::Holdings]. Zero amounts are not recorded.
+ */
+#[tracing::instrument(skip(transaction))]
+pub async fn transfer_shares(
+    transaction: &mut Transaction<'static, Postgres>,
+    user_id: &str,
+    stock_symbol: &str,
+    credit: Account,
+    debit: Account,
+    shares: Decimal,
+) -> anyhow::Result<()> {
+    if credit != Account::Holdings && debit != Account::Holdings {
+        bail!("shares can only be transferred into or out of holdings");
+    }
+
+    insert(
+        transaction.deref_mut(),
+        user_id,
+        credit,
+        debit,
+        Some(stock_symbol),
+        shares,
+    )
+    .await
+}
+
+async fn insert(
+    connection: &mut PgConnection,
+    user_id: &str,
+    credit: Account,
+    debit: Account,
+    stock_symbol: Option<&str>,
+    amount: Decimal,
+) -> anyhow::Result<()> {
+    if amount.is_zero() {
+        return Ok(());
+    }
+
+    if amount.is_sign_negative() {
+        bail!("cannot transfer a negative amount {amount}");
+    }
+
+    sqlx::query!(
+        "INSERT INTO ledger_entry (user_id, debit, credit, stock_symbol, amount) VALUES ($1, $2, $3, $4, $5)",
+        user_id,
+        debit.as_str(),
+        credit.as_str(),
+        stock_symbol,
+        amount
+    )
+    .execute(connection)
+    .await?;
+
+    Ok(())
+}
+
+/**
+ * Rebuilds a trader's cash balance from the ledger alone.
+ */
+#[tracing::instrument(skip(executor))]
+pub async fn cash_balance(executor: impl PgExecutor<'_>, user_id: &str) -> anyhow::Result<Decimal> {
+    let record = sqlx::query!(
+        r#"
+        SELECT COALESCE(SUM(CASE WHEN debit = 'cash' THEN amount ELSE -amount END), 0) as "balance!"
+        FROM ledger_entry
+        WHERE user_id = $1 AND 'cash' IN (debit, credit)
+        "#,
+        user_id
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(record.balance)
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Discrepancy {
+    pub user_id: String,
+    pub balance: Decimal,
+    pub ledger_balance: Decimal,
+}
+
+/**
+ * Every trader whose `trader.balance` does not match the balance rebuilt from the ledger.
+ */
+#[tracing::instrument(skip(executor))]
+pub async fn audit(executor: impl PgExecutor<'_>) -> anyhow::Result<Vec<Discrepancy>> {
+    Ok(sqlx::query_as!(
+        Discrepancy,
+        r#"
+        SELECT trader.user_id, trader.balance, COALESCE(ledger.balance, 0) as "ledger_balance!"
+        FROM trader
+        LEFT JOIN (
+            SELECT user_id, SUM(CASE WHEN debit = 'cash' THEN amount ELSE -amount END) as balance
+            FROM ledger_entry
+            WHERE 'cash' IN (debit, credit)
+            GROUP BY user_id
+        ) ledger ON ledger.user_id = trader.user_id
+        WHERE trader.balance <> COALESCE(ledger.balance, 0)
+        "#
+    )
+    .fetch_all(executor)
+    .await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::add::add;
+    use crate::buy::{cancel_buy, commit_buy, init_buy};
+    use crate::orders::OrderExpiry;
+    use crate::sell::{commit_sell, init_sell};
+    use crate::trigger::{cancel_set_buy, set_buy_amount, set_sell_amount};
+    use pretty_assertions::assert_eq;
+    use rust_decimal_macros::dec;
+    use sqlx::PgPool;
+
+    #[sqlx::test]
+    async fn test_balance_rebuilt_from_ledger(pool: PgPool) -> anyhow::Result<()> {
+        let _log = add(&pool, "marcus", dec!(1000)).await?;
+        let _log = init_buy(&pool, "marcus", "APPL", dec!(30), dec!(100)).await?;
+        commit_buy(&pool, "marcus", None, OrderExpiry::default().buy).await?;
+        let _log = init_buy(&pool, "marcus", "APPL", dec!(30), dec!(50)).await?;
+        let _log = cancel_buy(&pool, "marcus", None, OrderExpiry::default().buy).await?;
+        init_sell(&pool, "marcus", "APPL", dec!(30), dec!(40)).await?;
+        let _log = commit_sell(
+            &pool,
+            "marcus".to_string(),
+            None,
+            OrderExpiry::default().sell,
+        )
+        .await?;
+        let _log = set_buy_amount(&pool, "marcus", "GOOG", dec!(200)).await?;
+        let _log = cancel_set_buy(&pool, "marcus", "GOOG").await?;
+        set_sell_amount(&pool, "marcus", "APPL", dec!(1)).await?;
+
+        let balance = sqlx::query!("SELECT balance FROM trader WHERE user_id = 'marcus'")
+            .fetch_one(&pool)
+            .await?
+            .balance;
+
+        assert_eq!(balance, dec!(940));
+        assert_eq!(cash_balance(&pool, "marcus").await?, balance);
+        assert_eq!(audit(&pool).await?, vec![]);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_holdings_match_stock(pool: PgPool) -> anyhow::Result<()> {
+        let _log = add(&pool, "marcus", dec!(100)).await?;
+        let _log = init_buy(&pool, "marcus", "APPL", dec!(30), dec!(100)).await?;
+        commit_buy(&pool, "marcus", None, OrderExpiry::default().buy).await?;
+        set_sell_amount(&pool, "marcus", "APPL", dec!(1)).await?;
+
+        let holdings = sqlx::query!(
+            r#"
+            SELECT SUM(CASE WHEN debit = 'holdings' THEN amount ELSE -amount END) as "holdings!"
+            FROM ledger_entry
+            WHERE user_id = 'marcus' AND stock_symbol = 'APPL'
+            "#
+        )
+        .fetch_one(&pool)
+        .await?
+        .holdings;
+
+        let stock = sqlx::query!(
+            "SELECT amount FROM stock WHERE owner_id = 'marcus' AND stock_symbol = 'APPL'"
+        )
+        .fetch_one(&pool)
+        .await?
+        .amount;
+
+        assert_eq!(stock, dec!(3.333333));
+        assert_eq!(holdings, stock);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_audit_finds_unrecorded_change(pool: PgPool) -> anyhow::Result<()> {
+        let _log = add(&pool, "marcus", dec!(100)).await?;
+
+        sqlx::query!("UPDATE trader SET balance = balance + 1 WHERE user_id = 'marcus'")
+            .execute(&pool)
+            .await?;
+
+        assert_eq!(
+            audit(&pool).await?,
+            vec![Discrepancy {
+                user_id: "marcus".to_string(),
+                balance: dec!(101),
+                ledger_balance: dec!(100),
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_transfer_rejects_negative(pool: PgPool) -> anyhow::Result<()> {
+        let mut transaction = pool.begin().await?;
+
+        let result = transfer(
+            &mut transaction,
+            "marcus",
+            Account::House,
+            Account::Cash,
+            dec!(-1),
+        )
+        .await;
+
+        assert!(result.is_err(), "expected error but was {result:?}");
+
+        Ok(())
+    }
+}
@@ -1,9 +1,13 @@
-use anyhow::anyhow;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use rust_decimal::Decimal;
 use sqlx::{PgPool, Postgres, Transaction};
+use std::collections::HashSet;
 use std::env;
 use std::fmt::{Display, Formatter};
 use std::time::Duration;
 use tokio::io::AsyncReadExt;
+use tokio::sync::broadcast;
 use tokio::sync::mpsc::Sender;
 use tonic::transport::channel::Channel;
 use tonic::{Request, Response, Status};
@@ -13,16 +17,24 @@ use crate::proto::day_trader_server::DayTrader;
 use crate::proto::quote_client::QuoteClient;
 
 use crate::proto::{
-    AddRequest, AddResponse, BuyRequest, BuyResponse, BuyTrigger, CancelBuyRequest,
-    CancelBuyResponse, CancelSellRequest, CancelSellResponse, CancelSetBuyRequest,
+    AddRequest, AddResponse, BuyRequest, BuyResponse, BuyTrigger, CancelBookOrderRequest,
+    CancelBookOrderResponse, CancelBuyRequest, CancelBuyResponse, CancelOrderRequest,
+    CancelOrderResponse, CancelSellRequest, CancelSellResponse, CancelSetBuyRequest,
     CancelSetBuyResponse, CancelSetSellRequest, CancelSetSellResponse, CommitBuyRequest,
     CommitBuyResponse, CommitSellRequest, CommitSellResponse, DisplaySummaryRequest,
-    DisplaySummaryResponse, DumpLogRequest, DumpLogResponse, DumpLogUserRequest,
+    DisplaySummaryResponse, DumpLogChunk, DumpLogRequest, DumpLogResponse, DumpLogUserRequest,
     DumpLogUserResponse, FileRequest, FileResponse, GetAllStocksRequest, GetAllStocksResponse,
-    GetUserInfoRequest, GetUserInfoResponse, LoginRequest, LoginResponse, QuoteRequest,
-    QuoteRequestSimple, QuoteResponse, SellRequest, SellResponse, SellTrigger, SetBuyAmountRequest,
-    SetBuyAmountResponse, SetBuyTriggerRequest, SetBuyTriggerResponse, SetSellAmountRequest,
-    SetSellAmountResponse, SetSellTriggerRequest, SetSellTriggerResponse, Stock,
+    GetBookDepthRequest, GetBookDepthResponse, GetPortfolioRequest, GetPortfolioResponse,
+    GetPriceHistoryRequest, GetPriceHistoryResponse, GetUserInfoRequest, GetUserInfoResponse,
+    ListDumpsRequest, ListDumpsResponse, ListOrdersRequest, ListOrdersResponse,
+    ListPendingOrdersRequest, ListPendingOrdersResponse, LoginRequest, LoginResponse, OrderSide,
+    PlaceBookOrderRequest, PlaceBookOrderResponse, PlaceBuyTriggerRequest, PlaceBuyTriggerResponse,
+    PlaceOrderRequest, PlaceOrderResponse, PlaceSellTriggerRequest, PlaceSellTriggerResponse,
+    QuoteRequest, QuoteRequestSimple, QuoteResponse, RegisterRequest, RegisterResponse,
+    SellRequest, SellResponse, SellTrigger, SetBuyAmountRequest, SetBuyAmountResponse,
+    SetBuyTriggerRequest, SetBuyTriggerResponse, SetSellAmountRequest, SetSellAmountResponse,
+    SetSellTriggerRequest, SetSellTriggerResponse, Stock, SubscribeAccountEventsRequest,
+    SubscribePricesRequest, UpdateTriggerRequest, UpdateTriggerResponse, ValidateLogRequest,
 };
 
 #[tracing::instrument(skip_all)]
@@ -39,10 +51,19 @@ pub mod proto {
     tonic::include_proto!("day_trader");
 }
 
+use crate::auth::Authenticator;
+use crate::book::MatchingEngine;
+use crate::breaker::{BreakerConfig, CircuitBreaker, QuoteUnavailable};
+use crate::dumps::DumpDir;
+use crate::events::{AccountEventListener, AccountEvents};
+use crate::history::PriceHistoryQuery;
 use crate::log::{
-    AccountTransaction, AccountTransactionLog, CommandType, ErrorEventLog, Log, LogEntry,
-    QuoteServerLog, UserCommandLog,
+    AccountTransaction, AccountTransactionLog, CommandType, DumpFormat, ErrorEventLog, Log,
+    LogEntry, LogFilter, QuoteServerLog, UserCommandLog,
 };
+use crate::orders::OrderExpiry;
+use crate::poller::PricePoller;
+use crate::reaper::Reaper;
 use crate::trigger::{Triggerer, UpdatedPrice};
 use log::Logger;
 
@@ -58,10 +79,46 @@ mod log;
 
 mod account;
 
+mod money;
+
+pub mod ledger;
+
+mod holdings;
+
+mod poller;
+
+pub mod auth;
+
+pub mod dumps;
+
+mod orders;
+
+mod reaper;
+
+mod book;
+
+mod prices;
+
+mod events;
+
+mod portfolio;
+
+mod history;
+
+mod breaker;
+
+#[cfg(test)]
+mod fake_quote;
+
 pub struct DayTraderImpl {
     postgres: PgPool,
     quote: CachedQuote,
     log_sender: Sender<LogEntry>,
+    authenticator: Authenticator,
+    dumps: DumpDir,
+    expiry: OrderExpiry,
+    book: MatchingEngine,
+    events: AccountEvents,
 }
 
 impl DayTraderImpl {
@@ -95,6 +152,7 @@ impl DayTraderImpl {
             user_id,
             filename,
             request_num,
+            ..
         }: &DumpLogUserRequest,
     ) {
         let log_entry = LogEntry::new(
@@ -143,9 +201,11 @@ impl DayTraderImpl {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Ord, PartialOrd)]
-enum TransactionType {
+pub(crate) enum TransactionType {
     Add,
     Subtract,
+    /// funds reserved by a buy trigger were spent when the trigger fired
+    DebitReserved,
 }
 
 impl Display for TransactionType {
@@ -153,6 +213,7 @@ impl Display for TransactionType {
         match self {
             TransactionType::Add => write!(f, "ADD"),
             TransactionType::Subtract => write!(f, "SUBTRACT"),
+            TransactionType::DebitReserved => write!(f, "DEBIT_RESERVED"),
         }
     }
 }
@@ -200,7 +261,7 @@ impl DayTraderImpl {
             Log::UserCommand(UserCommandLog {
                 command: CommandType::SetSellTrigger,
                 stock_symbol: Some(stock_symbol),
-                funds: Some(amount),
+                funds: amount.parse().ok(),
                 filename: None,
             }),
         );
@@ -227,7 +288,7 @@ impl DayTraderImpl {
             Log::UserCommand(UserCommandLog {
                 command: CommandType::SetSellAmount,
                 stock_symbol: Some(stock_symbol),
-                funds: Some(amount),
+                funds: amount.parse().ok(),
                 filename: None,
             }),
         );
@@ -254,7 +315,7 @@ impl DayTraderImpl {
             Log::UserCommand(UserCommandLog {
                 command: CommandType::SetBuyTrigger,
                 stock_symbol: Some(stock_symbol),
-                funds: Some(amount),
+                funds: amount.parse().ok(),
                 filename: None,
             }),
         );
@@ -307,7 +368,7 @@ impl DayTraderImpl {
             Log::UserCommand(UserCommandLog {
                 command: CommandType::SetBuyAmount,
                 stock_symbol: Some(stock_symbol),
-                funds: Some(amount),
+                funds: amount.parse().ok(),
                 filename: None,
             }),
         );
@@ -324,6 +385,7 @@ impl DayTraderImpl {
         CancelSellRequest {
             user_id,
             request_num,
+            ..
         }: CancelSellRequest,
     ) {
         let log_entry = LogEntry::new(
@@ -349,6 +411,7 @@ impl DayTraderImpl {
         CommitSellRequest {
             user_id,
             request_num,
+            ..
         }: CommitSellRequest,
     ) {
         let log_entry = LogEntry::new(
@@ -384,7 +447,7 @@ impl DayTraderImpl {
             Log::UserCommand(UserCommandLog {
                 command: CommandType::Sell,
                 stock_symbol: Some(stock_symbol),
-                funds: Some(amount),
+                funds: amount.parse().ok(),
                 filename: None,
             }),
         );
@@ -402,6 +465,7 @@ impl DayTraderImpl {
         CancelBuyRequest {
             user_id,
             request_num,
+            ..
         }: &CancelBuyRequest,
     ) {
         let log_entry = LogEntry::new(
@@ -427,6 +491,7 @@ impl DayTraderImpl {
         DumpLogRequest {
             filename,
             request_num,
+            ..
         }: &DumpLogRequest,
     ) {
         let log_entry = LogEntry::new(
@@ -452,6 +517,7 @@ impl DayTraderImpl {
         CommitBuyRequest {
             user_id,
             request_num,
+            ..
         }: CommitBuyRequest,
     ) {
         let log_entry = LogEntry::new(
@@ -488,7 +554,7 @@ impl DayTraderImpl {
                 command: CommandType::Buy,
                 stock_symbol: Some(stock_symbol),
                 filename: None,
-                funds: Some(amount),
+                funds: amount.parse().ok(),
             }),
         );
 
@@ -515,7 +581,7 @@ impl DayTraderImpl {
                 command: CommandType::Add,
                 stock_symbol: None,
                 filename: None,
-                funds: Some(amount),
+                funds: amount.parse().ok(),
             }),
         );
         if let Err(err) = self.log_sender.send(log_entry).await {
@@ -524,17 +590,49 @@ impl DayTraderImpl {
     }
 }
 
+#[derive(Clone)]
 struct CachedQuote {
-    cache: moka::future::Cache<String, f64>,
+    cache: moka::future::Cache<String, Decimal>,
+    /// where every fetched price is recorded, for [history]
+    postgres: PgPool,
     quote: QuoteClient<Channel>,
     quote_update_sender: Sender<UpdatedPrice>,
+    /// every new price, for [prices::price_updates]
+    price_sender: broadcast::Sender<UpdatedPrice>,
     log_sender: Sender<LogEntry>,
+    breaker: CircuitBreaker,
+    /// how old a recorded price may be to be quoted while the quote server is unavailable, if at
+    /// all
+    stale_fallback: Option<Duration>,
+}
+
+/**
+ * A price and, if it was recorded earlier because the quote server is unavailable, when.
+ */
+#[derive(Debug, Clone, PartialEq)]
+struct Quoted {
+    price: Decimal,
+    stale_since: Option<time::PrimitiveDateTime>,
+}
+
+impl From<Quoted> for QuoteRequestSimple {
+    fn from(Quoted { price, stale_since }: Quoted) -> Self {
+        QuoteRequestSimple {
+            price: price.to_string(),
+            stale: stale_since.is_some(),
+            stale_since_unix_seconds: stale_since
+                .map(|it| it.assume_utc().unix_timestamp())
+                .unwrap_or_default(),
+        }
+    }
 }
 
 impl CachedQuote {
     pub fn new(
+        postgres: PgPool,
         quote: QuoteClient<Channel>,
         quote_update_sender: Sender<UpdatedPrice>,
+        price_sender: broadcast::Sender<UpdatedPrice>,
         log_sender: Sender<LogEntry>,
     ) -> Self {
         Self {
@@ -547,9 +645,18 @@ impl CachedQuote {
                         .expect("failed to parse QUOTE_CACHE_TTL"),
                 ))
                 .build(),
+            postgres,
             quote,
             quote_update_sender,
+            price_sender,
             log_sender,
+            breaker: CircuitBreaker::new(BreakerConfig::from_env()),
+            stale_fallback: env::var("QUOTE_STALE_FALLBACK_SECONDS").ok().map(|it| {
+                Duration::from_secs(
+                    it.parse::<u64>()
+                        .expect("failed to parse QUOTE_STALE_FALLBACK_SECONDS"),
+                )
+            }),
         }
     }
 
@@ -559,7 +666,7 @@ impl CachedQuote {
         request_num: i32,
         user_id: String,
         stock_symbol: String,
-    ) -> anyhow::Result<f64> {
+    ) -> anyhow::Result<Decimal> {
         self.cache
             .optionally_get_with(
                 stock_symbol.clone(),
@@ -567,16 +674,95 @@ impl CachedQuote {
                     self.log_sender.clone(),
                     request_num,
                     user_id,
-                    stock_symbol,
+                    stock_symbol.clone(),
                 ),
             )
             .await
             .ok_or_else(|| {
                 error!("failed to get quote");
-                anyhow!("failed to get quote")
+                let unavailable = if self.breaker.is_open() {
+                    QuoteUnavailable::CircuitOpen(stock_symbol)
+                } else {
+                    QuoteUnavailable::Failed(stock_symbol)
+                };
+                anyhow::Error::new(unavailable)
             })
     }
 
+    /**
+     * Like [Self::get_quote_maybe_cached], but while the quote server is unavailable quotes the
+     * last recorded price instead if stale fallback is on and it is recent enough. Only for
+     * showing prices, never for trading at them.
+     */
+    #[tracing::instrument(skip(self))]
+    async fn get_quote_or_stale(
+        &self,
+        request_num: i32,
+        user_id: String,
+        stock_symbol: String,
+    ) -> anyhow::Result<Quoted> {
+        let err = match self
+            .get_quote_maybe_cached(request_num, user_id, stock_symbol.clone())
+            .await
+        {
+            Ok(price) => {
+                return Ok(Quoted {
+                    price,
+                    stale_since: None,
+                })
+            }
+            Err(err) => err,
+        };
+
+        let Some(max_age) = self.stale_fallback else {
+            return Err(err);
+        };
+        // failing to look up a stale price doesn't make the quote any less unavailable
+        let latest = history::latest(&self.postgres, &stock_symbol, max_age)
+            .await
+            .unwrap_or_else(|e| {
+                error!("failed to look up a stale price of {stock_symbol}: {e}");
+                None
+            });
+        match latest {
+            Some(tick) => {
+                warn!(
+                    "quoting the price of {stock_symbol} from {} instead: {err}",
+                    tick.time
+                );
+                Ok(Quoted {
+                    price: tick.price,
+                    stale_since: Some(tick.time),
+                })
+            }
+            None => Err(err),
+        }
+    }
+
+    /**
+     * Fetches a fresh quote from the quote server, bypassing the cache, and caches the new price.
+     */
+    #[tracing::instrument(skip(self))]
+    async fn refresh(
+        &self,
+        request_num: i32,
+        user_id: String,
+        stock_symbol: String,
+    ) -> Option<Decimal> {
+        let quote = self
+            .quote_server_quote(
+                self.log_sender.clone(),
+                request_num,
+                user_id,
+                stock_symbol.clone(),
+            )
+            .await?;
+
+        self.cache.insert(stock_symbol, quote).await;
+
+        Some(quote)
+    }
+
     #[tracing::instrument(skip_all)]
     async fn quote_server_quote(
         &self,
@@ -584,9 +770,14 @@ impl CachedQuote {
         request_num: i32,
         user_id: String,
         stock_symbol: String,
-    ) -> Option<f64> {
+    ) -> Option<Decimal> {
         warn!("cache miss for {stock_symbol}");
 
+        if !self.breaker.allow() {
+            warn!("circuit breaker is open, not quoting {stock_symbol}");
+            return None;
+        }
+
         let result = match self
             .quote
             .clone()
@@ -598,36 +789,52 @@ impl CachedQuote {
             .await
         {
             Ok(quote_response) => {
+                // a quote that doesn't convert is a bad answer, but still an answer
+                self.breaker.record_success();
                 let quote_response = quote_response.into_inner();
-                let quote = quote_response.quote;
-
-                Self::log_quote_server_hit(sender, request_num, quote_response).await;
 
-                Some(quote)
+                match money::dollars_from_quote(quote_response.quote) {
+                    Ok(quote) => {
+                        Self::log_quote_server_hit(sender, request_num, quote, quote_response)
+                            .await;
+                        Some(quote)
+                    }
+                    Err(e) => {
+                        error!("failed to convert quote: {e}");
+                        None
+                    }
+                }
             }
             Err(e) => {
+                self.breaker.record_status(&e);
                 error!("failed to get quote: {e}");
                 None
             }
         };
 
         if let Some(result) = result {
-            self.send_quote_update(stock_symbol, result).await;
+            if let Err(err) = history::record(&self.postgres, &stock_symbol, result).await {
+                error!("failed to record price of {stock_symbol}: {err}");
+            }
+            self.send_quote_update(request_num, stock_symbol, result)
+                .await;
         };
 
         result
     }
 
     #[tracing::instrument(skip_all)]
-    async fn send_quote_update(&self, stock_symbol: String, result: f64) {
-        if let Err(err) = self
-            .quote_update_sender
-            .send(UpdatedPrice {
-                symbol: stock_symbol.to_string(),
-                price: result,
-            })
-            .await
-        {
+    async fn send_quote_update(&self, request_num: i32, stock_symbol: String, result: Decimal) {
+        let update = UpdatedPrice {
+            request_num,
+            symbol: stock_symbol.to_string(),
+            price: result,
+        };
+
+        // only fails when nobody is subscribed
+        let _ = self.price_sender.send(update.clone());
+
+        if let Err(err) = self.quote_update_sender.send(update).await {
             error!("failed to send quote update: {err}");
         }
     }
@@ -636,19 +843,20 @@ impl CachedQuote {
     async fn log_quote_server_hit(
         sender: Sender<LogEntry>,
         request_num: i32,
+        price: Decimal,
         QuoteResponse {
-            quote,
             sym,
             user_id,
             timestamp,
             crypto_key,
+            ..
         }: QuoteResponse,
     ) {
         let log_entry = LogEntry::new(
             request_num,
             user_id,
             Log::QuoteServerHits(QuoteServerLog {
-                price: quote,
+                price,
                 stock_symbol: sym,
                 quote_server_time: timestamp,
                 cryptokey: crypto_key,
@@ -679,20 +887,46 @@ impl DayTraderImpl {
 impl DayTraderImpl {
     /**
      * Creates a new instance of the DayTraderImpl.
-     * spawns the [Logger] and [Triggerer] tasks. which handle persisting logs and triggering buy and
-     * sell triggers.
+     * spawns the [Logger], [Triggerer], [PricePoller], [Reaper] and [AccountEventListener] tasks.
+     * which handle persisting logs, triggering buy and sell triggers, keeping prices of triggered
+     * symbols fresh, refunding expired queued buys and sells and fanning out account events.
      */
-    pub fn new(postgres: PgPool, quote: QuoteClient<Channel>) -> Self {
+    pub fn new(
+        postgres: PgPool,
+        quote: QuoteClient<Channel>,
+        authenticator: Authenticator,
+        dumps: DumpDir,
+    ) -> Self {
         let (logger, log_sender) = Logger::new(postgres.clone());
         let (triggerer, quote_update_sender) = Triggerer::new(postgres.clone());
+        let quote = CachedQuote::new(
+            postgres.clone(),
+            quote,
+            quote_update_sender,
+            prices::price_channel(),
+            log_sender.clone(),
+        );
+        let price_poller = PricePoller::new(postgres.clone(), quote.clone());
+        let expiry = OrderExpiry::from_env();
+        let reaper = Reaper::new(postgres.clone(), expiry);
+        let book = MatchingEngine::new(postgres.clone());
+        let (event_listener, events) = AccountEventListener::new(postgres.clone());
 
         tokio::spawn(logger.run());
         tokio::spawn(triggerer.run());
+        tokio::spawn(price_poller.run());
+        tokio::spawn(reaper.run());
+        tokio::spawn(event_listener.run());
 
         Self {
             postgres,
-            quote: CachedQuote::new(quote, quote_update_sender, log_sender.clone()),
+            quote,
             log_sender,
+            authenticator,
+            dumps,
+            expiry,
+            book,
+            events,
         }
     }
 
@@ -721,27 +955,82 @@ impl DayTraderImpl {
     }
 }
 
+/// 0 is the most recent order
+fn order_id(order_id: i64) -> Option<i64> {
+    (order_id != 0).then_some(order_id)
+}
+
+#[allow(clippy::result_large_err)]
+fn log_filter(filter: Option<proto::LogFilter>) -> Result<LogFilter, Status> {
+    filter
+        .map(LogFilter::try_from)
+        .transpose()
+        .map(Option::unwrap_or_default)
+        .map_err(|e| Status::invalid_argument(format!("invalid log filter: {e}")))
+}
+
+fn dump_chunks(
+    dump: BoxStream<'static, anyhow::Result<Vec<u8>>>,
+) -> BoxStream<'static, Result<DumpLogChunk, Status>> {
+    dump.map(dump_chunk).boxed()
+}
+
+#[allow(clippy::result_large_err)]
+fn dump_chunk(chunk: anyhow::Result<Vec<u8>>) -> Result<DumpLogChunk, Status> {
+    chunk.map(|data| DumpLogChunk { data }).map_err(|e| {
+        error!("failed to stream dump: {e}");
+        Status::internal("failed to stream dump")
+    })
+}
+
+#[allow(clippy::result_large_err)]
+fn log_violation(violation: anyhow::Result<log::Violation>) -> Result<proto::LogViolation, Status> {
+    violation.map(proto::LogViolation::from).map_err(|e| {
+        error!("failed to validate log: {e}");
+        Status::internal("failed to validate log")
+    })
+}
+
 #[tonic::async_trait]
 impl DayTrader for DayTraderImpl {
+    type StreamDumpLogStream = BoxStream<'static, Result<DumpLogChunk, Status>>;
+
+    type StreamDumpLogUserStream = BoxStream<'static, Result<DumpLogChunk, Status>>;
+
+    type ValidateLogStream = BoxStream<'static, Result<proto::LogViolation, Status>>;
+
+    type SubscribePricesStream = BoxStream<'static, Result<proto::PriceUpdate, Status>>;
+
+    type SubscribeAccountEventsStream = BoxStream<'static, Result<proto::AccountEvent, Status>>;
+
     #[tracing::instrument(skip_all, name = "grpc_dump_log_user")]
     async fn dump_log_user(
         &self,
         request: Request<DumpLogUserRequest>,
     ) -> Result<Response<DumpLogUserResponse>, Status> {
+        auth::authorize(&request, &request.get_ref().user_id)?;
+
         let dump_log_user_request = request.into_inner();
 
         self.log_dump_log_user_request(&dump_log_user_request).await;
 
+        let format = dump_log_user_request.format().into();
         let DumpLogUserRequest {
             filename,
             request_num,
             user_id,
+            filter,
+            ..
         } = dump_log_user_request;
 
-        match log::dump_log_user(&self.postgres, &filename.clone(), &user_id.clone()).await {
-            Ok(()) => Ok(Response::new(DumpLogUserResponse {
-                xml: filename.clone(),
-            })),
+        let filter = log_filter(filter)?.for_user(user_id.clone());
+
+        let (name, path) = self
+            .dumps
+            .new_dump("dumplog-user", DumpFormat::extension(format));
+
+        match log::dump_log(&self.postgres, &path, &filter, format).await {
+            Ok(()) => Ok(Response::new(DumpLogUserResponse { xml: name })),
             Err(e) => {
                 self.report_error(
                     request_num,
@@ -765,19 +1054,28 @@ impl DayTrader for DayTraderImpl {
         &self,
         request: Request<DumpLogRequest>,
     ) -> Result<Response<DumpLogResponse>, Status> {
+        auth::authorize_admin(&request)?;
+
         let dump_log_request = request.into_inner();
 
         self.log_dump_log_request(&dump_log_request).await;
 
+        let format = dump_log_request.format().into();
         let DumpLogRequest {
             filename,
             request_num,
+            filter,
+            ..
         } = dump_log_request;
 
-        match log::dump_log(&self.postgres, &filename.clone()).await {
-            Ok(()) => Ok(Response::new(DumpLogResponse {
-                xml: filename.clone(),
-            })),
+        let filter = log_filter(filter)?;
+
+        let (name, path) = self
+            .dumps
+            .new_dump("dumplog", DumpFormat::extension(format));
+
+        match log::dump_log(&self.postgres, &path, &filter, format).await {
+            Ok(()) => Ok(Response::new(DumpLogResponse { xml: name })),
             Err(e) => {
                 self.report_error(
                     request_num,
@@ -797,11 +1095,58 @@ impl DayTrader for DayTraderImpl {
         }
     }
 
+    #[tracing::instrument(skip_all, name = "grpc_stream_dump_log")]
+    async fn stream_dump_log(
+        &self,
+        request: Request<DumpLogRequest>,
+    ) -> Result<Response<Self::StreamDumpLogStream>, Status> {
+        auth::authorize_admin(&request)?;
+
+        let dump_log_request = request.into_inner();
+
+        self.log_dump_log_request(&dump_log_request).await;
+
+        let format = dump_log_request.format().into();
+        let filter = log_filter(dump_log_request.filter)?;
+
+        Ok(Response::new(dump_chunks(log::stream_dump_log(
+            self.postgres.clone(),
+            filter,
+            format,
+        ))))
+    }
+
+    #[tracing::instrument(skip_all, name = "grpc_stream_dump_log_user")]
+    async fn stream_dump_log_user(
+        &self,
+        request: Request<DumpLogUserRequest>,
+    ) -> Result<Response<Self::StreamDumpLogUserStream>, Status> {
+        auth::authorize(&request, &request.get_ref().user_id)?;
+
+        let dump_log_user_request = request.into_inner();
+
+        self.log_dump_log_user_request(&dump_log_user_request).await;
+
+        let format = dump_log_user_request.format().into();
+        let DumpLogUserRequest {
+            user_id, filter, ..
+        } = dump_log_user_request;
+        let filter = log_filter(filter)?.for_user(user_id);
+
+        Ok(Response::new(dump_chunks(log::stream_dump_log(
+            self.postgres.clone(),
+            filter,
+            format,
+        ))))
+    }
+
     #[tracing::instrument(skip_all, name = "grpc_display_summary")]
     async fn display_summary(
         &self,
         request: Request<DisplaySummaryRequest>,
     ) -> Result<Response<DisplaySummaryResponse>, Status> {
+        auth::authorize(&request, &request.get_ref().user_id)?;
+
         let display_summary_request = request.into_inner();
 
         self.log_display_summary_request(&display_summary_request)
@@ -834,6 +1179,8 @@ impl DayTrader for DayTraderImpl {
 
     #[tracing::instrument(skip_all, name = "grpc_add")]
     async fn add(&self, request: Request<AddRequest>) -> Result<Response<AddResponse>, Status> {
+        auth::authorize(&request, &request.get_ref().user_id)?;
+
         let add_request = request.into_inner();
         let log = self.log_add_request(add_request.clone());
 
@@ -843,7 +1190,10 @@ impl DayTrader for DayTraderImpl {
             request_num,
         } = add_request;
 
-        let add = add::add(&self.postgres, &user_id, amount);
+        let add = async {
+            let amount = money::parse_dollars(&amount)?;
+            add::add(&self.postgres, &user_id, amount).await
+        };
 
         let ((), add) = tokio::join!(log, add);
 
@@ -861,7 +1211,7 @@ impl DayTrader for DayTraderImpl {
                         command: CommandType::Add,
                         stock_symbol: None,
                         filename: None,
-                        funds: Some(amount),
+                        funds: amount.parse().ok(),
                         error_message: Some(e.to_string()),
                     },
                 )
@@ -873,6 +1223,8 @@ impl DayTrader for DayTraderImpl {
 
     #[tracing::instrument(skip_all, name = "grpc_buy")]
     async fn buy(&self, request: Request<BuyRequest>) -> Result<Response<BuyResponse>, Status> {
+        auth::authorize(&request, &request.get_ref().user_id)?;
+
         let buy_request = request.into_inner();
 
         let log = self.log_buy_request(buy_request.clone());
@@ -891,9 +1243,12 @@ impl DayTrader for DayTraderImpl {
                 .await
                 .map_err(|err| {
                     error!("failed to get quote: {}", err);
-                    Status::internal(err.to_string())
+                    breaker::status(&err, err.to_string())
                 })?;
 
+            let amount = money::parse_dollars(&amount)
+                .map_err(|err| Status::invalid_argument(err.to_string()))?;
+
             let init_buy = buy::init_buy(&self.postgres, &user_id, &stock_symbol, quote, amount)
                 .await
                 .map_err(|err| {
@@ -907,10 +1262,13 @@ impl DayTrader for DayTraderImpl {
         let ((), init_buy) = tokio::join!(log, init_buy);
 
         match init_buy {
-            Ok(account_transaction) => {
+            Ok((order_id, account_transaction)) => {
                 self.log_account_tnx(request_num, &user_id, account_transaction)
                     .await?;
-                Ok(Response::new(BuyResponse { success: true }))
+                Ok(Response::new(BuyResponse {
+                    success: true,
+                    order_id,
+                }))
             }
             Err(e) => {
                 self.report_error(
@@ -920,12 +1278,15 @@ impl DayTrader for DayTraderImpl {
                         command: CommandType::Buy,
                         stock_symbol: Some(stock_symbol),
                         filename: None,
-                        funds: Some(amount),
+                        funds: amount.parse().ok(),
                         error_message: Some(e.to_string()),
                     },
                 )
                 .await;
-                Err(Status::internal(format!("failed to buy: {}", e)))
+                Err(Status::new(
+                    e.code(),
+                    format!("failed to buy: {}", e.message()),
+                ))
             }
         }
     }
@@ -935,12 +1296,19 @@ impl DayTrader for DayTraderImpl {
         &self,
         request: Request<CommitBuyRequest>,
     ) -> Result<Response<CommitBuyResponse>, Status> {
+        auth::authorize(&request, &request.get_ref().user_id)?;
+
         let commit_buy_request = request.into_inner();
 
         let log = self.log_commit_buy_request(commit_buy_request.clone());
 
         let user_id = commit_buy_request.user_id.clone();
-        let commit_buy = buy::commit_buy(&self.postgres, &user_id);
+        let commit_buy = buy::commit_buy(
+            &self.postgres,
+            &user_id,
+            order_id(commit_buy_request.order_id),
+            self.expiry.buy,
+        );
 
         let ((), commit_buy) = tokio::join!(log, commit_buy);
 
@@ -969,12 +1337,19 @@ impl DayTrader for DayTraderImpl {
         &self,
         request: Request<CancelBuyRequest>,
     ) -> Result<Response<CancelBuyResponse>, Status> {
+        auth::authorize(&request, &request.get_ref().user_id)?;
+
         let cancel_buy_request = request.into_inner();
 
         let log = self.log_cancel_buy_request(&cancel_buy_request);
 
         let user_id = cancel_buy_request.user_id.clone();
-        let cancel = buy::cancel_buy(&self.postgres, &user_id);
+        let cancel = buy::cancel_buy(
+            &self.postgres,
+            &user_id,
+            order_id(cancel_buy_request.order_id),
+            self.expiry.buy,
+        );
 
         let ((), cancel) = tokio::join!(log, cancel);
 
@@ -1004,6 +1379,8 @@ impl DayTrader for DayTraderImpl {
 
     #[tracing::instrument(skip_all, name = "grpc_sell")]
     async fn sell(&self, request: Request<SellRequest>) -> Result<Response<SellResponse>, Status> {
+        auth::authorize(&request, &request.get_ref().user_id)?;
+
         let sell_request = request.into_inner();
 
         let log = self.log_sell_request(sell_request.clone());
@@ -1021,15 +1398,21 @@ impl DayTrader for DayTraderImpl {
                 .get_quote_maybe_cached(request_num, user_id.clone(), stock_symbol.clone())
                 .await?;
 
-            sell::init_sell(&self.postgres, &user_id, &stock_symbol, quote, amount).await?;
+            let amount = money::parse_dollars(&amount)?;
+
+            let order_id =
+                sell::init_sell(&self.postgres, &user_id, &stock_symbol, quote, amount).await?;
 
-            Ok::<(), anyhow::Error>(())
+            Ok::<i64, anyhow::Error>(order_id)
         };
 
         let ((), init_sell) = tokio::join!(log, init_sell);
 
         match init_sell {
-            Ok(()) => Ok(Response::new(SellResponse { success: true })),
+            Ok(order_id) => Ok(Response::new(SellResponse {
+                success: true,
+                order_id,
+            })),
             Err(e) => {
                 self.report_error(
                     request_num,
@@ -1038,12 +1421,12 @@ impl DayTrader for DayTraderImpl {
                         command: CommandType::Sell,
                         stock_symbol: Some(stock_symbol),
                         filename: None,
-                        funds: Some(amount),
+                        funds: amount.parse().ok(),
                         error_message: Some(e.to_string()),
                     },
                 )
                 .await;
-                Err(Status::internal(format!("failed to sell: {}", e)))
+                Err(breaker::status(&e, format!("failed to sell: {}", e)))
             }
         }
     }
@@ -1053,11 +1436,18 @@ impl DayTrader for DayTraderImpl {
         &self,
         request: Request<CommitSellRequest>,
     ) -> Result<Response<CommitSellResponse>, Status> {
+        auth::authorize(&request, &request.get_ref().user_id)?;
+
         let commit_sell_request = request.into_inner();
 
         let log = self.log_commit_sell_request(commit_sell_request.clone());
 
-        let commit_sell = sell::commit_sell(&self.postgres, commit_sell_request.user_id.clone());
+        let commit_sell = sell::commit_sell(
+            &self.postgres,
+            commit_sell_request.user_id.clone(),
+            order_id(commit_sell_request.order_id),
+            self.expiry.sell,
+        );
 
         let ((), commit_sell) = tokio::join!(log, commit_sell);
 
@@ -1090,11 +1480,17 @@ impl DayTrader for DayTraderImpl {
         &self,
         request: Request<CancelSellRequest>,
     ) -> Result<Response<CancelSellResponse>, Status> {
+        auth::authorize(&request, &request.get_ref().user_id)?;
+
         let cancel_sell_request = request.into_inner();
 
         let log = self.log_cancel_sell_request(cancel_sell_request.clone());
 
-        let cancel_sell = sell::cancel_sell(&self.postgres, cancel_sell_request.user_id.clone());
+        let cancel_sell = sell::cancel_sell(
+            &self.postgres,
+            cancel_sell_request.user_id.clone(),
+            order_id(cancel_sell_request.order_id),
+        );
 
         let ((), cancel_sell) = tokio::join!(log, cancel_sell);
 
@@ -1118,11 +1514,323 @@ impl DayTrader for DayTraderImpl {
         }
     }
 
+    #[tracing::instrument(skip_all, name = "grpc_list_pending_orders")]
+    async fn list_pending_orders(
+        &self,
+        request: Request<ListPendingOrdersRequest>,
+    ) -> Result<Response<ListPendingOrdersResponse>, Status> {
+        auth::authorize(&request, &request.get_ref().user_id)?;
+
+        let ListPendingOrdersRequest { user_id } = request.into_inner();
+
+        let orders = orders::list_pending_orders(&self.postgres, &user_id)
+            .await
+            .map_err(|e| {
+                error!("failed to list pending orders: {e}");
+                Status::internal("failed to list pending orders")
+            })?;
+
+        Ok(Response::new(ListPendingOrdersResponse { orders }))
+    }
+
+    #[tracing::instrument(skip_all, name = "grpc_place_order")]
+    async fn place_order(
+        &self,
+        request: Request<PlaceOrderRequest>,
+    ) -> Result<Response<PlaceOrderResponse>, Status> {
+        auth::authorize(&request, &request.get_ref().user_id)?;
+
+        let place_order_request = request.into_inner();
+        let order_type = orders::OrderType::from(place_order_request.order_type());
+        let time_in_force = orders::TimeInForce::from(place_order_request.time_in_force());
+
+        // limit and stop orders are the general form of the course's buy and sell triggers
+        let command = if order_type.is_buy() {
+            CommandType::SetBuyTrigger
+        } else {
+            CommandType::SetSellTrigger
+        };
+
+        let PlaceOrderRequest {
+            user_id,
+            stock_symbol,
+            amount,
+            price,
+            request_num,
+            ..
+        } = place_order_request;
+
+        let log = async {
+            let log_entry = LogEntry::new(
+                request_num,
+                user_id.clone(),
+                Log::UserCommand(UserCommandLog {
+                    command: command.clone(),
+                    stock_symbol: Some(stock_symbol.clone()),
+                    filename: None,
+                    funds: amount.parse().ok(),
+                }),
+            );
+            if let Err(err) = self.log_sender.send(log_entry).await {
+                error!("failed to send log entry: {err}");
+            }
+        };
+
+        let place_order = async {
+            let order = orders::NewOrder {
+                stock_symbol: stock_symbol.clone(),
+                order_type,
+                time_in_force,
+                amount: if order_type.is_buy() {
+                    money::parse_dollars(&amount)?
+                } else {
+                    money::parse_shares(&amount)?
+                },
+                price: money::parse_dollars(&price)?,
+            };
+
+            let price = self
+                .quote
+                .get_quote_maybe_cached(request_num, user_id.clone(), stock_symbol.clone())
+                .await?;
+            let current = UpdatedPrice {
+                request_num,
+                symbol: stock_symbol.clone(),
+                price,
+            };
+
+            orders::place_order(&self.postgres, &user_id, order, &current).await
+        };
+
+        let ((), place_order) = tokio::join!(log, place_order);
+
+        match place_order {
+            Ok((order_id, status, account_transaction)) => {
+                if order_type.is_buy() {
+                    self.log_account_tnx(request_num, &user_id, account_transaction)
+                        .await?;
+                }
+                Ok(Response::new(PlaceOrderResponse {
+                    order_id,
+                    status: proto::OrderStatus::from(status).into(),
+                }))
+            }
+            Err(e) => {
+                self.report_error(
+                    request_num,
+                    user_id,
+                    ErrorEventLog {
+                        command,
+                        stock_symbol: Some(stock_symbol),
+                        filename: None,
+                        funds: amount.parse().ok(),
+                        error_message: Some(e.to_string()),
+                    },
+                )
+                .await;
+                Err(breaker::status(&e, format!("failed to place order: {e}")))
+            }
+        }
+    }
+
+    #[tracing::instrument(skip_all, name = "grpc_cancel_order")]
+    async fn cancel_order(
+        &self,
+        request: Request<CancelOrderRequest>,
+    ) -> Result<Response<CancelOrderResponse>, Status> {
+        auth::authorize(&request, &request.get_ref().user_id)?;
+
+        let CancelOrderRequest {
+            user_id,
+            order_id,
+            request_num,
+        } = request.into_inner();
+
+        let account_transaction = orders::cancel_order(&self.postgres, &user_id, order_id)
+            .await
+            .map_err(|e| {
+                error!("failed to cancel order: {e}");
+                Status::internal(format!("failed to cancel order: {e}"))
+            })?;
+
+        if !account_transaction.0.is_zero() {
+            self.log_account_tnx(request_num, &user_id, account_transaction)
+                .await?;
+        }
+
+        Ok(Response::new(CancelOrderResponse { success: true }))
+    }
+
+    #[tracing::instrument(skip_all, name = "grpc_list_orders")]
+    async fn list_orders(
+        &self,
+        request: Request<ListOrdersRequest>,
+    ) -> Result<Response<ListOrdersResponse>, Status> {
+        auth::authorize(&request, &request.get_ref().user_id)?;
+
+        let ListOrdersRequest { user_id } = request.into_inner();
+
+        let orders = orders::list_orders(&self.postgres, &user_id)
+            .await
+            .map_err(|e| {
+                error!("failed to list orders: {e}");
+                Status::internal("failed to list orders")
+            })?;
+
+        Ok(Response::new(ListOrdersResponse {
+            orders: orders
+                .into_iter()
+                .map(proto::ConditionalOrder::from)
+                .collect(),
+        }))
+    }
+
+    #[tracing::instrument(skip_all, name = "grpc_place_book_order")]
+    async fn place_book_order(
+        &self,
+        request: Request<PlaceBookOrderRequest>,
+    ) -> Result<Response<PlaceBookOrderResponse>, Status> {
+        auth::authorize(&request, &request.get_ref().user_id)?;
+
+        let place_book_order_request = request.into_inner();
+        let side = book::Side::from(place_book_order_request.side());
+
+        let command = match side {
+            book::Side::Buy => CommandType::Buy,
+            book::Side::Sell => CommandType::Sell,
+        };
+
+        let PlaceBookOrderRequest {
+            user_id,
+            stock_symbol,
+            price,
+            quantity,
+            request_num,
+            ..
+        } = place_book_order_request;
+
+        let log = async {
+            let log_entry = LogEntry::new(
+                request_num,
+                user_id.clone(),
+                Log::UserCommand(UserCommandLog {
+                    command: command.clone(),
+                    stock_symbol: Some(stock_symbol.clone()),
+                    filename: None,
+                    funds: price.parse().ok(),
+                }),
+            );
+            if let Err(err) = self.log_sender.send(log_entry).await {
+                error!("failed to send log entry: {err}");
+            }
+        };
+
+        let place_book_order = async {
+            let order = book::NewBookOrder {
+                stock_symbol: stock_symbol.clone(),
+                side,
+                price: money::parse_dollars(&price)?,
+                quantity: money::parse_shares(&quantity)?,
+            };
+            self.book.place(&user_id, order, request_num).await
+        };
+
+        let ((), place_book_order) = tokio::join!(log, place_book_order);
+
+        match place_book_order {
+            Ok(placed) => {
+                if !placed.account_transaction.0.is_zero() {
+                    self.log_account_tnx(request_num, &user_id, placed.account_transaction)
+                        .await?;
+                }
+                Ok(Response::new(PlaceBookOrderResponse {
+                    order_id: placed.order_id,
+                    fills: placed
+                        .fills
+                        .into_iter()
+                        .map(proto::BookFill::from)
+                        .collect(),
+                    resting: placed.resting.to_string(),
+                }))
+            }
+            Err(e) => {
+                self.report_error(
+                    request_num,
+                    user_id,
+                    ErrorEventLog {
+                        command,
+                        stock_symbol: Some(stock_symbol),
+                        filename: None,
+                        funds: price.parse().ok(),
+                        error_message: Some(e.to_string()),
+                    },
+                )
+                .await;
+                Err(Status::internal(format!(
+                    "failed to place book order: {}",
+                    e
+                )))
+            }
+        }
+    }
+
+    #[tracing::instrument(skip_all, name = "grpc_cancel_book_order")]
+    async fn cancel_book_order(
+        &self,
+        request: Request<CancelBookOrderRequest>,
+    ) -> Result<Response<CancelBookOrderResponse>, Status> {
+        auth::authorize(&request, &request.get_ref().user_id)?;
+
+        let CancelBookOrderRequest {
+            user_id,
+            order_id,
+            request_num,
+        } = request.into_inner();
+
+        let account_transaction = self.book.cancel(&user_id, order_id).await.map_err(|e| {
+            error!("failed to cancel book order: {e}");
+            Status::internal(format!("failed to cancel book order: {e}"))
+        })?;
+
+        if !account_transaction.0.is_zero() {
+            self.log_account_tnx(request_num, &user_id, account_transaction)
+                .await?;
+        }
+
+        Ok(Response::new(CancelBookOrderResponse { success: true }))
+    }
+
+    #[tracing::instrument(skip_all, name = "grpc_get_book_depth")]
+    async fn get_book_depth(
+        &self,
+        request: Request<GetBookDepthRequest>,
+    ) -> Result<Response<GetBookDepthResponse>, Status> {
+        let GetBookDepthRequest {
+            stock_symbol,
+            levels,
+        } = request.into_inner();
+
+        let levels = match levels {
+            0 => 10,
+            levels => usize::try_from(levels)
+                .map_err(|_| Status::invalid_argument("levels must not be negative"))?,
+        };
+
+        let depth = self.book.depth(&stock_symbol, levels).await.map_err(|e| {
+            error!("failed to get book depth: {e}");
+            Status::internal("failed to get book depth")
+        })?;
+
+        Ok(Response::new(GetBookDepthResponse::from(depth)))
+    }
+
     #[tracing::instrument(skip_all, name = "grpc_set_buy_amount")]
     async fn set_buy_amount(
         &self,
         request: Request<SetBuyAmountRequest>,
     ) -> Result<Response<SetBuyAmountResponse>, Status> {
+        auth::authorize(&request, &request.get_ref().user_id)?;
+
         let set_buy_amount_request = request.into_inner();
 
         let log = self.log_set_buy_amount_request(set_buy_amount_request.clone());
@@ -1134,8 +1842,10 @@ impl DayTrader for DayTraderImpl {
             ..
         } = set_buy_amount_request;
 
-        let set_buy_amount =
-            trigger::set_buy_amount(&self.postgres, &user_id, &stock_symbol, amount);
+        let set_buy_amount = async {
+            let amount = money::parse_dollars(&amount)?;
+            trigger::set_buy_amount(&self.postgres, &user_id, &stock_symbol, amount).await
+        };
 
         let ((), set_buy_amount) = tokio::join!(log, set_buy_amount);
 
@@ -1153,7 +1863,7 @@ impl DayTrader for DayTraderImpl {
                         command: CommandType::SetBuyAmount,
                         stock_symbol: Some(stock_symbol),
                         filename: None,
-                        funds: Some(amount),
+                        funds: amount.parse().ok(),
                         error_message: Some(e.to_string()),
                     },
                 )
@@ -1168,6 +1878,8 @@ impl DayTrader for DayTraderImpl {
         &self,
         request: Request<CancelSetBuyRequest>,
     ) -> Result<Response<CancelSetBuyResponse>, Status> {
+        auth::authorize(&request, &request.get_ref().user_id)?;
+
         let cancel_set_buy_request = request.into_inner();
 
         let log = self.log_cancel_set_buy_request(cancel_set_buy_request.clone());
@@ -1211,6 +1923,8 @@ impl DayTrader for DayTraderImpl {
         &self,
         request: Request<SetBuyTriggerRequest>,
     ) -> Result<Response<SetBuyTriggerResponse>, Status> {
+        auth::authorize(&request, &request.get_ref().user_id)?;
+
         let set_buy_trigger_request = request.into_inner();
 
         let log = self.log_set_buy_trigger_request(set_buy_trigger_request.clone());
@@ -1222,8 +1936,10 @@ impl DayTrader for DayTraderImpl {
             ..
         } = set_buy_trigger_request;
 
-        let set_buy_trigger =
-            trigger::set_buy_trigger(&self.postgres, &user_id, &stock_symbol, amount);
+        let set_buy_trigger = async {
+            let trigger_price = money::parse_dollars(&amount)?;
+            trigger::set_buy_trigger(&self.postgres, &user_id, &stock_symbol, trigger_price).await
+        };
 
         let ((), set_buy_trigger) = tokio::join!(log, set_buy_trigger);
 
@@ -1237,7 +1953,7 @@ impl DayTrader for DayTraderImpl {
                         command: CommandType::SetBuyTrigger,
                         stock_symbol: Some(stock_symbol),
                         filename: None,
-                        funds: Some(amount),
+                        funds: amount.parse().ok(),
                         error_message: Some(e.to_string()),
                     },
                 )
@@ -1255,6 +1971,8 @@ impl DayTrader for DayTraderImpl {
         &self,
         request: Request<SetSellAmountRequest>,
     ) -> Result<Response<SetSellAmountResponse>, Status> {
+        auth::authorize(&request, &request.get_ref().user_id)?;
+
         let set_sell_amount_request = request.into_inner();
 
         let log = self.log_set_sell_amount_request(set_sell_amount_request.clone());
@@ -1266,8 +1984,10 @@ impl DayTrader for DayTraderImpl {
             ..
         } = set_sell_amount_request;
 
-        let set_sell_amount =
-            trigger::set_sell_amount(&self.postgres, &user_id, &stock_symbol, amount);
+        let set_sell_amount = async {
+            let amount = money::parse_shares(&amount)?;
+            trigger::set_sell_amount(&self.postgres, &user_id, &stock_symbol, amount).await
+        };
 
         let ((), set_sell_amount) = tokio::join!(log, set_sell_amount);
 
@@ -1281,7 +2001,7 @@ impl DayTrader for DayTraderImpl {
                         command: CommandType::SetSellAmount,
                         stock_symbol: Some(stock_symbol),
                         filename: None,
-                        funds: Some(amount),
+                        funds: amount.parse().ok(),
                         error_message: Some(e.to_string()),
                     },
                 )
@@ -1299,6 +2019,8 @@ impl DayTrader for DayTraderImpl {
         &self,
         request: Request<SetSellTriggerRequest>,
     ) -> Result<Response<SetSellTriggerResponse>, Status> {
+        auth::authorize(&request, &request.get_ref().user_id)?;
+
         let set_sell_trigger_request = request.into_inner();
 
         let log = self.log_set_sell_trigger_request(set_sell_trigger_request.clone());
@@ -1310,8 +2032,10 @@ impl DayTrader for DayTraderImpl {
             ..
         } = set_sell_trigger_request;
 
-        let set_sell_trigger =
-            trigger::set_sell_trigger(&self.postgres, &user_id, &stock_symbol, amount);
+        let set_sell_trigger = async {
+            let trigger_price = money::parse_dollars(&amount)?;
+            trigger::set_sell_trigger(&self.postgres, &user_id, &stock_symbol, trigger_price).await
+        };
 
         let ((), set_sell_trigger) = tokio::join!(log, set_sell_trigger);
 
@@ -1325,7 +2049,7 @@ impl DayTrader for DayTraderImpl {
                         command: CommandType::SetSellTrigger,
                         stock_symbol: Some(stock_symbol),
                         filename: None,
-                        funds: Some(amount),
+                        funds: amount.parse().ok(),
                         error_message: Some(e.to_string()),
                     },
                 )
@@ -1343,6 +2067,8 @@ impl DayTrader for DayTraderImpl {
         &self,
         request: Request<CancelSetSellRequest>,
     ) -> Result<Response<CancelSetSellResponse>, Status> {
+        auth::authorize(&request, &request.get_ref().user_id)?;
+
         let cancel_set_sell_request = request.into_inner();
 
         let log = self.log_cancel_set_sell_request(cancel_set_sell_request.clone());
@@ -1380,6 +2106,258 @@ impl DayTrader for DayTraderImpl {
         }
     }
 
+    #[tracing::instrument(skip_all, name = "grpc_place_buy_trigger")]
+    async fn place_buy_trigger(
+        &self,
+        request: Request<PlaceBuyTriggerRequest>,
+    ) -> Result<Response<PlaceBuyTriggerResponse>, Status> {
+        auth::authorize(&request, &request.get_ref().user_id)?;
+
+        let PlaceBuyTriggerRequest {
+            user_id,
+            stock_symbol,
+            amount,
+            trigger_price,
+            request_num,
+        } = request.into_inner();
+
+        // logged as the two commands it replaces
+        let log = async {
+            self.log_set_buy_amount_request(SetBuyAmountRequest {
+                user_id: user_id.clone(),
+                stock_symbol: stock_symbol.clone(),
+                amount: amount.clone(),
+                request_num,
+            })
+            .await;
+            self.log_set_buy_trigger_request(SetBuyTriggerRequest {
+                user_id: user_id.clone(),
+                stock_symbol: stock_symbol.clone(),
+                amount: trigger_price.clone(),
+                request_num,
+            })
+            .await;
+        };
+
+        let place_buy_trigger = async {
+            let amount = money::parse_dollars(&amount)?;
+            let trigger_price = money::parse_dollars(&trigger_price)?;
+            trigger::place_buy_trigger(
+                &self.postgres,
+                &user_id,
+                &stock_symbol,
+                amount,
+                trigger_price,
+            )
+            .await
+        };
+
+        let ((), place_buy_trigger) = tokio::join!(log, place_buy_trigger);
+
+        match place_buy_trigger {
+            Ok(account_transaction) => {
+                self.log_account_tnx(request_num, &user_id, account_transaction)
+                    .await?;
+                Ok(Response::new(PlaceBuyTriggerResponse { success: true }))
+            }
+            Err(e) => {
+                self.report_error(
+                    request_num,
+                    user_id,
+                    ErrorEventLog {
+                        command: CommandType::SetBuyTrigger,
+                        stock_symbol: Some(stock_symbol),
+                        filename: None,
+                        funds: amount.parse().ok(),
+                        error_message: Some(e.to_string()),
+                    },
+                )
+                .await;
+                Err(Status::internal(format!(
+                    "failed to place buy trigger: {}",
+                    e
+                )))
+            }
+        }
+    }
+
+    #[tracing::instrument(skip_all, name = "grpc_place_sell_trigger")]
+    async fn place_sell_trigger(
+        &self,
+        request: Request<PlaceSellTriggerRequest>,
+    ) -> Result<Response<PlaceSellTriggerResponse>, Status> {
+        auth::authorize(&request, &request.get_ref().user_id)?;
+
+        let PlaceSellTriggerRequest {
+            user_id,
+            stock_symbol,
+            amount,
+            trigger_price,
+            request_num,
+        } = request.into_inner();
+
+        // logged as the two commands it replaces
+        let log = async {
+            self.log_set_sell_amount_request(SetSellAmountRequest {
+                user_id: user_id.clone(),
+                stock_symbol: stock_symbol.clone(),
+                amount: amount.clone(),
+                request_num,
+            })
+            .await;
+            self.log_set_sell_trigger_request(SetSellTriggerRequest {
+                user_id: user_id.clone(),
+                stock_symbol: stock_symbol.clone(),
+                amount: trigger_price.clone(),
+                request_num,
+            })
+            .await;
+        };
+
+        let place_sell_trigger = async {
+            let amount = money::parse_shares(&amount)?;
+            let trigger_price = money::parse_dollars(&trigger_price)?;
+            trigger::place_sell_trigger(
+                &self.postgres,
+                &user_id,
+                &stock_symbol,
+                amount,
+                trigger_price,
+            )
+            .await
+        };
+
+        let ((), place_sell_trigger) = tokio::join!(log, place_sell_trigger);
+
+        match place_sell_trigger {
+            Ok(()) => Ok(Response::new(PlaceSellTriggerResponse { success: true })),
+            Err(e) => {
+                self.report_error(
+                    request_num,
+                    user_id,
+                    ErrorEventLog {
+                        command: CommandType::SetSellTrigger,
+                        stock_symbol: Some(stock_symbol),
+                        filename: None,
+                        funds: amount.parse().ok(),
+                        error_message: Some(e.to_string()),
+                    },
+                )
+                .await;
+                Err(Status::internal(format!(
+                    "failed to place sell trigger: {}",
+                    e
+                )))
+            }
+        }
+    }
+
+    #[tracing::instrument(skip_all, name = "grpc_update_trigger")]
+    async fn update_trigger(
+        &self,
+        request: Request<UpdateTriggerRequest>,
+    ) -> Result<Response<UpdateTriggerResponse>, Status> {
+        auth::authorize(&request, &request.get_ref().user_id)?;
+
+        let update_trigger_request = request.into_inner();
+        let side = update_trigger_request.side();
+
+        let UpdateTriggerRequest {
+            user_id,
+            stock_symbol,
+            amount,
+            trigger_price,
+            request_num,
+            ..
+        } = update_trigger_request;
+
+        let command = match side {
+            OrderSide::Buy => CommandType::SetBuyTrigger,
+            OrderSide::Sell => CommandType::SetSellTrigger,
+        };
+
+        let log = async {
+            let log_entry = LogEntry::new(
+                request_num,
+                user_id.clone(),
+                Log::UserCommand(UserCommandLog {
+                    command: command.clone(),
+                    stock_symbol: Some(stock_symbol.clone()),
+                    filename: None,
+                    funds: trigger_price.parse().ok(),
+                }),
+            );
+            if let Err(err) = self.log_sender.send(log_entry).await {
+                error!("failed to send log entry: {err}");
+            }
+        };
+
+        let update_trigger = async {
+            let trigger_price = match trigger_price.as_str() {
+                "" => None,
+                price => Some(money::parse_dollars(price)?),
+            };
+            match side {
+                OrderSide::Buy => {
+                    let amount = match amount.as_str() {
+                        "" => None,
+                        amount => Some(money::parse_dollars(amount)?),
+                    };
+                    trigger::update_buy_trigger(
+                        &self.postgres,
+                        &user_id,
+                        &stock_symbol,
+                        amount,
+                        trigger_price,
+                    )
+                    .await
+                }
+                OrderSide::Sell => {
+                    let amount = match amount.as_str() {
+                        "" => None,
+                        amount => Some(money::parse_shares(amount)?),
+                    };
+                    trigger::update_sell_trigger(
+                        &self.postgres,
+                        &user_id,
+                        &stock_symbol,
+                        amount,
+                        trigger_price,
+                    )
+                    .await
+                    .map(|()| AccountTransaction(Decimal::ZERO))
+                }
+            }
+        };
+
+        let ((), update_trigger) = tokio::join!(log, update_trigger);
+
+        match update_trigger {
+            Ok(account_transaction) => {
+                if !account_transaction.0.is_zero() {
+                    self.log_account_tnx(request_num, &user_id, account_transaction)
+                        .await?;
+                }
+                Ok(Response::new(UpdateTriggerResponse { success: true }))
+            }
+            Err(e) => {
+                self.report_error(
+                    request_num,
+                    user_id,
+                    ErrorEventLog {
+                        command,
+                        stock_symbol: Some(stock_symbol),
+                        filename: None,
+                        funds: trigger_price.parse().ok(),
+                        error_message: Some(e.to_string()),
+                    },
+                )
+                .await;
+                Err(Status::internal(format!("failed to update trigger: {}", e)))
+            }
+        }
+    }
+
     #[tracing::instrument(skip_all, name = "grpc_get_all_stocks")]
     async fn get_all_stocks(
         &self,
@@ -1392,23 +2370,120 @@ impl DayTrader for DayTraderImpl {
                 .into_iter()
                 .map(|(symbol, price)| Stock {
                     name: symbol.to_string(),
-                    price,
+                    price: price.to_string(),
                 })
                 .collect(),
         }))
     }
 
+    #[tracing::instrument(skip_all, name = "grpc_subscribe_prices")]
+    async fn subscribe_prices(
+        &self,
+        request: Request<SubscribePricesRequest>,
+    ) -> Result<Response<Self::SubscribePricesStream>, Status> {
+        let symbols = request
+            .into_inner()
+            .stock_symbols
+            .into_iter()
+            .collect::<HashSet<_>>();
+
+        // subscribe before reading the cache so no price falls between the two
+        let updates = prices::price_updates(self.quote.price_sender.subscribe(), symbols.clone());
+
+        let cached = self
+            .quote
+            .cache
+            .into_iter()
+            .filter(|(symbol, _)| symbols.is_empty() || symbols.contains(symbol.as_str()))
+            .map(|(symbol, price)| proto::PriceUpdate {
+                stock_symbol: symbol.to_string(),
+                price: price.to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        Ok(Response::new(
+            futures::stream::iter(cached)
+                .chain(updates.map(proto::PriceUpdate::from))
+                .map(Ok)
+                .boxed(),
+        ))
+    }
+
+    #[tracing::instrument(skip_all, name = "grpc_get_price_history")]
+    async fn get_price_history(
+        &self,
+        request: Request<GetPriceHistoryRequest>,
+    ) -> Result<Response<GetPriceHistoryResponse>, Status> {
+        let query = PriceHistoryQuery::try_from(request.into_inner())
+            .map_err(|err| Status::invalid_argument(err.to_string()))?;
+
+        let history = query
+            .fetch(&self.postgres)
+            .await
+            .map_err(|err| Status::internal(format!("failed to get price history: {err}")))?;
+
+        Ok(Response::new(history.into()))
+    }
+
+    #[tracing::instrument(skip_all, name = "grpc_subscribe_account_events")]
+    async fn subscribe_account_events(
+        &self,
+        request: Request<SubscribeAccountEventsRequest>,
+    ) -> Result<Response<Self::SubscribeAccountEventsStream>, Status> {
+        auth::authorize(&request, &request.get_ref().user_id)?;
+
+        let SubscribeAccountEventsRequest { user_id } = request.into_inner();
+
+        Ok(Response::new(
+            self.events
+                .subscribe(user_id)
+                .map(proto::AccountEvent::from)
+                .map(Ok)
+                .boxed(),
+        ))
+    }
+
+    #[tracing::instrument(skip_all, name = "grpc_get_portfolio")]
+    async fn get_portfolio(
+        &self,
+        request: Request<GetPortfolioRequest>,
+    ) -> Result<Response<GetPortfolioResponse>, Status> {
+        auth::authorize(&request, &request.get_ref().user_id)?;
+
+        let GetPortfolioRequest { user_id } = request.into_inner();
+
+        let holdings = portfolio::holdings(&self.postgres, &user_id)
+            .await
+            .map_err(|err| Status::internal(format!("failed to get portfolio: {err}")))?;
+
+        let mut valued = Vec::with_capacity(holdings.len());
+        for holding in holdings {
+            let price = self.quote.cache.get(&holding.stock_symbol).await;
+            valued.push(
+                holding
+                    .value(price)
+                    .map_err(|err| Status::internal(format!("failed to value holding: {err}")))?,
+            );
+        }
+
+        Ok(Response::new(
+            valued.into_iter().collect::<portfolio::Portfolio>().into(),
+        ))
+    }
+
     #[tracing::instrument(skip_all, name = "grpc_get_user_info")]
     async fn get_user_info(
         &self,
         request: Request<GetUserInfoRequest>,
     ) -> Result<Response<GetUserInfoResponse>, Status> {
+        auth::authorize(&request, &request.get_ref().user_id)?;
+
         let GetUserInfoRequest { user_id } = request.into_inner();
 
         let stock = async {
             sqlx::query_as!(
                 Stock,
-                "SELECT stock_symbol as name, amount as price FROM stock WHERE owner_id = $1",
+                r#"SELECT stock_symbol as name, (amount - reserved)::text as "price!" FROM stock WHERE owner_id = $1"#,
                 &user_id
             )
             .fetch_all(&self.postgres)
@@ -1426,8 +2501,8 @@ impl DayTrader for DayTraderImpl {
                 SELECT
                     owner_id as username,
                     stock_symbol as stock,
-                    amount_dollars as buy_amount,
-                    trigger_price as "trigger_amount!"
+                    amount_dollars::text as "buy_amount!",
+                    trigger_price::text as "trigger_amount!"
                 FROM buy_trigger
                 WHERE owner_id = $1 AND trigger_price IS NOT NULL
                 "#,
@@ -1443,8 +2518,8 @@ impl DayTrader for DayTraderImpl {
                 SELECT
                     owner_id as username,
                     stock_symbol as stock,
-                    amount_stock as shares_to_sell,
-                    trigger_price as "trigger_amount!"
+                    amount_stock::text as "shares_to_sell!",
+                    trigger_price::text as "trigger_amount!"
                 FROM sell_trigger
                 WHERE owner_id = $1 AND trigger_price IS NOT NULL
                 "#,
@@ -1463,7 +2538,7 @@ impl DayTrader for DayTraderImpl {
         };
 
         Ok(Response::new(GetUserInfoResponse {
-            balance: balance.balance,
+            balance: balance.balance.to_string(),
             buy_triggers,
             sell_triggers,
             stock,
@@ -1475,23 +2550,51 @@ impl DayTrader for DayTraderImpl {
         &self,
         request: Request<LoginRequest>,
     ) -> Result<Response<LoginResponse>, Status> {
-        let user_id = request.into_inner().user_id;
+        let LoginRequest { user_id, password } = request.into_inner();
 
-        let user = sqlx::query!("SELECT user_id FROM trader WHERE user_id = $1", &user_id)
-            .fetch_optional(&self.postgres)
+        let admin = auth::check_credentials(&self.postgres, &user_id, &password)
             .await
-            .map_err(|err| Status::internal(format!("failed to check if user exists: {err}")))?;
+            .map_err(|err| Status::internal(format!("failed to check credentials: {err}")))?;
 
-        match user {
+        match admin {
             None => Ok(Response::new(LoginResponse {
                 success: false,
-                user_id: user_id.clone(),
-            })),
-            Some(user) => Ok(Response::new(LoginResponse {
-                success: true,
-                user_id: user.user_id,
+                user_id,
+                token: String::new(),
             })),
+            Some(admin) => {
+                let token = self
+                    .authenticator
+                    .issue(&user_id, admin)
+                    .map_err(|err| Status::internal(format!("failed to issue token: {err}")))?;
+
+                Ok(Response::new(LoginResponse {
+                    success: true,
+                    user_id,
+                    token,
+                }))
+            }
+        }
+    }
+
+    #[tracing::instrument(skip_all, name = "grpc_register")]
+    async fn register(
+        &self,
+        request: Request<RegisterRequest>,
+    ) -> Result<Response<RegisterResponse>, Status> {
+        let RegisterRequest { user_id, password } = request.into_inner();
+
+        if user_id.is_empty() || password.is_empty() {
+            return Err(Status::invalid_argument(
+                "user_id and password must not be empty",
+            ));
         }
+
+        auth::register(&self.postgres, &user_id, &password)
+            .await
+            .map_err(|err| Status::already_exists(format!("failed to register: {err}")))?;
+
+        Ok(Response::new(RegisterResponse {}))
     }
 
     #[tracing::instrument(skip_all, name = "grpc_quote")]
@@ -1499,6 +2602,8 @@ impl DayTrader for DayTraderImpl {
         &self,
         request: Request<QuoteRequest>,
     ) -> Result<Response<QuoteRequestSimple>, Status> {
+        auth::authorize(&request, &request.get_ref().user_id)?;
+
         let quote_request = request.into_inner();
         let cloned = quote_request.clone();
         let log = self.log_quote_request(&cloned);
@@ -1511,12 +2616,12 @@ impl DayTrader for DayTraderImpl {
 
         let quote =
             self.quote
-                .get_quote_maybe_cached(request_num, user_id.clone(), stock_symbol.clone());
+                .get_quote_or_stale(request_num, user_id.clone(), stock_symbol.clone());
 
         let ((), quote) = tokio::join!(log, quote);
 
         match quote {
-            Ok(quote) => Ok(Response::new(QuoteRequestSimple { price: quote })),
+            Ok(quoted) => Ok(Response::new(QuoteRequestSimple::from(quoted))),
             Err(e) => {
                 self.report_error(
                     0,
@@ -1530,17 +2635,27 @@ impl DayTrader for DayTraderImpl {
                     },
                 )
                 .await;
-                Err(Status::internal(format!("failed to get quote: {}", e)))
+                Err(breaker::status(&e, format!("failed to get quote: {}", e)))
             }
         }
     }
 
     #[tracing::instrument(skip_all, name = "grpc_file")]
     async fn file(&self, request: Request<FileRequest>) -> Result<Response<FileResponse>, Status> {
+        auth::authorize_admin(&request)?;
+
         let FileRequest { filename } = request.into_inner();
 
+        let path = self
+            .dumps
+            .resolve(&filename)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
         let mut file =
-            tokio::io::BufReader::new(tokio::fs::File::open(filename).await.map_err(|e| {
+            tokio::io::BufReader::new(tokio::fs::File::open(path).await.map_err(|e| {
+                if e.kind() == std::io::ErrorKind::NotFound {
+                    return Status::not_found(format!("no dump named {filename}"));
+                }
                 error!("failed to open file: {e}");
                 Status::internal("failed to open file")
             })?);
@@ -1554,4 +2669,174 @@ impl DayTrader for DayTraderImpl {
 
         Ok(Response::new(FileResponse { contents: buf }))
     }
+
+    #[tracing::instrument(skip_all, name = "grpc_list_dumps")]
+    async fn list_dumps(
+        &self,
+        request: Request<ListDumpsRequest>,
+    ) -> Result<Response<ListDumpsResponse>, Status> {
+        auth::authorize_admin(&request)?;
+
+        let dumps = self.dumps.list().await.map_err(|e| {
+            error!("failed to list dumps: {e}");
+            Status::internal("failed to list dumps")
+        })?;
+
+        Ok(Response::new(ListDumpsResponse { dumps }))
+    }
+
+    #[tracing::instrument(skip_all, name = "grpc_validate_log")]
+    async fn validate_log(
+        &self,
+        request: Request<ValidateLogRequest>,
+    ) -> Result<Response<Self::ValidateLogStream>, Status> {
+        auth::authorize_admin(&request)?;
+
+        let filter = log_filter(request.into_inner().filter)?;
+
+        Ok(Response::new(
+            log::validate_log(self.postgres.clone(), filter)
+                .map(log_violation)
+                .boxed(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::Session;
+    use crate::fake_quote::cached_quote;
+    use pretty_assertions::assert_eq;
+    use rust_decimal_macros::dec;
+    use tonic::Code;
+
+    fn day_trader(pool: PgPool, quote: CachedQuote) -> anyhow::Result<DayTraderImpl> {
+        let (log_sender, _) = tokio::sync::mpsc::channel(100);
+        let (_, events) = AccountEventListener::new(pool.clone());
+        Ok(DayTraderImpl {
+            postgres: pool.clone(),
+            quote,
+            log_sender,
+            authenticator: Authenticator::new(b"secret", Duration::from_secs(60)),
+            dumps: DumpDir::new(env::temp_dir().join("lean-test-dumps"))?,
+            expiry: OrderExpiry::default(),
+            book: MatchingEngine::new(pool),
+            events,
+        })
+    }
+
+    /// a request as [Authenticator::intercept] passes it on for the user's own token
+    fn as_user<T>(user_id: &str, message: T) -> Request<T> {
+        let mut request = Request::new(message);
+        request.extensions_mut().insert(Session {
+            sub: user_id.to_string(),
+            admin: false,
+            exp: u64::MAX,
+        });
+        request
+    }
+
+    async fn quote_code(quote: &CachedQuote, stock_symbol: &str) -> Code {
+        let err = quote
+            .get_quote_or_stale(1, "marcus".to_string(), stock_symbol.to_string())
+            .await
+            .unwrap_err();
+        breaker::status(&err, err.to_string()).code()
+    }
+
+    #[sqlx::test]
+    async fn test_stale_fallback(pool: PgPool) -> anyhow::Result<()> {
+        history::record(&pool, "APPL", dec!(10)).await?;
+        let hour = Duration::from_secs(60 * 60);
+        let (quote, _updates) = cached_quote(pool.clone(), None, Some(hour)).await;
+
+        // buys and sells never trade at the recorded price
+        let err = quote
+            .get_quote_maybe_cached(1, "marcus".to_string(), "APPL".to_string())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            breaker::status(&err, err.to_string()).code(),
+            Code::Unavailable
+        );
+        assert!(quote.breaker.is_open());
+
+        let quoted = quote
+            .get_quote_or_stale(1, "marcus".to_string(), "APPL".to_string())
+            .await?;
+        assert_eq!(quoted.price, dec!(10));
+        let stale_since = quoted.stale_since.unwrap();
+
+        let response = QuoteRequestSimple::from(quoted);
+        assert_eq!(
+            response,
+            QuoteRequestSimple {
+                price: "10.00".to_string(),
+                stale: true,
+                stale_since_unix_seconds: stale_since.assume_utc().unix_timestamp(),
+            }
+        );
+        assert!(response.stale_since_unix_seconds > 0);
+
+        // nothing recorded to fall back on
+        assert_eq!(quote_code(&quote, "MSFT").await, Code::Unavailable);
+
+        let (no_fallback, _updates) = cached_quote(pool.clone(), None, None).await;
+        assert_eq!(quote_code(&no_fallback, "APPL").await, Code::Unavailable);
+
+        // nor when the recorded price can't be looked up
+        pool.close().await;
+        assert_eq!(quote_code(&quote, "APPL").await, Code::Unavailable);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_fresh_quotes_are_not_stale(pool: PgPool) -> anyhow::Result<()> {
+        let (quote, _updates) =
+            cached_quote(pool.clone(), Some(12.34), Some(Duration::from_secs(60))).await;
+
+        let quoted = quote
+            .get_quote_or_stale(1, "marcus".to_string(), "APPL".to_string())
+            .await?;
+        assert_eq!(
+            QuoteRequestSimple::from(quoted),
+            QuoteRequestSimple {
+                price: "12.34".to_string(),
+                stale: false,
+                stale_since_unix_seconds: 0,
+            }
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_buy_without_quotes_is_unavailable(pool: PgPool) -> anyhow::Result<()> {
+        let _log = add::add(&pool, "marcus", dec!(1000)).await?;
+        let (quote, _updates) = cached_quote(pool.clone(), None, None).await;
+        let day_trader = day_trader(pool, quote)?;
+
+        let status = day_trader
+            .buy(as_user(
+                "marcus",
+                BuyRequest {
+                    user_id: "marcus".to_string(),
+                    stock_symbol: "APPL".to_string(),
+                    amount: "100".to_string(),
+                    request_num: 1,
+                },
+            ))
+            .await
+            .unwrap_err();
+
+        assert_eq!(status.code(), Code::Unavailable);
+        assert!(
+            status.message().starts_with("failed to buy: "),
+            "{status:?}"
+        );
+
+        Ok(())
+    }
 }
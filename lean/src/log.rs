@@ -1,3 +1,4 @@
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use sqlx::pool::PoolConnection;
 use sqlx::types::JsonValue;
@@ -12,7 +13,7 @@ use tracing::info;
  */
 #[derive(Debug, PartialEq, Copy, Clone, PartialOrd)]
 #[must_use]
-pub struct AccountTransaction(pub f64);
+pub struct AccountTransaction(pub Decimal);
 
 impl Add for AccountTransaction {
     type Output = AccountTransaction;
@@ -22,6 +23,7 @@ impl Add for AccountTransaction {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub struct DbLogEntry {
     pub timestamp: PrimitiveDateTime,
     pub server: String,
@@ -138,6 +140,23 @@ impl Logger {
     }
 }
 
+/**
+ * Saves a single log entry with the given executor rather than through the [Logger], so it can be
+ * written in the same transaction as the change it describes.
+ */
+#[tracing::instrument(skip_all)]
+pub async fn save_log_entry(executor: impl PgExecutor<'_>, entry: LogEntry) -> anyhow::Result<()> {
+    save_log_entry_bulk(
+        executor,
+        &vec![entry.timestamp],
+        &vec![entry.server],
+        &vec![entry.transaction_num],
+        &vec![entry.username],
+        &vec![serde_json::to_value(&entry.log)?],
+    )
+    .await
+}
+
 #[tracing::instrument(skip_all)]
 async fn save_log_entry_bulk(
     pool: impl PgExecutor<'_>,
@@ -283,7 +302,7 @@ pub struct UserCommandLog {
     pub command: CommandType,
     pub stock_symbol: Option<String>,
     pub filename: Option<String>,
-    pub funds: Option<f64>,
+    pub funds: Option<Decimal>,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
@@ -308,7 +327,7 @@ pub enum CommandType {
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct QuoteServerLog {
-    pub price: f64,
+    pub price: Decimal,
     pub stock_symbol: String,
     pub quote_server_time: u64,
     pub cryptokey: String,
@@ -317,7 +336,7 @@ pub struct QuoteServerLog {
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct AccountTransactionLog {
     pub action: String,
-    pub funds: f64,
+    pub funds: Decimal,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
@@ -325,7 +344,7 @@ pub struct SystemEventLog {
     pub command: CommandType,
     pub stock_symbol: Option<String>,
     pub filename: Option<String>,
-    pub funds: Option<f64>,
+    pub funds: Option<Decimal>,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
@@ -333,7 +352,7 @@ pub struct ErrorEventLog {
     pub command: CommandType,
     pub stock_symbol: Option<String>,
     pub filename: Option<String>,
-    pub funds: Option<f64>,
+    pub funds: Option<Decimal>,
     pub error_message: Option<String>,
 }
 
@@ -342,14 +361,23 @@ pub struct DebugLog {
     pub command: CommandType,
     pub stock_symbol: Option<String>,
     pub filename: Option<String>,
-    pub funds: Option<f64>,
+    pub funds: Option<Decimal>,
     pub debug_message: Option<String>,
 }
 
-pub use dump_log::dump_log;
+pub use dump_log::{dump_log, DumpFormat};
 
 mod dump_log;
 
-pub use dump_log_user::dump_log_user;
+pub(crate) use filter::unix_seconds;
+pub use filter::LogFilter;
+
+mod filter;
+
+pub use stream_dump_log::stream_dump_log;
+
+mod stream_dump_log;
+
+pub use validate_log::{validate_log, Violation};
 
-mod dump_log_user;
+mod validate_log;
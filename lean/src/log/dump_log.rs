@@ -1,18 +1,21 @@
 use super::DbLogEntry;
+use rust_decimal::Decimal;
 
 use crate::log::{
-    AccountTransactionLog, CommandType, DebugLog, ErrorEventLog, Log, LogEntry, QuoteServerLog,
-    SystemEventLog, UserCommandLog,
+    AccountTransactionLog, CommandType, DebugLog, ErrorEventLog, Log, LogEntry, LogFilter,
+    QuoteServerLog, SystemEventLog, UserCommandLog,
 };
 
+use crate::proto;
 use anyhow::anyhow;
 use futures::stream::BoxStream;
 use futures::StreamExt;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
+use std::path::Path;
 use time::PrimitiveDateTime;
 use tokio::fs::File;
-use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::io::{AsyncWrite, AsyncWriteExt, BufWriter};
 
 /*
 <xsd:complexType name="LogType">
@@ -65,7 +68,7 @@ struct UserCommandType {
     #[serde(skip_serializing_if = "Option::is_none")]
     filename: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    funds: Option<f64>,
+    funds: Option<Decimal>,
 }
 
 /*
@@ -79,6 +82,11 @@ struct UserCommandType {
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 struct UnixTimeLimits(i64);
 
+impl UnixTimeLimits {
+    const MIN_INCLUSIVE: i64 = 1609459200000;
+    const MAX_INCLUSIVE: i64 = 1619827200000;
+}
+
 /*
 <xsd:complexType name="QuoteServerType">
 <xsd:all>
@@ -98,7 +106,7 @@ struct QuoteServerType {
     timestamp: UnixTimeLimits,
     server: String,
     transaction_num: i32,
-    price: f64,
+    price: Decimal,
     stock_symbol: String,
     username: String,
     quote_server_time: u64,
@@ -132,7 +140,7 @@ struct SystemEventType {
     #[serde(skip_serializing_if = "Option::is_none")]
     filename: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    funds: Option<f64>,
+    funds: Option<Decimal>,
 }
 
 /*
@@ -163,7 +171,7 @@ struct ErrorEventType {
     #[serde(skip_serializing_if = "Option::is_none")]
     filename: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    funds: Option<f64>,
+    funds: Option<Decimal>,
     #[serde(skip_serializing_if = "Option::is_none")]
     error_message: Option<String>,
 }
@@ -196,7 +204,7 @@ struct DebugType {
     #[serde(skip_serializing_if = "Option::is_none")]
     filename: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    funds: Option<f64>,
+    funds: Option<Decimal>,
     #[serde(skip_serializing_if = "Option::is_none")]
     debug_message: Option<String>,
 }
@@ -220,32 +228,35 @@ struct AccountTransactionType {
     transaction_num: i32,
     action: String,
     username: String,
-    funds: f64,
+    funds: Decimal,
 }
 
 #[tracing::instrument(skip(pool))]
-pub async fn dump_log(pool: &PgPool, filename: &str) -> anyhow::Result<()> {
-    let file = File::create(filename)
+pub async fn dump_log(
+    pool: &PgPool,
+    path: &Path,
+    filter: &LogFilter,
+    format: DumpFormat,
+) -> anyhow::Result<()> {
+    let file = File::create(path)
         .await
         .map_err(|e| anyhow!("failed to create file: {e}"))?;
     let mut file = BufWriter::new(file);
 
-    let mut rows =
-        sqlx::query_as!(DbLogEntry, "SELECT * FROM log_entry ORDER BY timestamp ASC").fetch(pool);
+    let mut rows = filter.fetch(pool);
 
-    write_entries(&mut file, &mut rows).await?;
+    write_entries(&mut file, &mut rows, format).await?;
 
     Ok(())
 }
 
 #[tracing::instrument(skip(file, rows))]
 pub(super) async fn write_entries(
-    file: &mut BufWriter<File>,
+    file: &mut (impl AsyncWrite + Unpin),
     rows: &mut BoxStream<'_, sqlx::Result<DbLogEntry>>,
+    format: DumpFormat,
 ) -> anyhow::Result<()> {
-    file.write_all(b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\r")
-        .await?;
-    file.write_all(b"<log>").await?;
+    file.write_all(format.header()).await?;
 
     while let Some(row) = rows.next().await {
         // db log entry
@@ -254,29 +265,255 @@ pub(super) async fn write_entries(
         let row = LogEntry::try_from(row).map_err(|e| anyhow!("failed to convert row: {e}"))?;
         // xmlifyable log entry
         let row = LogType::from(row);
-        // xml
-        let row =
-            serde_xml_rs::to_string(&row).map_err(|e| anyhow!("failed to serialize row: {e}"))?;
-        // xml with header removed
-        let row = row.replace(r#"<?xml version="1.0" encoding="UTF-8"?>"#, "");
+        // xml, json or csv
+        let row = format
+            .serialize(row)
+            .map_err(|e| anyhow!("failed to serialize row: {e}"))?;
         // write to file
-        file.write_all(row.as_bytes())
+        file.write_all(&row)
             .await
             .map_err(|e| anyhow!("failed to write row: {e}"))?;
     }
 
-    file.write_all(b"</log>").await?;
+    file.write_all(format.footer()).await?;
 
     file.flush().await?;
 
     Ok(())
 }
 
+/**
+ * Checks the element `entry` is dumped as against the XSD types mirrored above. Returns the name of
+ * the element and every child element that does not conform, with why.
+ */
+pub(super) fn xsd_violations(entry: LogEntry) -> (&'static str, Vec<(&'static str, String)>) {
+    let row = LogType::from(entry);
+    let (timestamp, transaction_num) = match &row {
+        LogType::UserCommandType(it) => (it.timestamp.0, it.transaction_num),
+        LogType::QuoteServerType(it) => (it.timestamp.0, it.transaction_num),
+        LogType::AccountTransactionType(it) => (it.timestamp.0, it.transaction_num),
+        LogType::SystemEventType(it) => (it.timestamp.0, it.transaction_num),
+        LogType::ErrorEventType(it) => (it.timestamp.0, it.transaction_num),
+        LogType::DebugEventType(it) => (it.timestamp.0, it.transaction_num),
+    };
+
+    let mut violations = Vec::new();
+    if !(UnixTimeLimits::MIN_INCLUSIVE..=UnixTimeLimits::MAX_INCLUSIVE).contains(&timestamp) {
+        violations.push((
+            "timestamp",
+            format!(
+                "{timestamp} is outside of unixTimeLimits [{}, {}]",
+                UnixTimeLimits::MIN_INCLUSIVE,
+                UnixTimeLimits::MAX_INCLUSIVE
+            ),
+        ));
+    }
+    if transaction_num < 1 {
+        violations.push((
+            "transactionNum",
+            format!("{transaction_num} is not a positiveInteger"),
+        ));
+    }
+
+    (row.element(), violations)
+}
+
+/**
+ * The format of a log dump. XML follows the course XSD; JSON Lines and CSV flatten every entry into
+ * a [FlatLogRecord] so every variant shares the same keys and columns.
+ */
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum DumpFormat {
+    #[default]
+    Xml,
+    JsonLines,
+    Csv,
+}
+
+impl DumpFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            DumpFormat::Xml => "xml",
+            DumpFormat::JsonLines => "jsonl",
+            DumpFormat::Csv => "csv",
+        }
+    }
+
+    fn header(self) -> &'static [u8] {
+        match self {
+            DumpFormat::Xml => b"<?xml version=\"1.0\" encoding=\"UTF-8\"?>\r<log>",
+            DumpFormat::JsonLines => b"",
+            DumpFormat::Csv => CSV_HEADER.as_bytes(),
+        }
+    }
+
+    fn footer(self) -> &'static [u8] {
+        match self {
+            DumpFormat::Xml => b"</log>",
+            DumpFormat::JsonLines | DumpFormat::Csv => b"",
+        }
+    }
+
+    fn serialize(self, row: LogType) -> anyhow::Result<Vec<u8>> {
+        match self {
+            DumpFormat::Xml => {
+                let row = serde_xml_rs::to_string(&row)?;
+                // xml with header removed
+                Ok(row
+                    .replace(r#"<?xml version="1.0" encoding="UTF-8"?>"#, "")
+                    .into_bytes())
+            }
+            DumpFormat::JsonLines => {
+                let mut row = serde_json::to_vec(&FlatLogRecord::from(row))?;
+                row.push(b'\n');
+                Ok(row)
+            }
+            DumpFormat::Csv => {
+                let mut writer = csv::WriterBuilder::new()
+                    .has_headers(false)
+                    .from_writer(Vec::new());
+                writer.serialize(FlatLogRecord::from(row))?;
+                Ok(writer.into_inner()?)
+            }
+        }
+    }
+}
+
+impl From<proto::DumpFormat> for DumpFormat {
+    fn from(value: proto::DumpFormat) -> Self {
+        match value {
+            proto::DumpFormat::Xml => DumpFormat::Xml,
+            proto::DumpFormat::JsonLines => DumpFormat::JsonLines,
+            proto::DumpFormat::Csv => DumpFormat::Csv,
+        }
+    }
+}
+
+/// The columns of a CSV dump, in the order of the fields of [FlatLogRecord].
+const CSV_HEADER: &str = "type,timestamp,server,transaction_num,command,action,username,stock_symbol,filename,funds,price,quote_server_time,cryptokey,error_message,debug_message\n";
+
+/**
+ * A [LogType] with every variant's fields side by side, `type` names the variant after its XSD
+ * element. Fields a variant does not have are null in JSON and empty in CSV.
+ */
+#[derive(Debug, Default, Serialize, PartialEq)]
+struct FlatLogRecord {
+    #[serde(rename = "type")]
+    log_type: &'static str,
+    timestamp: i64,
+    server: String,
+    transaction_num: i32,
+    command: Option<String>,
+    action: Option<String>,
+    username: Option<String>,
+    stock_symbol: Option<String>,
+    filename: Option<String>,
+    funds: Option<Decimal>,
+    price: Option<Decimal>,
+    quote_server_time: Option<u64>,
+    cryptokey: Option<String>,
+    error_message: Option<String>,
+    debug_message: Option<String>,
+}
+
+impl LogType {
+    /// The name of the XSD element the variant is.
+    fn element(&self) -> &'static str {
+        match self {
+            LogType::UserCommandType(_) => "userCommand",
+            LogType::QuoteServerType(_) => "quoteServer",
+            LogType::AccountTransactionType(_) => "accountTransaction",
+            LogType::SystemEventType(_) => "systemEvent",
+            LogType::ErrorEventType(_) => "errorEvent",
+            LogType::DebugEventType(_) => "debugEvent",
+        }
+    }
+}
+
+impl From<LogType> for FlatLogRecord {
+    fn from(value: LogType) -> Self {
+        let log_type = value.element();
+        match value {
+            LogType::UserCommandType(it) => Self {
+                log_type,
+                timestamp: it.timestamp.0,
+                server: it.server,
+                transaction_num: it.transaction_num,
+                command: Some(it.command),
+                username: it.username,
+                stock_symbol: it.stock_symbol,
+                filename: it.filename,
+                funds: it.funds,
+                ..Self::default()
+            },
+            LogType::QuoteServerType(it) => Self {
+                log_type,
+                timestamp: it.timestamp.0,
+                server: it.server,
+                transaction_num: it.transaction_num,
+                username: Some(it.username),
+                stock_symbol: Some(it.stock_symbol),
+                price: Some(it.price),
+                quote_server_time: Some(it.quote_server_time),
+                cryptokey: Some(it.cryptokey),
+                ..Self::default()
+            },
+            LogType::AccountTransactionType(it) => Self {
+                log_type,
+                timestamp: it.timestamp.0,
+                server: it.server,
+                transaction_num: it.transaction_num,
+                action: Some(it.action),
+                username: Some(it.username),
+                funds: Some(it.funds),
+                ..Self::default()
+            },
+            LogType::SystemEventType(it) => Self {
+                log_type,
+                timestamp: it.timestamp.0,
+                server: it.server,
+                transaction_num: it.transaction_num,
+                command: Some(it.command),
+                username: it.username,
+                stock_symbol: it.stock_symbol,
+                filename: it.filename,
+                funds: it.funds,
+                ..Self::default()
+            },
+            LogType::ErrorEventType(it) => Self {
+                log_type,
+                timestamp: it.timestamp.0,
+                server: it.server,
+                transaction_num: it.transaction_num,
+                command: Some(it.command),
+                username: it.username,
+                stock_symbol: it.stock_symbol,
+                filename: it.filename,
+                funds: it.funds,
+                error_message: it.error_message,
+                ..Self::default()
+            },
+            LogType::DebugEventType(it) => Self {
+                log_type,
+                timestamp: it.timestamp.0,
+                server: it.server,
+                transaction_num: it.transaction_num,
+                command: Some(it.command),
+                username: it.username,
+                stock_symbol: it.stock_symbol,
+                filename: it.filename,
+                funds: it.funds,
+                debug_message: it.debug_message,
+                ..Self::default()
+            },
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::log::save_log_entry_bulk;
-    use std::path::Path;
     use tokio::io::AsyncReadExt;
 
     #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
@@ -328,7 +565,13 @@ mod tests {
         )
         .await?;
 
-        dump_log(&pool, "log.xml").await?;
+        dump_log(
+            &pool,
+            Path::new("log.xml"),
+            &LogFilter::default(),
+            DumpFormat::Xml,
+        )
+        .await?;
 
         let file = File::open("log.xml").await?;
         let mut file = tokio::io::BufReader::new(file);
@@ -366,6 +609,84 @@ mod tests {
 
         assert_eq!(log, log2);
     }
+
+    fn account_transaction() -> LogType {
+        LogType::AccountTransactionType(AccountTransactionType {
+            timestamp: time::macros::datetime!(2021-01-02 03:04:05).into(),
+            server: "lean".to_string(),
+            transaction_num: 3,
+            action: "add".to_string(),
+            username: "marcus".to_string(),
+            funds: Decimal::new(10050, 2),
+        })
+    }
+
+    #[test]
+    fn test_json_lines_row() -> anyhow::Result<()> {
+        let row = DumpFormat::JsonLines.serialize(account_transaction())?;
+        let row = String::from_utf8(row)?;
+
+        assert_eq!(
+            row,
+            concat!(
+                r#"{"type":"accountTransaction","timestamp":1609556645000,"server":"lean","transaction_num":3,"#,
+                r#""command":null,"action":"add","username":"marcus","stock_symbol":null,"filename":null,"#,
+                r#""funds":"100.50","price":null,"quote_server_time":null,"cryptokey":null,"#,
+                r#""error_message":null,"debug_message":null}"#,
+                "\n"
+            )
+        );
+        Ok(())
+    }
+
+    /// the quote server rows the quote server adaptor's replay tests read, keep them in step
+    #[test]
+    fn test_quote_server_rows() -> anyhow::Result<()> {
+        let quote = || {
+            LogType::QuoteServerType(QuoteServerType {
+                timestamp: time::macros::datetime!(2021-01-02 03:04:05).into(),
+                server: "lean".to_string(),
+                transaction_num: 3,
+                price: Decimal::new(1234, 2),
+                stock_symbol: "ABC".to_string(),
+                username: "marcus".to_string(),
+                quote_server_time: 1609556644000,
+                cryptokey: "abc123/XYZ=".to_string(),
+            })
+        };
+
+        assert_eq!(
+            String::from_utf8(DumpFormat::JsonLines.serialize(quote())?)?,
+            concat!(
+                r#"{"type":"quoteServer","timestamp":1609556645000,"server":"lean","transaction_num":3,"#,
+                r#""command":null,"action":null,"username":"marcus","stock_symbol":"ABC","filename":null,"#,
+                r#""funds":null,"price":"12.34","quote_server_time":1609556644000,"cryptokey":"abc123/XYZ=","#,
+                r#""error_message":null,"debug_message":null}"#,
+                "\n"
+            )
+        );
+        assert_eq!(
+            String::from_utf8(DumpFormat::Csv.serialize(quote())?)?,
+            "quoteServer,1609556645000,lean,3,,,marcus,ABC,,,12.34,1609556644000,abc123/XYZ=,,\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_csv_row_matches_header() -> anyhow::Result<()> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.serialize(FlatLogRecord::from(account_transaction()))?;
+        let with_header = String::from_utf8(writer.into_inner()?)?;
+
+        let row = String::from_utf8(DumpFormat::Csv.serialize(account_transaction())?)?;
+
+        assert_eq!(with_header, format!("{CSV_HEADER}{row}"));
+        assert_eq!(
+            row,
+            "accountTransaction,1609556645000,lean,3,,add,marcus,,,100.50,,,,,\n"
+        );
+        Ok(())
+    }
 }
 
 struct RestOfTheLog {
@@ -470,7 +791,9 @@ impl From<CommandType> for String {
 
 impl From<PrimitiveDateTime> for UnixTimeLimits {
     fn from(value: PrimitiveDateTime) -> Self {
-        UnixTimeLimits(value.assume_utc().unix_timestamp())
+        // the xsd bounds are in milliseconds
+        let millis = value.assume_utc().unix_timestamp_nanos() / 1_000_000;
+        UnixTimeLimits(millis as i64)
     }
 }
 
@@ -0,0 +1,252 @@
+use crate::log::{CommandType, DbLogEntry};
+use crate::proto;
+use anyhow::{anyhow, bail};
+use futures::stream::BoxStream;
+use sqlx::PgPool;
+use time::{OffsetDateTime, PrimitiveDateTime};
+
+/// The names of the [crate::log::Log] variants, as stored in `log_entry.log`.
+const LOG_TYPES: [&str; 6] = [
+    "UserCommand",
+    "QuoteServerHits",
+    "AccountChanges",
+    "SystemEvents",
+    "ErrorMessages",
+    "DebugMessages",
+];
+
+/**
+ * Selects the log entries to dump. Every criterion is optional; empty lists match everything.
+ */
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LogFilter {
+    /// inclusive
+    pub from: Option<PrimitiveDateTime>,
+    /// exclusive
+    pub to: Option<PrimitiveDateTime>,
+    /// [crate::log::Log] variant names
+    pub log_types: Vec<String>,
+    /// [CommandType] variant names
+    pub commands: Vec<String>,
+    pub usernames: Vec<String>,
+}
+
+impl LogFilter {
+    /**
+     * Restricts the filter to a single user, replacing any requested users.
+     */
+    pub fn for_user(self, user_id: String) -> Self {
+        Self {
+            usernames: vec![user_id],
+            ..self
+        }
+    }
+
+    /**
+     * Streams the matching entries, oldest first.
+     */
+    pub fn fetch<'a>(&'a self, pool: &'a PgPool) -> BoxStream<'a, sqlx::Result<DbLogEntry>> {
+        sqlx::query_as!(
+            DbLogEntry,
+            "
+            SELECT timestamp, server, transaction_num, username, log
+            FROM log_entry
+            WHERE ($1::timestamp IS NULL OR timestamp >= $1)
+              AND ($2::timestamp IS NULL OR timestamp < $2)
+              AND (cardinality($3::text[]) = 0 OR log_entry_type(log) = ANY($3))
+              AND (cardinality($4::text[]) = 0 OR log_entry_command(log) = ANY($4))
+              AND (cardinality($5::text[]) = 0 OR username = ANY($5))
+            ORDER BY timestamp ASC
+            ",
+            self.from,
+            self.to,
+            &self.log_types,
+            &self.commands,
+            &self.usernames
+        )
+        .fetch(pool)
+    }
+}
+
+impl TryFrom<proto::LogFilter> for LogFilter {
+    type Error = anyhow::Error;
+
+    fn try_from(
+        proto::LogFilter {
+            from_unix_seconds,
+            to_unix_seconds,
+            log_types,
+            commands,
+            user_ids,
+        }: proto::LogFilter,
+    ) -> Result<Self, Self::Error> {
+        if let Some(log_type) = log_types
+            .iter()
+            .find(|it| !LOG_TYPES.contains(&it.as_str()))
+        {
+            bail!("unknown log type {log_type}, expected one of {LOG_TYPES:?}");
+        }
+
+        for command in &commands {
+            serde_json::from_value::<CommandType>(serde_json::Value::String(command.clone()))
+                .map_err(|_| anyhow!("unknown command {command}"))?;
+        }
+
+        Ok(Self {
+            from: unix_seconds(from_unix_seconds)?,
+            to: unix_seconds(to_unix_seconds)?,
+            log_types,
+            commands,
+            usernames: user_ids,
+        })
+    }
+}
+
+/// 0 is unbounded
+pub(crate) fn unix_seconds(seconds: i64) -> anyhow::Result<Option<PrimitiveDateTime>> {
+    if seconds == 0 {
+        return Ok(None);
+    }
+    let time = OffsetDateTime::from_unix_timestamp(seconds)?;
+    Ok(Some(PrimitiveDateTime::new(time.date(), time.time())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log::{
+        save_log_entry, AccountTransactionLog, ErrorEventLog, Log, LogEntry, UserCommandLog,
+    };
+    use futures::TryStreamExt;
+    use pretty_assertions::assert_eq;
+    use rust_decimal_macros::dec;
+    use time::macros::datetime;
+
+    fn entry(timestamp: PrimitiveDateTime, username: &str, log: Log) -> LogEntry {
+        LogEntry {
+            timestamp,
+            server: "lean".to_string(),
+            transaction_num: 1,
+            username: username.to_string(),
+            log,
+        }
+    }
+
+    fn user_command(command: CommandType) -> Log {
+        Log::UserCommand(UserCommandLog {
+            command,
+            stock_symbol: None,
+            filename: None,
+            funds: None,
+        })
+    }
+
+    async fn matching(pool: &PgPool, filter: LogFilter) -> anyhow::Result<Vec<LogEntry>> {
+        filter
+            .fetch(pool)
+            .map_err(anyhow::Error::from)
+            .and_then(|row| async { Ok(LogEntry::try_from(row)?) })
+            .try_collect()
+            .await
+    }
+
+    #[sqlx::test]
+    async fn test_filters(pool: PgPool) -> anyhow::Result<()> {
+        let add = entry(
+            datetime!(2021-01-01 00:00),
+            "marcus",
+            user_command(CommandType::Add),
+        );
+        let error = entry(
+            datetime!(2021-01-02 00:00),
+            "daws",
+            Log::ErrorMessages(ErrorEventLog {
+                command: CommandType::Buy,
+                stock_symbol: None,
+                filename: None,
+                funds: None,
+                error_message: Some("no money".to_string()),
+            }),
+        );
+        let account = entry(
+            datetime!(2021-01-03 00:00),
+            "marcus",
+            Log::AccountChanges(AccountTransactionLog {
+                action: "add".to_string(),
+                funds: dec!(1),
+            }),
+        );
+        for entry in [&account, &error, &add] {
+            save_log_entry(&pool, entry.clone()).await?;
+        }
+
+        assert_eq!(
+            matching(&pool, LogFilter::default()).await?,
+            vec![add.clone(), error.clone(), account.clone()]
+        );
+
+        let between = LogFilter {
+            from: Some(datetime!(2021-01-02 00:00)),
+            to: Some(datetime!(2021-01-03 00:00)),
+            ..LogFilter::default()
+        };
+        assert_eq!(matching(&pool, between).await?, vec![error.clone()]);
+
+        let errors_and_accounts = LogFilter {
+            log_types: vec!["ErrorMessages".to_string(), "AccountChanges".to_string()],
+            ..LogFilter::default()
+        };
+        assert_eq!(
+            matching(&pool, errors_and_accounts).await?,
+            vec![error.clone(), account.clone()]
+        );
+
+        let buys = LogFilter {
+            commands: vec!["Buy".to_string()],
+            ..LogFilter::default()
+        };
+        assert_eq!(matching(&pool, buys).await?, vec![error.clone()]);
+
+        let marcus = LogFilter {
+            usernames: vec!["daws".to_string()],
+            ..LogFilter::default()
+        }
+        .for_user("marcus".to_string());
+        assert_eq!(matching(&pool, marcus).await?, vec![add, account]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_proto_rejects_unknown_names() {
+        let unknown_type = LogFilter::try_from(proto::LogFilter {
+            log_types: vec!["Errors".to_string()],
+            ..proto::LogFilter::default()
+        });
+        assert!(unknown_type.is_err());
+
+        let unknown_command = LogFilter::try_from(proto::LogFilter {
+            commands: vec!["ADD".to_string()],
+            ..proto::LogFilter::default()
+        });
+        assert!(unknown_command.is_err());
+
+        let filter = LogFilter::try_from(proto::LogFilter {
+            from_unix_seconds: 1609459200,
+            log_types: vec!["ErrorMessages".to_string()],
+            commands: vec!["CommitBuy".to_string()],
+            ..proto::LogFilter::default()
+        })
+        .unwrap();
+        assert_eq!(
+            filter,
+            LogFilter {
+                from: Some(datetime!(2021-01-01 00:00)),
+                to: None,
+                log_types: vec!["ErrorMessages".to_string()],
+                commands: vec!["CommitBuy".to_string()],
+                usernames: vec![],
+            }
+        );
+    }
+}
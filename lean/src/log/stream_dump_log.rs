@@ -0,0 +1,138 @@
+use crate::log::dump_log::write_entries;
+use crate::log::{DumpFormat, LogFilter};
+use anyhow::anyhow;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use sqlx::PgPool;
+use std::future::Future;
+use tokio::io::{AsyncReadExt, BufWriter, DuplexStream};
+use tokio::task::JoinHandle;
+
+/// The size of the chunks a dump is streamed in.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/**
+ * Streams the dump of the entries matching `filter` in chunks as they are read from the database.
+ * Rows are only fetched as fast as the stream is consumed.
+ */
+pub fn stream_dump_log(
+    pool: PgPool,
+    filter: LogFilter,
+    format: DumpFormat,
+) -> BoxStream<'static, anyhow::Result<Vec<u8>>> {
+    stream_with(move |writer| async move {
+        let mut writer = BufWriter::new(writer);
+        let mut rows = filter.fetch(&pool);
+        write_entries(&mut writer, &mut rows, format).await
+    })
+}
+
+/**
+ * Runs `write` on a task, writing into an in-memory pipe, and streams what it writes. An error from
+ * the writer is yielded once everything written before it has been streamed. Dropping the stream
+ * closes the pipe, which stops the writer.
+ */
+fn stream_with<F, Fut>(write: F) -> BoxStream<'static, anyhow::Result<Vec<u8>>>
+where
+    F: FnOnce(DuplexStream) -> Fut,
+    Fut: Future<Output = anyhow::Result<()>> + Send + 'static,
+{
+    let (reader, writer) = tokio::io::duplex(CHUNK_SIZE);
+    let writer: JoinHandle<anyhow::Result<()>> = tokio::spawn(write(writer));
+
+    futures::stream::unfold(Some((reader, writer)), |state| async move {
+        let (mut reader, writer) = state?;
+
+        let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+        if let Err(e) = (&mut reader)
+            .take(CHUNK_SIZE as u64)
+            .read_to_end(&mut chunk)
+            .await
+        {
+            return Some((Err(anyhow!("failed to read dump: {e}")), None));
+        }
+
+        if !chunk.is_empty() {
+            return Some((Ok(chunk), Some((reader, writer))));
+        }
+
+        match writer.await {
+            Ok(Ok(())) => None,
+            Ok(Err(e)) => Some((Err(e), None)),
+            Err(e) => Some((Err(anyhow!("dump writer panicked: {e}")), None)),
+        }
+    })
+    .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log::{save_log_entry, CommandType, Log, LogEntry, UserCommandLog};
+    use futures::TryStreamExt;
+    use pretty_assertions::assert_eq;
+
+    fn entry(username: &str, transaction_num: i32) -> LogEntry {
+        LogEntry {
+            timestamp: time::macros::datetime!(2021-01-02 03:04:05),
+            server: "lean".to_string(),
+            transaction_num,
+            username: username.to_string(),
+            log: Log::UserCommand(UserCommandLog {
+                command: CommandType::Add,
+                stock_symbol: None,
+                filename: None,
+                funds: None,
+            }),
+        }
+    }
+
+    #[sqlx::test]
+    async fn test_stream_matches_file_dump(pool: PgPool) -> anyhow::Result<()> {
+        for transaction_num in 1..=2000 {
+            save_log_entry(&pool, entry("marcus", transaction_num)).await?;
+        }
+
+        let chunks = stream_dump_log(pool.clone(), LogFilter::default(), DumpFormat::Xml)
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert!(chunks.len() > 1, "expected more than one chunk");
+        assert!(chunks.iter().all(|chunk| chunk.len() <= CHUNK_SIZE));
+
+        let path = std::env::temp_dir().join(format!("stream-dump-{}.xml", std::process::id()));
+        crate::log::dump_log(&pool, &path, &LogFilter::default(), DumpFormat::Xml).await?;
+        let expected = tokio::fs::read(&path).await?;
+        tokio::fs::remove_file(&path).await?;
+
+        assert_eq!(chunks.concat(), expected);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_stream_user(pool: PgPool) -> anyhow::Result<()> {
+        save_log_entry(&pool, entry("marcus", 1)).await?;
+        save_log_entry(&pool, entry("daws", 2)).await?;
+
+        let filter = LogFilter::default().for_user("daws".to_string());
+        let xml = stream_dump_log(pool, filter, DumpFormat::Xml)
+            .try_collect::<Vec<_>>()
+            .await?
+            .concat();
+        let xml = String::from_utf8(xml)?;
+
+        assert!(xml.contains("<username>daws</username>"), "{xml}");
+        assert!(!xml.contains("<username>marcus</username>"), "{xml}");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stream_yields_writer_error() {
+        let result = stream_with(|_writer| async { Err(anyhow!("boom")) })
+            .try_collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(result.unwrap_err().to_string(), "boom");
+    }
+}
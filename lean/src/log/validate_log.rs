@@ -0,0 +1,208 @@
+use crate::log::dump_log::xsd_violations;
+use crate::log::{DbLogEntry, LogEntry, LogFilter};
+use crate::proto;
+use anyhow::anyhow;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use sqlx::PgPool;
+
+/// How many violations are buffered ahead of the consumer.
+const VIOLATION_BUFFER: usize = 64;
+
+/**
+ * An element of the XML dump that does not conform to the course XSD.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    /// the element the row is dumped as, e.g. `userCommand`
+    pub element: &'static str,
+    /// the offending child element, e.g. `transactionNum`
+    pub field: &'static str,
+    pub message: String,
+    /// the `log_entry` row the element is produced from
+    pub row: DbLogEntry,
+}
+
+/**
+ * Checks the XML dump of the entries matching `filter` against the course XSD, streaming a
+ * [Violation] for every offending element. Rows are only fetched as fast as the stream is consumed.
+ */
+pub fn validate_log(
+    pool: PgPool,
+    filter: LogFilter,
+) -> BoxStream<'static, anyhow::Result<Violation>> {
+    let (sender, receiver) = tokio::sync::mpsc::channel(VIOLATION_BUFFER);
+
+    tokio::spawn(async move {
+        let mut rows = filter.fetch(&pool);
+        while let Some(row) = rows.next().await {
+            let violations = match row {
+                Ok(row) => row_violations(row).into_iter().map(Ok).collect(),
+                Err(e) => vec![Err(anyhow!("failed to fetch row: {e}"))],
+            };
+            for violation in violations {
+                // the stream was dropped
+                if sender.send(violation).await.is_err() {
+                    return;
+                }
+            }
+        }
+    });
+
+    futures::stream::unfold(receiver, |mut receiver| async move {
+        let violation = receiver.recv().await?;
+        Some((violation, receiver))
+    })
+    .boxed()
+}
+
+fn row_violations(row: DbLogEntry) -> Vec<Violation> {
+    let entry = match LogEntry::try_from(row.clone()) {
+        Ok(entry) => entry,
+        Err(e) => {
+            return vec![Violation {
+                element: "log",
+                field: "log",
+                message: format!("not a log entry: {e}"),
+                row,
+            }]
+        }
+    };
+
+    let (element, violations) = xsd_violations(entry);
+
+    violations
+        .into_iter()
+        .map(|(field, message)| Violation {
+            element,
+            field,
+            message,
+            row: row.clone(),
+        })
+        .collect()
+}
+
+impl From<Violation> for proto::LogViolation {
+    fn from(
+        Violation {
+            element,
+            field,
+            message,
+            row,
+        }: Violation,
+    ) -> Self {
+        proto::LogViolation {
+            element: element.to_string(),
+            field: field.to_string(),
+            message,
+            timestamp_unix_seconds: row.timestamp.assume_utc().unix_timestamp(),
+            server: row.server,
+            transaction_num: row.transaction_num,
+            user_id: row.username,
+            log: row.log.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log::{save_log_entry, CommandType, ErrorEventLog, Log, UserCommandLog};
+    use futures::TryStreamExt;
+    use pretty_assertions::assert_eq;
+    use time::macros::datetime;
+    use time::PrimitiveDateTime;
+
+    fn entry(timestamp: PrimitiveDateTime, transaction_num: i32, log: Log) -> LogEntry {
+        LogEntry {
+            timestamp,
+            server: "lean".to_string(),
+            transaction_num,
+            username: "marcus".to_string(),
+            log,
+        }
+    }
+
+    fn fields(violations: &[Violation]) -> Vec<(&'static str, &'static str, i32)> {
+        violations
+            .iter()
+            .map(|it| (it.element, it.field, it.row.transaction_num))
+            .collect()
+    }
+
+    #[sqlx::test]
+    async fn test_validate_log(pool: PgPool) -> anyhow::Result<()> {
+        let add = Log::UserCommand(UserCommandLog {
+            command: CommandType::Add,
+            stock_symbol: None,
+            filename: None,
+            funds: None,
+        });
+        let error = Log::ErrorMessages(ErrorEventLog {
+            command: CommandType::Quote,
+            stock_symbol: Some("ABC".to_string()),
+            filename: None,
+            funds: None,
+            error_message: Some("quote server down".to_string()),
+        });
+
+        save_log_entry(&pool, entry(datetime!(2021-01-02 00:00), 1, add.clone())).await?;
+        // what report_error logs
+        save_log_entry(&pool, entry(datetime!(2021-01-03 00:00), 0, error)).await?;
+        save_log_entry(&pool, entry(datetime!(2024-01-04 00:00), 2, add)).await?;
+        sqlx::query!(
+            "
+            INSERT INTO log_entry (timestamp, server, transaction_num, username, log)
+            VALUES ('2021-01-05', 'lean', 3, 'marcus', '{\"Unknown\": {}}')
+            "
+        )
+        .execute(&pool)
+        .await?;
+
+        let violations = validate_log(pool, LogFilter::default())
+            .try_collect::<Vec<_>>()
+            .await?;
+
+        assert_eq!(
+            fields(&violations),
+            vec![
+                ("errorEvent", "transactionNum", 0),
+                ("log", "log", 3),
+                ("userCommand", "timestamp", 2),
+            ]
+        );
+        assert_eq!(
+            violations[2].message,
+            "1704326400000 is outside of unixTimeLimits [1609459200000, 1619827200000]"
+        );
+        assert_eq!(violations[0].row.username, "marcus");
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_validate_log_respects_filter(pool: PgPool) -> anyhow::Result<()> {
+        let add = Log::UserCommand(UserCommandLog {
+            command: CommandType::Add,
+            stock_symbol: None,
+            filename: None,
+            funds: None,
+        });
+        save_log_entry(&pool, entry(datetime!(2021-01-02 00:00), 0, add.clone())).await?;
+        save_log_entry(&pool, entry(datetime!(2021-01-03 00:00), 0, add)).await?;
+
+        let filter = LogFilter {
+            from: Some(datetime!(2021-01-03 00:00)),
+            ..LogFilter::default()
+        };
+        let violations = validate_log(pool, filter).try_collect::<Vec<_>>().await?;
+
+        assert_eq!(
+            fields(&violations),
+            vec![("userCommand", "transactionNum", 0)]
+        );
+        assert_eq!(violations[0].row.timestamp, datetime!(2021-01-03 00:00));
+
+        Ok(())
+    }
+}
@@ -15,6 +15,8 @@ use tracing_subscriber::prelude::*;
 use tracing_subscriber::util::SubscriberInitExt;
 use tracing_subscriber::EnvFilter;
 
+use lean::auth::Authenticator;
+use lean::dumps::DumpDir;
 use lean::proto::day_trader_server::DayTraderServer;
 use lean::proto::quote_client::QuoteClient;
 use lean::DayTraderImpl;
@@ -97,11 +99,40 @@ async fn main() -> anyhow::Result<()> {
         .await
         .map_err(|e| anyhow!("failed to migrate database: {e}"))?;
 
+    for discrepancy in lean::ledger::audit(&pool).await? {
+        warn!(
+            "balance of {} is {} but the ledger says {}",
+            discrepancy.user_id, discrepancy.balance, discrepancy.ledger_balance
+        );
+    }
+
+    let authenticator = Authenticator::from_env()?;
+
+    let dump_dir = DumpDir::from_env()?;
+
+    if let (Ok(admin_user_id), Ok(admin_password)) =
+        (env::var("ADMIN_USER_ID"), env::var("ADMIN_PASSWORD"))
+    {
+        lean::auth::upsert_admin(&pool, &admin_user_id, &admin_password)
+            .await
+            .map_err(|e| anyhow!("failed to create admin {admin_user_id}: {e}"))?;
+        info!("ensured admin {admin_user_id} exists");
+    }
+
     let quote_client_addr = env::var("QUOTE_CLIENT_ADDR")
         .map_err(|e| anyhow!("failed to get QUOTE_CLIENT_ADDR from env: {e}"))?;
 
+    // a quote server that hangs fails quotes rather than holding up every request that needs one
+    let quote_timeout = env::var("QUOTE_TIMEOUT_SECONDS")
+        .ok()
+        .map(|it| it.parse::<u64>())
+        .transpose()
+        .map_err(|e| anyhow!("failed to parse QUOTE_TIMEOUT_SECONDS: {e}"))?
+        .map_or(Duration::from_secs(5), Duration::from_secs);
+
     let channel = Channel::from_shared(quote_client_addr)
         .map_err(|e| anyhow!("failed to create channel: {e}"))?
+        .timeout(quote_timeout)
         .connect()
         .await
         .map_err(|e| anyhow!("failed to connect to quote client: {e}"))?;
@@ -135,7 +166,10 @@ async fn main() -> anyhow::Result<()> {
         //         .on_response(DefaultOnResponse::default().latency_unit(LatencyUnit::Micros))
         //         .make_span_with(DefaultMakeSpan::new().include_headers(true)),
         // )
-        .add_service(DayTraderServer::new(DayTraderImpl::new(pool, quote_client)))
+        .add_service(DayTraderServer::with_interceptor(
+            DayTraderImpl::new(pool, quote_client, authenticator.clone(), dump_dir),
+            authenticator,
+        ))
         .serve_with_shutdown(server_addr, async {
             tokio::signal::ctrl_c().await.unwrap();
             info!("server shutting down");
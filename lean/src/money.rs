@@ -0,0 +1,150 @@
+use anyhow::{anyhow, bail};
+use rust_decimal::prelude::FromPrimitive;
+use rust_decimal::{Decimal, RoundingStrategy};
+use std::str::FromStr;
+
+/// Balances, dollar amounts and prices are kept to the cent.
+pub const DOLLAR_SCALE: u32 = 2;
+
+/// Share quantities are kept to the millionth of a share.
+pub const SHARE_SCALE: u32 = 6;
+
+/**
+ * Parses a dollar amount sent by a client. Amounts finer than a cent are rejected rather than
+ * silently rounded.
+ */
+pub fn parse_dollars(value: &str) -> anyhow::Result<Decimal> {
+    parse_with_scale(value, DOLLAR_SCALE, "dollar amount")
+}
+
+/**
+ * Parses a share quantity sent by a client. Quantities finer than [SHARE_SCALE] are rejected
+ * rather than silently rounded.
+ */
+pub fn parse_shares(value: &str) -> anyhow::Result<Decimal> {
+    parse_with_scale(value, SHARE_SCALE, "share amount")
+}
+
+fn parse_with_scale(value: &str, scale: u32, what: &str) -> anyhow::Result<Decimal> {
+    let decimal = Decimal::from_str(value.trim())
+        .map_err(|e| anyhow!("invalid {what} \"{value}\": {e}"))?
+        .normalize();
+
+    if decimal.scale() > scale {
+        bail!("invalid {what} \"{value}\": at most {scale} decimal places are allowed");
+    }
+
+    Ok(decimal)
+}
+
+/**
+ * Converts a price from the quote server into cents, rounding half to even.
+ */
+pub fn dollars_from_quote(quote: f64) -> anyhow::Result<Decimal> {
+    Decimal::from_f64(quote)
+        .map(|quote| {
+            quote.round_dp_with_strategy(DOLLAR_SCALE, RoundingStrategy::MidpointNearestEven)
+        })
+        .ok_or_else(|| anyhow!("quote {quote} is not representable as a decimal"))
+}
+
+/**
+ * The number of shares `dollars` buys at `price`. Rounded down so a trader never receives a
+ * fraction of a share they did not pay for.
+ */
+pub fn shares_to_buy(dollars: Decimal, price: Decimal) -> anyhow::Result<Decimal> {
+    Ok(shares_for_dollars(dollars, price)?
+        .round_dp_with_strategy(SHARE_SCALE, RoundingStrategy::ToZero))
+}
+
+/**
+ * The number of shares that must be given up to receive `dollars` at `price`. Rounded up so a
+ * trader never receives dollars for a fraction of a share they did not give up.
+ */
+pub fn shares_to_sell(dollars: Decimal, price: Decimal) -> anyhow::Result<Decimal> {
+    Ok(shares_for_dollars(dollars, price)?
+        .round_dp_with_strategy(SHARE_SCALE, RoundingStrategy::AwayFromZero))
+}
+
+fn shares_for_dollars(dollars: Decimal, price: Decimal) -> anyhow::Result<Decimal> {
+    if !price.is_sign_positive() || price.is_zero() {
+        bail!("price must be positive but was {price}");
+    }
+
+    dollars
+        .checked_div(price)
+        .ok_or_else(|| anyhow!("overflow dividing {dollars} by {price}"))
+}
+
+/**
+ * The dollars received for `shares` at `price`, rounded down to the cent.
+ */
+pub fn dollars_for_shares(shares: Decimal, price: Decimal) -> anyhow::Result<Decimal> {
+    shares
+        .checked_mul(price)
+        .map(|dollars| dollars.round_dp_with_strategy(DOLLAR_SCALE, RoundingStrategy::ToZero))
+        .ok_or_else(|| anyhow!("overflow multiplying {shares} by {price}"))
+}
+
+/**
+ * The dollars that cover `shares` at `price`, rounded up to the cent so a reservation made with it
+ * always pays for the trade.
+ */
+pub fn dollars_to_cover(shares: Decimal, price: Decimal) -> anyhow::Result<Decimal> {
+    shares
+        .checked_mul(price)
+        .map(|dollars| dollars.round_dp_with_strategy(DOLLAR_SCALE, RoundingStrategy::AwayFromZero))
+        .ok_or_else(|| anyhow!("overflow multiplying {shares} by {price}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse_dollars() {
+        assert_eq!(parse_dollars("63511.53").unwrap(), dec!(63511.53));
+        assert_eq!(parse_dollars("100.500").unwrap(), dec!(100.5));
+        assert!(parse_dollars("0.001").is_err());
+        assert!(parse_dollars("NaN").is_err());
+    }
+
+    #[test]
+    fn test_parse_shares() {
+        assert_eq!(parse_shares("1.000001").unwrap(), dec!(1.000001));
+        assert!(parse_shares("1.0000001").is_err());
+    }
+
+    #[test]
+    fn test_dollars_from_quote() {
+        assert_eq!(dollars_from_quote(123.45).unwrap(), dec!(123.45));
+        assert_eq!(dollars_from_quote(0.125).unwrap(), dec!(0.12));
+        assert!(dollars_from_quote(f64::NAN).is_err());
+    }
+
+    #[test]
+    fn test_share_rounding() {
+        assert_eq!(shares_to_buy(dec!(100), dec!(30)).unwrap(), dec!(3.333333));
+        assert_eq!(shares_to_sell(dec!(100), dec!(30)).unwrap(), dec!(3.333334));
+        assert_eq!(
+            dollars_for_shares(dec!(3.333333), dec!(30)).unwrap(),
+            dec!(99.99)
+        );
+        assert_eq!(
+            dollars_to_cover(dec!(3.333333), dec!(30)).unwrap(),
+            dec!(100)
+        );
+        assert!(shares_to_buy(dec!(100), dec!(0)).is_err());
+    }
+
+    #[test]
+    fn test_no_drift() {
+        let mut balance = Decimal::ZERO;
+        for _ in 0..10_000 {
+            balance += dec!(0.10);
+        }
+        assert_eq!(balance, dec!(1000));
+    }
+}
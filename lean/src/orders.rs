@@ -0,0 +1,222 @@
+use crate::proto::{OrderSide, PendingOrder};
+use anyhow::anyhow;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use std::time::Duration;
+use time::PrimitiveDateTime;
+
+pub use cancel_order::cancel_order;
+pub use conditional::{list_orders, OrderType, TimeInForce};
+pub(crate) use conditional::{refund_order, take_order};
+pub use fill_order::fill_triggered_orders;
+pub use place_order::{place_order, NewOrder};
+
+mod cancel_order;
+
+mod conditional;
+
+mod fill_order;
+
+mod place_order;
+
+/**
+ * How long queued buys and sells can be committed for. Expired orders are refunded either when the
+ * user next touches them or by the [crate::reaper::Reaper], whichever comes first.
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrderExpiry {
+    pub buy: Duration,
+    pub sell: Duration,
+}
+
+impl Default for OrderExpiry {
+    fn default() -> Self {
+        Self {
+            buy: Duration::from_secs(60),
+            sell: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+impl OrderExpiry {
+    pub fn from_env() -> Self {
+        let default = Self::default();
+
+        let buy = std::env::var("QUEUED_BUY_EXPIRY_SECONDS")
+            .map(|it| {
+                it.parse::<u64>()
+                    .expect("QUEUED_BUY_EXPIRY_SECONDS must be a number")
+            })
+            .map_or(default.buy, Duration::from_secs);
+
+        let sell = std::env::var("QUEUED_SELL_EXPIRY_SECONDS")
+            .map(|it| {
+                it.parse::<u64>()
+                    .expect("QUEUED_SELL_EXPIRY_SECONDS must be a number")
+            })
+            .map_or(default.sell, Duration::from_secs);
+
+        Self { buy, sell }
+    }
+}
+
+/**
+ * Whether an order created at `time_created` is older than `expiry`.
+ */
+pub fn is_expired(time_created: PrimitiveDateTime, expiry: Duration) -> bool {
+    let now = time::OffsetDateTime::now_utc();
+    let now = PrimitiveDateTime::new(now.date(), now.time());
+    time_created + expiry < now
+}
+
+struct PendingOrderRow {
+    order_id: i64,
+    is_buy: bool,
+    stock_symbol: String,
+    quoted_price: Decimal,
+    amount_dollars: Decimal,
+    time_created: PrimitiveDateTime,
+}
+
+/**
+ * Lists the user's queued buys and sells that have not been committed or cancelled, oldest first.
+ */
+#[tracing::instrument(skip(pool))]
+pub async fn list_pending_orders(
+    pool: &PgPool,
+    user_id: &str,
+) -> anyhow::Result<Vec<PendingOrder>> {
+    let rows = sqlx::query_as!(
+        PendingOrderRow,
+        r#"
+        SELECT order_id as "order_id!", true as "is_buy!", stock_symbol as "stock_symbol!",
+               quoted_price as "quoted_price!", amount_dollars as "amount_dollars!",
+               time_created as "time_created!"
+        FROM queued_buy
+        WHERE user_id = $1
+        UNION ALL
+        SELECT order_id, false, stock_symbol, quoted_price, amount_dollars, time_created
+        FROM queued_sell
+        WHERE user_id = $1
+        ORDER BY 1
+        "#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().map(PendingOrder::from).collect())
+}
+
+/**
+ * The error for a commit or cancel that found nothing to act on. `side` is `buy` or `sell`.
+ */
+pub fn no_queued_order(side: &str, user_id: &str, order_id: Option<i64>) -> anyhow::Error {
+    match order_id {
+        Some(order_id) => anyhow!("no queued {side} {order_id} for user_id {user_id}"),
+        None => anyhow!("no queued {side} for user_id {user_id}"),
+    }
+}
+
+impl From<PendingOrderRow> for PendingOrder {
+    fn from(row: PendingOrderRow) -> Self {
+        let side = if row.is_buy {
+            OrderSide::Buy
+        } else {
+            OrderSide::Sell
+        };
+        PendingOrder {
+            order_id: row.order_id,
+            side: side.into(),
+            stock_symbol: row.stock_symbol,
+            quoted_price: row.quoted_price.to_string(),
+            amount_dollars: row.amount_dollars.to_string(),
+            created_unix_seconds: row.time_created.assume_utc().unix_timestamp(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::add::add;
+    use crate::buy::{cancel_buy, commit_buy, init_buy};
+    use crate::sell::{cancel_sell, commit_sell, init_sell};
+    use pretty_assertions::assert_eq;
+    use rust_decimal_macros::dec;
+
+    fn summary(orders: &[PendingOrder]) -> Vec<(i64, OrderSide, &str, &str)> {
+        orders
+            .iter()
+            .map(|it| {
+                (
+                    it.order_id,
+                    it.side(),
+                    it.stock_symbol.as_str(),
+                    it.amount_dollars.as_str(),
+                )
+            })
+            .collect()
+    }
+
+    #[sqlx::test]
+    async fn test_multiple_pending_orders(pool: PgPool) -> anyhow::Result<()> {
+        let _log = add(&pool, "marcus", dec!(1000)).await?;
+        let (abc, _log) = init_buy(&pool, "marcus", "ABC", dec!(10), dec!(100)).await?;
+        let (xyz, _log) = init_buy(&pool, "marcus", "XYZ", dec!(20), dec!(200)).await?;
+        let (other, _log) = init_buy(&pool, "marcus", "ABC", dec!(10), dec!(50)).await?;
+        commit_buy(&pool, "marcus", Some(abc), OrderExpiry::default().buy).await?;
+
+        let sell = init_sell(&pool, "marcus", "ABC", dec!(10), dec!(30)).await?;
+
+        assert_eq!(
+            summary(&list_pending_orders(&pool, "marcus").await?),
+            vec![
+                (xyz, OrderSide::Buy, "XYZ", "200.00"),
+                (other, OrderSide::Buy, "ABC", "50.00"),
+                (sell, OrderSide::Sell, "ABC", "30.00"),
+            ]
+        );
+        assert_eq!(list_pending_orders(&pool, "daws").await?, vec![]);
+
+        // the most recent buy
+        let _log = cancel_buy(&pool, "marcus", None, OrderExpiry::default().buy).await?;
+        let _log = commit_sell(
+            &pool,
+            "marcus".to_string(),
+            Some(sell),
+            OrderExpiry::default().sell,
+        )
+        .await?;
+
+        assert_eq!(
+            summary(&list_pending_orders(&pool, "marcus").await?),
+            vec![(xyz, OrderSide::Buy, "XYZ", "200.00")]
+        );
+
+        let balance = sqlx::query_scalar!("SELECT balance FROM trader WHERE user_id = 'marcus'")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(balance, dec!(1000) - dec!(100) - dec!(200) + dec!(30));
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_orders_of_other_users_are_not_found(pool: PgPool) -> anyhow::Result<()> {
+        let _log = add(&pool, "marcus", dec!(100)).await?;
+        let (order_id, _log) = init_buy(&pool, "marcus", "ABC", dec!(10), dec!(100)).await?;
+
+        let commit = commit_buy(&pool, "daws", Some(order_id), OrderExpiry::default().buy).await;
+        assert_eq!(
+            commit.unwrap_err().to_string(),
+            format!("no queued buy {order_id} for user_id daws")
+        );
+        let cancel = cancel_sell(&pool, "marcus".to_string(), Some(order_id)).await;
+        assert!(cancel.is_err(), "expected error but was {cancel:?}");
+
+        assert_eq!(list_pending_orders(&pool, "marcus").await?.len(), 1);
+
+        Ok(())
+    }
+}
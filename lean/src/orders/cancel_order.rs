@@ -0,0 +1,78 @@
+use crate::log::AccountTransaction;
+use crate::orders::conditional::{refund_order, take_order};
+use crate::{begin_transaction, commit_transaction};
+use anyhow::bail;
+use sqlx::PgPool;
+
+/**
+ * Cancels the user's open conditional order, returning what it reserved.
+ */
+#[tracing::instrument(skip(pool))]
+pub async fn cancel_order(
+    pool: &PgPool,
+    user_id: &str,
+    order_id: i64,
+) -> anyhow::Result<AccountTransaction> {
+    let mut transaction = begin_transaction(pool).await?;
+
+    let order = match take_order(&mut transaction, order_id).await? {
+        Some(order) if order.owner_id == user_id => order,
+        // dropping the transaction puts another user's order back
+        _ => bail!("no open order {order_id} for user_id {user_id}"),
+    };
+
+    let refunded = refund_order(&mut transaction, &order).await?;
+
+    commit_transaction(transaction).await?;
+
+    Ok(refunded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::add::add;
+    use crate::orders::{list_orders, place_order, NewOrder, OrderType, TimeInForce};
+    use crate::trigger::UpdatedPrice;
+    use pretty_assertions::assert_eq;
+    use rust_decimal_macros::dec;
+
+    #[sqlx::test]
+    async fn test_cancel_order(pool: PgPool) -> anyhow::Result<()> {
+        let _log = add(&pool, "marcus", dec!(100)).await?;
+        let order = NewOrder {
+            stock_symbol: "APPL".to_string(),
+            order_type: OrderType::LimitBuy,
+            time_in_force: TimeInForce::Gtc,
+            amount: dec!(100),
+            price: dec!(5),
+        };
+        let current = UpdatedPrice {
+            request_num: 1,
+            symbol: "APPL".to_string(),
+            price: dec!(10),
+        };
+        let (order_id, _, _log) = place_order(&pool, "marcus", order, &current).await?;
+
+        let other_user = cancel_order(&pool, "daws", order_id).await;
+        assert_eq!(
+            other_user.unwrap_err().to_string(),
+            format!("no open order {order_id} for user_id daws")
+        );
+        assert_eq!(list_orders(&pool, "marcus").await?.len(), 1);
+
+        let log = cancel_order(&pool, "marcus", order_id).await?;
+        assert_eq!(log, AccountTransaction(dec!(100)));
+        assert_eq!(list_orders(&pool, "marcus").await?, vec![]);
+
+        let balance = sqlx::query_scalar!("SELECT balance FROM trader WHERE user_id = 'marcus'")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(balance, dec!(100));
+
+        let again = cancel_order(&pool, "marcus", order_id).await;
+        assert!(again.is_err(), "expected error but was {again:?}");
+
+        Ok(())
+    }
+}
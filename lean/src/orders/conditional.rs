@@ -0,0 +1,488 @@
+use crate::ledger::Account;
+use crate::log::AccountTransaction;
+use crate::{holdings, ledger, proto};
+use anyhow::{anyhow, bail};
+use rust_decimal::Decimal;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::ops::DerefMut;
+use time::{OffsetDateTime, PrimitiveDateTime, UtcOffset};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderType {
+    /// buys with reserved cash once the price falls to the limit
+    LimitBuy,
+    /// sells reserved shares once the price rises to the limit
+    LimitSell,
+    /// sells reserved shares once the price falls to the stop
+    StopLoss,
+    /// sells reserved shares once the price falls the trail amount below its highest since placed
+    TrailingStop,
+}
+
+impl OrderType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OrderType::LimitBuy => "limit_buy",
+            OrderType::LimitSell => "limit_sell",
+            OrderType::StopLoss => "stop_loss",
+            OrderType::TrailingStop => "trailing_stop",
+        }
+    }
+
+    pub fn is_buy(self) -> bool {
+        self == OrderType::LimitBuy
+    }
+}
+
+impl TryFrom<&str> for OrderType {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "limit_buy" => Ok(OrderType::LimitBuy),
+            "limit_sell" => Ok(OrderType::LimitSell),
+            "stop_loss" => Ok(OrderType::StopLoss),
+            "trailing_stop" => Ok(OrderType::TrailingStop),
+            _ => bail!("unknown order type {value}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeInForce {
+    /// good till cancelled
+    Gtc,
+    /// cancelled at the end of the (UTC) day it was placed
+    Day,
+    /// immediate or cancel, filled at the current price or not at all
+    Ioc,
+}
+
+impl TimeInForce {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TimeInForce::Gtc => "gtc",
+            TimeInForce::Day => "day",
+            TimeInForce::Ioc => "ioc",
+        }
+    }
+
+    /**
+     * When an order placed now stops being fillable, `None` if never.
+     */
+    pub fn expires_at(self) -> Option<PrimitiveDateTime> {
+        self.expires_at_from(OffsetDateTime::now_utc())
+    }
+
+    fn expires_at_from(self, placed: OffsetDateTime) -> Option<PrimitiveDateTime> {
+        match self {
+            TimeInForce::Gtc | TimeInForce::Ioc => None,
+            TimeInForce::Day => {
+                let today = placed.to_offset(UtcOffset::UTC).date();
+                let tomorrow = today.next_day().unwrap_or(today);
+                Some(tomorrow.midnight())
+            }
+        }
+    }
+}
+
+impl TryFrom<&str> for TimeInForce {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "gtc" => Ok(TimeInForce::Gtc),
+            "day" => Ok(TimeInForce::Day),
+            "ioc" => Ok(TimeInForce::Ioc),
+            _ => bail!("unknown time in force {value}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderStatus {
+    /// waiting for the price to reach it
+    Open,
+    Filled,
+    /// an immediate or cancel order the current price did not fill
+    Cancelled,
+}
+
+/**
+ * A limit, stop loss or trailing stop order waiting in `conditional_order`.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConditionalOrder {
+    pub order_id: i64,
+    pub owner_id: String,
+    pub stock_symbol: String,
+    pub order_type: OrderType,
+    pub time_in_force: TimeInForce,
+    /// the cash reserved by a limit buy
+    pub amount_dollars: Option<Decimal>,
+    /// the shares reserved by every other order type
+    pub amount_stock: Option<Decimal>,
+    /// the limit price, or the stop price of a stop loss
+    pub limit_price: Option<Decimal>,
+    pub trail_amount: Option<Decimal>,
+    /// the highest price seen since a trailing stop was placed
+    pub high_water: Option<Decimal>,
+    pub time_created: PrimitiveDateTime,
+    pub expires_at: Option<PrimitiveDateTime>,
+}
+
+/// `conditional_order` as stored, see [ConditionalOrder]
+pub(super) struct ConditionalOrderRow {
+    pub(super) order_id: i64,
+    pub(super) owner_id: String,
+    pub(super) stock_symbol: String,
+    pub(super) order_type: String,
+    pub(super) time_in_force: String,
+    pub(super) amount_dollars: Option<Decimal>,
+    pub(super) amount_stock: Option<Decimal>,
+    pub(super) limit_price: Option<Decimal>,
+    pub(super) trail_amount: Option<Decimal>,
+    pub(super) high_water: Option<Decimal>,
+    pub(super) time_created: PrimitiveDateTime,
+    pub(super) expires_at: Option<PrimitiveDateTime>,
+}
+
+impl ConditionalOrder {
+    /**
+     * Whether the order should fill at `price`.
+     */
+    pub fn is_triggered(&self, price: Decimal) -> bool {
+        match (self.order_type, self.limit_price) {
+            (OrderType::LimitBuy, Some(limit)) => price <= limit,
+            (OrderType::LimitSell, Some(limit)) => price >= limit,
+            (OrderType::StopLoss, Some(stop)) => price <= stop,
+            (OrderType::TrailingStop, _) => match (self.high_water, self.trail_amount) {
+                (Some(high_water), Some(trail_amount)) => price <= high_water - trail_amount,
+                _ => false,
+            },
+            // rejected by [ConditionalOrder::try_from], never filled at an unknown limit
+            (_, None) => false,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        let now = OffsetDateTime::now_utc();
+        self.is_expired_at(PrimitiveDateTime::new(now.date(), now.time()))
+    }
+
+    fn is_expired_at(&self, now: PrimitiveDateTime) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+impl TryFrom<ConditionalOrderRow> for ConditionalOrder {
+    type Error = anyhow::Error;
+
+    fn try_from(row: ConditionalOrderRow) -> Result<Self, Self::Error> {
+        let order_type = OrderType::try_from(row.order_type.as_str())?;
+        match order_type {
+            OrderType::TrailingStop => {
+                if row.trail_amount.is_none() || row.high_water.is_none() {
+                    bail!(
+                        "trailing stop {} has no trail amount or high water",
+                        row.order_id
+                    );
+                }
+            }
+            OrderType::LimitBuy | OrderType::LimitSell | OrderType::StopLoss => {
+                if row.limit_price.is_none() {
+                    bail!(
+                        "{} order {} has no limit price",
+                        order_type.as_str(),
+                        row.order_id
+                    );
+                }
+            }
+        }
+
+        Ok(ConditionalOrder {
+            order_id: row.order_id,
+            owner_id: row.owner_id,
+            stock_symbol: row.stock_symbol,
+            order_type,
+            time_in_force: TimeInForce::try_from(row.time_in_force.as_str())?,
+            amount_dollars: row.amount_dollars,
+            amount_stock: row.amount_stock,
+            limit_price: row.limit_price,
+            trail_amount: row.trail_amount,
+            high_water: row.high_water,
+            time_created: row.time_created,
+            expires_at: row.expires_at,
+        })
+    }
+}
+
+/**
+ * The user's open conditional orders, oldest first.
+ */
+#[tracing::instrument(skip(pool))]
+pub async fn list_orders(pool: &PgPool, user_id: &str) -> anyhow::Result<Vec<ConditionalOrder>> {
+    sqlx::query_as!(
+        ConditionalOrderRow,
+        "SELECT * FROM conditional_order WHERE owner_id = $1 ORDER BY order_id",
+        user_id
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(ConditionalOrder::try_from)
+    .collect()
+}
+
+/**
+ * Deletes the order, returning it if it was still open.
+ */
+pub(crate) async fn take_order(
+    transaction: &mut Transaction<'static, Postgres>,
+    order_id: i64,
+) -> anyhow::Result<Option<ConditionalOrder>> {
+    sqlx::query_as!(
+        ConditionalOrderRow,
+        "DELETE FROM conditional_order WHERE order_id = $1 RETURNING *",
+        order_id
+    )
+    .fetch_optional(transaction.deref_mut())
+    .await?
+    .map(ConditionalOrder::try_from)
+    .transpose()
+}
+
+/**
+ * Returns what a taken order reserved to its owner: cash to their balance, or shares to their free
+ * holdings.
+ */
+pub(crate) async fn refund_order(
+    transaction: &mut Transaction<'static, Postgres>,
+    order: &ConditionalOrder,
+) -> anyhow::Result<AccountTransaction> {
+    match (order.amount_dollars, order.amount_stock) {
+        (Some(amount_dollars), _) => {
+            sqlx::query!(
+                "UPDATE trader SET balance = balance + $1 WHERE user_id = $2",
+                amount_dollars,
+                order.owner_id
+            )
+            .execute(transaction.deref_mut())
+            .await?;
+
+            ledger::transfer(
+                transaction,
+                &order.owner_id,
+                Account::ReservedCash,
+                Account::Cash,
+                amount_dollars,
+            )
+            .await?;
+
+            Ok(AccountTransaction(amount_dollars))
+        }
+        (None, Some(amount_stock)) => {
+            holdings::release(
+                transaction,
+                &order.owner_id,
+                &order.stock_symbol,
+                amount_stock,
+            )
+            .await?;
+
+            Ok(AccountTransaction(Decimal::ZERO))
+        }
+        (None, None) => Err(anyhow!("order {} reserves nothing", order.order_id)),
+    }
+}
+
+impl From<proto::OrderType> for OrderType {
+    fn from(value: proto::OrderType) -> Self {
+        match value {
+            proto::OrderType::LimitBuy => OrderType::LimitBuy,
+            proto::OrderType::LimitSell => OrderType::LimitSell,
+            proto::OrderType::StopLoss => OrderType::StopLoss,
+            proto::OrderType::TrailingStop => OrderType::TrailingStop,
+        }
+    }
+}
+
+impl From<OrderType> for proto::OrderType {
+    fn from(value: OrderType) -> Self {
+        match value {
+            OrderType::LimitBuy => proto::OrderType::LimitBuy,
+            OrderType::LimitSell => proto::OrderType::LimitSell,
+            OrderType::StopLoss => proto::OrderType::StopLoss,
+            OrderType::TrailingStop => proto::OrderType::TrailingStop,
+        }
+    }
+}
+
+impl From<proto::TimeInForce> for TimeInForce {
+    fn from(value: proto::TimeInForce) -> Self {
+        match value {
+            proto::TimeInForce::Gtc => TimeInForce::Gtc,
+            proto::TimeInForce::Day => TimeInForce::Day,
+            proto::TimeInForce::Ioc => TimeInForce::Ioc,
+        }
+    }
+}
+
+impl From<TimeInForce> for proto::TimeInForce {
+    fn from(value: TimeInForce) -> Self {
+        match value {
+            TimeInForce::Gtc => proto::TimeInForce::Gtc,
+            TimeInForce::Day => proto::TimeInForce::Day,
+            TimeInForce::Ioc => proto::TimeInForce::Ioc,
+        }
+    }
+}
+
+impl From<OrderStatus> for proto::OrderStatus {
+    fn from(value: OrderStatus) -> Self {
+        match value {
+            OrderStatus::Open => proto::OrderStatus::Open,
+            OrderStatus::Filled => proto::OrderStatus::Filled,
+            OrderStatus::Cancelled => proto::OrderStatus::Cancelled,
+        }
+    }
+}
+
+fn decimal_string(value: Option<Decimal>) -> String {
+    value.map(|it| it.to_string()).unwrap_or_default()
+}
+
+impl From<ConditionalOrder> for proto::ConditionalOrder {
+    fn from(order: ConditionalOrder) -> Self {
+        proto::ConditionalOrder {
+            order_id: order.order_id,
+            stock_symbol: order.stock_symbol,
+            order_type: proto::OrderType::from(order.order_type).into(),
+            time_in_force: proto::TimeInForce::from(order.time_in_force).into(),
+            amount: decimal_string(order.amount_dollars.or(order.amount_stock)),
+            price: decimal_string(order.limit_price.or(order.trail_amount)),
+            high_water: decimal_string(order.high_water),
+            created_unix_seconds: order.time_created.assume_utc().unix_timestamp(),
+            expires_unix_seconds: order
+                .expires_at
+                .map(|it| it.assume_utc().unix_timestamp())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rust_decimal_macros::dec;
+    use time::macros::datetime;
+
+    fn row(
+        order_type: OrderType,
+        limit_price: Option<Decimal>,
+        trail_amount: Option<Decimal>,
+        high_water: Option<Decimal>,
+    ) -> ConditionalOrderRow {
+        ConditionalOrderRow {
+            order_id: 1,
+            owner_id: "marcus".to_string(),
+            stock_symbol: "APPL".to_string(),
+            order_type: order_type.as_str().to_string(),
+            time_in_force: "gtc".to_string(),
+            amount_dollars: order_type.is_buy().then_some(dec!(100)),
+            amount_stock: (!order_type.is_buy()).then_some(dec!(5)),
+            limit_price,
+            trail_amount,
+            high_water,
+            time_created: datetime!(2024-02-22 10:00),
+            expires_at: None,
+        }
+    }
+
+    fn order(order_type: OrderType, limit_price: Decimal) -> ConditionalOrder {
+        ConditionalOrder::try_from(row(order_type, Some(limit_price), None, None)).unwrap()
+    }
+
+    #[test]
+    fn test_is_triggered() {
+        let limit_buy = order(OrderType::LimitBuy, dec!(10));
+        assert!(limit_buy.is_triggered(dec!(9.99)));
+        assert!(limit_buy.is_triggered(dec!(10)));
+        assert!(!limit_buy.is_triggered(dec!(10.01)));
+
+        let limit_sell = order(OrderType::LimitSell, dec!(10));
+        assert!(!limit_sell.is_triggered(dec!(9.99)));
+        assert!(limit_sell.is_triggered(dec!(10)));
+        assert!(limit_sell.is_triggered(dec!(10.01)));
+
+        let stop_loss = order(OrderType::StopLoss, dec!(10));
+        assert!(stop_loss.is_triggered(dec!(9.99)));
+        assert!(stop_loss.is_triggered(dec!(10)));
+        assert!(!stop_loss.is_triggered(dec!(10.01)));
+
+        // fills once the price falls 2 below the high water mark of 15
+        let trailing_stop = ConditionalOrder::try_from(row(
+            OrderType::TrailingStop,
+            None,
+            Some(dec!(2)),
+            Some(dec!(15)),
+        ))
+        .unwrap();
+        assert!(!trailing_stop.is_triggered(dec!(14)));
+        assert!(trailing_stop.is_triggered(dec!(13)));
+        assert!(trailing_stop.is_triggered(dec!(12)));
+    }
+
+    #[test]
+    fn test_missing_prices_are_rejected() {
+        for order_type in [
+            OrderType::LimitBuy,
+            OrderType::LimitSell,
+            OrderType::StopLoss,
+        ] {
+            let error = ConditionalOrder::try_from(row(order_type, None, None, None)).unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                format!("{} order 1 has no limit price", order_type.as_str())
+            );
+        }
+
+        let no_high_water = row(OrderType::TrailingStop, None, Some(dec!(2)), None);
+        assert!(ConditionalOrder::try_from(no_high_water).is_err());
+        let no_trail = row(OrderType::TrailingStop, None, None, Some(dec!(15)));
+        assert!(ConditionalOrder::try_from(no_trail).is_err());
+    }
+
+    #[test]
+    fn test_expires_at() {
+        let placed = datetime!(2024-02-22 10:00 UTC);
+        assert_eq!(TimeInForce::Gtc.expires_at_from(placed), None);
+        assert_eq!(TimeInForce::Ioc.expires_at_from(placed), None);
+        assert_eq!(
+            TimeInForce::Day.expires_at_from(placed),
+            Some(datetime!(2024-02-23 0:00))
+        );
+
+        // the end of the UTC day, whatever the offset it was placed at
+        assert_eq!(
+            TimeInForce::Day.expires_at_from(datetime!(2024-02-22 20:00 -8)),
+            Some(datetime!(2024-02-24 0:00))
+        );
+        assert_eq!(
+            TimeInForce::Day.expires_at_from(datetime!(2024-12-31 23:59:59 UTC)),
+            Some(datetime!(2025-01-01 0:00))
+        );
+    }
+
+    #[test]
+    fn test_is_expired() {
+        let mut order = order(OrderType::LimitBuy, dec!(10));
+        assert!(!order.is_expired_at(datetime!(2100-01-01 0:00)));
+
+        order.expires_at = Some(datetime!(2024-02-23 0:00));
+        assert!(!order.is_expired_at(datetime!(2024-02-22 23:59:59)));
+        assert!(order.is_expired_at(datetime!(2024-02-23 0:00)));
+        assert!(order.is_expired_at(datetime!(2024-02-23 0:00:01)));
+    }
+}
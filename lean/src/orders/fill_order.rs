@@ -0,0 +1,256 @@
+use crate::book::Side;
+use crate::events::{self, AccountEvent};
+use crate::orders::conditional::{take_order, ConditionalOrder, ConditionalOrderRow};
+use crate::trigger::{sell_reserved_shares, spend_reserved_cash, UpdatedPrice};
+use crate::{begin_transaction, commit_transaction};
+use anyhow::anyhow;
+use sqlx::{PgPool, Postgres, Transaction};
+use tracing::{error, info};
+
+/**
+ * Moves every trailing stop on the symbol up to a new high, then fills each open order on it that
+ * the updated price satisfies. Called by the [crate::trigger::Triggerer] for every price it sees.
+ */
+#[tracing::instrument(skip(pool))]
+pub async fn fill_triggered_orders(pool: &PgPool, next: &UpdatedPrice) -> anyhow::Result<()> {
+    sqlx::query!(
+        "
+        UPDATE conditional_order SET high_water = $1
+        WHERE stock_symbol = $2 AND high_water < $1
+        ",
+        next.price,
+        next.symbol
+    )
+    .execute(pool)
+    .await?;
+
+    let triggered = sqlx::query_as!(
+        ConditionalOrderRow,
+        "SELECT * FROM conditional_order WHERE stock_symbol = $1 ORDER BY order_id",
+        next.symbol
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(ConditionalOrder::try_from)
+    .collect::<anyhow::Result<Vec<_>>>()?
+    .into_iter()
+    .filter(|order| !order.is_expired() && order.is_triggered(next.price))
+    .collect::<Vec<_>>();
+
+    if !triggered.is_empty() {
+        info!(
+            "filling {} conditional orders for {}",
+            triggered.len(),
+            &next.symbol
+        );
+    }
+
+    for order in triggered {
+        if let Err(err) = fill_order(pool, order.order_id, next).await {
+            error!("failed to fill order {}: {err}", order.order_id);
+        }
+    }
+
+    Ok(())
+}
+
+/**
+ * Fills the order at the updated price. Returns `false` and leaves the order alone if it was
+ * cancelled, filled, or no longer satisfied by the price since it was selected.
+ */
+#[tracing::instrument(skip(pool))]
+async fn fill_order(pool: &PgPool, order_id: i64, next: &UpdatedPrice) -> anyhow::Result<bool> {
+    let mut transaction = begin_transaction(pool).await?;
+
+    let Some(order) = take_order(&mut transaction, order_id).await? else {
+        return Ok(false);
+    };
+
+    if order.is_expired() || !order.is_triggered(next.price) {
+        // dropping the transaction puts the order back
+        return Ok(false);
+    }
+
+    fill(&mut transaction, &order, next).await?;
+
+    commit_transaction(transaction).await?;
+
+    Ok(true)
+}
+
+/**
+ * Spends or sells what a taken order reserved at the updated price.
+ */
+pub(super) async fn fill(
+    transaction: &mut Transaction<'static, Postgres>,
+    order: &ConditionalOrder,
+    next: &UpdatedPrice,
+) -> anyhow::Result<()> {
+    let (side, shares, dollars) = match (order.amount_dollars, order.amount_stock) {
+        (Some(amount_dollars), _) => {
+            let shares =
+                spend_reserved_cash(transaction, &order.owner_id, next, amount_dollars).await?;
+            (Side::Buy, shares, amount_dollars)
+        }
+        (None, Some(amount_stock)) => {
+            let dollars =
+                sell_reserved_shares(transaction, &order.owner_id, next, amount_stock).await?;
+            (Side::Sell, amount_stock, dollars)
+        }
+        (None, None) => return Err(anyhow!("order {} reserves nothing", order.order_id)),
+    };
+
+    events::publish(
+        transaction,
+        &AccountEvent::fill(
+            &order.owner_id,
+            side,
+            &next.symbol,
+            Some(order.order_id),
+            shares,
+            next.price,
+            dollars,
+        ),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::add::add;
+    use crate::buy::{commit_buy, init_buy};
+    use crate::orders::conditional::OrderStatus;
+    use crate::orders::{list_orders, place_order, NewOrder, OrderExpiry, OrderType, TimeInForce};
+    use pretty_assertions::assert_eq;
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+
+    fn price(price: Decimal) -> UpdatedPrice {
+        UpdatedPrice {
+            request_num: 1,
+            symbol: "APPL".to_string(),
+            price,
+        }
+    }
+
+    async fn place(
+        pool: &PgPool,
+        order_type: OrderType,
+        amount: Decimal,
+        limit: Decimal,
+        current: Decimal,
+    ) -> anyhow::Result<i64> {
+        let order = NewOrder {
+            stock_symbol: "APPL".to_string(),
+            order_type,
+            time_in_force: TimeInForce::Gtc,
+            amount,
+            price: limit,
+        };
+        let (order_id, status, _log) = place_order(pool, "marcus", order, &price(current)).await?;
+        assert_eq!(status, OrderStatus::Open);
+        Ok(order_id)
+    }
+
+    async fn open_orders(pool: &PgPool) -> anyhow::Result<Vec<i64>> {
+        Ok(list_orders(pool, "marcus")
+            .await?
+            .into_iter()
+            .map(|it| it.order_id)
+            .collect())
+    }
+
+    async fn balance(pool: &PgPool) -> anyhow::Result<Decimal> {
+        Ok(
+            sqlx::query_scalar!("SELECT balance FROM trader WHERE user_id = 'marcus'")
+                .fetch_one(pool)
+                .await?,
+        )
+    }
+
+    async fn setup(pool: &PgPool) -> anyhow::Result<()> {
+        let _log = add(pool, "marcus", dec!(200)).await?;
+        let _log = init_buy(pool, "marcus", "APPL", dec!(10), dec!(100)).await?;
+        commit_buy(pool, "marcus", None, OrderExpiry::default().buy).await?;
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_fill_limit_orders(pool: PgPool) -> anyhow::Result<()> {
+        setup(&pool).await?;
+        let buy = place(&pool, OrderType::LimitBuy, dec!(50), dec!(8), dec!(10)).await?;
+        let sell = place(&pool, OrderType::LimitSell, dec!(4), dec!(12), dec!(10)).await?;
+
+        fill_triggered_orders(&pool, &price(dec!(11))).await?;
+        assert_eq!(open_orders(&pool).await?, vec![buy, sell]);
+
+        fill_triggered_orders(&pool, &price(dec!(12))).await?;
+        assert_eq!(open_orders(&pool).await?, vec![buy]);
+        assert_eq!(balance(&pool).await?, dec!(50) + dec!(48));
+
+        fill_triggered_orders(&pool, &price(dec!(5))).await?;
+        assert_eq!(open_orders(&pool).await?, Vec::<i64>::new());
+
+        let shares = sqlx::query_scalar!(
+            "SELECT amount FROM stock WHERE owner_id = 'marcus' AND stock_symbol = 'APPL'"
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(shares, dec!(10) - dec!(4) + dec!(10));
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_fill_stop_orders(pool: PgPool) -> anyhow::Result<()> {
+        setup(&pool).await?;
+        let stop = place(&pool, OrderType::StopLoss, dec!(2), dec!(8), dec!(10)).await?;
+        let trailing = place(&pool, OrderType::TrailingStop, dec!(3), dec!(2), dec!(10)).await?;
+
+        // the trailing stop follows the price up to 15, so sells at 13
+        fill_triggered_orders(&pool, &price(dec!(15))).await?;
+        fill_triggered_orders(&pool, &price(dec!(13.01))).await?;
+        assert_eq!(open_orders(&pool).await?, vec![stop, trailing]);
+        assert_eq!(
+            list_orders(&pool, "marcus").await?[1].high_water,
+            Some(dec!(15))
+        );
+
+        fill_triggered_orders(&pool, &price(dec!(13))).await?;
+        assert_eq!(open_orders(&pool).await?, vec![stop]);
+        assert_eq!(balance(&pool).await?, dec!(100) + dec!(39));
+
+        fill_triggered_orders(&pool, &price(dec!(7))).await?;
+        assert_eq!(open_orders(&pool).await?, Vec::<i64>::new());
+        assert_eq!(balance(&pool).await?, dec!(100) + dec!(39) + dec!(14));
+
+        let reserved = sqlx::query_scalar!(
+            "SELECT reserved FROM stock WHERE owner_id = 'marcus' AND stock_symbol = 'APPL'"
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(reserved, dec!(0));
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_expired_orders_do_not_fill(pool: PgPool) -> anyhow::Result<()> {
+        setup(&pool).await?;
+        let buy = place(&pool, OrderType::LimitBuy, dec!(50), dec!(8), dec!(10)).await?;
+
+        sqlx::query!(
+            "UPDATE conditional_order SET expires_at = now() - interval '1 minute' WHERE order_id = $1",
+            buy
+        )
+        .execute(&pool)
+        .await?;
+
+        fill_triggered_orders(&pool, &price(dec!(5))).await?;
+        assert_eq!(open_orders(&pool).await?, vec![buy]);
+
+        Ok(())
+    }
+}
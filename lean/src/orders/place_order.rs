@@ -0,0 +1,268 @@
+use crate::ledger::Account;
+use crate::log::AccountTransaction;
+use crate::orders::conditional::{
+    refund_order, take_order, ConditionalOrder, ConditionalOrderRow, OrderStatus, OrderType,
+    TimeInForce,
+};
+use crate::orders::fill_order::fill;
+use crate::trigger::UpdatedPrice;
+use crate::{begin_transaction, commit_transaction, holdings, ledger};
+use anyhow::bail;
+use rust_decimal::Decimal;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::ops::DerefMut;
+
+/**
+ * A conditional order as requested by a user.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewOrder {
+    pub stock_symbol: String,
+    pub order_type: OrderType,
+    pub time_in_force: TimeInForce,
+    /// dollars to spend for a limit buy, shares to sell for every other type
+    pub amount: Decimal,
+    /// the limit price, the stop price of a stop loss, or the trail amount of a trailing stop
+    pub price: Decimal,
+}
+
+/**
+ * Reserves what the order needs and places it. An order the current price already satisfies fills
+ * straight away, and an immediate or cancel order it does not is refunded rather than placed.
+ */
+#[tracing::instrument(skip(pool))]
+pub async fn place_order(
+    pool: &PgPool,
+    user_id: &str,
+    order: NewOrder,
+    current: &UpdatedPrice,
+) -> anyhow::Result<(i64, OrderStatus, AccountTransaction)> {
+    if order.amount <= Decimal::ZERO {
+        bail!("order amount must be positive, was {}", order.amount);
+    }
+    if order.price <= Decimal::ZERO {
+        bail!("order price must be positive, was {}", order.price);
+    }
+
+    let mut transaction = begin_transaction(pool).await?;
+
+    let reserved = reserve(&mut transaction, user_id, &order).await?;
+
+    let placed = insert_order(&mut transaction, user_id, &order, current.price).await?;
+
+    let (status, refunded) = if placed.is_triggered(current.price) {
+        take_order(&mut transaction, placed.order_id).await?;
+        fill(&mut transaction, &placed, current).await?;
+        (OrderStatus::Filled, AccountTransaction(Decimal::ZERO))
+    } else if placed.time_in_force == TimeInForce::Ioc {
+        take_order(&mut transaction, placed.order_id).await?;
+        let refunded = refund_order(&mut transaction, &placed).await?;
+        (OrderStatus::Cancelled, refunded)
+    } else {
+        (OrderStatus::Open, AccountTransaction(Decimal::ZERO))
+    };
+
+    commit_transaction(transaction).await?;
+
+    Ok((placed.order_id, status, reserved + refunded))
+}
+
+#[tracing::instrument(skip(transaction))]
+async fn reserve(
+    transaction: &mut Transaction<'static, Postgres>,
+    user_id: &str,
+    order: &NewOrder,
+) -> anyhow::Result<AccountTransaction> {
+    if !order.order_type.is_buy() {
+        holdings::reserve(transaction, user_id, &order.stock_symbol, order.amount).await?;
+        return Ok(AccountTransaction(Decimal::ZERO));
+    }
+
+    let result = sqlx::query!(
+        "UPDATE trader SET balance = balance - $1 WHERE user_id = $2 AND balance >= $1",
+        order.amount,
+        user_id,
+    )
+    .execute(transaction.deref_mut())
+    .await?;
+
+    if result.rows_affected() == 0 {
+        bail!("Insufficient funds");
+    }
+
+    ledger::transfer(
+        transaction,
+        user_id,
+        Account::Cash,
+        Account::ReservedCash,
+        order.amount,
+    )
+    .await?;
+
+    Ok(AccountTransaction(-order.amount))
+}
+
+#[tracing::instrument(skip(transaction))]
+async fn insert_order(
+    transaction: &mut Transaction<'static, Postgres>,
+    user_id: &str,
+    order: &NewOrder,
+    current_price: Decimal,
+) -> anyhow::Result<ConditionalOrder> {
+    let is_buy = order.order_type.is_buy();
+    let is_trailing = order.order_type == OrderType::TrailingStop;
+
+    let row = sqlx::query_as!(
+        ConditionalOrderRow,
+        "
+        INSERT INTO conditional_order (owner_id, stock_symbol, order_type, time_in_force,
+                                       amount_dollars, amount_stock, limit_price, trail_amount,
+                                       high_water, expires_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        RETURNING *
+        ",
+        user_id,
+        order.stock_symbol,
+        order.order_type.as_str(),
+        order.time_in_force.as_str(),
+        is_buy.then_some(order.amount),
+        (!is_buy).then_some(order.amount),
+        (!is_trailing).then_some(order.price),
+        is_trailing.then_some(order.price),
+        is_trailing.then_some(current_price),
+        order.time_in_force.expires_at(),
+    )
+    .fetch_one(transaction.deref_mut())
+    .await?;
+
+    ConditionalOrder::try_from(row)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::add::add;
+    use crate::buy::{commit_buy, init_buy};
+    use crate::orders::{list_orders, OrderExpiry};
+    use pretty_assertions::assert_eq;
+    use rust_decimal_macros::dec;
+
+    fn price(price: Decimal) -> UpdatedPrice {
+        UpdatedPrice {
+            request_num: 1,
+            symbol: "APPL".to_string(),
+            price,
+        }
+    }
+
+    fn order(order_type: OrderType, time_in_force: TimeInForce, amount: Decimal) -> NewOrder {
+        NewOrder {
+            stock_symbol: "APPL".to_string(),
+            order_type,
+            time_in_force,
+            amount,
+            price: dec!(10),
+        }
+    }
+
+    async fn balance(pool: &PgPool) -> anyhow::Result<Decimal> {
+        Ok(
+            sqlx::query_scalar!("SELECT balance FROM trader WHERE user_id = 'marcus'")
+                .fetch_one(pool)
+                .await?,
+        )
+    }
+
+    #[sqlx::test]
+    async fn test_place_limit_buy(pool: PgPool) -> anyhow::Result<()> {
+        let _log = add(&pool, "marcus", dec!(100)).await?;
+
+        let limit_buy = order(OrderType::LimitBuy, TimeInForce::Gtc, dec!(60));
+        let (order_id, status, log) =
+            place_order(&pool, "marcus", limit_buy, &price(dec!(12))).await?;
+
+        assert_eq!(status, OrderStatus::Open);
+        assert_eq!(log, AccountTransaction(dec!(-60)));
+        assert_eq!(balance(&pool).await?, dec!(40));
+
+        let orders = list_orders(&pool, "marcus").await?;
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].order_id, order_id);
+        assert_eq!(orders[0].amount_dollars, Some(dec!(60)));
+        assert_eq!(orders[0].limit_price, Some(dec!(10)));
+        assert_eq!(orders[0].expires_at, None);
+
+        // more than one per symbol
+        let limit_buy = order(OrderType::LimitBuy, TimeInForce::Day, dec!(40));
+        let (_, status, _log) = place_order(&pool, "marcus", limit_buy, &price(dec!(12))).await?;
+        assert_eq!(status, OrderStatus::Open);
+        assert_eq!(list_orders(&pool, "marcus").await?.len(), 2);
+
+        let limit_buy = order(OrderType::LimitBuy, TimeInForce::Gtc, dec!(1));
+        let broke = place_order(&pool, "marcus", limit_buy, &price(dec!(12))).await;
+        assert!(broke.is_err(), "expected error but was {broke:?}");
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_place_marketable_order_fills(pool: PgPool) -> anyhow::Result<()> {
+        let _log = add(&pool, "marcus", dec!(100)).await?;
+
+        let limit_buy = order(OrderType::LimitBuy, TimeInForce::Gtc, dec!(50));
+        let (_, status, _log) = place_order(&pool, "marcus", limit_buy, &price(dec!(5))).await?;
+
+        assert_eq!(status, OrderStatus::Filled);
+        assert_eq!(list_orders(&pool, "marcus").await?, vec![]);
+        let shares = sqlx::query_scalar!(
+            "SELECT amount FROM stock WHERE owner_id = 'marcus' AND stock_symbol = 'APPL'"
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(shares, dec!(10));
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_place_immediate_or_cancel(pool: PgPool) -> anyhow::Result<()> {
+        let _log = add(&pool, "marcus", dec!(100)).await?;
+        let _log = init_buy(&pool, "marcus", "APPL", dec!(10), dec!(50)).await?;
+        commit_buy(&pool, "marcus", None, OrderExpiry::default().buy).await?;
+
+        let limit_buy = order(OrderType::LimitBuy, TimeInForce::Ioc, dec!(50));
+        let (_, status, log) = place_order(&pool, "marcus", limit_buy, &price(dec!(11))).await?;
+        assert_eq!(status, OrderStatus::Cancelled);
+        assert_eq!(log, AccountTransaction(dec!(0)));
+        assert_eq!(balance(&pool).await?, dec!(50));
+
+        let stop_loss = order(OrderType::StopLoss, TimeInForce::Ioc, dec!(2));
+        let (_, status, _log) = place_order(&pool, "marcus", stop_loss, &price(dec!(9))).await?;
+        assert_eq!(status, OrderStatus::Filled);
+        assert_eq!(balance(&pool).await?, dec!(68));
+
+        assert_eq!(list_orders(&pool, "marcus").await?, vec![]);
+        let (amount, reserved) = sqlx::query!(
+            "SELECT amount, reserved FROM stock WHERE owner_id = 'marcus' AND stock_symbol = 'APPL'"
+        )
+        .fetch_one(&pool)
+        .await
+        .map(|it| (it.amount, it.reserved))?;
+        assert_eq!((amount, reserved), (dec!(3), dec!(0)));
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_place_sell_without_shares(pool: PgPool) -> anyhow::Result<()> {
+        let _log = add(&pool, "marcus", dec!(100)).await?;
+
+        let limit_sell = order(OrderType::LimitSell, TimeInForce::Gtc, dec!(1));
+        let place = place_order(&pool, "marcus", limit_sell, &price(dec!(5))).await;
+
+        assert!(place.is_err(), "expected error but was {place:?}");
+        assert_eq!(list_orders(&pool, "marcus").await?, vec![]);
+
+        Ok(())
+    }
+}
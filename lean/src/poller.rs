@@ -0,0 +1,192 @@
+use crate::CachedQuote;
+use futures::future::join_all;
+use sqlx::PgPool;
+use std::time::Duration;
+use tokio::time::MissedTickBehavior;
+use tracing::{error, info};
+
+/// the user id quotes fetched by the poller are requested and logged under
+const POLLER_USER_ID: &str = "price-poller";
+
+/**
+ * Periodically refreshes the price of every symbol with an active buy or sell trigger or an open
+ * conditional order. Refreshed prices reach the [crate::trigger::Triggerer] the same way a user's
+ * cache miss does, so triggers and orders fire on market movement rather than on incidental user
+ * traffic.
+ */
+pub struct PricePoller {
+    pool: PgPool,
+    quote: CachedQuote,
+    interval: Duration,
+    batch_size: usize,
+}
+
+impl PricePoller {
+    pub fn new(pool: PgPool, quote: CachedQuote) -> Self {
+        let interval = std::env::var("PRICE_POLL_INTERVAL_SECONDS")
+            .unwrap_or_else(|_| "10".to_string())
+            .parse::<u64>()
+            .expect("PRICE_POLL_INTERVAL_SECONDS must be a number");
+
+        let batch_size = std::env::var("PRICE_POLL_BATCH_SIZE")
+            .unwrap_or_else(|_| "50".to_string())
+            .parse::<usize>()
+            .expect("PRICE_POLL_BATCH_SIZE must be a number");
+
+        Self {
+            pool,
+            quote,
+            interval: Duration::from_secs(interval),
+            batch_size: batch_size.max(1),
+        }
+    }
+
+    pub async fn run(self) -> anyhow::Result<()> {
+        if self.interval.is_zero() {
+            info!("price polling disabled");
+            return Ok(());
+        }
+
+        let mut interval = tokio::time::interval(self.interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        // the poll number doubles as the transaction number of the quotes it requests
+        let mut poll_num: i32 = 0;
+
+        loop {
+            interval.tick().await;
+            poll_num = poll_num.checked_add(1).unwrap_or(1);
+
+            if let Err(err) = self.poll(poll_num).await {
+                error!("failed to poll prices: {err}");
+            }
+        }
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn poll(&self, poll_num: i32) -> anyhow::Result<()> {
+        let symbols = active_trigger_symbols(&self.pool).await?;
+
+        if symbols.is_empty() {
+            return Ok(());
+        }
+
+        info!("polling prices for {} symbols", symbols.len());
+
+        for batch in symbols.chunks(self.batch_size) {
+            join_all(batch.iter().map(|symbol| {
+                self.quote
+                    .refresh(poll_num, POLLER_USER_ID.to_string(), symbol.clone())
+            }))
+            .await;
+        }
+
+        Ok(())
+    }
+}
+
+/**
+ * Every symbol with a buy or sell trigger that has a trigger price set, or with an open conditional
+ * order.
+ */
+#[tracing::instrument(skip_all)]
+async fn active_trigger_symbols(pool: &PgPool) -> anyhow::Result<Vec<String>> {
+    Ok(sqlx::query_scalar!(
+        r#"
+        SELECT stock_symbol as "stock_symbol!" FROM buy_trigger WHERE trigger_price IS NOT NULL
+        UNION
+        SELECT stock_symbol as "stock_symbol!" FROM sell_trigger WHERE trigger_price IS NOT NULL
+        UNION
+        SELECT stock_symbol as "stock_symbol!" FROM conditional_order
+        ORDER BY 1
+        "#
+    )
+    .fetch_all(pool)
+    .await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::add::add;
+    use crate::buy::{commit_buy, init_buy};
+    use crate::fake_quote::cached_quote;
+    use crate::orders::{
+        fill_triggered_orders, list_orders, place_order, NewOrder, OrderExpiry, OrderType,
+        TimeInForce,
+    };
+    use crate::trigger::UpdatedPrice;
+    use crate::trigger::{set_buy_amount, set_buy_trigger, set_sell_amount, set_sell_trigger};
+    use pretty_assertions::assert_eq;
+    use rust_decimal_macros::dec;
+
+    #[sqlx::test]
+    async fn test_active_trigger_symbols(pool: PgPool) -> anyhow::Result<()> {
+        let _log = add(&pool, "marcus", dec!(1000)).await?;
+        let _log = set_buy_amount(&pool, "marcus", "GOOG", dec!(100)).await?;
+        set_buy_trigger(&pool, "marcus", "GOOG", dec!(10)).await?;
+        let _log = set_buy_amount(&pool, "marcus", "MSFT", dec!(100)).await?;
+        let _log = init_buy(&pool, "marcus", "APPL", dec!(10), dec!(100)).await?;
+        commit_buy(&pool, "marcus", None, OrderExpiry::default().buy).await?;
+        set_sell_amount(&pool, "marcus", "APPL", dec!(5)).await?;
+        set_sell_trigger(&pool, "marcus", "APPL", dec!(20)).await?;
+        let _log = set_buy_amount(&pool, "marcus", "APPL", dec!(100)).await?;
+        set_buy_trigger(&pool, "marcus", "APPL", dec!(5)).await?;
+
+        // MSFT has no trigger price yet so is not polled, APPL is only polled once
+        assert_eq!(
+            active_trigger_symbols(&pool).await?,
+            vec!["APPL".to_string(), "GOOG".to_string()]
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_conditional_order_symbols_are_polled(pool: PgPool) -> anyhow::Result<()> {
+        let _log = add(&pool, "marcus", dec!(1000)).await?;
+        let order = NewOrder {
+            stock_symbol: "TSLA".to_string(),
+            order_type: OrderType::LimitBuy,
+            time_in_force: TimeInForce::Gtc,
+            amount: dec!(80),
+            price: dec!(8),
+        };
+        let current = UpdatedPrice {
+            request_num: 1,
+            symbol: "TSLA".to_string(),
+            price: dec!(10),
+        };
+        let (_, _, _log) = place_order(&pool, "marcus", order, &current).await?;
+
+        assert_eq!(
+            active_trigger_symbols(&pool).await?,
+            vec!["TSLA".to_string()]
+        );
+
+        let (quote, mut updates) = cached_quote(pool.clone(), Some(7.5), None).await;
+        let poller = PricePoller {
+            pool: pool.clone(),
+            quote,
+            interval: Duration::from_secs(10),
+            batch_size: 50,
+        };
+        poller.poll(1).await?;
+
+        // what the triggerer does with the polled price
+        let polled = updates.recv().await.unwrap();
+        assert_eq!(polled.symbol, "TSLA");
+        assert_eq!(polled.price, dec!(7.50));
+        fill_triggered_orders(&pool, &polled).await?;
+
+        assert!(list_orders(&pool, "marcus").await?.is_empty());
+        let shares = sqlx::query_scalar!(
+            "SELECT amount FROM stock WHERE owner_id = 'marcus' AND stock_symbol = 'TSLA'"
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert!(shares > dec!(0), "{shares}");
+
+        Ok(())
+    }
+}
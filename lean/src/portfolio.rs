@@ -0,0 +1,224 @@
+use crate::money::dollars_for_shares;
+use crate::proto;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+
+/**
+ * A user's position in one stock, including one they have since sold out of.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Holding {
+    pub stock_symbol: String,
+    /// reserved shares included
+    pub quantity: Decimal,
+    /// what the shares held cost in total, by average cost
+    pub cost_basis: Decimal,
+    pub realized_pnl: Decimal,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValuedHolding {
+    pub holding: Holding,
+    pub average_cost: Decimal,
+    /// `None` when the stock has no cached price, as are the values derived from it
+    pub price: Option<Decimal>,
+    pub market_value: Option<Decimal>,
+    pub unrealized_pnl: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Portfolio {
+    pub holdings: Vec<ValuedHolding>,
+    /// of the holdings with a price only
+    pub market_value: Decimal,
+    pub cost_basis: Decimal,
+    /// of the holdings with a price only
+    pub unrealized_pnl: Decimal,
+    pub realized_pnl: Decimal,
+}
+
+/**
+ * Every stock the user holds or has realized a profit or loss on.
+ */
+#[tracing::instrument(skip(pool))]
+pub async fn holdings(pool: &PgPool, user_id: &str) -> anyhow::Result<Vec<Holding>> {
+    Ok(sqlx::query_as!(
+        Holding,
+        "
+        SELECT stock_symbol, amount AS quantity, cost_basis, realized_pnl FROM stock
+        WHERE owner_id = $1 AND (amount > 0 OR realized_pnl <> 0)
+        ORDER BY stock_symbol
+        ",
+        user_id
+    )
+    .fetch_all(pool)
+    .await?)
+}
+
+impl Holding {
+    pub fn value(self, price: Option<Decimal>) -> anyhow::Result<ValuedHolding> {
+        let average_cost = if self.quantity.is_zero() {
+            Decimal::ZERO
+        } else {
+            (self.cost_basis / self.quantity).round_dp(2)
+        };
+        let market_value = price
+            .map(|price| dollars_for_shares(self.quantity, price))
+            .transpose()?;
+
+        Ok(ValuedHolding {
+            average_cost,
+            price,
+            market_value,
+            unrealized_pnl: market_value.map(|it| it - self.cost_basis),
+            holding: self,
+        })
+    }
+}
+
+impl FromIterator<ValuedHolding> for Portfolio {
+    fn from_iter<T: IntoIterator<Item = ValuedHolding>>(iter: T) -> Self {
+        iter.into_iter()
+            .fold(Portfolio::default(), |mut portfolio, valued| {
+                portfolio.market_value += valued.market_value.unwrap_or_default();
+                portfolio.cost_basis += valued.holding.cost_basis;
+                portfolio.unrealized_pnl += valued.unrealized_pnl.unwrap_or_default();
+                portfolio.realized_pnl += valued.holding.realized_pnl;
+                portfolio.holdings.push(valued);
+                portfolio
+            })
+    }
+}
+
+impl From<ValuedHolding> for proto::PortfolioHolding {
+    fn from(valued: ValuedHolding) -> Self {
+        let text = |it: Option<Decimal>| it.map(|it| it.to_string()).unwrap_or_default();
+        proto::PortfolioHolding {
+            stock_symbol: valued.holding.stock_symbol,
+            quantity: valued.holding.quantity.to_string(),
+            average_cost: valued.average_cost.to_string(),
+            cost_basis: valued.holding.cost_basis.to_string(),
+            price: text(valued.price),
+            market_value: text(valued.market_value),
+            unrealized_pnl: text(valued.unrealized_pnl),
+            realized_pnl: valued.holding.realized_pnl.to_string(),
+        }
+    }
+}
+
+impl From<Portfolio> for proto::GetPortfolioResponse {
+    fn from(portfolio: Portfolio) -> Self {
+        proto::GetPortfolioResponse {
+            holdings: portfolio
+                .holdings
+                .into_iter()
+                .map(proto::PortfolioHolding::from)
+                .collect(),
+            market_value: portfolio.market_value.to_string(),
+            cost_basis: portfolio.cost_basis.to_string(),
+            unrealized_pnl: portfolio.unrealized_pnl.to_string(),
+            realized_pnl: portfolio.realized_pnl.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::add::add;
+    use crate::buy::{commit_buy, init_buy};
+    use crate::orders::OrderExpiry;
+    use crate::sell::{commit_sell, init_sell};
+    use pretty_assertions::assert_eq;
+    use rust_decimal_macros::dec;
+
+    async fn buy(pool: &PgPool, price: Decimal, dollars: Decimal) -> anyhow::Result<()> {
+        let _log = init_buy(pool, "marcus", "AAPL", price, dollars).await?;
+        commit_buy(pool, "marcus", None, OrderExpiry::default().buy).await
+    }
+
+    async fn sell(pool: &PgPool, price: Decimal, dollars: Decimal) -> anyhow::Result<()> {
+        init_sell(pool, "marcus", "AAPL", price, dollars).await?;
+        let _log = commit_sell(
+            pool,
+            "marcus".to_string(),
+            None,
+            OrderExpiry::default().sell,
+        )
+        .await?;
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_average_cost_and_realized_pnl(pool: PgPool) -> anyhow::Result<()> {
+        let _log = add(&pool, "marcus", dec!(1000)).await?;
+        buy(&pool, dec!(10), dec!(100)).await?;
+        buy(&pool, dec!(20), dec!(100)).await?;
+
+        // 15 shares for $200
+        assert_eq!(
+            holdings(&pool, "marcus").await?,
+            vec![Holding {
+                stock_symbol: "AAPL".to_string(),
+                quantity: dec!(15),
+                cost_basis: dec!(200),
+                realized_pnl: dec!(0),
+            }]
+        );
+
+        // 5 shares at $30 that cost $66.67 on average
+        sell(&pool, dec!(30), dec!(150)).await?;
+        let portfolio = holdings(&pool, "marcus")
+            .await?
+            .into_iter()
+            .map(|it| it.value(Some(dec!(12))))
+            .collect::<anyhow::Result<Portfolio>>()?;
+
+        let aapl = &portfolio.holdings[0];
+        assert_eq!(aapl.holding.quantity, dec!(10));
+        assert_eq!(aapl.holding.cost_basis, dec!(133.33));
+        assert_eq!(aapl.holding.realized_pnl, dec!(83.33));
+        assert_eq!(aapl.average_cost, dec!(13.33));
+        assert_eq!(aapl.market_value, Some(dec!(120)));
+        assert_eq!(aapl.unrealized_pnl, Some(dec!(-13.33)));
+
+        // selling out removes the rest of the cost basis but keeps the realized profit
+        sell(&pool, dec!(12), dec!(120)).await?;
+        let sold_out = holdings(&pool, "marcus").await?;
+        assert_eq!(
+            sold_out,
+            vec![Holding {
+                stock_symbol: "AAPL".to_string(),
+                quantity: dec!(0),
+                cost_basis: dec!(0),
+                realized_pnl: dec!(70),
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unpriced_holdings_left_out_of_totals() {
+        let holding = |symbol: &str, price: Option<Decimal>| {
+            Holding {
+                stock_symbol: symbol.to_string(),
+                quantity: dec!(2),
+                cost_basis: dec!(10),
+                realized_pnl: dec!(1),
+            }
+            .value(price)
+            .unwrap()
+        };
+
+        let portfolio = [holding("AAPL", Some(dec!(7))), holding("XYZ", None)]
+            .into_iter()
+            .collect::<Portfolio>();
+
+        assert_eq!(portfolio.holdings[1].market_value, None);
+        assert_eq!(portfolio.market_value, dec!(14));
+        assert_eq!(portfolio.cost_basis, dec!(20));
+        assert_eq!(portfolio.unrealized_pnl, dec!(4));
+        assert_eq!(portfolio.realized_pnl, dec!(2));
+    }
+}
@@ -0,0 +1,118 @@
+use crate::proto;
+use crate::trigger::UpdatedPrice;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use std::collections::HashSet;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::{Receiver, Sender};
+use tracing::warn;
+
+/**
+ * Creates the channel every new price is broadcast on for [price_updates] to subscribe to.
+ */
+pub fn price_channel() -> Sender<UpdatedPrice> {
+    let price_channel_size = std::env::var("PRICE_CHANNEL_SIZE")
+        .unwrap_or_else(|_| "1000".to_string())
+        .parse::<usize>()
+        .expect("PRICE_CHANNEL_SIZE must be a number");
+
+    let (sender, _) = tokio::sync::broadcast::channel(price_channel_size);
+    sender
+}
+
+/**
+ * Every price broadcast after `receiver` subscribed, for the given symbols only unless `symbols`
+ * is empty. A subscriber that falls more than the channel size behind skips the prices it missed
+ * rather than holding up the others.
+ */
+pub fn price_updates(
+    receiver: Receiver<UpdatedPrice>,
+    symbols: HashSet<String>,
+) -> BoxStream<'static, UpdatedPrice> {
+    receiver_stream(receiver, "price")
+        .filter(move |price| {
+            let wanted = symbols.is_empty() || symbols.contains(&price.symbol);
+            futures::future::ready(wanted)
+        })
+        .boxed()
+}
+
+/**
+ * Everything broadcast after `receiver` subscribed, skipping whatever it lagged behind on.
+ */
+pub(crate) fn receiver_stream<T: Clone + Send + 'static>(
+    receiver: Receiver<T>,
+    what: &'static str,
+) -> BoxStream<'static, T> {
+    futures::stream::unfold(receiver, move |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(next) => return Some((next, receiver)),
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("{what} subscriber fell behind and skipped {skipped} messages")
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+    .boxed()
+}
+
+impl From<UpdatedPrice> for proto::PriceUpdate {
+    fn from(price: UpdatedPrice) -> Self {
+        proto::PriceUpdate {
+            stock_symbol: price.symbol,
+            price: price.price.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+
+    fn price(symbol: &str, price: Decimal) -> UpdatedPrice {
+        UpdatedPrice {
+            request_num: 1,
+            symbol: symbol.to_string(),
+            price,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_price_updates_filter_and_lag() {
+        let (sender, _) = tokio::sync::broadcast::channel(2);
+
+        let every = price_updates(sender.subscribe(), HashSet::new());
+        let only_abc = price_updates(sender.subscribe(), HashSet::from(["ABC".to_string()]));
+
+        for next in [
+            price("ABC", dec!(1)),
+            price("XYZ", dec!(2)),
+            price("ABC", dec!(3)),
+        ] {
+            sender.send(next).unwrap();
+        }
+        drop(sender);
+
+        let summary = |prices: Vec<UpdatedPrice>| {
+            prices
+                .into_iter()
+                .map(|it| (it.symbol, it.price))
+                .collect::<Vec<_>>()
+        };
+
+        // the channel only holds two, so the first price is skipped
+        assert_eq!(
+            summary(every.collect().await),
+            vec![("XYZ".to_string(), dec!(2)), ("ABC".to_string(), dec!(3))]
+        );
+        assert_eq!(
+            summary(only_abc.collect().await),
+            vec![("ABC".to_string(), dec!(3))]
+        );
+    }
+}
@@ -0,0 +1,603 @@
+use crate::book::Side;
+use crate::events::{self, AccountEvent};
+use crate::ledger::Account;
+use crate::log::{
+    save_log_entry, AccountTransaction, AccountTransactionLog, CommandType, Log, LogEntry,
+    SystemEventLog,
+};
+use crate::money::shares_to_sell;
+use crate::orders::{refund_order, take_order, OrderExpiry};
+use crate::{begin_transaction, commit_transaction, holdings, ledger, TransactionType};
+use rust_decimal::Decimal;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::ops::DerefMut;
+use std::time::Duration;
+use time::{OffsetDateTime, PrimitiveDateTime};
+use tokio::time::MissedTickBehavior;
+use tracing::{error, info};
+
+/**
+ * Periodically refunds and deletes queued buys and sells older than their [OrderExpiry], and
+ * conditional orders past their time in force, so cash and shares reserved by users who never
+ * commit or cancel are not locked up indefinitely.
+ */
+pub struct Reaper {
+    pool: PgPool,
+    expiry: OrderExpiry,
+    interval: Duration,
+}
+
+/// A queued buy or sell removed by the reaper.
+#[derive(Debug, PartialEq)]
+struct ExpiredOrder {
+    order_id: i64,
+    user_id: String,
+    stock_symbol: String,
+    quoted_price: Decimal,
+    amount_dollars: Decimal,
+}
+
+impl Reaper {
+    pub fn new(pool: PgPool, expiry: OrderExpiry) -> Self {
+        let interval = std::env::var("REAPER_INTERVAL_SECONDS")
+            .unwrap_or_else(|_| "10".to_string())
+            .parse::<u64>()
+            .expect("REAPER_INTERVAL_SECONDS must be a number");
+
+        Self {
+            pool,
+            expiry,
+            interval: Duration::from_secs(interval),
+        }
+    }
+
+    pub async fn run(self) -> anyhow::Result<()> {
+        if self.interval.is_zero() {
+            info!("reaping expired orders disabled");
+            return Ok(());
+        }
+
+        let mut interval = tokio::time::interval(self.interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        // the reap number doubles as the transaction number of the logs it writes
+        let mut reap_num: i32 = 0;
+
+        loop {
+            interval.tick().await;
+            reap_num = reap_num.checked_add(1).unwrap_or(1);
+
+            if let Err(err) = reap(&self.pool, self.expiry, reap_num).await {
+                error!("failed to reap expired orders: {err}");
+            }
+        }
+    }
+}
+
+/**
+ * Refunds every queued order that has expired, each in its own transaction so one bad order does
+ * not hold up the rest. Returns the number of orders reaped.
+ */
+#[tracing::instrument(skip(pool))]
+async fn reap(pool: &PgPool, expiry: OrderExpiry, reap_num: i32) -> anyhow::Result<usize> {
+    let mut reaped = 0;
+
+    let buys = sqlx::query_scalar!(
+        "SELECT order_id FROM queued_buy WHERE time_created < $1 ORDER BY order_id",
+        cutoff(expiry.buy)
+    )
+    .fetch_all(pool)
+    .await?;
+    for order_id in buys {
+        match reap_buy(pool, order_id, cutoff(expiry.buy), reap_num).await {
+            Ok(true) => reaped += 1,
+            Ok(false) => {}
+            Err(err) => error!("failed to reap queued buy {order_id}: {err}"),
+        }
+    }
+
+    let sells = sqlx::query_scalar!(
+        "SELECT order_id FROM queued_sell WHERE time_created < $1 ORDER BY order_id",
+        cutoff(expiry.sell)
+    )
+    .fetch_all(pool)
+    .await?;
+    for order_id in sells {
+        match reap_sell(pool, order_id, cutoff(expiry.sell), reap_num).await {
+            Ok(true) => reaped += 1,
+            Ok(false) => {}
+            Err(err) => error!("failed to reap queued sell {order_id}: {err}"),
+        }
+    }
+
+    let orders = sqlx::query_scalar!(
+        "SELECT order_id FROM conditional_order WHERE expires_at <= $1 ORDER BY order_id",
+        cutoff(Duration::ZERO)
+    )
+    .fetch_all(pool)
+    .await?;
+    for order_id in orders {
+        match reap_order(pool, order_id, reap_num).await {
+            Ok(true) => reaped += 1,
+            Ok(false) => {}
+            Err(err) => error!("failed to reap conditional order {order_id}: {err}"),
+        }
+    }
+
+    if reaped > 0 {
+        info!("reaped {reaped} expired orders");
+    }
+
+    Ok(reaped)
+}
+
+/// orders created before this have expired
+fn cutoff(expiry: Duration) -> PrimitiveDateTime {
+    let now = OffsetDateTime::now_utc();
+    PrimitiveDateTime::new(now.date(), now.time()) - expiry
+}
+
+/**
+ * Returns the buy's reserved cash to the user. `false` if it was committed or cancelled first.
+ */
+#[tracing::instrument(skip(pool))]
+async fn reap_buy(
+    pool: &PgPool,
+    order_id: i64,
+    cutoff: PrimitiveDateTime,
+    reap_num: i32,
+) -> anyhow::Result<bool> {
+    let mut transaction = begin_transaction(pool).await?;
+
+    let Some(buy) = sqlx::query_as!(
+        ExpiredOrder,
+        "
+        DELETE FROM queued_buy WHERE order_id = $1 AND time_created < $2
+        RETURNING order_id, user_id, stock_symbol, quoted_price, amount_dollars
+        ",
+        order_id,
+        cutoff
+    )
+    .fetch_optional(transaction.deref_mut())
+    .await?
+    else {
+        return Ok(false);
+    };
+
+    sqlx::query!(
+        "UPDATE trader SET balance = balance + $1 WHERE user_id = $2",
+        buy.amount_dollars,
+        buy.user_id
+    )
+    .execute(transaction.deref_mut())
+    .await?;
+
+    ledger::transfer(
+        &mut transaction,
+        &buy.user_id,
+        Account::ReservedCash,
+        Account::Cash,
+        buy.amount_dollars,
+    )
+    .await?;
+
+    events::publish(
+        &mut transaction,
+        &AccountEvent::expired(
+            &buy.user_id,
+            Side::Buy,
+            &buy.stock_symbol,
+            Some(buy.order_id),
+        ),
+    )
+    .await?;
+
+    log_reaped(
+        &mut transaction,
+        &buy.user_id,
+        &buy.stock_symbol,
+        Some(buy.amount_dollars),
+        Some(buy.amount_dollars),
+        reap_num,
+        CommandType::CancelBuy,
+    )
+    .await?;
+
+    commit_transaction(transaction).await?;
+
+    Ok(true)
+}
+
+/**
+ * Releases the sell's reserved shares back to the user. `false` if it was committed or cancelled
+ * first.
+ */
+#[tracing::instrument(skip(pool))]
+async fn reap_sell(
+    pool: &PgPool,
+    order_id: i64,
+    cutoff: PrimitiveDateTime,
+    reap_num: i32,
+) -> anyhow::Result<bool> {
+    let mut transaction = begin_transaction(pool).await?;
+
+    let Some(sell) = sqlx::query_as!(
+        ExpiredOrder,
+        "
+        DELETE FROM queued_sell WHERE order_id = $1 AND time_created < $2
+        RETURNING order_id, user_id, stock_symbol, quoted_price, amount_dollars
+        ",
+        order_id,
+        cutoff
+    )
+    .fetch_optional(transaction.deref_mut())
+    .await?
+    else {
+        return Ok(false);
+    };
+
+    let shares = shares_to_sell(sell.amount_dollars, sell.quoted_price)?;
+    holdings::release(&mut transaction, &sell.user_id, &sell.stock_symbol, shares).await?;
+
+    events::publish(
+        &mut transaction,
+        &AccountEvent::expired(
+            &sell.user_id,
+            Side::Sell,
+            &sell.stock_symbol,
+            Some(sell.order_id),
+        ),
+    )
+    .await?;
+
+    // only shares were released, so there is no balance change to log
+    log_reaped(
+        &mut transaction,
+        &sell.user_id,
+        &sell.stock_symbol,
+        None,
+        Some(sell.amount_dollars),
+        reap_num,
+        CommandType::CancelSell,
+    )
+    .await?;
+
+    commit_transaction(transaction).await?;
+
+    Ok(true)
+}
+
+/**
+ * Returns what a day order reserved to the user. `false` if it was filled or cancelled first.
+ */
+#[tracing::instrument(skip(pool))]
+async fn reap_order(pool: &PgPool, order_id: i64, reap_num: i32) -> anyhow::Result<bool> {
+    let mut transaction = begin_transaction(pool).await?;
+
+    let Some(order) = take_order(&mut transaction, order_id).await? else {
+        return Ok(false);
+    };
+
+    if !order.is_expired() {
+        return Ok(false);
+    }
+
+    let AccountTransaction(refunded) = refund_order(&mut transaction, &order).await?;
+
+    let (command, side) = if order.order_type.is_buy() {
+        (CommandType::CancelSetBuy, Side::Buy)
+    } else {
+        (CommandType::CancelSetSell, Side::Sell)
+    };
+    events::publish(
+        &mut transaction,
+        &AccountEvent::expired(
+            &order.owner_id,
+            side,
+            &order.stock_symbol,
+            Some(order.order_id),
+        ),
+    )
+    .await?;
+
+    // sells reserve shares, not cash, so only a buy's refund changes the balance
+    log_reaped(
+        &mut transaction,
+        &order.owner_id,
+        &order.stock_symbol,
+        order.amount_dollars.map(|_| refunded),
+        order.amount_dollars,
+        reap_num,
+        command,
+    )
+    .await?;
+
+    commit_transaction(transaction).await?;
+
+    Ok(true)
+}
+
+/**
+ * Logs the reaped order as a system event worth `funds` dollars, preceded by a balance change if
+ * `refunded` cash was returned to the user.
+ */
+#[tracing::instrument(skip(transaction))]
+async fn log_reaped(
+    transaction: &mut Transaction<'static, Postgres>,
+    user_id: &str,
+    stock_symbol: &str,
+    refunded: Option<Decimal>,
+    funds: Option<Decimal>,
+    reap_num: i32,
+    command: CommandType,
+) -> anyhow::Result<()> {
+    if let Some(refunded) = refunded {
+        save_log_entry(
+            transaction.deref_mut(),
+            LogEntry::new(
+                reap_num,
+                user_id.to_string(),
+                Log::AccountChanges(AccountTransactionLog {
+                    action: TransactionType::Add.to_string(),
+                    funds: refunded,
+                }),
+            ),
+        )
+        .await?;
+    }
+
+    save_log_entry(
+        transaction.deref_mut(),
+        LogEntry::new(
+            reap_num,
+            user_id.to_string(),
+            Log::SystemEvents(SystemEventLog {
+                command,
+                stock_symbol: Some(stock_symbol.to_string()),
+                filename: None,
+                funds,
+            }),
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::add::add;
+    use crate::buy::{commit_buy, init_buy};
+    use crate::log::DbLogEntry;
+    use crate::orders::{list_orders, place_order, NewOrder, OrderType, TimeInForce};
+    use crate::sell::init_sell;
+    use crate::trigger::UpdatedPrice;
+    use pretty_assertions::assert_eq;
+    use rust_decimal_macros::dec;
+
+    async fn logs(pool: &PgPool, username: &str) -> anyhow::Result<Vec<Log>> {
+        let entries = sqlx::query_as!(
+            DbLogEntry,
+            "SELECT * FROM log_entry WHERE username = $1 AND transaction_num = 3 ORDER BY timestamp, log_entry_type(log)",
+            username
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(entries
+            .into_iter()
+            .map(LogEntry::try_from)
+            .map(|entry| entry.map(|entry| entry.log))
+            .collect::<Result<_, _>>()?)
+    }
+
+    #[sqlx::test]
+    async fn test_reap_expired_orders(pool: PgPool) -> anyhow::Result<()> {
+        let _log = add(&pool, "marcus", dec!(1000)).await?;
+        let _log = init_buy(&pool, "marcus", "APPL", dec!(10), dec!(100)).await?;
+        commit_buy(&pool, "marcus", None, OrderExpiry::default().buy).await?;
+
+        let (expired_buy, _log) = init_buy(&pool, "marcus", "APPL", dec!(10), dec!(200)).await?;
+        let (fresh_buy, _log) = init_buy(&pool, "marcus", "APPL", dec!(10), dec!(300)).await?;
+        let expired_sell = init_sell(&pool, "marcus", "APPL", dec!(10), dec!(50)).await?;
+
+        sqlx::query!(
+            "UPDATE queued_buy SET time_created = time_created - interval '2 minutes' WHERE order_id = $1",
+            expired_buy
+        )
+        .execute(&pool)
+        .await?;
+        sqlx::query!(
+            "UPDATE queued_sell SET time_created = time_created - interval '6 minutes' WHERE order_id = $1",
+            expired_sell
+        )
+        .execute(&pool)
+        .await?;
+
+        assert_eq!(reap(&pool, OrderExpiry::default(), 3).await?, 2);
+
+        let remaining = sqlx::query_scalar!(
+            r#"
+            SELECT order_id as "order_id!" FROM queued_buy
+            UNION ALL
+            SELECT order_id FROM queued_sell
+            "#
+        )
+        .fetch_all(&pool)
+        .await?;
+        assert_eq!(remaining, vec![fresh_buy]);
+
+        let balance = sqlx::query_scalar!("SELECT balance FROM trader WHERE user_id = 'marcus'")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(balance, dec!(1000) - dec!(100) - dec!(300));
+
+        let reserved = sqlx::query_scalar!(
+            "SELECT reserved FROM stock WHERE owner_id = 'marcus' AND stock_symbol = 'APPL'"
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(reserved, dec!(0));
+
+        assert_eq!(
+            logs(&pool, "marcus").await?,
+            vec![
+                Log::AccountChanges(AccountTransactionLog {
+                    action: "ADD".to_string(),
+                    funds: dec!(200),
+                }),
+                Log::SystemEvents(SystemEventLog {
+                    command: CommandType::CancelBuy,
+                    stock_symbol: Some("APPL".to_string()),
+                    filename: None,
+                    funds: Some(dec!(200)),
+                }),
+                Log::SystemEvents(SystemEventLog {
+                    command: CommandType::CancelSell,
+                    stock_symbol: Some("APPL".to_string()),
+                    filename: None,
+                    funds: Some(dec!(50)),
+                }),
+            ]
+        );
+
+        // nothing left to reap
+        assert_eq!(reap(&pool, OrderExpiry::default(), 4).await?, 0);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_reap_respects_expiry(pool: PgPool) -> anyhow::Result<()> {
+        let _log = add(&pool, "marcus", dec!(1000)).await?;
+        let _log = init_buy(&pool, "marcus", "APPL", dec!(10), dec!(100)).await?;
+
+        sqlx::query!(
+            "UPDATE queued_buy SET time_created = time_created - interval '2 minutes' WHERE user_id = 'marcus'"
+        )
+        .execute(&pool)
+        .await?;
+
+        let expiry = OrderExpiry {
+            buy: Duration::from_secs(5 * 60),
+            ..OrderExpiry::default()
+        };
+        assert_eq!(reap(&pool, expiry, 3).await?, 0);
+        assert_eq!(reap(&pool, OrderExpiry::default(), 3).await?, 1);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_reap_expired_day_orders(pool: PgPool) -> anyhow::Result<()> {
+        let _log = add(&pool, "marcus", dec!(1000)).await?;
+        let current = UpdatedPrice {
+            request_num: 1,
+            symbol: "APPL".to_string(),
+            price: dec!(10),
+        };
+        let day = NewOrder {
+            stock_symbol: "APPL".to_string(),
+            order_type: OrderType::LimitBuy,
+            time_in_force: TimeInForce::Day,
+            amount: dec!(100),
+            price: dec!(5),
+        };
+        let gtc = NewOrder {
+            time_in_force: TimeInForce::Gtc,
+            ..day.clone()
+        };
+        let (day, _, _log) = place_order(&pool, "marcus", day, &current).await?;
+        let (gtc, _, _log) = place_order(&pool, "marcus", gtc, &current).await?;
+
+        // not until the end of the day
+        assert_eq!(reap(&pool, OrderExpiry::default(), 3).await?, 0);
+
+        sqlx::query!(
+            "UPDATE conditional_order SET expires_at = now() - interval '1 minute' WHERE order_id = $1",
+            day
+        )
+        .execute(&pool)
+        .await?;
+
+        assert_eq!(reap(&pool, OrderExpiry::default(), 3).await?, 1);
+
+        let remaining = list_orders(&pool, "marcus").await?;
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].order_id, gtc);
+
+        let balance = sqlx::query_scalar!("SELECT balance FROM trader WHERE user_id = 'marcus'")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(balance, dec!(900));
+
+        assert_eq!(
+            logs(&pool, "marcus").await?,
+            vec![
+                Log::AccountChanges(AccountTransactionLog {
+                    action: "ADD".to_string(),
+                    funds: dec!(100),
+                }),
+                Log::SystemEvents(SystemEventLog {
+                    command: CommandType::CancelSetBuy,
+                    stock_symbol: Some("APPL".to_string()),
+                    filename: None,
+                    funds: Some(dec!(100)),
+                }),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_reap_expired_day_sell(pool: PgPool) -> anyhow::Result<()> {
+        let _log = add(&pool, "marcus", dec!(1000)).await?;
+        let _log = init_buy(&pool, "marcus", "APPL", dec!(10), dec!(100)).await?;
+        commit_buy(&pool, "marcus", None, OrderExpiry::default().buy).await?;
+
+        let current = UpdatedPrice {
+            request_num: 1,
+            symbol: "APPL".to_string(),
+            price: dec!(10),
+        };
+        let sell = NewOrder {
+            stock_symbol: "APPL".to_string(),
+            order_type: OrderType::LimitSell,
+            time_in_force: TimeInForce::Day,
+            amount: dec!(4),
+            price: dec!(20),
+        };
+        let (sell, _, _log) = place_order(&pool, "marcus", sell, &current).await?;
+
+        sqlx::query!(
+            "UPDATE conditional_order SET expires_at = now() - interval '1 minute' WHERE order_id = $1",
+            sell
+        )
+        .execute(&pool)
+        .await?;
+
+        assert_eq!(reap(&pool, OrderExpiry::default(), 3).await?, 1);
+
+        let reserved = sqlx::query_scalar!(
+            "SELECT reserved FROM stock WHERE owner_id = 'marcus' AND stock_symbol = 'APPL'"
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(reserved, dec!(0));
+
+        // releasing shares moves no cash, so there is no balance change and no dollar amount
+        assert_eq!(
+            logs(&pool, "marcus").await?,
+            vec![Log::SystemEvents(SystemEventLog {
+                command: CommandType::CancelSetSell,
+                stock_symbol: Some("APPL".to_string()),
+                filename: None,
+                funds: None,
+            })]
+        );
+
+        Ok(())
+    }
+}
@@ -1,12 +1,22 @@
-use crate::{begin_transaction, commit_transaction};
+use crate::money::shares_to_sell;
+use crate::orders::no_queued_order;
+use crate::{begin_transaction, commit_transaction, holdings};
+use rust_decimal::Decimal;
 use sqlx::{PgPool, Postgres, Transaction};
 use std::ops::DerefMut;
 
+/**
+ * Cancels the user's queued sell `order_id`, or their most recent one if `None`.
+ */
 #[tracing::instrument(skip(pool))]
-pub async fn cancel_sell(pool: &PgPool, user_id: String) -> anyhow::Result<()> {
+pub async fn cancel_sell(
+    pool: &PgPool,
+    user_id: String,
+    order_id: Option<i64>,
+) -> anyhow::Result<()> {
     let mut transaction = begin_transaction(pool).await?;
 
-    let record = delete_queued_sell(&mut transaction, &user_id).await?;
+    let record = delete_queued_sell(&mut transaction, &user_id, order_id).await?;
 
     update_stock_holdings(&mut transaction, user_id, record).await?;
 
@@ -21,41 +31,45 @@ async fn update_stock_holdings(
     user_id: String,
     record: Record,
 ) -> anyhow::Result<()> {
-    sqlx::query!(
-        "
-        INSERT INTO stock (owner_id, stock_symbol, amount)
-        VALUES ($1, $2, $3)
-        ON CONFLICT (owner_id, stock_symbol)
-        DO UPDATE SET
-            amount = stock.amount + $3
-        ",
-        user_id,
-        record.stock_symbol,
-        record.amount_dollars / record.quoted_price
-    )
-    .execute(transaction.deref_mut())
-    .await?;
+    let shares = shares_to_sell(record.amount_dollars, record.quoted_price)?;
+
+    holdings::release(transaction, &user_id, &record.stock_symbol, shares).await?;
 
     Ok(())
 }
 
 struct Record {
-    amount_dollars: f64,
+    amount_dollars: Decimal,
     stock_symbol: String,
-    quoted_price: f64,
+    quoted_price: Decimal,
 }
 
 #[tracing::instrument(skip(transaction))]
 async fn delete_queued_sell(
     transaction: &mut Transaction<'static, Postgres>,
     user_id: &str,
+    order_id: Option<i64>,
 ) -> anyhow::Result<Record> {
-    let record = sqlx::query_as!(Record,
-        "DELETE FROM queued_sell WHERE user_id = $1 RETURNING amount_dollars, stock_symbol, quoted_price",
-        user_id
+    let Some(record) = sqlx::query_as!(
+        Record,
+        "
+        DELETE FROM queued_sell
+        WHERE order_id = (
+            SELECT order_id FROM queued_sell
+            WHERE user_id = $1 AND ($2::bigint IS NULL OR order_id = $2)
+            ORDER BY order_id DESC
+            LIMIT 1
+        )
+        RETURNING amount_dollars, stock_symbol, quoted_price
+        ",
+        user_id,
+        order_id
     )
-        .fetch_one(transaction.deref_mut())
-        .await?;
+    .fetch_optional(transaction.deref_mut())
+    .await?
+    else {
+        return Err(no_queued_order("sell", user_id, order_id));
+    };
     Ok(record)
 }
 
@@ -64,11 +78,13 @@ mod tests {
     use super::*;
     use crate::add::add;
     use crate::buy::{commit_buy, init_buy};
+    use crate::orders::OrderExpiry;
     use crate::sell::init_sell;
+    use rust_decimal_macros::dec;
 
     #[sqlx::test]
     async fn test_cancel_sell_no_queued_sell(pool: PgPool) -> anyhow::Result<()> {
-        let cancel = cancel_sell(&pool, "marcus".to_string()).await;
+        let cancel = cancel_sell(&pool, "marcus".to_string(), None).await;
         assert!(cancel.is_err(), "expected error but was {cancel:?}");
 
         Ok(())
@@ -76,13 +92,13 @@ mod tests {
 
     #[sqlx::test]
     async fn test_cancel_sell_with_pending_sell(pool: PgPool) -> anyhow::Result<()> {
-        let _log = add(&pool, "marcus", 200_f64).await?;
-        let _log = init_buy(&pool, "marcus", "AAPL", 100_f64, 100_f64).await?;
-        commit_buy(&pool, "marcus").await?;
+        let _log = add(&pool, "marcus", dec!(200)).await?;
+        let _log = init_buy(&pool, "marcus", "AAPL", dec!(100), dec!(100)).await?;
+        commit_buy(&pool, "marcus", None, OrderExpiry::default().buy).await?;
 
-        init_sell(&pool, "marcus", "AAPL", 100_f64, 100_f64).await?;
+        init_sell(&pool, "marcus", "AAPL", dec!(100), dec!(100)).await?;
 
-        let cancel = cancel_sell(&pool, "marcus".to_string()).await;
+        let cancel = cancel_sell(&pool, "marcus".to_string(), None).await;
         assert!(cancel.is_ok(), "expected ok but was {cancel:?}");
 
         let stock = sqlx::query!(
@@ -93,7 +109,7 @@ mod tests {
         .fetch_one(&pool)
         .await?;
 
-        assert_eq!(stock.amount, 1_f64);
+        assert_eq!(stock.amount, dec!(1));
 
         let queued_sell = sqlx::query!("SELECT * FROM queued_sell WHERE user_id = $1", "marcus")
             .fetch_optional(&pool)
@@ -1,25 +1,71 @@
+use crate::book::Side;
+use crate::events::{self, AccountEvent};
+use crate::ledger::Account;
 use crate::log::AccountTransaction;
-use crate::{begin_transaction, commit_transaction};
+use crate::money::shares_to_sell;
+use crate::orders::{is_expired, no_queued_order};
+use crate::{begin_transaction, commit_transaction, holdings, ledger};
 use anyhow::bail;
+use rust_decimal::Decimal;
 use sqlx::{PgPool, Postgres, Transaction};
 use std::ops::DerefMut;
+use std::time::Duration;
 use time::PrimitiveDateTime;
 
+/**
+ * Commits the user's queued sell `order_id`, or their most recent one if `None`. A sell older than
+ * `expiry` has its shares released instead.
+ */
 #[tracing::instrument(skip(pool))]
-pub async fn commit_sell(pool: &PgPool, user_id: String) -> anyhow::Result<AccountTransaction> {
+pub async fn commit_sell(
+    pool: &PgPool,
+    user_id: String,
+    order_id: Option<i64>,
+    expiry: Duration,
+) -> anyhow::Result<AccountTransaction> {
     let mut transaction = begin_transaction(pool).await?;
 
-    let queued_sell = delete_queued_sell_by_user(&user_id, &mut transaction).await?;
+    let queued_sell = delete_queued_sell_by_user(&user_id, order_id, &mut transaction).await?;
 
-    let now = time::OffsetDateTime::now_utc();
-    let now = PrimitiveDateTime::new(now.date(), now.time());
-    if queued_sell.time_created + time::Duration::minutes(5) < now {
-        restore_stock(&user_id, &mut transaction, &queued_sell).await?;
+    if is_expired(queued_sell.time_created, expiry) {
+        let shares = shares_to_sell(queued_sell.amount_dollars, queued_sell.quoted_price)?;
+        holdings::release(
+            &mut transaction,
+            &user_id,
+            &queued_sell.stock_symbol,
+            shares,
+        )
+        .await?;
+        events::publish(
+            &mut transaction,
+            &AccountEvent::expired(
+                &user_id,
+                Side::Sell,
+                &queued_sell.stock_symbol,
+                Some(queued_sell.order_id),
+            ),
+        )
+        .await?;
 
         commit_transaction(transaction).await?;
         bail!("queued sell expired");
     }
 
+    let shares = remove_stock(&user_id, &mut transaction, &queued_sell).await?;
+    events::publish(
+        &mut transaction,
+        &AccountEvent::fill(
+            &user_id,
+            Side::Sell,
+            &queued_sell.stock_symbol,
+            Some(queued_sell.order_id),
+            shares,
+            queued_sell.quoted_price,
+            queued_sell.amount_dollars,
+        ),
+    )
+    .await?;
+
     let acc_trans = update_balance(user_id, &mut transaction, queued_sell).await?;
 
     commit_transaction(transaction).await?;
@@ -42,46 +88,81 @@ async fn update_balance(
     .execute(transaction.deref_mut())
     .await?;
 
+    ledger::transfer(
+        transaction,
+        &user_id,
+        Account::House,
+        Account::Cash,
+        queued_sell.amount_dollars,
+    )
+    .await?;
+
     Ok(AccountTransaction(queued_sell.amount_dollars))
 }
 
 #[tracing::instrument(skip_all)]
-async fn restore_stock(
-    user_id: &String,
+async fn remove_stock(
+    user_id: &str,
     transaction: &mut Transaction<'static, Postgres>,
     queued_sell: &Record,
-) -> anyhow::Result<()> {
-    sqlx::query!(
-        "UPDATE stock SET amount = amount + $1 WHERE owner_id = $2 AND stock_symbol = $3",
-        queued_sell.amount_dollars / queued_sell.quoted_price,
+) -> anyhow::Result<Decimal> {
+    let shares = shares_to_sell(queued_sell.amount_dollars, queued_sell.quoted_price)?;
+
+    holdings::remove_reserved(
+        transaction,
         user_id,
-        queued_sell.stock_symbol
+        &queued_sell.stock_symbol,
+        shares,
+        queued_sell.amount_dollars,
+    )
+    .await?;
+
+    ledger::transfer_shares(
+        transaction,
+        user_id,
+        &queued_sell.stock_symbol,
+        Account::Holdings,
+        Account::House,
+        shares,
     )
-    .execute(transaction.deref_mut())
     .await?;
 
-    Ok(())
+    Ok(shares)
 }
 
 struct Record {
-    amount_dollars: f64,
+    order_id: i64,
+    amount_dollars: Decimal,
     time_created: PrimitiveDateTime,
-    quoted_price: f64,
+    quoted_price: Decimal,
     stock_symbol: String,
 }
 
 #[tracing::instrument(skip_all)]
 async fn delete_queued_sell_by_user(
     user_id: &String,
+    order_id: Option<i64>,
     transaction: &mut Transaction<'static, Postgres>,
 ) -> anyhow::Result<Record> {
-    let Some(queued_sell) = sqlx::query_as!(Record,
-        "DELETE FROM queued_sell WHERE user_id = $1 RETURNING amount_dollars, time_created, quoted_price, stock_symbol",
-        user_id
+    let Some(queued_sell) = sqlx::query_as!(
+        Record,
+        "
+        DELETE FROM queued_sell
+        WHERE order_id = (
+            SELECT order_id FROM queued_sell
+            WHERE user_id = $1 AND ($2::bigint IS NULL OR order_id = $2)
+            ORDER BY order_id DESC
+            LIMIT 1
+        )
+        RETURNING order_id, amount_dollars, time_created, quoted_price, stock_symbol
+        ",
+        user_id,
+        order_id
     )
-        .fetch_optional(transaction.deref_mut())
-        .await? else {
-        bail!("no queued sell for user_id {user_id}");
+    .fetch_optional(transaction.deref_mut())
+    .await?
+    else {
+        return Err(no_queued_order("sell", user_id, order_id));
     };
 
     Ok(queued_sell)
@@ -92,25 +173,39 @@ mod tests {
     use super::*;
     use crate::add::add;
     use crate::buy::{commit_buy, init_buy};
+    use crate::orders::OrderExpiry;
     use crate::sell::init_sell;
     use pretty_assertions::assert_eq;
+    use rust_decimal_macros::dec;
 
     #[sqlx::test]
     async fn test_commit_sell_no_queued_sell(pool: PgPool) -> anyhow::Result<()> {
-        let result = commit_sell(&pool, "test_user_id".to_string()).await;
+        let result = commit_sell(
+            &pool,
+            "test_user_id".to_string(),
+            None,
+            OrderExpiry::default().sell,
+        )
+        .await;
         assert!(result.is_err());
         Ok(())
     }
 
     #[sqlx::test]
     async fn test_commit_sell_with_pending_sell(pool: PgPool) -> anyhow::Result<()> {
-        let _log = add(&pool, "marcus", 200_f64).await?;
-        let _log = init_buy(&pool, "marcus", "AAPL", 100_f64, 100_f64).await?;
-        commit_buy(&pool, "marcus").await?;
+        let _log = add(&pool, "marcus", dec!(200)).await?;
+        let _log = init_buy(&pool, "marcus", "AAPL", dec!(100), dec!(100)).await?;
+        commit_buy(&pool, "marcus", None, OrderExpiry::default().buy).await?;
 
-        init_sell::init_sell(&pool, "marcus", "AAPL", 100_f64, 100_f64).await?;
+        init_sell::init_sell(&pool, "marcus", "AAPL", dec!(100), dec!(100)).await?;
 
-        let result = commit_sell(&pool, "marcus".to_string()).await;
+        let result = commit_sell(
+            &pool,
+            "marcus".to_string(),
+            None,
+            OrderExpiry::default().sell,
+        )
+        .await;
         assert!(result.is_ok(), "expected ok but was {result:?}");
 
         let balance = sqlx::query!("SELECT balance FROM trader WHERE user_id = $1", "marcus")
@@ -118,7 +213,7 @@ mod tests {
             .await?
             .balance;
 
-        assert_eq!(balance, 200_f64);
+        assert_eq!(balance, dec!(200));
 
         let stock_amount = sqlx::query!(
             "SELECT amount FROM stock WHERE owner_id = $1 AND stock_symbol = $2",
@@ -129,7 +224,7 @@ mod tests {
         .await?
         .amount;
 
-        assert_eq!(stock_amount, 0_f64);
+        assert_eq!(stock_amount, dec!(0));
 
         let queued_sell = sqlx::query!("SELECT * FROM queued_sell WHERE user_id = $1", "marcus")
             .fetch_optional(&pool)
@@ -145,11 +240,11 @@ mod tests {
 
     #[sqlx::test]
     async fn test_commit_sell_with_expired_queued_sell(pool: PgPool) -> anyhow::Result<()> {
-        let _log = add(&pool, "marcus", 200_f64).await?;
-        let _log = init_buy(&pool, "marcus", "AAPL", 100_f64, 100_f64).await?;
-        commit_buy(&pool, "marcus").await?;
+        let _log = add(&pool, "marcus", dec!(200)).await?;
+        let _log = init_buy(&pool, "marcus", "AAPL", dec!(100), dec!(100)).await?;
+        commit_buy(&pool, "marcus", None, OrderExpiry::default().buy).await?;
 
-        init_sell::init_sell(&pool, "marcus", "AAPL", 100_f64, 100_f64).await?;
+        init_sell::init_sell(&pool, "marcus", "AAPL", dec!(100), dec!(100)).await?;
 
         // set time for queued sell to be expired
         sqlx::query!(
@@ -159,7 +254,13 @@ mod tests {
         .execute(&pool)
         .await?;
 
-        let result = commit_sell(&pool, "marcus".to_string()).await;
+        let result = commit_sell(
+            &pool,
+            "marcus".to_string(),
+            None,
+            OrderExpiry::default().sell,
+        )
+        .await;
         assert!(result.is_err(), "expected error but was {result:?}");
 
         let balance = sqlx::query!("SELECT balance FROM trader WHERE user_id = $1", "marcus")
@@ -167,7 +268,7 @@ mod tests {
             .await?
             .balance;
 
-        assert_eq!(balance, 100_f64);
+        assert_eq!(balance, dec!(100));
 
         let stock_amount = sqlx::query!(
             "SELECT amount FROM stock WHERE owner_id = $1 AND stock_symbol = $2",
@@ -178,7 +279,7 @@ mod tests {
         .await?
         .amount;
 
-        assert_eq!(stock_amount, 1_f64);
+        assert_eq!(stock_amount, dec!(1));
 
         Ok(())
     }
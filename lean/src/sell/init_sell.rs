@@ -1,6 +1,6 @@
-use crate::{begin_transaction, commit_transaction};
-use anyhow::bail;
-use sqlx::postgres::PgQueryResult;
+use crate::money::shares_to_sell;
+use crate::{begin_transaction, commit_transaction, holdings};
+use rust_decimal::Decimal;
 use sqlx::{PgPool, Postgres, Transaction};
 use std::ops::DerefMut;
 
@@ -8,37 +8,30 @@ use std::ops::DerefMut;
 struct QueuedSell {
     user_id: String,
     stock_symbol: String,
-    quoted_price: f64,
-    amount_dollars: f64,
+    quoted_price: Decimal,
+    amount_dollars: Decimal,
     time_created: time::PrimitiveDateTime,
+    order_id: i64,
 }
 
+/**
+ * Queues a sell alongside any the user already has pending. Returns the order id of the new sell.
+ */
 #[tracing::instrument(skip(pool))]
 pub async fn init_sell(
     pool: &PgPool,
     user_id: &str,
     stock_symbol: &str,
-    quote: f64,
-    dollar_amount: f64,
-) -> anyhow::Result<()> {
+    quote: Decimal,
+    dollar_amount: Decimal,
+) -> anyhow::Result<i64> {
     let mut transaction = begin_transaction(pool).await?;
 
-    resolve_old_queued_sell(user_id, &mut transaction).await?;
+    let shares = shares_to_sell(dollar_amount, quote)?;
 
-    let query_result = update_stock_holdings(
-        user_id,
-        stock_symbol,
-        quote,
-        dollar_amount,
-        &mut transaction,
-    )
-    .await?;
+    holdings::reserve(&mut transaction, user_id, stock_symbol, shares).await?;
 
-    if query_result.rows_affected() != 1 {
-        bail!("not enough stocks to sell");
-    }
-
-    created_queued_sell(
+    let order_id = created_queued_sell(
         user_id,
         stock_symbol,
         quote,
@@ -49,90 +42,44 @@ pub async fn init_sell(
 
     commit_transaction(transaction).await?;
 
-    Ok(())
-}
-
-#[tracing::instrument(skip_all)]
-async fn resolve_old_queued_sell(
-    user_id: &str,
-    transaction: &mut Transaction<'static, Postgres>,
-) -> anyhow::Result<()> {
-    let transaction = transaction.deref_mut();
-    if let Some(record) = sqlx::query!("DELETE FROM queued_sell WHERE user_id = $1 returning quoted_price, amount_dollars, stock_symbol", user_id)
-        .fetch_optional(&mut *transaction)
-        .await? {
-        sqlx::query!("UPDATE stock SET amount = amount + $1 WHERE owner_id = $2 AND stock_symbol = $3", record.amount_dollars / record.quoted_price, user_id, record.stock_symbol)
-            .execute(&mut *transaction)
-            .await?;
-    }
-
-    Ok(())
+    Ok(order_id)
 }
 
 #[tracing::instrument(skip_all)]
 async fn created_queued_sell(
     user_id: &str,
     stock_symbol: &str,
-    quote: f64,
-    dollar_amount: f64,
+    quote: Decimal,
+    dollar_amount: Decimal,
     transaction: &mut Transaction<'static, Postgres>,
-) -> anyhow::Result<()> {
-    sqlx::query!(
+) -> anyhow::Result<i64> {
+    let order_id = sqlx::query_scalar!(
         "
         INSERT INTO queued_sell (user_id, stock_symbol, quoted_price, amount_dollars)
         VALUES ($1, $2, $3, $4)
-        ON CONFLICT (user_id)
-        DO UPDATE SET
-            stock_symbol = $2,
-            quoted_price = $3,
-            amount_dollars = $4,
-            time_created = NOW()
+        RETURNING order_id
         ",
         user_id,
         stock_symbol,
         quote,
         dollar_amount
     )
-    .execute(transaction.deref_mut())
-    .await?;
-
-    Ok(())
-}
-
-#[tracing::instrument(skip_all)]
-async fn update_stock_holdings(
-    user_id: &str,
-    stock_symbol: &str,
-    quote: f64,
-    dollar_amount: f64,
-    transaction: &mut Transaction<'static, Postgres>,
-) -> anyhow::Result<PgQueryResult> {
-    let query_result = sqlx::query!(
-        "
-    UPDATE stock SET amount = amount - $1
-    WHERE 
-        owner_id = $2 AND
-        stock_symbol = $3 AND
-        amount >= $1
-    ",
-        dollar_amount / quote,
-        user_id,
-        stock_symbol
-    )
-    .execute(transaction.deref_mut())
+    .fetch_one(transaction.deref_mut())
     .await?;
 
-    Ok(query_result)
+    Ok(order_id)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::orders::OrderExpiry;
     use pretty_assertions::assert_eq;
+    use rust_decimal_macros::dec;
 
     #[sqlx::test]
     async fn test_init_sell_with_no_funds(pool: PgPool) -> anyhow::Result<()> {
-        let sell = init_sell(&pool, "marcus", "APPL", 50_f64, 100_f64).await;
+        let sell = init_sell(&pool, "marcus", "APPL", dec!(50), dec!(100)).await;
         assert!(sell.is_err(), "expected error but was {sell:?}");
 
         Ok(())
@@ -142,16 +89,17 @@ mod tests {
     struct Stock {
         owner_id: String,
         stock_symbol: String,
-        amount: f64,
+        amount: Decimal,
+        reserved: Decimal,
     }
 
     #[sqlx::test]
     async fn test_init_sell_with_stocks_to_sell(pool: PgPool) -> anyhow::Result<()> {
-        let _log = crate::add::add(&pool, "marcus", 100_f64).await?;
-        let _log = crate::buy::init_buy(&pool, "marcus", "APPL", 50_f64, 100_f64).await?;
-        crate::buy::commit_buy(&pool, "marcus").await?;
+        let _log = crate::add::add(&pool, "marcus", dec!(100)).await?;
+        let _log = crate::buy::init_buy(&pool, "marcus", "APPL", dec!(50), dec!(100)).await?;
+        crate::buy::commit_buy(&pool, "marcus", None, OrderExpiry::default().buy).await?;
 
-        let sell = init_sell(&pool, "marcus", "APPL", 50_f64, 100_f64).await;
+        let sell = init_sell(&pool, "marcus", "APPL", dec!(50), dec!(100)).await;
         assert!(sell.is_ok(), "expected ok but was {sell:?}");
 
         let sell = sqlx::query_as!(
@@ -172,23 +120,28 @@ mod tests {
             QueuedSell {
                 user_id: "marcus".to_string(),
                 stock_symbol: "APPL".to_string(),
-                quoted_price: 50_f64,
-                amount_dollars: 100_f64,
+                quoted_price: dec!(50),
+                amount_dollars: dec!(100),
                 time_created: sell.time_created,
+                order_id: sell.order_id,
             }
         );
 
-        let stock = sqlx::query_as!(Stock, "SELECT * FROM stock WHERE owner_id = 'marcus'")
-            .fetch_optional(&pool)
-            .await?
-            .expect("expected stock to exist");
+        let stock = sqlx::query_as!(
+            Stock,
+            "SELECT owner_id, stock_symbol, amount, reserved FROM stock WHERE owner_id = 'marcus'"
+        )
+        .fetch_optional(&pool)
+        .await?
+        .expect("expected stock to exist");
 
         assert_eq!(
             stock,
             Stock {
                 owner_id: "marcus".to_string(),
                 stock_symbol: "APPL".to_string(),
-                amount: 0_f64,
+                amount: dec!(2),
+                reserved: dec!(2),
             }
         );
 
@@ -197,11 +150,11 @@ mod tests {
 
     #[sqlx::test]
     async fn test_init_buy_with_insufficient_stocks_to_sell(pool: PgPool) -> anyhow::Result<()> {
-        let _log = crate::add::add(&pool, "marcus", 100_f64).await?;
-        let _log = crate::buy::init_buy(&pool, "marcus", "APPL", 50_f64, 100_f64).await?;
-        crate::buy::commit_buy(&pool, "marcus").await?;
+        let _log = crate::add::add(&pool, "marcus", dec!(100)).await?;
+        let _log = crate::buy::init_buy(&pool, "marcus", "APPL", dec!(50), dec!(100)).await?;
+        crate::buy::commit_buy(&pool, "marcus", None, OrderExpiry::default().buy).await?;
 
-        let sell = init_sell(&pool, "marcus", "APPL", 50_f64, 200_f64).await;
+        let sell = init_sell(&pool, "marcus", "APPL", dec!(50), dec!(200)).await;
         assert!(sell.is_err(), "expected error but was {sell:?}");
 
         let queued_sell = sqlx::query!("SELECT * FROM queued_sell WHERE user_id = 'marcus'")
@@ -213,16 +166,20 @@ mod tests {
             "expected no queued sell but was {queued_sell:?}"
         );
 
-        let stock = sqlx::query_as!(Stock, "SELECT * FROM stock WHERE owner_id = 'marcus'")
-            .fetch_one(&pool)
-            .await?;
+        let stock = sqlx::query_as!(
+            Stock,
+            "SELECT owner_id, stock_symbol, amount, reserved FROM stock WHERE owner_id = 'marcus'"
+        )
+        .fetch_one(&pool)
+        .await?;
 
         assert_eq!(
             stock,
             Stock {
                 owner_id: "marcus".to_string(),
                 stock_symbol: "APPL".to_string(),
-                amount: 2_f64,
+                amount: dec!(2),
+                reserved: dec!(0),
             }
         );
 
@@ -230,50 +187,46 @@ mod tests {
     }
 
     #[sqlx::test]
-    async fn test_override_queued_sell(pool: PgPool) -> anyhow::Result<()> {
-        let _log = crate::add::add(&pool, "marcus", 400_f64).await?;
-        let _log = crate::buy::init_buy(&pool, "marcus", "APPL", 50_f64, 200_f64).await?;
-        crate::buy::commit_buy(&pool, "marcus").await?;
+    async fn test_multiple_queued_sells(pool: PgPool) -> anyhow::Result<()> {
+        let _log = crate::add::add(&pool, "marcus", dec!(400)).await?;
+        let _log = crate::buy::init_buy(&pool, "marcus", "APPL", dec!(50), dec!(200)).await?;
+        crate::buy::commit_buy(&pool, "marcus", None, OrderExpiry::default().buy).await?;
 
-        init_sell(&pool, "marcus", "APPL", 50_f64, 200_f64).await?;
-        let sell = init_sell(&pool, "marcus", "APPL", 50_f64, 100_f64).await;
+        let first = init_sell(&pool, "marcus", "APPL", dec!(50), dec!(100)).await?;
+        let sell = init_sell(&pool, "marcus", "APPL", dec!(50), dec!(50)).await;
 
         assert!(sell.is_ok(), "expected ok but was {sell:?}");
+        assert_ne!(first, sell?);
 
-        let sell = sqlx::query_as!(
-            QueuedSell,
+        let amounts = sqlx::query_scalar!(
             "
-            SELECT * FROM queued_sell
+            SELECT amount_dollars FROM queued_sell
             WHERE user_id = $1 AND stock_symbol = $2
+            ORDER BY order_id
             ",
             "marcus",
             "APPL",
         )
-        .fetch_one(&pool)
+        .fetch_all(&pool)
         .await?;
 
-        assert_eq!(
-            sell,
-            QueuedSell {
-                user_id: "marcus".to_string(),
-                stock_symbol: "APPL".to_string(),
-                quoted_price: 50_f64,
-                amount_dollars: 100_f64,
-                time_created: sell.time_created,
-            }
-        );
+        assert_eq!(amounts, vec![dec!(100), dec!(50)]);
 
-        let stock = sqlx::query_as!(Stock, "SELECT * FROM stock WHERE owner_id = 'marcus'")
-            .fetch_optional(&pool)
-            .await?
-            .expect("expected stock to exist");
+        let stock = sqlx::query_as!(
+            Stock,
+            "SELECT owner_id, stock_symbol, amount, reserved FROM stock WHERE owner_id = 'marcus'"
+        )
+        .fetch_optional(&pool)
+        .await?
+        .expect("expected stock to exist");
 
         assert_eq!(
             stock,
             Stock {
                 owner_id: "marcus".to_string(),
                 stock_symbol: "APPL".to_string(),
-                amount: 2_f64,
+                amount: dec!(4),
+                reserved: dec!(3),
             }
         );
 
@@ -1,12 +1,28 @@
 pub use buy::cancel_set_buy;
+pub use buy::place_buy_trigger;
 pub use buy::set_buy_amount;
 pub use buy::set_buy_trigger;
-use sqlx::PgPool;
-use tracing::info;
+pub use buy::update_buy_trigger;
+use rust_decimal::Decimal;
+use sqlx::{PgPool, Postgres, Transaction};
+use tracing::{error, info};
+
+use crate::book::Side;
+use crate::events::{self, AccountEvent};
+use crate::ledger::Account;
+use crate::log::{
+    save_log_entry, AccountTransactionLog, CommandType, Log, LogEntry, SystemEventLog,
+};
+use crate::money::{dollars_for_shares, shares_to_buy};
+use crate::orders;
+use crate::{begin_transaction, commit_transaction, holdings, ledger, TransactionType};
+use std::ops::DerefMut;
 
 pub use sell::cancel_set_sell;
+pub use sell::place_sell_trigger;
 pub use sell::set_sell_amount;
 pub use sell::set_sell_trigger;
+pub use sell::update_sell_trigger;
 
 mod buy;
 
@@ -14,8 +30,10 @@ mod sell;
 
 #[derive(Debug, Clone)]
 pub struct UpdatedPrice {
+    /// the request that fetched the price, used as the transaction number of any triggers it fires
+    pub(crate) request_num: i32,
     pub(crate) symbol: String,
-    pub(crate) price: f64,
+    pub(crate) price: Decimal,
 }
 
 pub struct Triggerer /*reeeeeee*/ {
@@ -40,6 +58,11 @@ impl Triggerer {
                 let for_buy = next.clone();
                 let pool = self.pool.clone();
                 tokio::spawn(async move { Self::check_buy_triggers(&pool, &for_buy).await });
+                let for_orders = next.clone();
+                let pool = self.pool.clone();
+                tokio::spawn(
+                    async move { orders::fill_triggered_orders(&pool, &for_orders).await },
+                );
                 let pool = self.pool.clone();
                 tokio::spawn(async move { Self::check_sell_triggers(&pool, &next).await });
             }
@@ -47,108 +70,286 @@ impl Triggerer {
     }
 
     async fn check_sell_triggers(pool: &PgPool, next: &UpdatedPrice) -> anyhow::Result<()> {
-        let sell_triggers = sqlx::query_as!(
-            SellTrigger,
-            "DELETE FROM sell_trigger WHERE trigger_price <= $1 AND stock_symbol = $2 RETURNING owner_id, amount_stock",
+        let owners = sqlx::query_scalar!(
+            "SELECT owner_id FROM sell_trigger WHERE trigger_price <= $1 AND stock_symbol = $2",
             &next.price,
             &next.symbol,
         )
         .fetch_all(pool)
         .await?;
 
-        if !sell_triggers.is_empty() {
+        if !owners.is_empty() {
             info!(
                 "executing {}, sell triggers for {}",
-                sell_triggers.len(),
+                owners.len(),
                 &next.symbol
             );
-            execute_sell_triggers(pool, sell_triggers, next.price).await?;
+        }
+
+        for owner_id in owners {
+            if let Err(err) = execute_sell_trigger(pool, &owner_id, next).await {
+                error!("failed to execute sell trigger for {owner_id}: {err}");
+            }
         }
 
         Ok(())
     }
 
     async fn check_buy_triggers(pool: &PgPool, next: &UpdatedPrice) -> anyhow::Result<()> {
-        let buy_triggers = sqlx::query_as!(
-            BuyTrigger,
-            "
-                        DELETE FROM buy_trigger
-                        WHERE trigger_price >= $1 AND stock_symbol = $2 RETURNING owner_id, amount_dollars
-                        ",
+        let owners = sqlx::query_scalar!(
+            "SELECT owner_id FROM buy_trigger WHERE trigger_price >= $1 AND stock_symbol = $2",
             &next.price,
             &next.symbol,
         )
         .fetch_all(pool)
         .await?;
 
-        if !buy_triggers.is_empty() {
+        if !owners.is_empty() {
             info!(
                 "executing {}, buy triggers for {}",
-                buy_triggers.len(),
+                owners.len(),
                 &next.symbol
             );
-            execute_buy_triggers(pool, buy_triggers, &next.symbol, next.price).await?;
+        }
+
+        for owner_id in owners {
+            if let Err(err) = execute_buy_trigger(pool, &owner_id, next).await {
+                error!("failed to execute buy trigger for {owner_id}: {err}");
+            }
         }
 
         Ok(())
     }
 }
 
-struct SellTrigger {
-    owner_id: String,
-    amount_stock: f64,
+/**
+ * Spends the dollars reserved by a buy trigger on shares at the updated price. The trigger is
+ * removed, the shares added, and the account change and system event logged in one transaction.
+ * Does nothing if the trigger was cancelled, changed or already executed since it was selected.
+ */
+#[tracing::instrument(skip(pool))]
+async fn execute_buy_trigger(
+    pool: &PgPool,
+    owner_id: &str,
+    next: &UpdatedPrice,
+) -> anyhow::Result<()> {
+    let mut transaction = begin_transaction(pool).await?;
+
+    let Some(amount_dollars) = sqlx::query_scalar!(
+        "
+        DELETE FROM buy_trigger
+        WHERE owner_id = $1 AND stock_symbol = $2 AND trigger_price >= $3
+        RETURNING amount_dollars
+        ",
+        owner_id,
+        &next.symbol,
+        &next.price,
+    )
+    .fetch_optional(transaction.deref_mut())
+    .await?
+    else {
+        return Ok(());
+    };
+
+    let shares = spend_reserved_cash(&mut transaction, owner_id, next, amount_dollars).await?;
+    events::publish(
+        &mut transaction,
+        &AccountEvent::trigger_executed(
+            owner_id,
+            Side::Buy,
+            &next.symbol,
+            shares,
+            next.price,
+            amount_dollars,
+        ),
+    )
+    .await?;
+
+    commit_transaction(transaction).await?;
+
+    Ok(())
 }
 
-struct BuyTrigger {
-    owner_id: String,
-    amount_dollars: f64,
+/**
+ * Spends `amount_dollars` of the owner's reserved cash on shares at the updated price and logs the
+ * account change and system event, as a fired buy trigger or limit buy does. Returns the shares
+ * bought.
+ */
+#[tracing::instrument(skip(transaction))]
+pub(crate) async fn spend_reserved_cash(
+    transaction: &mut Transaction<'static, Postgres>,
+    owner_id: &str,
+    next: &UpdatedPrice,
+    amount_dollars: Decimal,
+) -> anyhow::Result<Decimal> {
+    let amount = shares_to_buy(amount_dollars, next.price)?;
+
+    holdings::add(transaction, owner_id, &next.symbol, amount, amount_dollars).await?;
+
+    ledger::transfer(
+        transaction,
+        owner_id,
+        Account::ReservedCash,
+        Account::House,
+        amount_dollars,
+    )
+    .await?;
+    ledger::transfer_shares(
+        transaction,
+        owner_id,
+        &next.symbol,
+        Account::House,
+        Account::Holdings,
+        amount,
+    )
+    .await?;
+
+    log_trigger(
+        transaction,
+        owner_id,
+        next,
+        CommandType::SetBuyTrigger,
+        TransactionType::DebitReserved,
+        amount_dollars,
+    )
+    .await?;
+
+    Ok(amount)
 }
 
-#[tracing::instrument(skip_all)]
-async fn execute_buy_triggers(
+/**
+ * Sells the shares reserved by a sell trigger at the updated price. The trigger is removed, the
+ * shares taken from the owner's holdings, the proceeds added to the balance, and the account
+ * change and system event logged in one transaction. Does nothing if the trigger was cancelled,
+ * changed or already executed since it was selected.
+ */
+#[tracing::instrument(skip(pool))]
+async fn execute_sell_trigger(
     pool: &PgPool,
-    buy: Vec<BuyTrigger>,
-    symbol: &str,
-    quote: f64,
+    owner_id: &str,
+    next: &UpdatedPrice,
 ) -> anyhow::Result<()> {
-    for trigger in buy {
-        let amount = trigger.amount_dollars / quote;
-
-        sqlx::query!(
-            "
-        INSERT INTO stock (owner_id, stock_symbol, amount)
-        VALUES ($1, $2, $3)
-        ON CONFLICT (owner_id, stock_symbol)
-        DO UPDATE SET
-            amount = stock.amount + $3
+    let mut transaction = begin_transaction(pool).await?;
+
+    let Some(amount_stock) = sqlx::query_scalar!(
+        "
+        DELETE FROM sell_trigger
+        WHERE owner_id = $1 AND stock_symbol = $2 AND trigger_price <= $3
+        RETURNING amount_stock
         ",
-            trigger.owner_id,
-            symbol,
-            amount
-        )
-        .execute(pool)
-        .await?;
-    }
+        owner_id,
+        &next.symbol,
+        &next.price,
+    )
+    .fetch_optional(transaction.deref_mut())
+    .await?
+    else {
+        return Ok(());
+    };
+
+    let dollars = sell_reserved_shares(&mut transaction, owner_id, next, amount_stock).await?;
+    events::publish(
+        &mut transaction,
+        &AccountEvent::trigger_executed(
+            owner_id,
+            Side::Sell,
+            &next.symbol,
+            amount_stock,
+            next.price,
+            dollars,
+        ),
+    )
+    .await?;
+
+    commit_transaction(transaction).await?;
 
     Ok(())
 }
 
-async fn execute_sell_triggers(
-    pool: &PgPool,
-    sell: Vec<SellTrigger>,
-    quote: f64,
+/**
+ * Sells `amount_stock` of the owner's reserved shares at the updated price, adds the proceeds to
+ * their balance and logs the account change and system event, as a fired sell trigger, limit sell
+ * or stop does. Returns the proceeds.
+ */
+#[tracing::instrument(skip(transaction))]
+pub(crate) async fn sell_reserved_shares(
+    transaction: &mut Transaction<'static, Postgres>,
+    owner_id: &str,
+    next: &UpdatedPrice,
+    amount_stock: Decimal,
+) -> anyhow::Result<Decimal> {
+    let amount = dollars_for_shares(amount_stock, next.price)?;
+
+    holdings::remove_reserved(transaction, owner_id, &next.symbol, amount_stock, amount).await?;
+    ledger::transfer_shares(
+        transaction,
+        owner_id,
+        &next.symbol,
+        Account::Holdings,
+        Account::House,
+        amount_stock,
+    )
+    .await?;
+
+    sqlx::query!(
+        "UPDATE trader SET balance = balance + $2 WHERE user_id = $1",
+        owner_id,
+        amount
+    )
+    .execute(transaction.deref_mut())
+    .await?;
+
+    ledger::transfer(transaction, owner_id, Account::House, Account::Cash, amount).await?;
+
+    log_trigger(
+        transaction,
+        owner_id,
+        next,
+        CommandType::SetSellTrigger,
+        TransactionType::Add,
+        amount,
+    )
+    .await?;
+
+    Ok(amount)
+}
+
+#[tracing::instrument(skip(transaction))]
+async fn log_trigger(
+    transaction: &mut Transaction<'static, Postgres>,
+    owner_id: &str,
+    next: &UpdatedPrice,
+    command: CommandType,
+    action: TransactionType,
+    funds: Decimal,
 ) -> anyhow::Result<()> {
-    for trigger in sell {
-        let amount = trigger.amount_stock * quote;
+    save_log_entry(
+        transaction.deref_mut(),
+        LogEntry::new(
+            next.request_num,
+            owner_id.to_string(),
+            Log::AccountChanges(AccountTransactionLog {
+                action: action.to_string(),
+                funds,
+            }),
+        ),
+    )
+    .await?;
 
-        sqlx::query!(
-            "UPDATE trader SET balance = balance + $2 WHERE user_id = $1",
-            trigger.owner_id,
-            amount
-        )
-        .execute(pool)
-        .await?;
-    }
+    save_log_entry(
+        transaction.deref_mut(),
+        LogEntry::new(
+            next.request_num,
+            owner_id.to_string(),
+            Log::SystemEvents(SystemEventLog {
+                command,
+                stock_symbol: Some(next.symbol.clone()),
+                filename: None,
+                funds: Some(funds),
+            }),
+        ),
+    )
+    .await?;
 
     Ok(())
 }
@@ -157,17 +358,44 @@ async fn execute_sell_triggers(
 mod tests {
     use super::*;
     use crate::add::add;
+    use crate::buy::{commit_buy, init_buy};
+    use crate::log::DbLogEntry;
+    use crate::orders::OrderExpiry;
+    use crate::trigger::{set_buy_amount, set_buy_trigger, set_sell_amount, set_sell_trigger};
+    use pretty_assertions::assert_eq;
+    use rust_decimal_macros::dec;
+
+    fn updated_price(price: Decimal) -> UpdatedPrice {
+        UpdatedPrice {
+            request_num: 7,
+            symbol: "APPL".to_string(),
+            price,
+        }
+    }
+
+    async fn logs(pool: &PgPool, username: &str) -> anyhow::Result<Vec<Log>> {
+        let entries = sqlx::query_as!(
+            DbLogEntry,
+            "SELECT * FROM log_entry WHERE username = $1 AND transaction_num = 7",
+            username
+        )
+        .fetch_all(pool)
+        .await?;
+
+        Ok(entries
+            .into_iter()
+            .map(LogEntry::try_from)
+            .map(|entry| entry.map(|entry| entry.log))
+            .collect::<Result<_, _>>()?)
+    }
 
     #[sqlx::test]
     async fn test_execute_buy_trigger(pool: PgPool) -> anyhow::Result<()> {
-        let trigger = BuyTrigger {
-            owner_id: "test".to_string(),
-            amount_dollars: 100.0,
-        };
-
-        let _log = add(&pool, "test", 100_f64).await?;
+        let _log = add(&pool, "test", dec!(100)).await?;
+        let _log = set_buy_amount(&pool, "test", "APPL", dec!(100)).await?;
+        set_buy_trigger(&pool, "test", "APPL", dec!(2)).await?;
 
-        execute_buy_triggers(&pool, vec![trigger], "APPL", 1_f64).await?;
+        Triggerer::check_buy_triggers(&pool, &updated_price(dec!(1))).await?;
 
         let stock = sqlx::query!(
             "SELECT amount FROM stock WHERE owner_id = $1 AND stock_symbol = $2",
@@ -178,7 +406,84 @@ mod tests {
         .await?;
 
         assert_eq!(stock.len(), 1);
-        assert_eq!(stock[0].amount, 100_f64);
+        assert_eq!(stock[0].amount, dec!(100));
+
+        let buy_trigger = sqlx::query!("SELECT * FROM buy_trigger WHERE owner_id = 'test'")
+            .fetch_optional(&pool)
+            .await?;
+        assert!(buy_trigger.is_none(), "expected trigger to be removed");
+
+        assert_eq!(
+            logs(&pool, "test").await?,
+            vec![
+                Log::AccountChanges(AccountTransactionLog {
+                    action: "DEBIT_RESERVED".to_string(),
+                    funds: dec!(100),
+                }),
+                Log::SystemEvents(SystemEventLog {
+                    command: CommandType::SetBuyTrigger,
+                    stock_symbol: Some("APPL".to_string()),
+                    filename: None,
+                    funds: Some(dec!(100)),
+                }),
+            ]
+        );
+
+        assert_eq!(ledger::audit(&pool).await?, vec![]);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_buy_trigger_above_price_not_executed(pool: PgPool) -> anyhow::Result<()> {
+        let _log = add(&pool, "test", dec!(100)).await?;
+        let _log = set_buy_amount(&pool, "test", "APPL", dec!(100)).await?;
+        set_buy_trigger(&pool, "test", "APPL", dec!(2)).await?;
+
+        Triggerer::check_buy_triggers(&pool, &updated_price(dec!(3))).await?;
+
+        let stock = sqlx::query!("SELECT amount FROM stock WHERE owner_id = 'test'")
+            .fetch_optional(&pool)
+            .await?;
+        assert!(stock.is_none(), "expected no stock but was {stock:?}");
+        assert_eq!(logs(&pool, "test").await?, vec![]);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_execute_sell_trigger(pool: PgPool) -> anyhow::Result<()> {
+        let _log = add(&pool, "test", dec!(100)).await?;
+        let _log = init_buy(&pool, "test", "APPL", dec!(10), dec!(100)).await?;
+        commit_buy(&pool, "test", None, OrderExpiry::default().buy).await?;
+        set_sell_amount(&pool, "test", "APPL", dec!(4)).await?;
+        set_sell_trigger(&pool, "test", "APPL", dec!(20)).await?;
+
+        Triggerer::check_sell_triggers(&pool, &updated_price(dec!(25))).await?;
+
+        let balance = sqlx::query!("SELECT balance FROM trader WHERE user_id = 'test'")
+            .fetch_one(&pool)
+            .await?
+            .balance;
+        assert_eq!(balance, dec!(100));
+
+        assert_eq!(
+            logs(&pool, "test").await?,
+            vec![
+                Log::AccountChanges(AccountTransactionLog {
+                    action: "ADD".to_string(),
+                    funds: dec!(100),
+                }),
+                Log::SystemEvents(SystemEventLog {
+                    command: CommandType::SetSellTrigger,
+                    stock_symbol: Some("APPL".to_string()),
+                    filename: None,
+                    funds: Some(dec!(100)),
+                }),
+            ]
+        );
+
+        assert_eq!(ledger::audit(&pool).await?, vec![]);
 
         Ok(())
     }
@@ -1,9 +1,15 @@
 pub use cancel_set_buy::cancel_set_buy;
+pub use place_buy_trigger::place_buy_trigger;
 pub use set_buy_amount::set_buy_amount;
 pub use set_buy_trigger::set_buy_trigger;
+pub use update_buy_trigger::update_buy_trigger;
 
 mod set_buy_amount;
 
 mod cancel_set_buy;
 
 mod set_buy_trigger;
+
+mod place_buy_trigger;
+
+mod update_buy_trigger;
@@ -1,5 +1,7 @@
+use crate::ledger::Account;
 use crate::log::AccountTransaction;
-use crate::{begin_transaction, commit_transaction};
+use crate::{begin_transaction, commit_transaction, ledger};
+use rust_decimal::Decimal;
 use sqlx::{PgPool, Postgres, Transaction};
 use std::ops::DerefMut;
 
@@ -34,11 +36,20 @@ async fn update_trader_balance(
     .execute(transaction.deref_mut())
     .await?;
 
+    ledger::transfer(
+        transaction,
+        user_id,
+        Account::ReservedCash,
+        Account::Cash,
+        record.amount_dollars,
+    )
+    .await?;
+
     Ok(AccountTransaction(record.amount_dollars))
 }
 
 struct Record {
-    amount_dollars: f64,
+    amount_dollars: Decimal,
 }
 
 #[tracing::instrument(skip_all)]
@@ -63,6 +74,7 @@ mod tests {
     use super::*;
     use crate::add::add;
     use crate::trigger::set_buy_amount;
+    use rust_decimal_macros::dec;
 
     #[sqlx::test]
     async fn test_cancel_set_buy_no_buy(pool: PgPool) -> anyhow::Result<()> {
@@ -73,8 +85,8 @@ mod tests {
 
     #[sqlx::test]
     async fn test_cancel_set_buy_with_buy(pool: PgPool) -> anyhow::Result<()> {
-        let _log = add(&pool, "marcus", 100_f64).await?;
-        let _log = set_buy_amount(&pool, "marcus", "APPL", 100_f64).await?;
+        let _log = add(&pool, "marcus", dec!(100)).await?;
+        let _log = set_buy_amount(&pool, "marcus", "APPL", dec!(100)).await?;
 
         let cancel = cancel_set_buy(&pool, "marcus", "APPL").await;
         assert!(cancel.is_ok(), "expected ok but was {cancel:?}");
@@ -84,7 +96,7 @@ mod tests {
             .await?
             .balance;
 
-        assert_eq!(balance, 100_f64);
+        assert_eq!(balance, dec!(100));
 
         let buy_trigger = sqlx::query!(
             "SELECT * FROM buy_trigger WHERE owner_id = 'marcus' AND stock_symbol = 'APPL'"
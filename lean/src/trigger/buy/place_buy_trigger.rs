@@ -0,0 +1,110 @@
+use crate::log::AccountTransaction;
+use crate::trigger::buy::set_buy_amount::{remove_previous_buy_trigger, remove_requisite_balance};
+use crate::{begin_transaction, commit_transaction};
+use anyhow::bail;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use std::ops::DerefMut;
+
+/**
+ * Reserves the cash and arms a buy trigger in one transaction, replacing (and refunding) any
+ * trigger the user already had on the stock.
+ */
+#[tracing::instrument(skip(pool))]
+pub async fn place_buy_trigger(
+    pool: &PgPool,
+    user_id: &str,
+    stock_symbol: &str,
+    amount_dollars: Decimal,
+    trigger_price: Decimal,
+) -> anyhow::Result<AccountTransaction> {
+    if amount_dollars <= Decimal::ZERO {
+        bail!("buy amount must be positive, was {amount_dollars}");
+    }
+    if trigger_price <= Decimal::ZERO {
+        bail!("trigger price must be positive, was {trigger_price}");
+    }
+
+    let mut transaction = begin_transaction(pool).await?;
+
+    let acc_trans = remove_previous_buy_trigger(user_id, stock_symbol, &mut transaction).await?;
+
+    let acc_trans =
+        acc_trans + remove_requisite_balance(user_id, amount_dollars, &mut transaction).await?;
+
+    sqlx::query!(
+        "
+        INSERT INTO buy_trigger (owner_id, stock_symbol, amount_dollars, trigger_price)
+        VALUES ($1, $2, $3, $4)
+        ",
+        user_id,
+        stock_symbol,
+        amount_dollars,
+        trigger_price,
+    )
+    .execute(transaction.deref_mut())
+    .await?;
+
+    commit_transaction(transaction).await?;
+
+    Ok(acc_trans)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::add::add;
+    use pretty_assertions::assert_eq;
+    use rust_decimal_macros::dec;
+
+    #[sqlx::test]
+    async fn test_place_buy_trigger(pool: PgPool) -> anyhow::Result<()> {
+        let _log = add(&pool, "marcus", dec!(100)).await?;
+
+        let log = place_buy_trigger(&pool, "marcus", "AAPL", dec!(60), dec!(10)).await?;
+        assert_eq!(log, AccountTransaction(dec!(-60)));
+
+        // replacing refunds the first trigger, so the whole balance is available again
+        let log = place_buy_trigger(&pool, "marcus", "AAPL", dec!(100), dec!(9)).await?;
+        assert_eq!(log, AccountTransaction(dec!(60) - dec!(100)));
+
+        let record = sqlx::query!(
+            "SELECT amount_dollars, trigger_price FROM buy_trigger WHERE owner_id = $1 AND stock_symbol = $2",
+            "marcus",
+            "AAPL"
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(record.amount_dollars, dec!(100));
+        assert_eq!(record.trigger_price, Some(dec!(9)));
+
+        let balance = sqlx::query_scalar!("SELECT balance FROM trader WHERE user_id = 'marcus'")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(balance, dec!(0));
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_place_buy_trigger_insufficient_funds(pool: PgPool) -> anyhow::Result<()> {
+        let _log = add(&pool, "marcus", dec!(50)).await?;
+        let _log = place_buy_trigger(&pool, "marcus", "AAPL", dec!(20), dec!(10)).await?;
+
+        let place = place_buy_trigger(&pool, "marcus", "AAPL", dec!(80), dec!(10)).await;
+        assert!(place.is_err(), "expected error but was {place:?}");
+
+        // the failed placement leaves the old trigger armed
+        let record = sqlx::query!(
+            "SELECT amount_dollars, trigger_price FROM buy_trigger WHERE owner_id = $1 AND stock_symbol = $2",
+            "marcus",
+            "AAPL"
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(record.amount_dollars, dec!(20));
+        assert_eq!(record.trigger_price, Some(dec!(10)));
+
+        Ok(())
+    }
+}
@@ -1,6 +1,8 @@
+use crate::ledger::Account;
 use crate::log::AccountTransaction;
-use crate::{begin_transaction, commit_transaction};
+use crate::{begin_transaction, commit_transaction, ledger};
 use anyhow::bail;
+use rust_decimal::Decimal;
 use std::ops::DerefMut;
 
 use sqlx::{PgPool, Postgres, Transaction};
@@ -10,7 +12,7 @@ pub async fn set_buy_amount(
     pool: &PgPool,
     user_id: &str,
     stock_symbol: &str,
-    amount_dollars: f64,
+    amount_dollars: Decimal,
 ) -> anyhow::Result<AccountTransaction> {
     let mut transaction = begin_transaction(pool).await?;
 
@@ -31,7 +33,7 @@ async fn create_buy_trigger(
     transaction: &mut Transaction<'static, Postgres>,
     user_id: &str,
     stock_symbol: &str,
-    amount_dollars: f64,
+    amount_dollars: Decimal,
 ) -> anyhow::Result<()> {
     sqlx::query!(
         "INSERT INTO buy_trigger (owner_id, stock_symbol, amount_dollars) VALUES ($1, $2, $3)",
@@ -45,14 +47,14 @@ async fn create_buy_trigger(
     Ok(())
 }
 
-async fn remove_previous_buy_trigger(
+pub(super) async fn remove_previous_buy_trigger(
     user_id: &str,
     stock_symbol: &str,
     transaction: &mut Transaction<'static, Postgres>,
 ) -> anyhow::Result<AccountTransaction> {
-    let transaction = transaction.deref_mut();
+    let connection = transaction.deref_mut();
     Ok(match sqlx::query!("DELETE FROM buy_trigger WHERE owner_id = $1 AND stock_symbol = $2 RETURNING amount_dollars", user_id, stock_symbol)
-        .fetch_optional(&mut *transaction)
+        .fetch_optional(&mut *connection)
         .await? {
         Some(record) => {
             sqlx::query!(
@@ -60,19 +62,21 @@ async fn remove_previous_buy_trigger(
                 record.amount_dollars,
                 user_id
             )
-                .execute(&mut *transaction)
+                .execute(&mut *connection)
                 .await?;
 
+            ledger::transfer(transaction, user_id, Account::ReservedCash, Account::Cash, record.amount_dollars).await?;
+
             AccountTransaction(record.amount_dollars)
         }
-        None => AccountTransaction(0.0)
+        None => AccountTransaction(Decimal::ZERO)
     })
 }
 
 #[tracing::instrument(skip_all)]
-async fn remove_requisite_balance(
+pub(super) async fn remove_requisite_balance(
     user_id: &str,
-    amount_dollars: f64,
+    amount_dollars: Decimal,
     transaction: &mut Transaction<'static, Postgres>,
 ) -> anyhow::Result<AccountTransaction> {
     let result = sqlx::query!(
@@ -84,6 +88,15 @@ async fn remove_requisite_balance(
         .await?;
 
     if result.balance.is_sign_positive() {
+        ledger::transfer(
+            transaction,
+            user_id,
+            Account::Cash,
+            Account::ReservedCash,
+            amount_dollars,
+        )
+        .await?;
+
         Ok(AccountTransaction(-amount_dollars))
     } else {
         bail!("Insufficient funds")
@@ -94,10 +107,11 @@ async fn remove_requisite_balance(
 mod tests {
     use super::*;
     use crate::add::add;
+    use rust_decimal_macros::dec;
 
     #[sqlx::test]
     async fn test_set_buy_amount_no_user(pool: PgPool) -> anyhow::Result<()> {
-        let set = set_buy_amount(&pool, "marcus", "AAPL", 100_f64).await;
+        let set = set_buy_amount(&pool, "marcus", "AAPL", dec!(100)).await;
         assert!(set.is_err(), "expected error but was {set:?}");
 
         Ok(())
@@ -105,9 +119,9 @@ mod tests {
 
     #[sqlx::test]
     async fn test_set_buy_amount_sufficient_funds(pool: PgPool) -> anyhow::Result<()> {
-        let _log = add(&pool, "marcus", 200_f64).await?;
+        let _log = add(&pool, "marcus", dec!(200)).await?;
 
-        let set = set_buy_amount(&pool, "marcus", "AAPL", 100_f64).await;
+        let set = set_buy_amount(&pool, "marcus", "AAPL", dec!(100)).await;
         assert!(set.is_ok(), "expected ok but was {set:?}");
 
         let buy_trigger = sqlx::query!(
@@ -118,25 +132,25 @@ mod tests {
         .fetch_one(&pool)
         .await?;
 
-        assert_eq!(buy_trigger.amount_dollars, 100_f64);
+        assert_eq!(buy_trigger.amount_dollars, dec!(100));
 
         let trader = sqlx::query!("SELECT * FROM trader WHERE user_id = $1", "marcus")
             .fetch_one(&pool)
             .await?;
 
-        assert_eq!(trader.balance, 100_f64);
+        assert_eq!(trader.balance, dec!(100));
 
         Ok(())
     }
 
     #[sqlx::test]
     async fn test_set_buy_with_already_existing_buy(pool: PgPool) -> anyhow::Result<()> {
-        let _log = add(&pool, "marcus", 200_f64).await?;
+        let _log = add(&pool, "marcus", dec!(200)).await?;
 
-        let set = set_buy_amount(&pool, "marcus", "AAPL", 100_f64).await;
+        let set = set_buy_amount(&pool, "marcus", "AAPL", dec!(100)).await;
         assert!(set.is_ok(), "expected ok but was {set:?}");
 
-        let set = set_buy_amount(&pool, "marcus", "AAPL", 50_f64).await;
+        let set = set_buy_amount(&pool, "marcus", "AAPL", dec!(50)).await;
         assert!(set.is_ok(), "expected ok but was {set:?}");
 
         let buy_trigger = sqlx::query!(
@@ -147,13 +161,13 @@ mod tests {
         .fetch_one(&pool)
         .await?;
 
-        assert_eq!(buy_trigger.amount_dollars, 50_f64);
+        assert_eq!(buy_trigger.amount_dollars, dec!(50));
 
         let trader = sqlx::query!("SELECT * FROM trader WHERE user_id = $1", "marcus")
             .fetch_one(&pool)
             .await?;
 
-        assert_eq!(trader.balance, 150_f64);
+        assert_eq!(trader.balance, dec!(150));
 
         Ok(())
     }
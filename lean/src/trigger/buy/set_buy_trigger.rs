@@ -1,4 +1,5 @@
 use anyhow::bail;
+use rust_decimal::Decimal;
 use sqlx::PgPool;
 
 #[tracing::instrument(skip(pool))]
@@ -6,7 +7,7 @@ pub async fn set_buy_trigger(
     pool: &PgPool,
     user_id: &str,
     stock_symbol: &str,
-    trigger_price: f64,
+    trigger_price: Decimal,
 ) -> anyhow::Result<()> {
     let result = sqlx::query!(
         "UPDATE buy_trigger SET trigger_price = $3 WHERE owner_id = $1 AND stock_symbol = $2",
@@ -29,10 +30,11 @@ mod tests {
     use super::*;
     use crate::add::add;
     use crate::trigger::set_buy_amount;
+    use rust_decimal_macros::dec;
 
     #[sqlx::test]
     async fn test_set_buy_trigger_with_no_set_buy(pool: PgPool) -> anyhow::Result<()> {
-        let set = set_buy_trigger(&pool, "marcus", "AAPL", 100_f64).await;
+        let set = set_buy_trigger(&pool, "marcus", "AAPL", dec!(100)).await;
         assert!(set.is_err(), "expected error but was {set:?}");
 
         Ok(())
@@ -40,9 +42,9 @@ mod tests {
 
     #[sqlx::test]
     async fn test_set_buy_trigger_with_set_buy(pool: PgPool) -> anyhow::Result<()> {
-        let _log = add(&pool, "marcus", 1000_f64).await?;
-        let _log = set_buy_amount(&pool, "marcus", "AAPL", 100_f64).await?;
-        let set = set_buy_trigger(&pool, "marcus", "AAPL", 100_f64).await;
+        let _log = add(&pool, "marcus", dec!(1000)).await?;
+        let _log = set_buy_amount(&pool, "marcus", "AAPL", dec!(100)).await?;
+        let set = set_buy_trigger(&pool, "marcus", "AAPL", dec!(100)).await;
         assert!(set.is_ok(), "expected error but was {set:?}");
 
         let balance = sqlx::query!("SELECT balance FROM trader WHERE user_id = $1", "marcus")
@@ -50,7 +52,7 @@ mod tests {
             .await?
             .balance;
 
-        assert_eq!(balance, 900_f64);
+        assert_eq!(balance, dec!(900));
 
         let buy_trigger = sqlx::query!(
             "SELECT * FROM buy_trigger WHERE owner_id = $1 AND stock_symbol = $2",
@@ -60,8 +62,8 @@ mod tests {
         .fetch_one(&pool)
         .await?;
 
-        assert_eq!(buy_trigger.amount_dollars, 100_f64);
-        assert_eq!(buy_trigger.trigger_price, Some(100_f64));
+        assert_eq!(buy_trigger.amount_dollars, dec!(100));
+        assert_eq!(buy_trigger.trigger_price, Some(dec!(100)));
 
         Ok(())
     }
@@ -0,0 +1,155 @@
+use crate::ledger::Account;
+use crate::log::AccountTransaction;
+use crate::{begin_transaction, commit_transaction, ledger};
+use anyhow::{bail, Context};
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use std::ops::DerefMut;
+
+/**
+ * Amends the amount and/or price of a buy trigger in place. A larger amount reserves the
+ * difference from the user's balance, a smaller one refunds it. `None` leaves a field as it was.
+ */
+#[tracing::instrument(skip(pool))]
+pub async fn update_buy_trigger(
+    pool: &PgPool,
+    user_id: &str,
+    stock_symbol: &str,
+    amount_dollars: Option<Decimal>,
+    trigger_price: Option<Decimal>,
+) -> anyhow::Result<AccountTransaction> {
+    if amount_dollars.is_some_and(|it| it <= Decimal::ZERO) {
+        bail!("buy amount must be positive, was {amount_dollars:?}");
+    }
+    if trigger_price.is_some_and(|it| it <= Decimal::ZERO) {
+        bail!("trigger price must be positive, was {trigger_price:?}");
+    }
+
+    let mut transaction = begin_transaction(pool).await?;
+
+    let previous = sqlx::query_scalar!(
+        "SELECT amount_dollars FROM buy_trigger WHERE owner_id = $1 AND stock_symbol = $2 FOR UPDATE",
+        user_id,
+        stock_symbol
+    )
+    .fetch_optional(transaction.deref_mut())
+    .await?
+    .context("No buy trigger set")?;
+
+    let amount_dollars = amount_dollars.unwrap_or(previous);
+    let difference = amount_dollars - previous;
+
+    if difference > Decimal::ZERO {
+        let result = sqlx::query!(
+            "UPDATE trader SET balance = balance - $1 WHERE user_id = $2 AND balance >= $1",
+            difference,
+            user_id,
+        )
+        .execute(transaction.deref_mut())
+        .await?;
+
+        if result.rows_affected() == 0 {
+            bail!("Insufficient funds");
+        }
+
+        ledger::transfer(
+            &mut transaction,
+            user_id,
+            Account::Cash,
+            Account::ReservedCash,
+            difference,
+        )
+        .await?;
+    } else if difference < Decimal::ZERO {
+        sqlx::query!(
+            "UPDATE trader SET balance = balance + $1 WHERE user_id = $2",
+            -difference,
+            user_id,
+        )
+        .execute(transaction.deref_mut())
+        .await?;
+
+        ledger::transfer(
+            &mut transaction,
+            user_id,
+            Account::ReservedCash,
+            Account::Cash,
+            -difference,
+        )
+        .await?;
+    }
+
+    sqlx::query!(
+        "
+        UPDATE buy_trigger SET amount_dollars = $3, trigger_price = coalesce($4, trigger_price)
+        WHERE owner_id = $1 AND stock_symbol = $2
+        ",
+        user_id,
+        stock_symbol,
+        amount_dollars,
+        trigger_price,
+    )
+    .execute(transaction.deref_mut())
+    .await?;
+
+    commit_transaction(transaction).await?;
+
+    Ok(AccountTransaction(-difference))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::add::add;
+    use crate::trigger::buy::place_buy_trigger;
+    use pretty_assertions::assert_eq;
+    use rust_decimal_macros::dec;
+
+    async fn trigger(pool: &PgPool) -> anyhow::Result<(Decimal, Option<Decimal>, Decimal)> {
+        let record = sqlx::query!(
+            "
+            SELECT amount_dollars, trigger_price, balance FROM buy_trigger
+            JOIN trader ON trader.user_id = buy_trigger.owner_id
+            WHERE owner_id = 'marcus' AND stock_symbol = 'AAPL'
+            "
+        )
+        .fetch_one(pool)
+        .await?;
+        Ok((record.amount_dollars, record.trigger_price, record.balance))
+    }
+
+    #[sqlx::test]
+    async fn test_update_buy_trigger(pool: PgPool) -> anyhow::Result<()> {
+        let _log = add(&pool, "marcus", dec!(100)).await?;
+        let _log = place_buy_trigger(&pool, "marcus", "AAPL", dec!(40), dec!(10)).await?;
+
+        let log = update_buy_trigger(&pool, "marcus", "AAPL", None, Some(dec!(8))).await?;
+        assert_eq!(log, AccountTransaction(dec!(0)));
+        assert_eq!(trigger(&pool).await?, (dec!(40), Some(dec!(8)), dec!(60)));
+
+        let log = update_buy_trigger(&pool, "marcus", "AAPL", Some(dec!(90)), None).await?;
+        assert_eq!(log, AccountTransaction(dec!(-50)));
+        assert_eq!(trigger(&pool).await?, (dec!(90), Some(dec!(8)), dec!(10)));
+
+        let log =
+            update_buy_trigger(&pool, "marcus", "AAPL", Some(dec!(30)), Some(dec!(7))).await?;
+        assert_eq!(log, AccountTransaction(dec!(60)));
+        assert_eq!(trigger(&pool).await?, (dec!(30), Some(dec!(7)), dec!(70)));
+
+        let broke = update_buy_trigger(&pool, "marcus", "AAPL", Some(dec!(101)), None).await;
+        assert!(broke.is_err(), "expected error but was {broke:?}");
+        assert_eq!(trigger(&pool).await?, (dec!(30), Some(dec!(7)), dec!(70)));
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_update_buy_trigger_not_set(pool: PgPool) -> anyhow::Result<()> {
+        let _log = add(&pool, "marcus", dec!(100)).await?;
+
+        let update = update_buy_trigger(&pool, "marcus", "AAPL", None, Some(dec!(8))).await;
+        assert!(update.is_err(), "expected error but was {update:?}");
+
+        Ok(())
+    }
+}
@@ -1,9 +1,15 @@
 pub use cancel_set_sell::cancel_set_sell;
+pub use place_sell_trigger::place_sell_trigger;
 pub use set_sell_amount::set_sell_amount;
 pub use set_sell_trigger::set_sell_trigger;
+pub use update_sell_trigger::update_sell_trigger;
 
 mod set_sell_amount;
 
 mod set_sell_trigger;
 
 mod cancel_set_sell;
+
+mod place_sell_trigger;
+
+mod update_sell_trigger;
@@ -1,4 +1,7 @@
-use sqlx::PgPool;
+use crate::{begin_transaction, commit_transaction, holdings};
+use rust_decimal::Decimal;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::ops::DerefMut;
 
 #[tracing::instrument(skip(pool))]
 pub async fn cancel_set_sell(
@@ -6,39 +9,36 @@ pub async fn cancel_set_sell(
     user_id: &str,
     stock_symbol: &str,
 ) -> anyhow::Result<()> {
-    let record = delete_sell_trigger(pool, user_id, stock_symbol).await?;
+    let mut transaction = begin_transaction(pool).await?;
 
-    update_stock(pool, user_id, stock_symbol, record).await?;
+    let record = delete_sell_trigger(&mut transaction, user_id, stock_symbol).await?;
+
+    update_stock(&mut transaction, user_id, stock_symbol, record).await?;
+
+    commit_transaction(transaction).await?;
 
     Ok(())
 }
 
 #[tracing::instrument(skip_all)]
 async fn update_stock(
-    pool: &PgPool,
+    transaction: &mut Transaction<'static, Postgres>,
     user_id: &str,
     stock_symbol: &str,
     record: Record,
 ) -> anyhow::Result<()> {
-    sqlx::query!(
-        "UPDATE stock SET amount = amount + $1 WHERE owner_id = $2 AND stock_symbol = $3",
-        record.amount_stock,
-        user_id,
-        stock_symbol
-    )
-    .execute(pool)
-    .await?;
+    holdings::release(transaction, user_id, stock_symbol, record.amount_stock).await?;
 
     Ok(())
 }
 
 struct Record {
-    amount_stock: f64,
+    amount_stock: Decimal,
 }
 
 #[tracing::instrument(skip_all)]
 async fn delete_sell_trigger(
-    pool: &PgPool,
+    transaction: &mut Transaction<'static, Postgres>,
     user_id: &str,
     stock_symbol: &str,
 ) -> anyhow::Result<Record> {
@@ -48,7 +48,7 @@ async fn delete_sell_trigger(
         user_id,
         stock_symbol
     )
-        .fetch_optional(pool)
+        .fetch_optional(transaction.deref_mut())
         .await?
     else {
         return Err(anyhow::anyhow!(
@@ -64,7 +64,9 @@ mod tests {
     use super::*;
     use crate::add::add;
     use crate::buy::{commit_buy, init_buy};
+    use crate::orders::OrderExpiry;
     use crate::trigger::{set_sell_amount, set_sell_trigger};
+    use rust_decimal_macros::dec;
 
     #[sqlx::test]
     async fn test_cancel_set_sell_no_set_sell(pool: PgPool) -> anyhow::Result<()> {
@@ -76,11 +78,11 @@ mod tests {
 
     #[sqlx::test]
     async fn test_cancel_set_sell_with_set_sell(pool: PgPool) -> anyhow::Result<()> {
-        let _log = add(&pool, "marcus", 100.0).await?;
-        let _log = init_buy(&pool, "marcus", "TEST", 50_f64, 100.0).await?;
-        commit_buy(&pool, "marcus").await?;
-        set_sell_amount(&pool, "marcus", "TEST", 1.0).await?;
-        set_sell_trigger(&pool, "marcus", "TEST", 40_f64).await?;
+        let _log = add(&pool, "marcus", dec!(100)).await?;
+        let _log = init_buy(&pool, "marcus", "TEST", dec!(50), dec!(100)).await?;
+        commit_buy(&pool, "marcus", None, OrderExpiry::default().buy).await?;
+        set_sell_amount(&pool, "marcus", "TEST", dec!(1)).await?;
+        set_sell_trigger(&pool, "marcus", "TEST", dec!(40)).await?;
 
         let result = cancel_set_sell(&pool, "marcus", "TEST").await;
         assert!(result.is_ok(), "expected error but was {result:?}");
@@ -93,7 +95,8 @@ mod tests {
         .fetch_one(&pool)
         .await?;
 
-        assert_eq!(stock.amount, 2_f64);
+        assert_eq!(stock.amount, dec!(2));
+        assert_eq!(stock.reserved, dec!(0));
 
         let trigger = sqlx::query!(
             "SELECT * FROM sell_trigger WHERE owner_id = $1 AND stock_symbol = $2",
@@ -0,0 +1,92 @@
+use crate::trigger::sell::set_sell_amount::remove_prev_sell_trigger;
+use crate::{begin_transaction, commit_transaction, holdings};
+use anyhow::bail;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use std::ops::DerefMut;
+
+/**
+ * Reserves the shares and arms a sell trigger in one transaction, replacing (and releasing) any
+ * trigger the user already had on the stock.
+ */
+#[tracing::instrument(skip(pool))]
+pub async fn place_sell_trigger(
+    pool: &PgPool,
+    user_id: &str,
+    stock_symbol: &str,
+    amount_stock: Decimal,
+    trigger_price: Decimal,
+) -> anyhow::Result<()> {
+    if amount_stock <= Decimal::ZERO {
+        bail!("sell amount must be positive, was {amount_stock}");
+    }
+    if trigger_price <= Decimal::ZERO {
+        bail!("trigger price must be positive, was {trigger_price}");
+    }
+
+    let mut transaction = begin_transaction(pool).await?;
+
+    remove_prev_sell_trigger(user_id, stock_symbol, &mut transaction).await?;
+
+    holdings::reserve(&mut transaction, user_id, stock_symbol, amount_stock).await?;
+
+    sqlx::query!(
+        "
+        INSERT INTO sell_trigger (owner_id, stock_symbol, amount_stock, trigger_price)
+        VALUES ($1, $2, $3, $4)
+        ",
+        user_id,
+        stock_symbol,
+        amount_stock,
+        trigger_price,
+    )
+    .execute(transaction.deref_mut())
+    .await?;
+
+    commit_transaction(transaction).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::add::add;
+    use crate::buy::{commit_buy, init_buy};
+    use crate::orders::OrderExpiry;
+    use pretty_assertions::assert_eq;
+    use rust_decimal_macros::dec;
+
+    #[sqlx::test]
+    async fn test_place_sell_trigger(pool: PgPool) -> anyhow::Result<()> {
+        let _log = add(&pool, "marcus", dec!(100)).await?;
+        let _log = init_buy(&pool, "marcus", "AAPL", dec!(25), dec!(100)).await?;
+        commit_buy(&pool, "marcus", None, OrderExpiry::default().buy).await?;
+
+        place_sell_trigger(&pool, "marcus", "AAPL", dec!(3), dec!(30)).await?;
+        // replacing releases the first trigger's shares before reserving again
+        place_sell_trigger(&pool, "marcus", "AAPL", dec!(4), dec!(35)).await?;
+
+        let record = sqlx::query!(
+            "SELECT amount_stock, trigger_price FROM sell_trigger WHERE owner_id = $1 AND stock_symbol = $2",
+            "marcus",
+            "AAPL"
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(record.amount_stock, dec!(4));
+        assert_eq!(record.trigger_price, Some(dec!(35)));
+
+        let place = place_sell_trigger(&pool, "marcus", "AAPL", dec!(5), dec!(35)).await;
+        assert!(place.is_err(), "expected error but was {place:?}");
+
+        let reserved = sqlx::query_scalar!(
+            "SELECT reserved FROM stock WHERE owner_id = 'marcus' AND stock_symbol = 'AAPL'"
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(reserved, dec!(4));
+
+        Ok(())
+    }
+}
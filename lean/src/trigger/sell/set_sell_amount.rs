@@ -1,6 +1,5 @@
-use crate::{begin_transaction, commit_transaction};
-use anyhow::bail;
-use sqlx::postgres::PgQueryResult;
+use crate::{begin_transaction, commit_transaction, holdings};
+use rust_decimal::Decimal;
 use sqlx::{PgPool, Postgres, Transaction};
 use std::ops::DerefMut;
 
@@ -9,17 +8,13 @@ pub async fn set_sell_amount(
     pool: &PgPool,
     user_id: &str,
     stock_symbol: &str,
-    amount_stock: f64,
+    amount_stock: Decimal,
 ) -> anyhow::Result<()> {
     let mut transaction = begin_transaction(pool).await?;
 
     remove_prev_sell_trigger(user_id, stock_symbol, &mut transaction).await?;
 
-    let result = remove_stock(user_id, stock_symbol, amount_stock, &mut transaction).await?;
-
-    if result.rows_affected() == 0 {
-        bail!("Insufficient stock");
-    }
+    holdings::reserve(&mut transaction, user_id, stock_symbol, amount_stock).await?;
 
     insert_sell_trigger(user_id, stock_symbol, amount_stock, &mut transaction).await?;
 
@@ -32,7 +27,7 @@ pub async fn set_sell_amount(
 async fn insert_sell_trigger(
     user_id: &str,
     stock_symbol: &str,
-    amount_stock: f64,
+    amount_stock: Decimal,
     transaction: &mut Transaction<'static, Postgres>,
 ) -> anyhow::Result<()> {
     sqlx::query!(
@@ -48,44 +43,20 @@ async fn insert_sell_trigger(
 }
 
 #[tracing::instrument(skip_all)]
-async fn remove_stock(
-    user_id: &str,
-    stock_symbol: &str,
-    amount_stock: f64,
-    transaction: &mut Transaction<'static, Postgres>,
-) -> anyhow::Result<PgQueryResult> {
-    let result = sqlx::query!(
-        "UPDATE stock SET amount = amount - $1 WHERE owner_id = $2 AND stock_symbol = $3 AND amount >= $1",
-        amount_stock,
-        user_id,
-        stock_symbol
-    ).execute(transaction.deref_mut()).await?;
-    Ok(result)
-}
-
-#[tracing::instrument(skip_all)]
-async fn remove_prev_sell_trigger(
+pub(super) async fn remove_prev_sell_trigger(
     user_id: &str,
     stock_symbol: &str,
     transaction: &mut Transaction<'static, Postgres>,
 ) -> anyhow::Result<()> {
-    let transaction = transaction.deref_mut();
     if let Some(record) = sqlx::query!(
         "DELETE FROM sell_trigger WHERE owner_id = $1 AND stock_symbol = $2 RETURNING amount_stock",
         user_id,
         stock_symbol
     )
-    .fetch_optional(&mut *transaction)
+    .fetch_optional(transaction.deref_mut())
     .await?
     {
-        sqlx::query!(
-            "UPDATE stock SET amount = amount + $1 WHERE owner_id = $2 AND stock_symbol = $3",
-            record.amount_stock,
-            user_id,
-            stock_symbol
-        )
-        .execute(&mut *transaction)
-        .await?;
+        holdings::release(transaction, user_id, stock_symbol, record.amount_stock).await?;
     }
 
     Ok(())
@@ -96,12 +67,14 @@ mod tests {
     use super::*;
     use crate::add::add;
     use crate::buy::{commit_buy, init_buy};
+    use crate::orders::OrderExpiry;
     use crate::trigger::sell::set_sell_amount;
     use pretty_assertions::assert_eq;
+    use rust_decimal_macros::dec;
 
     #[sqlx::test]
     async fn test_set_sell_amount_no_stock(pool: PgPool) -> anyhow::Result<()> {
-        let set = set_sell_amount(&pool, "marcus", "AAPL", 100_f64).await;
+        let set = set_sell_amount(&pool, "marcus", "AAPL", dec!(100)).await;
         assert!(set.is_err(), "expected error but was {set:?}");
 
         Ok(())
@@ -109,9 +82,9 @@ mod tests {
 
     #[sqlx::test]
     async fn test_set_sell_amount_with_stock(pool: PgPool) -> anyhow::Result<()> {
-        let _log = add(&pool, "marcus", 1000_f64).await?;
-        let _log = init_buy(&pool, "marcus", "AAPL", 50_f64, 100_f64).await?;
-        commit_buy(&pool, "marcus").await?;
+        let _log = add(&pool, "marcus", dec!(1000)).await?;
+        let _log = init_buy(&pool, "marcus", "AAPL", dec!(50), dec!(100)).await?;
+        commit_buy(&pool, "marcus", None, OrderExpiry::default().buy).await?;
 
         let stock = sqlx::query!(
             "SELECT amount FROM stock WHERE owner_id = $1 AND stock_symbol = $2",
@@ -121,21 +94,22 @@ mod tests {
         .fetch_one(&pool)
         .await?;
 
-        assert_eq!(stock.amount, 2_f64);
+        assert_eq!(stock.amount, dec!(2));
 
-        let set = set_sell_amount(&pool, "marcus", "AAPL", 1_f64).await;
+        let set = set_sell_amount(&pool, "marcus", "AAPL", dec!(1)).await;
 
         assert!(set.is_ok(), "expected error but was {set:?}");
 
         let stock = sqlx::query!(
-            "SELECT amount FROM stock WHERE owner_id = $1 AND stock_symbol = $2",
+            "SELECT amount, reserved FROM stock WHERE owner_id = $1 AND stock_symbol = $2",
             "marcus",
             "AAPL"
         )
         .fetch_one(&pool)
         .await?;
 
-        assert_eq!(stock.amount, 1_f64);
+        assert_eq!(stock.amount, dec!(2));
+        assert_eq!(stock.reserved, dec!(1));
 
         let sell_trigger = sqlx::query!(
             "SELECT * FROM sell_trigger WHERE owner_id = $1 AND stock_symbol = $2",
@@ -145,7 +119,7 @@ mod tests {
         .fetch_one(&pool)
         .await?;
 
-        assert_eq!(sell_trigger.amount_stock, 1_f64);
+        assert_eq!(sell_trigger.amount_stock, dec!(1));
         assert_eq!(sell_trigger.trigger_price, None);
 
         Ok(())
@@ -153,13 +127,13 @@ mod tests {
 
     #[sqlx::test]
     async fn test_set_sell_amount_with_prev_sell_trigger(pool: PgPool) -> anyhow::Result<()> {
-        let _log = add(&pool, "marcus", 1000_f64).await?;
-        let _log = init_buy(&pool, "marcus", "AAPL", 50_f64, 100_f64).await?;
-        commit_buy(&pool, "marcus").await?;
+        let _log = add(&pool, "marcus", dec!(1000)).await?;
+        let _log = init_buy(&pool, "marcus", "AAPL", dec!(50), dec!(100)).await?;
+        commit_buy(&pool, "marcus", None, OrderExpiry::default().buy).await?;
 
-        set_sell_amount(&pool, "marcus", "AAPL", 2_f64).await?;
+        set_sell_amount(&pool, "marcus", "AAPL", dec!(2)).await?;
 
-        let set_sell_amount = set_sell_amount(&pool, "marcus", "AAPL", 1_f64).await;
+        let set_sell_amount = set_sell_amount(&pool, "marcus", "AAPL", dec!(1)).await;
 
         assert!(
             set_sell_amount.is_ok(),
@@ -174,7 +148,8 @@ mod tests {
         .fetch_one(&pool)
         .await?;
 
-        assert_eq!(stock.amount, 1_f64);
+        assert_eq!(stock.amount, dec!(2));
+        assert_eq!(stock.reserved, dec!(1));
 
         let sell_trigger = sqlx::query!(
             "SELECT * FROM sell_trigger WHERE owner_id = $1 AND stock_symbol = $2",
@@ -184,9 +159,39 @@ mod tests {
         .fetch_one(&pool)
         .await?;
 
-        assert_eq!(sell_trigger.amount_stock, 1_f64);
+        assert_eq!(sell_trigger.amount_stock, dec!(1));
         assert_eq!(sell_trigger.trigger_price, None);
 
         Ok(())
     }
+
+    #[sqlx::test]
+    async fn test_set_sell_amount_shares_reserved_by_sell(pool: PgPool) -> anyhow::Result<()> {
+        let _log = add(&pool, "marcus", dec!(1000)).await?;
+        let _log = init_buy(&pool, "marcus", "AAPL", dec!(50), dec!(100)).await?;
+        commit_buy(&pool, "marcus", None, OrderExpiry::default().buy).await?;
+
+        crate::sell::init_sell(&pool, "marcus", "AAPL", dec!(50), dec!(50)).await?;
+
+        let set = set_sell_amount(&pool, "marcus", "AAPL", dec!(2)).await;
+        assert!(set.is_err(), "expected error but was {set:?}");
+
+        set_sell_amount(&pool, "marcus", "AAPL", dec!(1)).await?;
+
+        let sell = crate::sell::init_sell(&pool, "marcus", "AAPL", dec!(50), dec!(100)).await;
+        assert!(sell.is_err(), "expected error but was {sell:?}");
+
+        let stock = sqlx::query!(
+            "SELECT amount, reserved FROM stock WHERE owner_id = $1 AND stock_symbol = $2",
+            "marcus",
+            "AAPL"
+        )
+        .fetch_one(&pool)
+        .await?;
+
+        assert_eq!(stock.amount, dec!(2));
+        assert_eq!(stock.reserved, dec!(2));
+
+        Ok(())
+    }
 }
@@ -1,4 +1,5 @@
 use anyhow::bail;
+use rust_decimal::Decimal;
 use sqlx::PgPool;
 
 #[tracing::instrument(skip_all)]
@@ -6,7 +7,7 @@ pub async fn set_sell_trigger(
     pool: &PgPool,
     user_id: &str,
     stock_symbol: &str,
-    trigger_price: f64,
+    trigger_price: Decimal,
 ) -> anyhow::Result<()> {
     let result = sqlx::query!(
         "UPDATE sell_trigger SET trigger_price = $1 WHERE owner_id = $2 AND stock_symbol = $3",
@@ -29,15 +30,17 @@ mod tests {
     use super::*;
     use crate::add::add;
     use crate::buy::{commit_buy, init_buy};
+    use crate::orders::OrderExpiry;
     use crate::trigger::set_sell_amount;
+    use rust_decimal_macros::dec;
 
     #[sqlx::test]
     async fn test_set_sell_trigger_no_set_amount(pool: PgPool) -> anyhow::Result<()> {
-        let _log = add(&pool, "marcus", 100.0).await?;
-        let _log = init_buy(&pool, "marcus", "TEST", 50_f64, 100.0).await?;
-        commit_buy(&pool, "marcus").await?;
+        let _log = add(&pool, "marcus", dec!(100)).await?;
+        let _log = init_buy(&pool, "marcus", "TEST", dec!(50), dec!(100)).await?;
+        commit_buy(&pool, "marcus", None, OrderExpiry::default().buy).await?;
 
-        let result = set_sell_trigger(&pool, "marcus", "TEST", 1.0).await;
+        let result = set_sell_trigger(&pool, "marcus", "TEST", dec!(1)).await;
         assert!(result.is_err(), "expected error but was {result:?}");
 
         let record = sqlx::query!(
@@ -55,12 +58,12 @@ mod tests {
 
     #[sqlx::test]
     async fn test_set_sell_trigger_with_set_amount(pool: PgPool) -> anyhow::Result<()> {
-        let _log = add(&pool, "marcus", 100.0).await?;
-        let _log = init_buy(&pool, "marcus", "TEST", 50_f64, 100.0).await?;
-        commit_buy(&pool, "marcus").await?;
+        let _log = add(&pool, "marcus", dec!(100)).await?;
+        let _log = init_buy(&pool, "marcus", "TEST", dec!(50), dec!(100)).await?;
+        commit_buy(&pool, "marcus", None, OrderExpiry::default().buy).await?;
 
-        set_sell_amount(&pool, "marcus", "TEST", 1_f64).await?;
-        set_sell_trigger(&pool, "marcus", "TEST", 60_f64).await?;
+        set_sell_amount(&pool, "marcus", "TEST", dec!(1)).await?;
+        set_sell_trigger(&pool, "marcus", "TEST", dec!(60)).await?;
 
         let record = sqlx::query!(
             "SELECT * FROM sell_trigger WHERE owner_id = $1 AND stock_symbol = $2",
@@ -72,8 +75,8 @@ mod tests {
 
         assert!(record.is_some());
         let record = record.unwrap();
-        assert_eq!(record.trigger_price, Some(60_f64));
-        assert_eq!(record.amount_stock, 1_f64);
+        assert_eq!(record.trigger_price, Some(dec!(60)));
+        assert_eq!(record.amount_stock, dec!(1));
 
         Ok(())
     }
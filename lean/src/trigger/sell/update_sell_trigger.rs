@@ -0,0 +1,112 @@
+use crate::{begin_transaction, commit_transaction, holdings};
+use anyhow::{bail, Context};
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use std::ops::DerefMut;
+
+/**
+ * Amends the amount and/or price of a sell trigger in place. A larger amount reserves the extra
+ * shares, a smaller one releases them. `None` leaves a field as it was.
+ */
+#[tracing::instrument(skip(pool))]
+pub async fn update_sell_trigger(
+    pool: &PgPool,
+    user_id: &str,
+    stock_symbol: &str,
+    amount_stock: Option<Decimal>,
+    trigger_price: Option<Decimal>,
+) -> anyhow::Result<()> {
+    if amount_stock.is_some_and(|it| it <= Decimal::ZERO) {
+        bail!("sell amount must be positive, was {amount_stock:?}");
+    }
+    if trigger_price.is_some_and(|it| it <= Decimal::ZERO) {
+        bail!("trigger price must be positive, was {trigger_price:?}");
+    }
+
+    let mut transaction = begin_transaction(pool).await?;
+
+    let previous = sqlx::query_scalar!(
+        "SELECT amount_stock FROM sell_trigger WHERE owner_id = $1 AND stock_symbol = $2 FOR UPDATE",
+        user_id,
+        stock_symbol
+    )
+    .fetch_optional(transaction.deref_mut())
+    .await?
+    .context("No sell trigger set")?;
+
+    let amount_stock = amount_stock.unwrap_or(previous);
+    let difference = amount_stock - previous;
+
+    if difference > Decimal::ZERO {
+        holdings::reserve(&mut transaction, user_id, stock_symbol, difference).await?;
+    } else if difference < Decimal::ZERO {
+        holdings::release(&mut transaction, user_id, stock_symbol, -difference).await?;
+    }
+
+    sqlx::query!(
+        "
+        UPDATE sell_trigger SET amount_stock = $3, trigger_price = coalesce($4, trigger_price)
+        WHERE owner_id = $1 AND stock_symbol = $2
+        ",
+        user_id,
+        stock_symbol,
+        amount_stock,
+        trigger_price,
+    )
+    .execute(transaction.deref_mut())
+    .await?;
+
+    commit_transaction(transaction).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::add::add;
+    use crate::buy::{commit_buy, init_buy};
+    use crate::orders::OrderExpiry;
+    use crate::trigger::sell::place_sell_trigger;
+    use pretty_assertions::assert_eq;
+    use rust_decimal_macros::dec;
+
+    async fn trigger(pool: &PgPool) -> anyhow::Result<(Decimal, Option<Decimal>, Decimal)> {
+        let record = sqlx::query!(
+            "
+            SELECT amount_stock, trigger_price, reserved FROM sell_trigger
+            JOIN stock USING (owner_id, stock_symbol)
+            WHERE owner_id = 'marcus' AND stock_symbol = 'AAPL'
+            "
+        )
+        .fetch_one(pool)
+        .await?;
+        Ok((record.amount_stock, record.trigger_price, record.reserved))
+    }
+
+    #[sqlx::test]
+    async fn test_update_sell_trigger(pool: PgPool) -> anyhow::Result<()> {
+        let _log = add(&pool, "marcus", dec!(100)).await?;
+        let _log = init_buy(&pool, "marcus", "AAPL", dec!(25), dec!(100)).await?;
+        commit_buy(&pool, "marcus", None, OrderExpiry::default().buy).await?;
+        place_sell_trigger(&pool, "marcus", "AAPL", dec!(2), dec!(30)).await?;
+
+        update_sell_trigger(&pool, "marcus", "AAPL", None, Some(dec!(28))).await?;
+        assert_eq!(trigger(&pool).await?, (dec!(2), Some(dec!(28)), dec!(2)));
+
+        update_sell_trigger(&pool, "marcus", "AAPL", Some(dec!(4)), None).await?;
+        assert_eq!(trigger(&pool).await?, (dec!(4), Some(dec!(28)), dec!(4)));
+
+        update_sell_trigger(&pool, "marcus", "AAPL", Some(dec!(1)), Some(dec!(40))).await?;
+        assert_eq!(trigger(&pool).await?, (dec!(1), Some(dec!(40)), dec!(1)));
+
+        let short = update_sell_trigger(&pool, "marcus", "AAPL", Some(dec!(5)), None).await;
+        assert!(short.is_err(), "expected error but was {short:?}");
+        assert_eq!(trigger(&pool).await?, (dec!(1), Some(dec!(40)), dec!(1)));
+
+        let none = update_sell_trigger(&pool, "marcus", "MSFT", None, Some(dec!(40))).await;
+        assert!(none.is_err(), "expected error but was {none:?}");
+
+        Ok(())
+    }
+}
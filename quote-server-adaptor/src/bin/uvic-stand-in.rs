@@ -0,0 +1,25 @@
+use quote_server_adaptor::stand_in::{serve, StandInConfig};
+use std::env;
+use std::error::Error;
+use tokio::net::TcpListener;
+use tracing_subscriber::EnvFilter;
+
+/**
+ * Serves the UVic quote server's line protocol locally, for running the adaptor without the real
+ * one.
+ */
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .init();
+
+    let addr = env::var("STAND_IN_ADDR").unwrap_or_else(|_| String::from("0.0.0.0:4000"));
+    let listener = TcpListener::bind(&addr).await?;
+
+    tokio::select! {
+        result = serve(listener, StandInConfig::from_env()) => result?,
+        _ = tokio::signal::ctrl_c() => {}
+    }
+    Ok(())
+}
@@ -0,0 +1,379 @@
+use quote_server_adaptor::quote_server::Quote;
+use quote_server_adaptor::{QuoteRequest, QuoteResponse};
+use rand::distributions::Alphanumeric;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashMap;
+use std::env;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tonic::{async_trait, Request, Response, Status};
+use tracing::{info, instrument};
+
+/**
+ * Simulates the UVic quote server with a price per symbol that evolves between quotes, rather than
+ * a new random price every time. Log prices follow a geometric Brownian motion, optionally pulled
+ * back towards the symbol's starting price, with scripted jumps on top.
+ */
+pub struct FakeQuoteServer {
+    model: MarketModel,
+    /// the market's start, for wall clock time steps
+    started: Instant,
+    symbols: Mutex<HashMap<String, Symbol>>,
+}
+
+/**
+ * Configured from `FAKE_*` environment variables. Rates are per minute of simulated time.
+ */
+#[derive(Debug, Clone)]
+struct MarketModel {
+    /// seeds every symbol's random walk, so a run with the same seed, time step and requests
+    /// quotes the same prices
+    seed: u64,
+    /// expected log return per minute
+    drift: f64,
+    /// standard deviation of the log return over one minute
+    volatility: f64,
+    /// how strongly the log price is pulled back towards its start, per minute. 0 for pure GBM
+    mean_reversion: f64,
+    /// every symbol starts here, or at a random price in 50..300 if `None`
+    initial_price: Option<f64>,
+    /// advance each symbol's clock by this much per quote instead of by wall clock time
+    time_step: Option<Duration>,
+    jumps: HashMap<String, Vec<Jump>>,
+}
+
+/// a one off relative price change once the symbol's clock passes `at`
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Jump {
+    at: Duration,
+    /// 0.1 is a 10% rise, -0.2 a 20% fall
+    change: f64,
+}
+
+struct Symbol {
+    price: f64,
+    initial_price: f64,
+    /// simulated time since the symbol's first quote
+    clock: Duration,
+    /// the wall clock time `clock` was last advanced to
+    last_quoted: Duration,
+    rng: StdRng,
+    /// the jumps not yet applied, latest first
+    pending_jumps: Vec<Jump>,
+}
+
+impl FakeQuoteServer {
+    pub fn from_env() -> Self {
+        let model = MarketModel::from_env();
+        info!("simulating quotes with {model:?}");
+        Self {
+            model,
+            started: Instant::now(),
+            symbols: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn next_price(&self, stock_symbol: &str) -> f64 {
+        let now = self.started.elapsed();
+        let mut symbols = self.symbols.lock().expect("symbols lock poisoned");
+        let symbol = symbols
+            .entry(stock_symbol.to_string())
+            .or_insert_with(|| self.model.new_symbol(stock_symbol, now));
+
+        let step = match self.model.time_step {
+            Some(step) => step,
+            None => now.saturating_sub(symbol.last_quoted),
+        };
+        symbol.last_quoted = now;
+        self.model.advance(symbol, step);
+
+        // the UVic quote server quotes in cents
+        (symbol.price * 100.0).round().max(1.0) / 100.0
+    }
+}
+
+impl MarketModel {
+    fn from_env() -> Self {
+        Self {
+            seed: env_or("FAKE_SEED", rand::thread_rng().gen()),
+            drift: env_or("FAKE_DRIFT", 0.0),
+            volatility: env_or("FAKE_VOLATILITY", 0.01),
+            mean_reversion: env_or("FAKE_MEAN_REVERSION", 0.0),
+            initial_price: env::var("FAKE_INITIAL_PRICE")
+                .ok()
+                .map(|it| it.parse().expect("failed to parse FAKE_INITIAL_PRICE")),
+            time_step: env::var("FAKE_TIME_STEP_SECONDS")
+                .ok()
+                .map(|it| it.parse().expect("failed to parse FAKE_TIME_STEP_SECONDS"))
+                .map(Duration::from_secs_f64),
+            jumps: env::var("FAKE_JUMPS")
+                .map(|it| parse_jumps(&it).expect("failed to parse FAKE_JUMPS"))
+                .unwrap_or_default(),
+        }
+    }
+
+    fn new_symbol(&self, stock_symbol: &str, now: Duration) -> Symbol {
+        // each symbol gets its own stream so the prices of one don't depend on how often the
+        // others are quoted
+        let mut rng = StdRng::seed_from_u64(self.seed ^ fnv1a(stock_symbol));
+        let initial_price = self
+            .initial_price
+            .unwrap_or_else(|| rng.gen_range(50_f64..300_f64));
+
+        let mut pending_jumps = self.jumps.get(stock_symbol).cloned().unwrap_or_default();
+        pending_jumps.sort_by_key(|it| std::cmp::Reverse(it.at));
+
+        Symbol {
+            price: initial_price,
+            initial_price,
+            clock: Duration::ZERO,
+            last_quoted: now,
+            rng,
+            pending_jumps,
+        }
+    }
+
+    /// moves the symbol's price `step` of simulated time forward
+    fn advance(&self, symbol: &mut Symbol, step: Duration) {
+        let minutes = step.as_secs_f64() / 60.0;
+        if minutes > 0.0 {
+            let log_price = symbol.price.ln();
+            let pull = self.mean_reversion * (symbol.initial_price.ln() - log_price);
+            let shock = self.volatility * minutes.sqrt() * standard_normal(&mut symbol.rng);
+            let log_return =
+                (self.drift - self.volatility * self.volatility / 2.0 + pull) * minutes + shock;
+            symbol.price = (log_price + log_return).exp();
+        }
+
+        symbol.clock += step;
+        while let Some(jump) = symbol.pending_jumps.last() {
+            if jump.at > symbol.clock {
+                break;
+            }
+            symbol.price *= 1.0 + jump.change;
+            symbol.pending_jumps.pop();
+        }
+    }
+}
+
+#[async_trait]
+impl Quote for FakeQuoteServer {
+    #[instrument(skip_all)]
+    async fn quote(
+        &self,
+        request: Request<QuoteRequest>,
+    ) -> Result<Response<QuoteResponse>, Status> {
+        let QuoteRequest {
+            user_id,
+            stock_symbol,
+            ..
+        } = request.into_inner();
+        let quote = self.next_price(&stock_symbol);
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time went backwards")
+            .as_millis();
+
+        let timestamp = u64::try_from(timestamp).map_err(|e| {
+            Status::internal(format!("failed to convert timestamp to 64 bits: {e}"))
+        })?;
+
+        let crypto_key = rand::thread_rng()
+            .sample_iter(Alphanumeric)
+            .take(57)
+            .map(char::from)
+            .collect::<String>();
+
+        Ok(Response::new(QuoteResponse {
+            quote,
+            sym: stock_symbol,
+            user_id,
+            timestamp,
+            crypto_key,
+        }))
+    }
+}
+
+fn env_or<T: FromStr>(name: &str, default: T) -> T {
+    match env::var(name) {
+        Ok(value) => value
+            .parse()
+            .unwrap_or_else(|_| panic!("failed to parse {name}")),
+        Err(_) => default,
+    }
+}
+
+/**
+ * Parses comma separated `SYMBOL:SECONDS:PERCENT` jumps, e.g. `ABC:60:-20,XYZ:90:5` drops ABC by
+ * 20% a minute after its first quote and raises XYZ by 5% a minute and a half after its own.
+ */
+fn parse_jumps(value: &str) -> Result<HashMap<String, Vec<Jump>>, String> {
+    let mut jumps = HashMap::<String, Vec<Jump>>::new();
+    for jump in value.split(',').filter(|it| !it.trim().is_empty()) {
+        let [symbol, seconds, percent] = jump.trim().split(':').collect::<Vec<_>>()[..] else {
+            return Err(format!("expected SYMBOL:SECONDS:PERCENT, was \"{jump}\""));
+        };
+        let seconds = seconds
+            .parse::<f64>()
+            .map_err(|e| format!("invalid seconds in \"{jump}\": {e}"))?;
+        let percent = percent
+            .parse::<f64>()
+            .map_err(|e| format!("invalid percent in \"{jump}\": {e}"))?;
+        if seconds < 0.0 || percent <= -100.0 {
+            return Err(format!("\"{jump}\" is out of range"));
+        }
+        jumps.entry(symbol.to_string()).or_default().push(Jump {
+            at: Duration::from_secs_f64(seconds),
+            change: percent / 100.0,
+        });
+    }
+    Ok(jumps)
+}
+
+/// a sample of N(0, 1) by the Box-Muller transform
+fn standard_normal(rng: &mut StdRng) -> f64 {
+    // 1 - gen() is in (0, 1] so the log is finite
+    let u1 = 1.0 - rng.gen::<f64>();
+    let u2 = rng.gen::<f64>();
+    (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos()
+}
+
+/// a hash of the symbol that, unlike the std hasher, is stable across releases
+fn fnv1a(value: &str) -> u64 {
+    value.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model(seed: u64) -> MarketModel {
+        MarketModel {
+            seed,
+            drift: 0.0,
+            volatility: 0.01,
+            mean_reversion: 0.0,
+            initial_price: None,
+            time_step: Some(Duration::from_secs(60)),
+            jumps: HashMap::new(),
+        }
+    }
+
+    fn server(model: MarketModel) -> FakeQuoteServer {
+        FakeQuoteServer {
+            model,
+            started: Instant::now(),
+            symbols: Mutex::default(),
+        }
+    }
+
+    fn prices(server: &FakeQuoteServer, symbol: &str, quotes: usize) -> Vec<f64> {
+        (0..quotes).map(|_| server.next_price(symbol)).collect()
+    }
+
+    #[test]
+    fn test_same_seed_same_prices() {
+        let first = server(model(42));
+        let second = server(model(42));
+
+        // quoting another symbol in between doesn't change a symbol's prices
+        let abc = prices(&first, "ABC", 20);
+        let xyz = prices(&first, "XYZ", 20);
+        let mut interleaved = (vec![], vec![]);
+        for _ in 0..20 {
+            interleaved.1.push(second.next_price("XYZ"));
+            interleaved.0.push(second.next_price("ABC"));
+        }
+
+        assert_eq!(abc, interleaved.0);
+        assert_eq!(xyz, interleaved.1);
+        assert_ne!(abc, xyz);
+    }
+
+    #[test]
+    fn test_different_seed_different_prices() {
+        assert_ne!(
+            prices(&server(model(42)), "ABC", 20),
+            prices(&server(model(43)), "ABC", 20)
+        );
+    }
+
+    #[test]
+    fn test_mean_reversion() {
+        let model = MarketModel {
+            volatility: 0.0,
+            mean_reversion: 0.5,
+            initial_price: Some(100.0),
+            ..model(42)
+        };
+        let mut symbol = model.new_symbol("ABC", Duration::ZERO);
+        symbol.price = 200.0;
+
+        // half the log distance back to the start in a minute
+        model.advance(&mut symbol, Duration::from_secs(60));
+        assert!(
+            (symbol.price - 200_f64.sqrt() * 10.0).abs() < 1e-9,
+            "{}",
+            symbol.price
+        );
+
+        for _ in 0..20 {
+            model.advance(&mut symbol, Duration::from_secs(60));
+        }
+        assert!((symbol.price - 100.0).abs() < 0.01, "{}", symbol.price);
+    }
+
+    #[test]
+    fn test_jump() {
+        let server = server(MarketModel {
+            volatility: 0.0,
+            initial_price: Some(100.0),
+            jumps: parse_jumps("ABC:120:-20").unwrap(),
+            ..model(42)
+        });
+
+        assert_eq!(prices(&server, "ABC", 3), vec![100.0, 80.0, 80.0]);
+        assert_eq!(prices(&server, "XYZ", 3), vec![100.0, 100.0, 100.0]);
+    }
+
+    #[test]
+    fn test_parse_jumps() {
+        let jumps = parse_jumps("ABC:60:-20, XYZ:90:5,ABC:30.5:10").unwrap();
+        assert_eq!(
+            jumps["ABC"],
+            vec![
+                Jump {
+                    at: Duration::from_secs(60),
+                    change: -0.2
+                },
+                Jump {
+                    at: Duration::from_millis(30_500),
+                    change: 0.1
+                },
+            ]
+        );
+        assert_eq!(
+            jumps["XYZ"],
+            vec![Jump {
+                at: Duration::from_secs(90),
+                change: 0.05
+            }]
+        );
+        assert!(parse_jumps("").unwrap().is_empty());
+
+        for malformed in [
+            "ABC:60",
+            "ABC:60:5:1",
+            "ABC:soon:5",
+            "ABC:60:lots",
+            "ABC:-1:5",
+            "ABC:60:-100",
+        ] {
+            assert!(parse_jumps(malformed).is_err(), "{malformed}");
+        }
+    }
+}
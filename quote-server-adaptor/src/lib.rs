@@ -1 +1,5 @@
 tonic::include_proto!("day_trader");
+
+pub mod stand_in;
+
+pub mod uvic;
@@ -0,0 +1,453 @@
+use quote_server_adaptor::quote_server::Quote;
+use quote_server_adaptor::{QuoteRequest, QuoteResponse};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::sync::Mutex;
+use tonic::{async_trait, Request, Response, Status};
+use tracing::{info, instrument};
+
+/**
+ * Serves the quotes recorded on a tape, in order, so a replay of the same requests gets the same
+ * prices. Each symbol's quotes are replayed independently: the nth quote of a symbol is the nth
+ * one recorded for it, whoever asks.
+ */
+pub struct ReplayQuoteServer {
+    tapes: Mutex<HashMap<String, Tape>>,
+    when_exhausted: WhenExhausted,
+}
+
+/// what to quote once every recorded quote of a symbol has been served
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WhenExhausted {
+    /// keep quoting the last one
+    Hold,
+    /// start over from the first
+    Loop,
+    /// fail the request
+    Error,
+}
+
+struct Tape {
+    ticks: Vec<Tick>,
+    next: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Tick {
+    symbol: String,
+    price: f64,
+    /// as sent by the quote server, in milliseconds
+    timestamp: u64,
+    crypto_key: String,
+}
+
+/**
+ * A row of a lean log dump in JSON lines or CSV. Only `quoteServer` rows are quotes.
+ */
+#[derive(Debug, Deserialize)]
+struct DumpRecord {
+    #[serde(rename = "type")]
+    log_type: String,
+    timestamp: u64,
+    stock_symbol: Option<String>,
+    price: Option<String>,
+    quote_server_time: Option<u64>,
+    cryptokey: Option<String>,
+}
+
+impl ReplayQuoteServer {
+    /**
+     * Loads a tape from either a log dump, as JSON lines (`.jsonl`) or CSV, or a CSV of
+     * `symbol,timestamp,price` rows with an optional cryptokey column and an optional header.
+     */
+    pub fn from_file(
+        path: impl AsRef<Path>,
+        when_exhausted: WhenExhausted,
+    ) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        let ticks = match path.extension().and_then(|it| it.to_str()) {
+            Some("jsonl") => read_json_lines(BufReader::new(File::open(path)?))?,
+            _ => read_csv(File::open(path)?)?,
+        };
+
+        let tapes = tapes(ticks);
+        info!(
+            "replaying {} quotes of {} symbols from {}",
+            tapes.values().map(|it| it.ticks.len()).sum::<usize>(),
+            tapes.len(),
+            path.display()
+        );
+
+        Ok(Self {
+            tapes: Mutex::new(tapes),
+            when_exhausted,
+        })
+    }
+}
+
+impl WhenExhausted {
+    pub fn from_env() -> Self {
+        match env::var("REPLAY_WHEN_EXHAUSTED").as_deref() {
+            Err(_) | Ok("hold") => WhenExhausted::Hold,
+            Ok("loop") => WhenExhausted::Loop,
+            Ok("error") => WhenExhausted::Error,
+            Ok(other) => panic!("REPLAY_WHEN_EXHAUSTED must be hold, loop or error, was {other}"),
+        }
+    }
+}
+
+#[async_trait]
+impl Quote for ReplayQuoteServer {
+    #[instrument(skip_all)]
+    async fn quote(
+        &self,
+        request: Request<QuoteRequest>,
+    ) -> Result<Response<QuoteResponse>, Status> {
+        let QuoteRequest {
+            user_id,
+            stock_symbol,
+            ..
+        } = request.into_inner();
+
+        let tick = {
+            let mut tapes = self.tapes.lock().expect("tapes lock poisoned");
+            let tape = tapes.get_mut(&stock_symbol).ok_or_else(|| {
+                Status::not_found(format!("no quotes recorded for {stock_symbol}"))
+            })?;
+
+            let index = if tape.next < tape.ticks.len() {
+                tape.next
+            } else {
+                match self.when_exhausted {
+                    WhenExhausted::Hold => tape.ticks.len() - 1,
+                    WhenExhausted::Loop => 0,
+                    WhenExhausted::Error => {
+                        return Err(Status::out_of_range(format!(
+                            "all {} recorded quotes for {stock_symbol} have been replayed",
+                            tape.ticks.len()
+                        )))
+                    }
+                }
+            };
+            tape.next = index + 1;
+            tape.ticks[index].clone()
+        };
+
+        Ok(Response::new(QuoteResponse {
+            quote: tick.price,
+            sym: tick.symbol,
+            user_id,
+            timestamp: tick.timestamp,
+            crypto_key: tick.crypto_key,
+        }))
+    }
+}
+
+/// each symbol's ticks, oldest first
+fn tapes(ticks: Vec<Tick>) -> HashMap<String, Tape> {
+    let mut tapes = HashMap::<String, Tape>::new();
+    for tick in ticks {
+        tapes
+            .entry(tick.symbol.clone())
+            .or_insert_with(|| Tape {
+                ticks: vec![],
+                next: 0,
+            })
+            .ticks
+            .push(tick);
+    }
+    for tape in tapes.values_mut() {
+        // stable, so quotes recorded in the same millisecond keep their order
+        tape.ticks.sort_by_key(|it| it.timestamp);
+    }
+    tapes
+}
+
+fn read_json_lines(reader: impl BufRead) -> Result<Vec<Tick>, Box<dyn Error>> {
+    let mut ticks = vec![];
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = serde_json::from_str::<DumpRecord>(&line)
+            .map_err(|e| format!("line {}: {e}", number + 1))?;
+        ticks.extend(
+            record
+                .into_tick()
+                .map_err(|e| format!("line {}: {e}", number + 1))?,
+        );
+    }
+    Ok(ticks)
+}
+
+fn read_csv(reader: impl Read) -> Result<Vec<Tick>, Box<dyn Error>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(reader);
+    let mut records = reader.records();
+
+    let Some(first) = records.next().transpose()? else {
+        return Ok(vec![]);
+    };
+
+    let mut ticks = vec![];
+    match first.get(0) {
+        // a log dump, with every kind of entry
+        Some("type") => {
+            for record in records {
+                let record = record?;
+                ticks.extend(
+                    record
+                        .deserialize::<DumpRecord>(Some(&first))?
+                        .into_tick()?,
+                );
+            }
+        }
+        Some("symbol") => {
+            for record in records {
+                ticks.push(tick_from_row(&record?, ticks.len())?);
+            }
+        }
+        _ => {
+            ticks.push(tick_from_row(&first, 0)?);
+            for record in records {
+                ticks.push(tick_from_row(&record?, ticks.len())?);
+            }
+        }
+    }
+    Ok(ticks)
+}
+
+/// a `symbol,timestamp,price[,cryptokey]` row
+fn tick_from_row(row: &csv::StringRecord, index: usize) -> Result<Tick, Box<dyn Error>> {
+    let field = |i: usize, name: &str| {
+        row.get(i)
+            .map(str::trim)
+            .ok_or_else(|| format!("missing {name} in {row:?}"))
+    };
+    let symbol = field(0, "symbol")?.to_string();
+    Ok(Tick {
+        price: field(2, "price")?.parse()?,
+        timestamp: field(1, "timestamp")?.parse()?,
+        crypto_key: row
+            .get(3)
+            .map(str::to_string)
+            // lean logs the key of every quote, give each one its own
+            .unwrap_or_else(|| format!("replay-{symbol}-{index}")),
+        symbol,
+    })
+}
+
+impl DumpRecord {
+    fn into_tick(self) -> Result<Option<Tick>, String> {
+        if self.log_type != "quoteServer" {
+            return Ok(None);
+        }
+        let (Some(symbol), Some(price)) = (self.stock_symbol, self.price) else {
+            return Err("quoteServer entry without a stock_symbol or price".to_string());
+        };
+        Ok(Some(Tick {
+            price: price
+                .parse()
+                .map_err(|e| format!("invalid price {price}: {e}"))?,
+            timestamp: self.quote_server_time.unwrap_or(self.timestamp),
+            crypto_key: self.cryptokey.unwrap_or_default(),
+            symbol,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::Code;
+
+    /// a quote as lean's dump_log writes it, pinned by its test_quote_server_rows
+    const LEAN_JSON_LINE: &str = concat!(
+        r#"{"type":"quoteServer","timestamp":1609556645000,"server":"lean","transaction_num":3,"#,
+        r#""command":null,"action":null,"username":"marcus","stock_symbol":"ABC","filename":null,"#,
+        r#""funds":null,"price":"12.34","quote_server_time":1609556644000,"cryptokey":"abc123/XYZ=","#,
+        r#""error_message":null,"debug_message":null}"#,
+    );
+    const LEAN_CSV_HEADER: &str = "type,timestamp,server,transaction_num,command,action,username,stock_symbol,filename,funds,price,quote_server_time,cryptokey,error_message,debug_message";
+    const LEAN_CSV_ROW: &str =
+        "quoteServer,1609556645000,lean,3,,,marcus,ABC,,,12.34,1609556644000,abc123/XYZ=,,";
+
+    fn tick(symbol: &str, price: f64, timestamp: u64, crypto_key: &str) -> Tick {
+        Tick {
+            symbol: symbol.to_string(),
+            price,
+            timestamp,
+            crypto_key: crypto_key.to_string(),
+        }
+    }
+
+    fn lean_tick() -> Tick {
+        tick("ABC", 12.34, 1609556644000, "abc123/XYZ=")
+    }
+
+    fn server(ticks: Vec<Tick>, when_exhausted: WhenExhausted) -> ReplayQuoteServer {
+        ReplayQuoteServer {
+            tapes: Mutex::new(tapes(ticks)),
+            when_exhausted,
+        }
+    }
+
+    async fn quote(server: &ReplayQuoteServer, symbol: &str) -> Result<f64, Code> {
+        server
+            .quote(Request::new(QuoteRequest {
+                user_id: "marcus".to_string(),
+                stock_symbol: symbol.to_string(),
+                ..Default::default()
+            }))
+            .await
+            .map(|it| it.into_inner().quote)
+            .map_err(|it| it.code())
+    }
+
+    #[test]
+    fn test_read_json_lines() {
+        let dump = [
+            r#"{"type":"userCommand","timestamp":1609556645000,"server":"lean","transaction_num":3,"command":"QUOTE","action":null,"username":"marcus","stock_symbol":"ABC","filename":null,"funds":null,"price":null,"quote_server_time":null,"cryptokey":null,"error_message":null,"debug_message":null}"#,
+            LEAN_JSON_LINE,
+            "",
+            r#"{"type":"quoteServer","timestamp":1609556646000,"stock_symbol":"XYZ","price":"5"}"#,
+        ]
+        .join("\n");
+
+        assert_eq!(
+            read_json_lines(dump.as_bytes()).unwrap(),
+            vec![lean_tick(), tick("XYZ", 5.0, 1609556646000, "")]
+        );
+
+        let error = read_json_lines("{\"type\":".as_bytes()).unwrap_err();
+        assert!(error.to_string().starts_with("line 1:"), "{error}");
+    }
+
+    #[test]
+    fn test_read_csv_dump() {
+        let dump = format!(
+            "{LEAN_CSV_HEADER}\nuserCommand,1609556645000,lean,3,QUOTE,,marcus,ABC,,,,,,,\n{LEAN_CSV_ROW}\n"
+        );
+
+        assert_eq!(read_csv(dump.as_bytes()).unwrap(), vec![lean_tick()]);
+    }
+
+    #[test]
+    fn test_read_csv_with_symbol_header() {
+        let csv = "symbol,timestamp,price,cryptokey\nABC,1000,12.34,key\nXYZ,2000,5\n";
+
+        assert_eq!(
+            read_csv(csv.as_bytes()).unwrap(),
+            vec![
+                tick("ABC", 12.34, 1000, "key"),
+                tick("XYZ", 5.0, 2000, "replay-XYZ-1"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_csv_without_header() {
+        let csv = "ABC,1000,12.34\nABC,900, 12\n";
+
+        assert_eq!(
+            read_csv(csv.as_bytes()).unwrap(),
+            vec![
+                tick("ABC", 12.34, 1000, "replay-ABC-0"),
+                tick("ABC", 12.0, 900, "replay-ABC-1"),
+            ]
+        );
+        assert!(read_csv("ABC,1000\n".as_bytes()).is_err());
+        assert!(read_csv("ABC,1000,lots\n".as_bytes()).is_err());
+        assert!(read_csv("".as_bytes()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_into_tick() {
+        let record =
+            |log_type: &str, price: Option<&str>, quote_server_time: Option<u64>| DumpRecord {
+                log_type: log_type.to_string(),
+                timestamp: 2000,
+                stock_symbol: Some("ABC".to_string()),
+                price: price.map(str::to_string),
+                quote_server_time,
+                cryptokey: Some("key".to_string()),
+            };
+
+        assert_eq!(record("userCommand", None, None).into_tick(), Ok(None));
+        assert_eq!(
+            record("quoteServer", Some("12.34"), Some(1000)).into_tick(),
+            Ok(Some(tick("ABC", 12.34, 1000, "key")))
+        );
+        assert_eq!(
+            record("quoteServer", Some("12.34"), None).into_tick(),
+            Ok(Some(tick("ABC", 12.34, 2000, "key")))
+        );
+        assert!(record("quoteServer", None, None).into_tick().is_err());
+        assert!(record("quoteServer", Some("lots"), None)
+            .into_tick()
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_symbols_are_replayed_in_timestamp_order() {
+        let server = server(
+            vec![
+                tick("ABC", 3.0, 3000, ""),
+                tick("XYZ", 20.0, 2000, ""),
+                tick("ABC", 1.0, 1000, ""),
+                tick("XYZ", 10.0, 1000, ""),
+                // recorded in the same millisecond as the one before it
+                tick("ABC", 2.0, 1000, ""),
+            ],
+            WhenExhausted::Hold,
+        );
+
+        assert_eq!(quote(&server, "XYZ").await, Ok(10.0));
+        assert_eq!(quote(&server, "ABC").await, Ok(1.0));
+        assert_eq!(quote(&server, "ABC").await, Ok(2.0));
+        assert_eq!(quote(&server, "XYZ").await, Ok(20.0));
+        assert_eq!(quote(&server, "ABC").await, Ok(3.0));
+        assert_eq!(quote(&server, "DEF").await, Err(Code::NotFound));
+    }
+
+    #[tokio::test]
+    async fn test_when_exhausted() {
+        let ticks = vec![tick("ABC", 1.0, 1000, ""), tick("ABC", 2.0, 2000, "")];
+        let quotes = |when_exhausted| {
+            let server = server(ticks.clone(), when_exhausted);
+            async move {
+                let mut quotes = vec![];
+                for _ in 0..4 {
+                    quotes.push(quote(&server, "ABC").await);
+                }
+                quotes
+            }
+        };
+
+        assert_eq!(
+            quotes(WhenExhausted::Hold).await,
+            vec![Ok(1.0), Ok(2.0), Ok(2.0), Ok(2.0)]
+        );
+        assert_eq!(
+            quotes(WhenExhausted::Loop).await,
+            vec![Ok(1.0), Ok(2.0), Ok(1.0), Ok(2.0)]
+        );
+        assert_eq!(
+            quotes(WhenExhausted::Error).await,
+            vec![
+                Ok(1.0),
+                Ok(2.0),
+                Err(Code::OutOfRange),
+                Err(Code::OutOfRange)
+            ]
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use rand::distributions::Alphanumeric;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::env;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info, warn};
+
+/**
+ * How a [serve]d stand-in for the UVic quote server misbehaves. The defaults answer every request
+ * immediately and correctly.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct StandInConfig {
+    /// how long every answer takes
+    pub latency: Duration,
+    /// up to this much longer again, at random
+    pub jitter: Duration,
+    /// the chance a request is answered by closing the connection
+    pub error_rate: f64,
+    /// the chance a request is answered with a line that isn't a valid quote
+    pub malformed_rate: f64,
+    /// seeds the prices, keys and faults, so the same requests in the same order get the same
+    /// answers
+    pub seed: u64,
+}
+
+impl Default for StandInConfig {
+    fn default() -> Self {
+        Self {
+            latency: Duration::ZERO,
+            jitter: Duration::ZERO,
+            error_rate: 0.0,
+            malformed_rate: 0.0,
+            seed: 0,
+        }
+    }
+}
+
+impl StandInConfig {
+    /**
+     * Configured from `STAND_IN_LATENCY_MS`, `STAND_IN_JITTER_MS`, `STAND_IN_ERROR_RATE`,
+     * `STAND_IN_MALFORMED_RATE` and `STAND_IN_SEED`.
+     */
+    pub fn from_env() -> Self {
+        Self {
+            latency: Duration::from_millis(env_or("STAND_IN_LATENCY_MS", 0)),
+            jitter: Duration::from_millis(env_or("STAND_IN_JITTER_MS", 0)),
+            error_rate: env_or("STAND_IN_ERROR_RATE", 0.0),
+            malformed_rate: env_or("STAND_IN_MALFORMED_RATE", 0.0),
+            seed: env_or("STAND_IN_SEED", rand::thread_rng().gen()),
+        }
+    }
+}
+
+enum Answer {
+    Line(String),
+    Hangup,
+}
+
+/**
+ * Answers `user,SYM` lines with `price,sym,user,timestamp,cryptokey` lines, like the UVic quote
+ * server, on every connection accepted from `listener`. Connections stay open for as many
+ * requests as the client sends.
+ */
+pub async fn serve(listener: TcpListener, config: StandInConfig) -> std::io::Result<()> {
+    info!(
+        "standing in for the quote server on {} with {config:?}",
+        listener.local_addr()?
+    );
+    let rng = Arc::new(Mutex::new(StdRng::seed_from_u64(config.seed)));
+
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let config = config.clone();
+        let rng = rng.clone();
+        tokio::spawn(async move {
+            if let Err(e) = answer(stream, &config, &rng).await {
+                warn!("connection from {peer} failed: {e}");
+            }
+        });
+    }
+}
+
+async fn answer(
+    stream: TcpStream,
+    config: &StandInConfig,
+    rng: &Mutex<StdRng>,
+) -> std::io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Some(request) = lines.next_line().await? {
+        let Some((user_id, stock_symbol)) = request.trim_end().split_once(',') else {
+            debug!("hanging up on invalid request \"{request}\"");
+            return Ok(());
+        };
+
+        let (delay, answer) = {
+            let mut rng = rng.lock().expect("rng lock poisoned");
+            (
+                config.latency + config.jitter.mul_f64(rng.gen()),
+                config.answer(&mut rng, user_id, stock_symbol),
+            )
+        };
+        tokio::time::sleep(delay).await;
+
+        match answer {
+            Answer::Line(line) => writer.write_all(line.as_bytes()).await?,
+            Answer::Hangup => return Ok(()),
+        }
+    }
+    Ok(())
+}
+
+impl StandInConfig {
+    fn answer(&self, rng: &mut StdRng, user_id: &str, stock_symbol: &str) -> Answer {
+        let price = (rng.gen_range(50.0..300.0_f64) * 100.0).round() / 100.0;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time went backwards")
+            .as_millis();
+        let crypto_key = rng
+            .sample_iter(Alphanumeric)
+            .take(44)
+            .map(char::from)
+            .collect::<String>();
+
+        let fault = rng.gen::<f64>();
+        if fault < self.error_rate {
+            return Answer::Hangup;
+        }
+        if fault < self.error_rate + self.malformed_rate {
+            return Answer::Line(match rng.gen_range(0..4) {
+                0 => format!("{price:.2},{stock_symbol}\n"),
+                1 => format!("N/A,{stock_symbol},{user_id},{timestamp},{crypto_key}\n"),
+                2 => format!("{price:.2},{stock_symbol},{user_id},soon,{crypto_key}\n"),
+                _ => "\n".to_string(),
+            });
+        }
+        Answer::Line(format!(
+            "{price:.2},{stock_symbol},{user_id},{timestamp},{crypto_key}\n"
+        ))
+    }
+}
+
+fn env_or<T: FromStr>(name: &str, default: T) -> T {
+    match env::var(name) {
+        Ok(value) => value
+            .parse()
+            .unwrap_or_else(|_| panic!("failed to parse {name}")),
+        Err(_) => default,
+    }
+}
@@ -0,0 +1,401 @@
+use crate::quote_server::Quote;
+use crate::{QuoteRequest, QuoteResponse};
+use std::env;
+use std::fmt::{Debug, Display, Formatter};
+use std::mem::size_of;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::BufReader;
+use tokio::io::{AsyncBufRead, AsyncWriteExt};
+use tokio::io::{AsyncBufReadExt, AsyncWrite};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+use tokio::task::JoinSet;
+use tonic::{async_trait, Request, Response, Status};
+use tracing::{debug, instrument};
+
+/**
+ * Quotes from the UVic quote server over its line protocol: `user,SYM\n` is answered with
+ * `price,sym,user,timestamp,cryptokey`.
+ *
+ * Every request is hedged: if the first attempt hasn't succeeded within the hedge delay, or
+ * fails, another is sent, up to `hackery_levels` in all. The first valid quote wins and the
+ * attempts still running are cancelled. Connections are kept alive and reused between requests.
+ */
+#[derive(Clone)]
+pub struct UVicQuoter {
+    config: UVicConfig,
+    pool: Arc<ConnectionPool>,
+    metrics: Arc<HedgeMetrics>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UVicConfig {
+    /// the most attempts per request
+    pub hackery_levels: u8,
+    /// how long an attempt, connecting included, may take before it counts as failed
+    pub attempt_timeout: Duration,
+    /// how long to wait for an attempt before hedging it with another
+    pub hedge_delay: Duration,
+    /// the most connections kept open between requests
+    pub max_idle_connections: usize,
+}
+
+impl Default for UVicConfig {
+    fn default() -> Self {
+        Self {
+            hackery_levels: 5,
+            attempt_timeout: Duration::from_secs(2),
+            hedge_delay: Duration::ZERO,
+            max_idle_connections: 16,
+        }
+    }
+}
+
+impl UVicConfig {
+    /**
+     * Configured from `HACKERY_LEVELS`, `QUOTE_ATTEMPT_TIMEOUT_MS`, `HEDGE_DELAY_MS` and
+     * `MAX_IDLE_CONNECTIONS`.
+     */
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        Self {
+            hackery_levels: env::var("HACKERY_LEVELS")
+                .map(|it| it.parse().expect("failed to parse HACKERY_LEVELS"))
+                .unwrap_or(default.hackery_levels),
+            attempt_timeout: env::var("QUOTE_ATTEMPT_TIMEOUT_MS")
+                .map(|it| {
+                    it.parse()
+                        .expect("failed to parse QUOTE_ATTEMPT_TIMEOUT_MS")
+                })
+                .map(Duration::from_millis)
+                .unwrap_or(default.attempt_timeout),
+            hedge_delay: env::var("HEDGE_DELAY_MS")
+                .map(|it| it.parse().expect("failed to parse HEDGE_DELAY_MS"))
+                .map(Duration::from_millis)
+                .unwrap_or(default.hedge_delay),
+            max_idle_connections: env::var("MAX_IDLE_CONNECTIONS")
+                .map(|it| it.parse().expect("failed to parse MAX_IDLE_CONNECTIONS"))
+                .unwrap_or(default.max_idle_connections),
+        }
+    }
+}
+
+/**
+ * Counts of how requests were won. Hedging pays off when later attempts win.
+ */
+#[derive(Debug, Default)]
+pub struct HedgeMetrics {
+    requests: AtomicU64,
+    failed_requests: AtomicU64,
+    /// indexed by when the winning attempt was sent, the first being 0
+    wins: Mutex<Vec<u64>>,
+    failed_attempts: AtomicU64,
+    timed_out_attempts: AtomicU64,
+    cancelled_attempts: AtomicU64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HedgeMetricsSnapshot {
+    pub requests: u64,
+    pub failed_requests: u64,
+    pub wins: Vec<u64>,
+    pub failed_attempts: u64,
+    pub timed_out_attempts: u64,
+    pub cancelled_attempts: u64,
+}
+
+impl HedgeMetrics {
+    pub fn snapshot(&self) -> HedgeMetricsSnapshot {
+        HedgeMetricsSnapshot {
+            requests: self.requests.load(Ordering::Relaxed),
+            failed_requests: self.failed_requests.load(Ordering::Relaxed),
+            wins: self.wins.lock().expect("wins lock poisoned").clone(),
+            failed_attempts: self.failed_attempts.load(Ordering::Relaxed),
+            timed_out_attempts: self.timed_out_attempts.load(Ordering::Relaxed),
+            cancelled_attempts: self.cancelled_attempts.load(Ordering::Relaxed),
+        }
+    }
+
+    fn win(&self, attempt: usize) {
+        let mut wins = self.wins.lock().expect("wins lock poisoned");
+        if wins.len() <= attempt {
+            wins.resize(attempt + 1, 0);
+        }
+        wins[attempt] += 1;
+    }
+}
+
+impl HedgeMetricsSnapshot {
+    /// wins by any attempt but the first
+    pub fn hedge_wins(&self) -> u64 {
+        self.wins.iter().skip(1).sum()
+    }
+}
+
+impl UVicQuoter {
+    pub fn new(quote_server_addr: String, config: UVicConfig) -> Self {
+        Self {
+            pool: Arc::new(ConnectionPool {
+                addr: quote_server_addr,
+                idle: Mutex::new(vec![]),
+                max_idle: config.max_idle_connections,
+            }),
+            config,
+            metrics: Arc::default(),
+        }
+    }
+
+    pub fn metrics(&self) -> Arc<HedgeMetrics> {
+        self.metrics.clone()
+    }
+
+    fn launch(
+        &self,
+        attempts: &mut JoinSet<(usize, Result<QuoteResponse, AttemptError>)>,
+        attempt: usize,
+        message: &str,
+    ) {
+        let quoter = self.clone();
+        let message = message.to_string();
+        attempts.spawn(async move {
+            let result =
+                tokio::time::timeout(quoter.config.attempt_timeout, quoter.query(&message))
+                    .await
+                    .unwrap_or(Err(AttemptError::TimedOut));
+            (attempt, result)
+        });
+    }
+
+    /// one attempt, on a pooled connection if there is one
+    #[instrument(skip_all)]
+    async fn query(&self, message: &str) -> Result<QuoteResponse, AttemptError> {
+        if let Some(connection) = self.pool.take_idle() {
+            match connection.send(message).await {
+                Ok((connection, line)) => return self.finish(connection, &line),
+                // the quote server may have closed it while it sat idle
+                Err(e) => debug!("pooled connection failed, reconnecting: {e}"),
+            }
+        }
+
+        let connection = self.pool.connect().await.map_err(|e| {
+            AttemptError::Failed(format!("failed to connect to {}: {e}", self.pool.addr))
+        })?;
+        let (connection, line) = connection
+            .send(message)
+            .await
+            .map_err(|e| AttemptError::Failed(format!("failed to get response: {e}")))?;
+        self.finish(connection, &line)
+    }
+
+    fn finish(&self, connection: Connection, line: &str) -> Result<QuoteResponse, AttemptError> {
+        // an answer we can't parse leaves us unsure what the server will send next
+        let response = response_from_quote_server_string(line).map_err(AttemptError::Failed)?;
+        self.pool.put_back(connection);
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl Quote for UVicQuoter {
+    #[instrument(skip(self))]
+    async fn quote(
+        &self,
+        request: Request<QuoteRequest>,
+    ) -> Result<Response<QuoteResponse>, Status> {
+        let QuoteRequest {
+            user_id,
+            stock_symbol,
+            ..
+        } = request.into_inner();
+        self.metrics.requests.fetch_add(1, Ordering::Relaxed);
+
+        let message = make_socket_message(user_id, &stock_symbol);
+        let levels = usize::from(self.config.hackery_levels.max(1));
+        let mut attempts = JoinSet::new();
+        let mut failures = vec![];
+
+        self.launch(&mut attempts, 0, &message);
+        let mut launched = 1;
+
+        loop {
+            let hedge = async {
+                if launched < levels {
+                    tokio::time::sleep(self.config.hedge_delay).await
+                } else {
+                    std::future::pending().await
+                }
+            };
+
+            tokio::select! {
+                joined = attempts.join_next() => match joined {
+                    None => break,
+                    Some(Ok((attempt, Ok(response)))) => {
+                        self.metrics.win(attempt);
+                        let losers = attempts.len() as u64;
+                        self.metrics.cancelled_attempts.fetch_add(losers, Ordering::Relaxed);
+                        attempts.abort_all();
+                        return Ok(Response::new(response));
+                    }
+                    Some(Ok((attempt, Err(e)))) => {
+                        match e {
+                            AttemptError::TimedOut => &self.metrics.timed_out_attempts,
+                            AttemptError::Failed(_) => &self.metrics.failed_attempts,
+                        }
+                        .fetch_add(1, Ordering::Relaxed);
+                        debug!("attempt {attempt} failed: {e}");
+                        failures.push(format!("attempt {attempt}: {e}"));
+                        // don't wait out the hedge delay to replace a failure
+                        if launched < levels {
+                            self.launch(&mut attempts, launched, &message);
+                            launched += 1;
+                        }
+                    }
+                    Some(Err(e)) => failures.push(format!("failed to join: {e}")),
+                },
+                _ = hedge => {
+                    self.launch(&mut attempts, launched, &message);
+                    launched += 1;
+                }
+            }
+        }
+
+        self.metrics.failed_requests.fetch_add(1, Ordering::Relaxed);
+        Err(Status::unavailable(format!(
+            "failed to quote {stock_symbol}: {}",
+            failures.join(", ")
+        )))
+    }
+}
+
+#[derive(Debug)]
+enum AttemptError {
+    TimedOut,
+    Failed(String),
+}
+
+impl Display for AttemptError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AttemptError::TimedOut => write!(f, "timed out"),
+            AttemptError::Failed(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+struct ConnectionPool {
+    addr: String,
+    idle: Mutex<Vec<Connection>>,
+    max_idle: usize,
+}
+
+struct Connection {
+    reader: BufReader<OwnedReadHalf>,
+    writer: OwnedWriteHalf,
+}
+
+impl ConnectionPool {
+    fn take_idle(&self) -> Option<Connection> {
+        self.idle.lock().expect("idle lock poisoned").pop()
+    }
+
+    fn put_back(&self, connection: Connection) {
+        let mut idle = self.idle.lock().expect("idle lock poisoned");
+        if idle.len() < self.max_idle {
+            idle.push(connection);
+        }
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn connect(&self) -> std::io::Result<Connection> {
+        let (reader, writer) = TcpStream::connect(&self.addr).await?.into_split();
+        Ok(Connection {
+            reader: BufReader::new(reader),
+            writer,
+        })
+    }
+}
+
+impl Connection {
+    /// hands the connection back only if the exchange completed
+    async fn send(mut self, message: &str) -> Result<(Self, String), &'static str> {
+        let line = get_response(&mut self.writer, &mut self.reader, message).await?;
+        Ok((self, line))
+    }
+}
+
+#[instrument(skip(writer, reader))]
+async fn get_response<W, R>(
+    writer: &mut W,
+    reader: &mut R,
+    message: &str,
+) -> Result<String, &'static str>
+where
+    W: AsyncWrite + Unpin + Debug,
+    R: AsyncBufRead + Unpin + Debug,
+{
+    if writer.write_all(message.as_bytes()).await.is_err() {
+        return Err("Failed to write to socket.");
+    }
+
+    let mut line = String::new();
+    match reader.read_line(&mut line).await {
+        Err(_) => Err("Failed to read from socket."),
+        Ok(0) => Err("Connection closed by quote server."),
+        Ok(_) => Ok(line),
+    }
+}
+
+fn make_socket_message(mut user_id: String, stock_symbol: &str) -> String {
+    user_id.reserve(size_of::<char>() + stock_symbol.len() + size_of::<char>());
+    user_id.push(',');
+    user_id.push_str(stock_symbol);
+    user_id.push('\n');
+    user_id
+}
+
+/**
+ * Parses a `price,sym,user,timestamp,cryptokey` line from the quote server. The line ending, if
+ * any, is not part of the cryptokey.
+ */
+pub fn response_from_quote_server_string(line: &str) -> Result<QuoteResponse, String> {
+    let line = line.trim_end_matches(['\r', '\n']);
+    let mut returned = line.split(',');
+
+    let quote_str = returned.next().ok_or_else(|| {
+        format!("Invalid response from quote server. (Missing quote in \"{line}\")")
+    })?;
+    let quote = quote_str
+        .parse()
+        .map_err(|err| format!("Invalid response from quote server. (Invalid quote \"{quote_str}\": {err} in \"{line}\")"))?;
+    let sym = returned
+        .next()
+        .ok_or_else(|| format!("Invalid response from quote server. (Missing sym in \"{line}\")"))?
+        .to_string();
+    let user_id = returned
+        .next()
+        .ok_or_else(|| {
+            format!("Invalid response from quote server. (Missing user_id in \"{line}\")")
+        })?
+        .to_string();
+    let timestamp_str = returned.next().ok_or_else(|| {
+        format!("Invalid response from quote server. (Missing timestamp in \"{line}\")")
+    })?;
+    let timestamp = timestamp_str
+        .parse()
+        .map_err(|err| format!("Invalid response from quote server. (Invalid timestamp \"{timestamp_str}\" due to {err} in \"{line}\")"))?;
+    let crypto_key = returned
+        .next()
+        .ok_or_else(|| {
+            format!("Invalid response from quote server. (Missing crypto_key in \"{line}\")")
+        })?
+        .to_string();
+    Ok(QuoteResponse {
+        quote,
+        sym,
+        user_id,
+        timestamp,
+        crypto_key,
+    })
+}
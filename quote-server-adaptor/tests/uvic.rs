@@ -0,0 +1,294 @@
+use quote_server_adaptor::quote_server::Quote;
+use quote_server_adaptor::stand_in::{serve, StandInConfig};
+use quote_server_adaptor::uvic::{response_from_quote_server_string, UVicConfig, UVicQuoter};
+use quote_server_adaptor::QuoteRequest;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tonic::{Code, Request};
+
+/// starts a stand-in on a free port and returns its address
+async fn stand_in(config: StandInConfig) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    tokio::spawn(serve(listener, config));
+    addr
+}
+
+/// how a [scripted] quote server treats the first connection. The rest are answered
+#[derive(Clone, Copy)]
+enum First {
+    Hangup,
+    Hang,
+}
+
+/// starts a quote server that counts connections and answers every line with the same quote
+async fn scripted(first: Option<First>) -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let connections = Arc::new(AtomicUsize::new(0));
+    let counted = connections.clone();
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let index = counted.fetch_add(1, Ordering::SeqCst);
+            tokio::spawn(async move {
+                match (index, first) {
+                    (0, Some(First::Hangup)) => drop(stream),
+                    (0, Some(First::Hang)) => {
+                        tokio::time::sleep(Duration::from_secs(60)).await;
+                    }
+                    _ => {
+                        let (reader, mut writer) = stream.into_split();
+                        let mut lines = BufReader::new(reader).lines();
+                        while let Ok(Some(_)) = lines.next_line().await {
+                            writer
+                                .write_all(b"12.34,ABC,marcus,1700000000000,key\n")
+                                .await
+                                .unwrap();
+                        }
+                    }
+                }
+            });
+        }
+    });
+    (addr, connections)
+}
+
+fn quoter(addr: String, hackery_levels: u8) -> UVicQuoter {
+    UVicQuoter::new(
+        addr,
+        UVicConfig {
+            hackery_levels,
+            ..Default::default()
+        },
+    )
+}
+
+fn request(user_id: &str, stock_symbol: &str) -> Request<QuoteRequest> {
+    Request::new(QuoteRequest {
+        user_id: user_id.to_string(),
+        stock_symbol: stock_symbol.to_string(),
+        ..Default::default()
+    })
+}
+
+/// sends `user,SYM` lines over one connection and returns the raw answers
+async fn raw_lines(addr: &str, requests: &[&str]) -> Vec<String> {
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+
+    let mut lines = vec![];
+    for request in requests {
+        writer.write_all(request.as_bytes()).await.unwrap();
+        let mut line = String::new();
+        reader.read_line(&mut line).await.unwrap();
+        lines.push(line);
+    }
+    lines
+}
+
+#[tokio::test]
+async fn test_quote() {
+    let addr = stand_in(StandInConfig::default()).await;
+
+    let quote = quoter(addr, 3)
+        .quote(request("marcus", "ABC"))
+        .await
+        .unwrap()
+        .into_inner();
+
+    assert_eq!(quote.sym, "ABC");
+    assert_eq!(quote.user_id, "marcus");
+    assert!((50.0..300.0).contains(&quote.quote), "{}", quote.quote);
+    assert!(quote.timestamp > 0);
+    assert_eq!(quote.crypto_key.len(), 44);
+    assert!(quote.crypto_key.chars().all(char::is_alphanumeric));
+}
+
+#[tokio::test]
+async fn test_connection_serves_many_requests() {
+    let addr = stand_in(StandInConfig::default()).await;
+
+    let lines = raw_lines(&addr, &["marcus,ABC\n", "marcus,XYZ\n"]).await;
+
+    let symbols = lines
+        .iter()
+        .map(|it| response_from_quote_server_string(it).unwrap().sym)
+        .collect::<Vec<_>>();
+    assert_eq!(symbols, vec!["ABC", "XYZ"]);
+}
+
+#[tokio::test]
+async fn test_malformed_responses_are_rejected() {
+    let addr = stand_in(StandInConfig {
+        malformed_rate: 1.0,
+        ..Default::default()
+    })
+    .await;
+
+    let lines = raw_lines(&addr, &["marcus,ABC\n"; 16]).await;
+    for line in &lines {
+        let error = response_from_quote_server_string(line).unwrap_err();
+        assert!(error.starts_with("Invalid response"), "{error}");
+    }
+
+    let status = quoter(addr, 1)
+        .quote(request("marcus", "ABC"))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Unavailable);
+}
+
+#[tokio::test]
+async fn test_hangup_is_an_error() {
+    let addr = stand_in(StandInConfig {
+        error_rate: 1.0,
+        ..Default::default()
+    })
+    .await;
+
+    let status = quoter(addr, 2)
+        .quote(request("marcus", "ABC"))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Unavailable);
+}
+
+#[tokio::test]
+async fn test_latency() {
+    let addr = stand_in(StandInConfig {
+        latency: Duration::from_millis(200),
+        jitter: Duration::from_millis(50),
+        ..Default::default()
+    })
+    .await;
+
+    let start = Instant::now();
+    quoter(addr, 2)
+        .quote(request("marcus", "ABC"))
+        .await
+        .unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(200));
+}
+
+#[tokio::test]
+async fn test_same_seed_same_answers() {
+    let config = StandInConfig {
+        seed: 42,
+        malformed_rate: 0.5,
+        ..Default::default()
+    };
+    let first = stand_in(config.clone()).await;
+    let second = stand_in(config).await;
+
+    let requests = ["marcus,ABC\n"; 8];
+    // the timestamps differ, everything else is seeded
+    let answers = |lines: Vec<String>| {
+        lines
+            .into_iter()
+            .map(|it| match response_from_quote_server_string(&it) {
+                Ok(quote) => Ok((quote.quote.to_string(), quote.crypto_key)),
+                Err(_) => Err(it.split(',').next().map(str::to_string)),
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        answers(raw_lines(&first, &requests).await),
+        answers(raw_lines(&second, &requests).await)
+    );
+}
+
+#[tokio::test]
+async fn test_connections_are_reused() {
+    let (addr, connections) = scripted(None).await;
+    let quoter = quoter(addr, 1);
+
+    for _ in 0..3 {
+        quoter.quote(request("marcus", "ABC")).await.unwrap();
+    }
+
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+    assert_eq!(quoter.metrics().snapshot().wins, vec![3]);
+}
+
+#[tokio::test]
+async fn test_failed_attempts_are_ignored() {
+    let (addr, _) = scripted(Some(First::Hangup)).await;
+    let quoter = UVicQuoter::new(
+        addr,
+        UVicConfig {
+            hackery_levels: 2,
+            hedge_delay: Duration::from_secs(5),
+            ..Default::default()
+        },
+    );
+
+    // a failure is replaced straight away rather than after the hedge delay
+    let start = Instant::now();
+    let quote = quoter
+        .quote(request("marcus", "ABC"))
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(quote.crypto_key, "key");
+    assert!(start.elapsed() < Duration::from_secs(1));
+
+    let metrics = quoter.metrics().snapshot();
+    assert_eq!(metrics.failed_attempts, 1);
+    assert_eq!(metrics.wins, vec![0, 1]);
+    assert_eq!(metrics.failed_requests, 0);
+}
+
+#[tokio::test]
+async fn test_hedge_wins_and_loser_is_cancelled() {
+    let (addr, connections) = scripted(Some(First::Hang)).await;
+    let quoter = UVicQuoter::new(
+        addr,
+        UVicConfig {
+            hackery_levels: 3,
+            hedge_delay: Duration::from_millis(100),
+            ..Default::default()
+        },
+    );
+
+    let start = Instant::now();
+    quoter.quote(request("marcus", "ABC")).await.unwrap();
+    assert!(start.elapsed() < Duration::from_secs(1));
+
+    // the hanging first attempt was hedged once and the hedge won
+    let metrics = quoter.metrics().snapshot();
+    assert_eq!(metrics.wins, vec![0, 1]);
+    assert_eq!(metrics.hedge_wins(), 1);
+    assert_eq!(metrics.cancelled_attempts, 1);
+    assert_eq!(connections.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_attempts_time_out() {
+    let addr = stand_in(StandInConfig {
+        latency: Duration::from_secs(5),
+        ..Default::default()
+    })
+    .await;
+    let quoter = UVicQuoter::new(
+        addr,
+        UVicConfig {
+            hackery_levels: 2,
+            attempt_timeout: Duration::from_millis(100),
+            ..Default::default()
+        },
+    );
+
+    let start = Instant::now();
+    let status = quoter.quote(request("marcus", "ABC")).await.unwrap_err();
+    assert_eq!(status.code(), Code::Unavailable);
+    assert!(start.elapsed() < Duration::from_secs(1));
+
+    let metrics = quoter.metrics().snapshot();
+    assert_eq!(metrics.timed_out_attempts, 2);
+    assert_eq!(metrics.failed_requests, 1);
+}